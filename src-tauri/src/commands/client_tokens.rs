@@ -0,0 +1,155 @@
+//! 客户端令牌命令
+//!
+//! 共享代理时的客户端认证开关、令牌签发/吊销与配额用量查询
+
+use crate::error::AppError;
+use crate::proxy::client_auth::{
+    display_prefix, generate_secret, hash_secret, is_valid_quota_period, ClientToken,
+    ClientTokenUsage, IssuedClientToken, QUOTA_PERIOD_DAILY,
+};
+use crate::proxy::types::ClientAuthConfig;
+use crate::store::AppState;
+use crate::time_util::period_start;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+/// 校验并规范化令牌名称与配额
+fn validate_token(token: &mut ClientToken) -> Result<(), String> {
+    token.name = token.name.trim().to_string();
+    if token.name.is_empty() {
+        return Err(AppError::localized(
+            "error.clientTokenNameRequired",
+            "客户端令牌名称不能为空",
+            "Client token name is required",
+        )
+        .to_string());
+    }
+    if !is_valid_quota_period(&token.quota_period) {
+        return Err(AppError::localized(
+            "error.invalidQuotaPeriod",
+            format!("无效的配额周期: {}", token.quota_period),
+            format!("Invalid quota period: {}", token.quota_period),
+        )
+        .to_string());
+    }
+    token.cost_limit_usd = token
+        .cost_limit_usd
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string);
+    if let Some(limit) = &token.cost_limit_usd {
+        match Decimal::from_str(limit) {
+            Ok(value) if value >= Decimal::ZERO => {}
+            _ => {
+                return Err(AppError::localized(
+                    "error.invalidCostLimit",
+                    format!("无效的成本配额: {limit}"),
+                    format!("Invalid cost limit: {limit}"),
+                )
+                .to_string())
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 获取客户端认证配置
+#[tauri::command]
+pub async fn get_client_auth_config(
+    state: tauri::State<'_, AppState>,
+) -> Result<ClientAuthConfig, String> {
+    state.db.get_client_auth_config().map_err(|e| e.to_string())
+}
+
+/// 设置客户端认证配置（下一个请求即生效）
+#[tauri::command]
+pub async fn set_client_auth_config(
+    state: tauri::State<'_, AppState>,
+    config: ClientAuthConfig,
+) -> Result<bool, String> {
+    state
+        .db
+        .set_client_auth_config(&config)
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// 获取全部客户端令牌
+#[tauri::command]
+pub async fn list_client_tokens(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ClientToken>, String> {
+    state.db.list_client_tokens().map_err(|e| e.to_string())
+}
+
+/// 签发客户端令牌，明文只在此处返回一次
+#[tauri::command]
+pub async fn create_client_token(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    request_limit: Option<u64>,
+    cost_limit_usd: Option<String>,
+    quota_period: Option<String>,
+) -> Result<IssuedClientToken, String> {
+    let secret = generate_secret();
+    let mut token = ClientToken {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        token_prefix: display_prefix(&secret),
+        request_limit,
+        cost_limit_usd,
+        quota_period: quota_period.unwrap_or_else(|| QUOTA_PERIOD_DAILY.to_string()),
+        created_at: chrono::Utc::now().timestamp(),
+        last_used_at: None,
+        revoked_at: None,
+    };
+    validate_token(&mut token)?;
+
+    state
+        .db
+        .insert_client_token(&token, &hash_secret(&secret))
+        .map_err(|e| e.to_string())?;
+    Ok(IssuedClientToken { token, secret })
+}
+
+/// 更新令牌名称与配额
+#[tauri::command]
+pub async fn update_client_token(
+    state: tauri::State<'_, AppState>,
+    mut token: ClientToken,
+) -> Result<ClientToken, String> {
+    validate_token(&mut token)?;
+    state
+        .db
+        .update_client_token(&token)
+        .map_err(|e| e.to_string())?;
+    Ok(token)
+}
+
+/// 吊销客户端令牌（立即生效）
+#[tauri::command]
+pub async fn revoke_client_token(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    state.db.revoke_client_token(&id).map_err(|e| e.to_string())
+}
+
+/// 获取令牌在当前配额周期内的用量
+#[tauri::command]
+pub async fn get_client_token_usage(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<ClientTokenUsage, String> {
+    let token = state
+        .db
+        .get_client_token(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("客户端令牌不存在: {id}"))?;
+    let since = period_start(&token.quota_period, chrono::Local::now());
+    state
+        .db
+        .get_client_token_usage(&id, since)
+        .map_err(|e| e.to_string())
+}
@@ -2,10 +2,14 @@
 //!
 //! 提供获取、设置和测试全局代理的 Tauri 命令。
 
+use crate::provider::UpstreamTlsConfig;
 use crate::proxy::http_client;
+use crate::proxy::upstream_rules::{
+    ProxyDecision, ProxyRuleSet, UpstreamProxyRules, PROXY_TARGET_DIRECT,
+};
 use crate::store::AppState;
 use serde::Serialize;
-use std::net::{Ipv4Addr, SocketAddrV4, TcpStream};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream, ToSocketAddrs};
 use std::time::{Duration, Instant};
 
 /// 获取全局代理 URL
@@ -69,6 +73,44 @@ pub fn set_global_proxy_url(state: tauri::State<'_, AppState>, url: String) -> R
     Ok(())
 }
 
+/// 获取全局上游 TLS 配置
+#[tauri::command]
+pub fn get_upstream_tls_config(
+    state: tauri::State<'_, AppState>,
+) -> Result<UpstreamTlsConfig, String> {
+    state
+        .db
+        .get_upstream_tls_config()
+        .map_err(|e| e.to_string())
+}
+
+/// 设置全局上游 TLS 配置
+///
+/// 执行顺序与全局代理一致：先验证证书文件 → 写 DB → 再应用
+#[tauri::command]
+pub fn set_upstream_tls_config(
+    state: tauri::State<'_, AppState>,
+    config: UpstreamTlsConfig,
+) -> Result<(), String> {
+    http_client::validate_tls_config(&config)?;
+
+    state
+        .db
+        .set_upstream_tls_config(&config)
+        .map_err(|e| e.to_string())?;
+
+    http_client::apply_tls_config(Some(&config))?;
+
+    log::info!(
+        "[GlobalProxy] [GP-013] Upstream TLS updated: enabled={}, ca_bundles={}, client_cert={}",
+        config.enabled,
+        config.ca_cert_paths.len(),
+        config.client_cert_path.is_some()
+    );
+
+    Ok(())
+}
+
 /// 代理测试结果
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -84,17 +126,25 @@ pub struct ProxyTestResult {
 /// 测试代理连接
 ///
 /// 通过指定的代理 URL 发送测试请求，返回连接结果和延迟。
-/// 使用多个测试目标，任一成功即认为代理可用。
+/// 使用多个测试目标，任一成功即认为代理可用；
+/// 传入 target_url 时优先测试该地址（如规则中该代理负责的 API 域名）。
 #[tauri::command]
-pub async fn test_proxy_url(url: String) -> Result<ProxyTestResult, String> {
+pub async fn test_proxy_url(
+    url: String,
+    target_url: Option<String>,
+) -> Result<ProxyTestResult, String> {
     if url.trim().is_empty() {
         return Err("Proxy URL is empty".to_string());
     }
+    probe_proxy(&url, target_url.as_deref()).await
+}
 
+/// 通过代理依次请求测试目标
+async fn probe_proxy(url: &str, target_url: Option<&str>) -> Result<ProxyTestResult, String> {
     let start = Instant::now();
 
     // 构建带代理的临时客户端
-    let proxy = reqwest::Proxy::all(&url).map_err(|e| format!("Invalid proxy URL: {e}"))?;
+    let proxy = reqwest::Proxy::all(url).map_err(|e| format!("Invalid proxy URL: {e}"))?;
 
     let client = reqwest::Client::builder()
         .proxy(proxy)
@@ -105,11 +155,15 @@ pub async fn test_proxy_url(url: String) -> Result<ProxyTestResult, String> {
 
     // 使用多个测试目标，提高兼容性
     // 优先使用 httpbin（专门用于 HTTP 测试），回退到其他公共端点
-    let test_urls = [
+    let default_urls = [
         "https://httpbin.org/get",
         "https://www.google.com",
         "https://api.anthropic.com",
     ];
+    let test_urls: Vec<&str> = match target_url.map(str::trim).filter(|u| !u.is_empty()) {
+        Some(target) => vec![target],
+        None => default_urls.to_vec(),
+    };
 
     let mut last_error = None;
 
@@ -119,7 +173,7 @@ pub async fn test_proxy_url(url: String) -> Result<ProxyTestResult, String> {
                 let latency = start.elapsed().as_millis() as u64;
                 log::debug!(
                     "[GlobalProxy] Test successful: {} -> {} via {} ({}ms)",
-                    http_client::mask_url(&url),
+                    http_client::mask_url(url),
                     test_url,
                     resp.status(),
                     latency
@@ -145,7 +199,7 @@ pub async fn test_proxy_url(url: String) -> Result<ProxyTestResult, String> {
 
     log::debug!(
         "[GlobalProxy] Test failed: {} -> {} ({}ms)",
-        http_client::mask_url(&url),
+        http_client::mask_url(url),
         error_msg,
         latency
     );
@@ -157,6 +211,175 @@ pub async fn test_proxy_url(url: String) -> Result<ProxyTestResult, String> {
     })
 }
 
+/// 获取上游代理规则
+#[tauri::command]
+pub fn get_upstream_proxy_rules(
+    state: tauri::State<'_, AppState>,
+) -> Result<UpstreamProxyRules, String> {
+    state
+        .db
+        .get_upstream_proxy_rules()
+        .map_err(|e| e.to_string())
+}
+
+/// 设置上游代理规则
+///
+/// 执行顺序与全局代理一致：先编译校验 → 写 DB → 再应用
+#[tauri::command]
+pub async fn set_upstream_proxy_rules(
+    state: tauri::State<'_, AppState>,
+    rules: UpstreamProxyRules,
+) -> Result<(), String> {
+    // PAC 校验会执行脚本（可能触发 DNS 解析），放到阻塞线程
+    let to_validate = rules.clone();
+    tokio::task::spawn_blocking(move || http_client::validate_proxy_rules(&to_validate))
+        .await
+        .map_err(|e| format!("Proxy rules validation task failed: {e}"))??;
+
+    state
+        .db
+        .set_upstream_proxy_rules(&rules)
+        .map_err(|e| e.to_string())?;
+
+    http_client::apply_proxy_rules(Some(&rules))?;
+
+    log::info!(
+        "[GlobalProxy] [GP-017] Upstream proxy rules updated: enabled={}, mode={}, rules={}",
+        rules.enabled,
+        rules.mode,
+        rules.rules.len()
+    );
+
+    Ok(())
+}
+
+/// 单个目标的代理选择结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyRouteDecision {
+    /// 测试目标 URL
+    pub url: String,
+    /// direct / default（回退全局或系统代理）/ 代理 URL（已隐藏认证信息）
+    pub route: String,
+    /// 目标 URL 无效时的错误信息
+    pub error: Option<String>,
+}
+
+/// 规则中引用的代理检测结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleProxyCheck {
+    /// 代理 URL（已隐藏认证信息）
+    pub proxy_url: String,
+    /// 代理端口是否可连接
+    pub reachable: bool,
+    /// 通过该代理的连通性测试结果（端口不可达时为空）
+    pub result: Option<ProxyTestResult>,
+}
+
+/// 上游代理规则测试结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyRulesTestResult {
+    pub decisions: Vec<ProxyRouteDecision>,
+    pub proxies: Vec<RuleProxyCheck>,
+}
+
+/// 测试上游代理规则（不保存）
+///
+/// 编译规则后对给定的示例 URL 求值，并对规则引用到的每个代理
+/// 做端口探测与连通性测试（与 scan_local_proxies / test_proxy_url 相同的检查）。
+#[tauri::command]
+pub async fn test_upstream_proxy_rules(
+    rules: UpstreamProxyRules,
+    urls: Vec<String>,
+) -> Result<ProxyRulesTestResult, String> {
+    let (decisions, proxy_urls) = tokio::task::spawn_blocking(move || {
+        let compiled = ProxyRuleSet::compile(&rules)?;
+        let mut proxy_urls = compiled.proxy_urls();
+        let mut decisions = Vec::with_capacity(urls.len());
+
+        for raw in urls {
+            let target = raw.trim().to_string();
+            let decision = match url::Url::parse(&target) {
+                Ok(parsed) => match compiled.decide(&parsed) {
+                    ProxyDecision::Direct => PROXY_TARGET_DIRECT.to_string(),
+                    ProxyDecision::Default => "default".to_string(),
+                    ProxyDecision::Proxy(proxy) => {
+                        let masked = http_client::mask_url(proxy.as_str());
+                        if !proxy_urls.contains(&proxy) {
+                            proxy_urls.push(proxy);
+                        }
+                        masked
+                    }
+                },
+                Err(e) => {
+                    decisions.push(ProxyRouteDecision {
+                        url: target,
+                        route: String::new(),
+                        error: Some(format!("Invalid URL: {e}")),
+                    });
+                    continue;
+                }
+            };
+            decisions.push(ProxyRouteDecision {
+                url: target,
+                route: decision,
+                error: None,
+            });
+        }
+
+        Ok::<_, String>((decisions, proxy_urls))
+    })
+    .await
+    .map_err(|e| format!("Proxy rules test task failed: {e}"))??;
+
+    let mut proxies = Vec::with_capacity(proxy_urls.len());
+    for proxy in proxy_urls {
+        let reachable = proxy_port_open(&proxy).await;
+        let result = if reachable {
+            Some(probe_proxy(proxy.as_str(), None).await?)
+        } else {
+            None
+        };
+        proxies.push(RuleProxyCheck {
+            proxy_url: http_client::mask_url(proxy.as_str()),
+            reachable,
+            result,
+        });
+    }
+
+    Ok(ProxyRulesTestResult { decisions, proxies })
+}
+
+/// 探测代理端口是否可连接
+async fn proxy_port_open(proxy: &url::Url) -> bool {
+    let Some(host) = proxy.host_str().map(str::to_string) else {
+        return false;
+    };
+    let Some(port) = proxy.port_or_known_default().or(match proxy.scheme() {
+        "socks5" | "socks5h" => Some(1080),
+        _ => None,
+    }) else {
+        return false;
+    };
+
+    tokio::task::spawn_blocking(move || {
+        (host.as_str(), port)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .is_some_and(|addr| port_open(addr, Duration::from_millis(500)))
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// TCP 端口探测
+fn port_open(addr: SocketAddr, timeout: Duration) -> bool {
+    TcpStream::connect_timeout(&addr, timeout).is_ok()
+}
+
 /// 获取当前出站代理状态
 ///
 /// 返回当前是否启用了出站代理以及代理 URL。
@@ -217,7 +440,7 @@ pub async fn scan_local_proxies() -> Vec<DetectedProxy> {
 
         for &(port, primary_type, is_mixed) in PROXY_PORTS {
             let addr = SocketAddrV4::new(Ipv4Addr::LOCALHOST, port);
-            if TcpStream::connect_timeout(&addr.into(), Duration::from_millis(100)).is_ok() {
+            if port_open(addr.into(), Duration::from_millis(100)) {
                 // 添加主要类型
                 found.push(DetectedProxy {
                     url: format!("{primary_type}://127.0.0.1:{port}"),
@@ -0,0 +1,57 @@
+//! 代理钩子命令
+//!
+//! 管理在代理中改写请求/响应的 JS 钩子（每个 app 独立）
+
+use crate::error::AppError;
+use crate::proxy::hooks::{validate_hook, ProxyHook, MAX_HOOK_TIMEOUT_MS};
+use crate::store::AppState;
+
+/// 获取钩子列表（按 sort_index 排序）
+#[tauri::command]
+pub async fn get_proxy_hooks(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<Vec<ProxyHook>, String> {
+    state
+        .db
+        .get_proxy_hooks(&app_type)
+        .map_err(|e| e.to_string())
+}
+
+/// 新增或更新钩子（id 为空时自动生成），保存前先编译脚本校验
+#[tauri::command]
+pub async fn save_proxy_hook(
+    state: tauri::State<'_, AppState>,
+    mut hook: ProxyHook,
+) -> Result<ProxyHook, String> {
+    if hook.id.trim().is_empty() {
+        hook.id = uuid::Uuid::new_v4().to_string();
+    }
+    hook.name = hook.name.trim().to_string();
+    hook.timeout_ms = hook.timeout_ms.clamp(1, MAX_HOOK_TIMEOUT_MS);
+
+    let probe = hook.clone();
+    tauri::async_runtime::spawn_blocking(move || validate_hook(&probe))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| {
+            AppError::localized(
+                "error.invalidProxyHook",
+                format!("钩子脚本无效: {e}"),
+                format!("Invalid hook script: {e}"),
+            )
+            .to_string()
+        })?;
+
+    state.db.save_proxy_hook(&hook).map_err(|e| e.to_string())?;
+    Ok(hook)
+}
+
+/// 删除钩子
+#[tauri::command]
+pub async fn delete_proxy_hook(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    state.db.delete_proxy_hook(&id).map_err(|e| e.to_string())
+}
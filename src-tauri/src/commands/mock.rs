@@ -0,0 +1,63 @@
+//! Mock 上游命令
+//!
+//! 管理离线 Mock 上游返回的 fixture（每个 app 独立），支持从请求日志录制
+
+use crate::proxy::mock_upstream::MockFixture;
+use crate::store::AppState;
+
+/// 获取 fixture 列表（按 sort_index 排序）
+#[tauri::command]
+pub async fn get_mock_fixtures(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<Vec<MockFixture>, String> {
+    state
+        .db
+        .get_mock_fixtures(&app_type)
+        .map_err(|e| e.to_string())
+}
+
+/// 新增或更新 fixture（id 为空时自动生成）
+#[tauri::command]
+pub async fn save_mock_fixture(
+    state: tauri::State<'_, AppState>,
+    mut fixture: MockFixture,
+) -> Result<MockFixture, String> {
+    if fixture.id.trim().is_empty() {
+        fixture.id = uuid::Uuid::new_v4().to_string();
+    }
+    fixture.name = fixture.name.trim().to_string();
+
+    state
+        .db
+        .save_mock_fixture(&fixture)
+        .map_err(|e| e.to_string())?;
+    Ok(fixture)
+}
+
+/// 删除 fixture
+#[tauri::command]
+pub async fn delete_mock_fixture(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    state.db.delete_mock_fixture(&id).map_err(|e| e.to_string())
+}
+
+/// 从请求日志录制 fixture
+#[tauri::command]
+pub async fn capture_mock_fixture(
+    state: tauri::State<'_, AppState>,
+    request_id: String,
+    name: Option<String>,
+) -> Result<MockFixture, String> {
+    let name = name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| format!("Recorded {request_id}"));
+
+    state
+        .db
+        .capture_mock_fixture(&request_id, &uuid::Uuid::new_v4().to_string(), &name)
+        .map_err(|e| e.to_string())
+}
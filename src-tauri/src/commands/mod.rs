@@ -1,19 +1,23 @@
 #![allow(non_snake_case)]
 
+mod client_tokens;
 mod config;
 mod deeplink;
 mod env;
 mod failover;
 mod global_proxy;
+mod hooks;
 mod import_export;
 mod mcp;
 mod misc;
+mod mock;
 mod omo;
 mod openclaw;
 mod plugin;
 mod prompt;
 mod provider;
 mod proxy;
+mod routing;
 mod session_manager;
 mod settings;
 pub mod skill;
@@ -23,20 +27,24 @@ mod usage;
 mod webdav_sync;
 mod workspace;
 
+pub use client_tokens::*;
 pub use config::*;
 pub use deeplink::*;
 pub use env::*;
 pub use failover::*;
 pub use global_proxy::*;
+pub use hooks::*;
 pub use import_export::*;
 pub use mcp::*;
 pub use misc::*;
+pub use mock::*;
 pub use omo::*;
 pub use openclaw::*;
 pub use plugin::*;
 pub use prompt::*;
 pub use provider::*;
 pub use proxy::*;
+pub use routing::*;
 pub use session_manager::*;
 pub use settings::*;
 pub use skill::*;
@@ -5,7 +5,7 @@ use crate::app_config::AppType;
 use crate::error::AppError;
 use crate::provider::Provider;
 use crate::services::{
-    EndpointLatency, ProviderService, ProviderSortUpdate, SpeedtestService, SwitchResult,
+    balance, EndpointLatency, ProviderService, ProviderSortUpdate, SpeedtestService, SwitchResult,
 };
 use crate::store::AppState;
 use std::str::FromStr;
@@ -122,10 +122,66 @@ pub async fn queryProviderUsage(
     state: State<'_, AppState>,
     #[allow(non_snake_case)] providerId: String, // 使用 camelCase 匹配前端
     app: String,
+    app_handle: tauri::AppHandle,
 ) -> Result<crate::provider::UsageResult, String> {
     let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
-    ProviderService::query_usage(state.inner(), app_type, &providerId)
+    let result = ProviderService::query_usage(state.inner(), app_type.clone(), &providerId)
         .await
+        .map_err(|e| e.to_string())?;
+
+    // 记录余额历史，失败不影响查询结果
+    let now = chrono::Utc::now().timestamp();
+    match state.db.get_provider_by_id(&providerId, app_type.as_str()) {
+        Ok(Some(provider)) => {
+            match state
+                .db
+                .record_usage_result(app_type.as_str(), &provider, &result, now)
+            {
+                Ok(Some(alert)) => balance::notify_balance_alert(&app_handle, &alert),
+                Ok(None) => balance::clear_recovered_balance_alert(
+                    &app_handle,
+                    app_type.as_str(),
+                    &provider,
+                    &result,
+                ),
+                Err(e) => log::warn!("[USG-008] 记录供应商 {providerId} 余额历史失败: {e}"),
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("[USG-008] 记录供应商 {providerId} 余额历史失败: {e}"),
+    }
+
+    Ok(result)
+}
+
+/// 获取供应商余额历史
+#[tauri::command]
+pub fn get_balance_history(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] providerId: String,
+    app: String,
+    start: Option<i64>,
+    end: Option<i64>,
+) -> Result<Vec<balance::BalanceSnapshot>, String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    state
+        .db
+        .get_balance_history(app_type.as_str(), &providerId, start, end)
+        .map_err(|e| e.to_string())
+}
+
+/// 获取供应商余额耗尽预测
+#[tauri::command]
+pub fn get_balance_forecast(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] providerId: String,
+    app: String,
+) -> Result<Option<balance::BalanceForecast>, String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().timestamp();
+    state
+        .db
+        .get_balance_forecast(app_type.as_str(), &providerId, now)
         .map_err(|e| e.to_string())
 }
 
@@ -175,10 +231,25 @@ pub fn patch_claude_live_settings(patch: serde_json::Value) -> Result<bool, Stri
 
 #[tauri::command]
 pub async fn test_api_endpoints(
+    state: State<'_, AppState>,
     urls: Vec<String>,
     #[allow(non_snake_case)] timeoutSecs: Option<u64>,
+    app: Option<String>,
+    #[allow(non_snake_case)] providerId: Option<String>,
 ) -> Result<Vec<EndpointLatency>, String> {
-    SpeedtestService::test_endpoints(urls, timeoutSecs)
+    // 指定供应商时使用其代理/TLS 配置测速
+    let provider_meta = match (app, providerId) {
+        (Some(app), Some(provider_id)) => {
+            let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+            state
+                .db
+                .get_provider_by_id(&provider_id, app_type.as_str())
+                .map_err(|e| e.to_string())?
+                .and_then(|p| p.meta)
+        }
+        _ => None,
+    };
+    SpeedtestService::test_endpoints(urls, timeoutSecs, provider_meta.as_ref())
         .await
         .map_err(|e| e.to_string())
 }
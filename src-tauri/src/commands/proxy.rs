@@ -63,6 +63,50 @@ pub async fn update_proxy_config(
     state.proxy_service.update_config(&config).await
 }
 
+/// 获取代理监听 TLS 配置
+#[tauri::command]
+pub async fn get_proxy_tls_config(
+    state: tauri::State<'_, AppState>,
+) -> Result<ProxyTlsConfig, String> {
+    state.db.get_proxy_tls_config().map_err(|e| e.to_string())
+}
+
+/// 更新代理监听 TLS 配置（运行中会自动重启代理并同步接管地址）
+#[tauri::command]
+pub async fn update_proxy_tls_config(
+    state: tauri::State<'_, AppState>,
+    config: ProxyTlsConfig,
+) -> Result<(), String> {
+    if !matches!(
+        config.cert_source.as_str(),
+        TLS_CERT_SOURCE_SELF_SIGNED | TLS_CERT_SOURCE_CUSTOM
+    ) {
+        return Err(AppError::localized(
+            "error.invalidTlsCertSource",
+            format!("无效的证书来源: {}", config.cert_source),
+            format!("Invalid certificate source: {}", config.cert_source),
+        )
+        .to_string());
+    }
+    state.proxy_service.update_tls_config(&config).await
+}
+
+/// 获取本地代理 CA 证书（PEM，不存在或未覆盖当前主机时生成）
+#[tauri::command]
+pub async fn get_proxy_ca_cert(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    state.proxy_service.ca_cert_pem().await
+}
+
+/// 导出本地代理 CA 证书到指定路径，供客户端加入信任
+#[tauri::command]
+pub async fn export_proxy_ca_cert(
+    state: tauri::State<'_, AppState>,
+    file_path: String,
+) -> Result<(), String> {
+    let pem = state.proxy_service.ca_cert_pem().await?;
+    std::fs::write(&file_path, pem).map_err(|e| format!("导出 CA 证书失败: {e}"))
+}
+
 // ==================== Global & Per-App Config ====================
 
 /// 获取全局代理配置
@@ -234,6 +278,33 @@ pub async fn set_pricing_model_source(
         .map_err(|e| e.to_string())
 }
 
+/// 获取负载均衡策略
+#[tauri::command]
+pub async fn get_load_balance_strategy(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<LoadBalanceStrategy, String> {
+    state
+        .db
+        .get_load_balance_strategy(&app_type)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 设置负载均衡策略
+#[tauri::command]
+pub async fn set_load_balance_strategy(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    value: String,
+) -> Result<(), String> {
+    state
+        .db
+        .set_load_balance_strategy(&app_type, &value)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// 检查代理服务器是否正在运行
 #[tauri::command]
 pub async fn is_proxy_running(state: tauri::State<'_, AppState>) -> Result<bool, String> {
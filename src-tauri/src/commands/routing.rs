@@ -0,0 +1,43 @@
+//! 模型路由规则命令
+//!
+//! 按模型/请求特征把请求路由到指定供应商（每个 app 独立）
+
+use crate::proxy::routing_rules::RoutingRule;
+use crate::store::AppState;
+
+/// 获取路由规则（按 sort_index 排序）
+#[tauri::command]
+pub async fn get_routing_rules(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<Vec<RoutingRule>, String> {
+    state
+        .db
+        .get_routing_rules(&app_type)
+        .map_err(|e| e.to_string())
+}
+
+/// 新增或更新路由规则（id 为空时自动生成），返回保存后的规则
+#[tauri::command]
+pub async fn save_routing_rule(
+    state: tauri::State<'_, AppState>,
+    mut rule: RoutingRule,
+) -> Result<RoutingRule, String> {
+    if rule.id.trim().is_empty() {
+        rule.id = uuid::Uuid::new_v4().to_string();
+    }
+    state
+        .db
+        .save_routing_rule(&rule)
+        .map_err(|e| e.to_string())?;
+    Ok(rule)
+}
+
+/// 删除路由规则
+#[tauri::command]
+pub async fn delete_routing_rule(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    state.db.delete_routing_rule(&id).map_err(|e| e.to_string())
+}
@@ -1,12 +1,23 @@
 #![allow(non_snake_case)]
 
 use crate::session_manager;
+use crate::store::AppState;
 
 #[tauri::command]
-pub async fn list_sessions() -> Result<Vec<session_manager::SessionMeta>, String> {
-    let sessions = tauri::async_runtime::spawn_blocking(session_manager::scan_sessions)
-        .await
-        .map_err(|e| format!("Failed to scan sessions: {e}"))?;
+pub async fn list_sessions(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<session_manager::SessionMeta>, String> {
+    let db = state.db.clone();
+    let sessions = tauri::async_runtime::spawn_blocking(move || {
+        let mut sessions = session_manager::scan_sessions();
+        match db.get_session_usage_stats(None, None, None, None) {
+            Ok(stats) => session_manager::attach_usage(&mut sessions, stats),
+            Err(e) => log::warn!("查询会话用量失败: {e}"),
+        }
+        sessions
+    })
+    .await
+    .map_err(|e| format!("Failed to scan sessions: {e}"))?;
     Ok(sessions)
 }
 
@@ -145,6 +145,153 @@ pub async fn set_rectifier_config(
     Ok(true)
 }
 
+/// 获取会话粘性路由配置
+#[tauri::command]
+pub async fn get_session_affinity_config(
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<crate::proxy::types::SessionAffinityConfig, String> {
+    state
+        .db
+        .get_session_affinity_config()
+        .map_err(|e| e.to_string())
+}
+
+/// 设置会话粘性路由配置
+#[tauri::command]
+pub async fn set_session_affinity_config(
+    state: tauri::State<'_, crate::AppState>,
+    config: crate::proxy::types::SessionAffinityConfig,
+) -> Result<bool, String> {
+    state
+        .db
+        .set_session_affinity_config(&config)
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// 获取上下文超长回退配置
+#[tauri::command]
+pub async fn get_context_overflow_config(
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<crate::proxy::types::ContextOverflowConfig, String> {
+    state
+        .db
+        .get_context_overflow_config()
+        .map_err(|e| e.to_string())
+}
+
+/// 设置上下文超长回退配置（下一个请求即生效）
+#[tauri::command]
+pub async fn set_context_overflow_config(
+    state: tauri::State<'_, crate::AppState>,
+    mut config: crate::proxy::types::ContextOverflowConfig,
+) -> Result<bool, String> {
+    let normalize = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    for (app, target) in config.targets.iter_mut() {
+        target.provider_id = normalize(target.provider_id.take());
+        target.model = normalize(target.model.take());
+        if target.provider_id.is_none() && target.model.is_none() {
+            return Err(crate::error::AppError::localized(
+                "error.invalidContextFallback",
+                format!("{app} 的回退目标需要设置供应商或模型"),
+                format!("Fallback target for {app} requires a provider or a model"),
+            )
+            .to_string());
+        }
+    }
+    state
+        .db
+        .set_context_overflow_config(&config)
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// 获取对冲请求配置
+#[tauri::command]
+pub async fn get_hedging_config(
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<crate::proxy::types::HedgingConfig, String> {
+    state.db.get_hedging_config().map_err(|e| e.to_string())
+}
+
+/// 设置对冲请求配置
+#[tauri::command]
+pub async fn set_hedging_config(
+    state: tauri::State<'_, crate::AppState>,
+    config: crate::proxy::types::HedgingConfig,
+) -> Result<bool, String> {
+    if !(1..=99).contains(&config.latency_percentile) {
+        return Err(crate::error::AppError::localized(
+            "error.invalidHedgingPercentile",
+            "延迟分位数必须在 1-99 之间",
+            "Latency percentile must be between 1 and 99",
+        )
+        .to_string());
+    }
+    state
+        .db
+        .set_hedging_config(&config)
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// 获取响应缓存配置
+#[tauri::command]
+pub async fn get_response_cache_config(
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<crate::proxy::types::ResponseCacheConfig, String> {
+    state
+        .db
+        .get_response_cache_config()
+        .map_err(|e| e.to_string())
+}
+
+/// 设置响应缓存配置
+#[tauri::command]
+pub async fn set_response_cache_config(
+    state: tauri::State<'_, crate::AppState>,
+    config: crate::proxy::types::ResponseCacheConfig,
+) -> Result<bool, String> {
+    state
+        .db
+        .set_response_cache_config(&config)
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// 获取出站请求密钥扫描配置
+#[tauri::command]
+pub async fn get_secret_scan_config(
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<crate::proxy::types::SecretScanConfig, String> {
+    state.db.get_secret_scan_config().map_err(|e| e.to_string())
+}
+
+/// 设置出站请求密钥扫描配置（下一个请求即生效）
+#[tauri::command]
+pub async fn set_secret_scan_config(
+    state: tauri::State<'_, crate::AppState>,
+    config: crate::proxy::types::SecretScanConfig,
+) -> Result<bool, String> {
+    crate::proxy::secret_scanner::validate_config(&config).map_err(|e| {
+        crate::error::AppError::localized(
+            "error.invalidSecretScanConfig",
+            format!("密钥扫描配置无效: {e}"),
+            format!("Invalid secret scan config: {e}"),
+        )
+        .to_string()
+    })?;
+    state
+        .db
+        .set_secret_scan_config(&config)
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
 /// 获取日志配置
 #[tauri::command]
 pub async fn get_log_config(
@@ -159,15 +306,38 @@ pub async fn set_log_config(
     state: tauri::State<'_, crate::AppState>,
     config: crate::proxy::types::LogConfig,
 ) -> Result<bool, String> {
+    if !(0.0..=1.0).contains(&config.otlp.sample_ratio) {
+        return Err(crate::error::AppError::localized(
+            "error.invalidOtlpSampleRatio",
+            "采样比例必须在 0-1 之间",
+            "Sample ratio must be between 0 and 1",
+        )
+        .to_string());
+    }
+    if config.otlp.enabled
+        && !matches!(
+            url::Url::parse(&config.otlp.endpoint).map(|u| u.scheme().to_string()),
+            Ok(scheme) if scheme == "http" || scheme == "https"
+        )
+    {
+        return Err(crate::error::AppError::localized(
+            "error.invalidOtlpEndpoint",
+            "OTLP 端点必须是 http(s) 地址",
+            "OTLP endpoint must be an http(s) URL",
+        )
+        .to_string());
+    }
     state
         .db
         .set_log_config(&config)
         .map_err(|e| e.to_string())?;
     log::set_max_level(config.to_level_filter());
+    crate::proxy::otel::apply_config(&config.otlp);
     log::info!(
-        "日志配置已更新: enabled={}, level={}",
+        "日志配置已更新: enabled={}, level={}, otlp={}",
         config.enabled,
-        config.level
+        config.level,
+        config.otlp.enabled
     );
     Ok(true)
 }
@@ -1,8 +1,18 @@
 //! 使用统计相关命令
 
 use crate::error::AppError;
+use crate::proxy::usage::calculator::{ModelPricing, PricingTier};
+use crate::services::budget::{BudgetStatus, UsageBudget};
+use crate::services::currency::ExchangeRate;
+use crate::services::latency_stats::{
+    LatencyFilters, LatencyGroupBy, LatencyStats, LatencyTrendPoint,
+};
+use crate::services::pricing_catalog::{load_catalog_source, CatalogPreview, CatalogPrice};
+use crate::services::transcript_usage::{TranscriptImportResult, TRANSCRIPT_APPS};
+use crate::services::usage_export::{UsageExportFormat, UsageReport};
 use crate::services::usage_stats::*;
 use crate::store::AppState;
+use std::path::PathBuf;
 use tauri::State;
 
 /// 获取使用量汇总
@@ -11,8 +21,12 @@ pub fn get_usage_summary(
     state: State<'_, AppState>,
     start_date: Option<i64>,
     end_date: Option<i64>,
+    currency: Option<String>,
+    source: Option<String>,
 ) -> Result<UsageSummary, AppError> {
-    state.db.get_usage_summary(start_date, end_date)
+    state
+        .db
+        .get_usage_summary(start_date, end_date, currency.as_deref(), source.as_deref())
 }
 
 /// 获取每日趋势
@@ -21,20 +35,93 @@ pub fn get_usage_trends(
     state: State<'_, AppState>,
     start_date: Option<i64>,
     end_date: Option<i64>,
+    currency: Option<String>,
+    source: Option<String>,
 ) -> Result<Vec<DailyStats>, AppError> {
-    state.db.get_daily_trends(start_date, end_date)
+    state
+        .db
+        .get_daily_trends(start_date, end_date, currency.as_deref(), source.as_deref())
 }
 
 /// 获取 Provider 统计
 #[tauri::command]
-pub fn get_provider_stats(state: State<'_, AppState>) -> Result<Vec<ProviderStats>, AppError> {
-    state.db.get_provider_stats()
+pub fn get_provider_stats(
+    state: State<'_, AppState>,
+    currency: Option<String>,
+    source: Option<String>,
+) -> Result<Vec<ProviderStats>, AppError> {
+    state
+        .db
+        .get_provider_stats(currency.as_deref(), source.as_deref())
 }
 
 /// 获取模型统计
 #[tauri::command]
-pub fn get_model_stats(state: State<'_, AppState>) -> Result<Vec<ModelStats>, AppError> {
-    state.db.get_model_stats()
+pub fn get_model_stats(
+    state: State<'_, AppState>,
+    currency: Option<String>,
+    source: Option<String>,
+) -> Result<Vec<ModelStats>, AppError> {
+    state
+        .db
+        .get_model_stats(currency.as_deref(), source.as_deref())
+}
+
+/// 按会话汇总用量
+#[tauri::command]
+pub fn get_session_usage_stats(
+    state: State<'_, AppState>,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    currency: Option<String>,
+    source: Option<String>,
+) -> Result<Vec<SessionUsageStats>, AppError> {
+    state
+        .db
+        .get_session_usage_stats(start_date, end_date, currency.as_deref(), source.as_deref())
+}
+
+/// 按项目目录汇总用量
+#[tauri::command]
+pub async fn get_project_usage_stats(
+    state: State<'_, AppState>,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    currency: Option<String>,
+    source: Option<String>,
+) -> Result<Vec<ProjectUsageStats>, AppError> {
+    let db = state.db.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let sessions = crate::session_manager::scan_sessions();
+        db.get_project_usage_stats(
+            &sessions,
+            start_date,
+            end_date,
+            currency.as_deref(),
+            source.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| AppError::Message(format!("汇总项目用量失败: {e}")))?
+}
+
+/// 按供应商 / 模型统计延迟分位数、首字延迟、吞吐量与错误率
+#[tauri::command]
+pub fn get_latency_stats(
+    state: State<'_, AppState>,
+    filters: LatencyFilters,
+    group_by: LatencyGroupBy,
+) -> Result<Vec<LatencyStats>, AppError> {
+    state.db.get_latency_stats(&filters, group_by)
+}
+
+/// 延迟分位数时间序列
+#[tauri::command]
+pub fn get_latency_trends(
+    state: State<'_, AppState>,
+    filters: LatencyFilters,
+) -> Result<Vec<LatencyTrendPoint>, AppError> {
+    state.db.get_latency_trends(&filters)
 }
 
 /// 获取请求日志列表
@@ -57,6 +144,15 @@ pub fn get_request_detail(
     state.db.get_request_detail(&request_id)
 }
 
+/// 获取会话的缓存命中统计
+#[tauri::command]
+pub fn get_session_cache_stats(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Option<SessionCacheStats>, AppError> {
+    state.db.get_session_cache_stats(&session_id)
+}
+
 /// 获取指定时间范围内所有可用的 Provider 和 Model 筛选选项
 #[tauri::command]
 pub fn get_available_filters(
@@ -92,9 +188,10 @@ pub fn get_model_pricing(state: State<'_, AppState>) -> Result<Vec<ModelPricingI
 
     let mut stmt = conn.prepare(
         "SELECT model_id, display_name, input_cost_per_million, output_cost_per_million,
-                cache_read_cost_per_million, cache_creation_cost_per_million
+                cache_read_cost_per_million, cache_creation_cost_per_million,
+                effective_from, effective_until, tiers
          FROM model_pricing
-         ORDER BY display_name",
+         ORDER BY display_name, effective_from",
     )?;
 
     let rows = stmt.query_map([], |row| {
@@ -105,6 +202,12 @@ pub fn get_model_pricing(state: State<'_, AppState>) -> Result<Vec<ModelPricingI
             output_cost_per_million: row.get(3)?,
             cache_read_cost_per_million: row.get(4)?,
             cache_creation_cost_per_million: row.get(5)?,
+            effective_from: row.get(6)?,
+            effective_until: row.get(7)?,
+            tiers: row
+                .get::<_, Option<String>>(8)?
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
         })
     })?;
 
@@ -118,7 +221,10 @@ pub fn get_model_pricing(state: State<'_, AppState>) -> Result<Vec<ModelPricingI
 }
 
 /// 更新模型定价
+///
+/// `effective_from` 与 model_id 共同确定一个定价版本（未指定时为 0，即不限起始时间）
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn update_model_pricing(
     state: State<'_, AppState>,
     model_id: String,
@@ -127,22 +233,60 @@ pub fn update_model_pricing(
     output_cost: String,
     cache_read_cost: String,
     cache_creation_cost: String,
+    effective_from: Option<i64>,
+    effective_until: Option<i64>,
+    tiers: Option<Vec<PricingTier>>,
 ) -> Result<(), AppError> {
+    let effective_from = effective_from.unwrap_or(0).max(0);
+    let tiers = tiers.unwrap_or_default();
+
+    if effective_until.is_some_and(|until| until <= effective_from) {
+        return Err(AppError::localized(
+            "error.invalidModelPricing",
+            "定价失效时间必须晚于生效时间",
+            "Pricing end time must be after its start time",
+        ));
+    }
+    ModelPricing::from_strings(
+        &input_cost,
+        &output_cost,
+        &cache_read_cost,
+        &cache_creation_cost,
+    )
+    .map_err(|e| e.to_string())
+    .and_then(|pricing| pricing.with_tiers(&tiers))
+    .map_err(|e| {
+        AppError::localized(
+            "error.invalidModelPricing",
+            format!("模型定价无效: {e}"),
+            format!("Invalid model pricing: {e}"),
+        )
+    })?;
+    let tiers_json = if tiers.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&tiers).map_err(|e| AppError::JsonSerialize { source: e })?)
+    };
+
     let db = state.db.clone();
     let conn = crate::database::lock_conn!(db.conn);
 
     conn.execute(
         "INSERT OR REPLACE INTO model_pricing (
             model_id, display_name, input_cost_per_million, output_cost_per_million,
-            cache_read_cost_per_million, cache_creation_cost_per_million
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            cache_read_cost_per_million, cache_creation_cost_per_million,
+            effective_from, effective_until, tiers
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         rusqlite::params![
             model_id,
             display_name,
             input_cost,
             output_cost,
             cache_read_cost,
-            cache_creation_cost
+            cache_creation_cost,
+            effective_from,
+            effective_until,
+            tiers_json
         ],
     )
     .map_err(|e| AppError::Database(format!("更新模型定价失败: {e}")))?;
@@ -156,19 +300,26 @@ pub fn check_provider_limits(
     state: State<'_, AppState>,
     provider_id: String,
     app_type: String,
+    currency: Option<String>,
 ) -> Result<crate::services::usage_stats::ProviderLimitStatus, AppError> {
-    state.db.check_provider_limits(&provider_id, &app_type)
+    state
+        .db
+        .check_provider_limits(&provider_id, &app_type, currency.as_deref())
 }
 
-/// 删除模型定价
+/// 删除模型定价（指定 `effective_from` 时只删除该版本，否则删除该模型的全部版本）
 #[tauri::command]
-pub fn delete_model_pricing(state: State<'_, AppState>, model_id: String) -> Result<(), AppError> {
+pub fn delete_model_pricing(
+    state: State<'_, AppState>,
+    model_id: String,
+    effective_from: Option<i64>,
+) -> Result<(), AppError> {
     let db = state.db.clone();
     let conn = crate::database::lock_conn!(db.conn);
 
     conn.execute(
-        "DELETE FROM model_pricing WHERE model_id = ?1",
-        rusqlite::params![model_id],
+        "DELETE FROM model_pricing WHERE model_id = ?1 AND (?2 IS NULL OR effective_from = ?2)",
+        rusqlite::params![model_id, effective_from],
     )
     .map_err(|e| AppError::Database(format!("删除模型定价失败: {e}")))?;
 
@@ -196,6 +347,171 @@ pub fn count_request_logs_by_date(
     state.db.count_request_logs_by_date(start_date, end_date)
 }
 
+/// 预览定价目录导入（LiteLLM / OpenRouter JSON，本地文件路径或 URL）
+#[tauri::command]
+pub async fn preview_pricing_catalog(
+    state: State<'_, AppState>,
+    source: String,
+) -> Result<CatalogPreview, AppError> {
+    let content = load_catalog_source(&source).await?;
+    state.db.preview_pricing_catalog(&content)
+}
+
+/// 应用预览中选中的定价（`effective_from` 为空时覆盖基础版本）
+#[tauri::command]
+pub fn apply_pricing_catalog(
+    state: State<'_, AppState>,
+    prices: Vec<CatalogPrice>,
+    effective_from: Option<i64>,
+) -> Result<usize, AppError> {
+    state
+        .db
+        .apply_pricing_catalog(&prices, effective_from.unwrap_or(0))
+}
+
+/// 获取汇率表
+#[tauri::command]
+pub fn get_exchange_rates(state: State<'_, AppState>) -> Result<Vec<ExchangeRate>, AppError> {
+    state.db.get_exchange_rates()
+}
+
+/// 手动设置汇率（1 美元可兑换的该币种数量）
+#[tauri::command]
+pub fn save_exchange_rate(
+    state: State<'_, AppState>,
+    currency: String,
+    rate_per_usd: String,
+) -> Result<ExchangeRate, AppError> {
+    state
+        .db
+        .save_exchange_rate(&currency, &rate_per_usd, Some("manual"))
+}
+
+/// 删除汇率
+#[tauri::command]
+pub fn delete_exchange_rate(state: State<'_, AppState>, currency: String) -> Result<(), AppError> {
+    state.db.delete_exchange_rate(&currency)
+}
+
+/// 从 JSON / CSV 文件导入汇率
+#[tauri::command]
+pub fn import_exchange_rates(
+    state: State<'_, AppState>,
+    file_path: String,
+) -> Result<Vec<ExchangeRate>, AppError> {
+    let content = std::fs::read_to_string(&file_path).map_err(|e| AppError::io(&file_path, e))?;
+    let source = std::path::Path::new(&file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_path.clone());
+
+    let rates = state.db.import_exchange_rates(&content, &source)?;
+    log::info!("已从 {file_path} 导入 {} 条汇率", rates.len());
+    Ok(rates)
+}
+
+/// 从 CLI 会话记录导入用量（未指定时导入全部支持的 CLI）
+#[tauri::command]
+pub async fn import_transcript_usage(
+    state: State<'_, AppState>,
+    app_types: Option<Vec<String>>,
+) -> Result<TranscriptImportResult, AppError> {
+    let db = state.db.clone();
+    let app_types =
+        app_types.unwrap_or_else(|| TRANSCRIPT_APPS.iter().map(|app| app.to_string()).collect());
+    tauri::async_runtime::spawn_blocking(move || db.import_transcript_usage(&app_types))
+        .await
+        .map_err(|e| AppError::Message(format!("导入会话记录用量失败: {e}")))?
+}
+
+/// 按筛选条件导出请求日志，返回导出条数
+#[tauri::command]
+pub async fn export_request_logs(
+    state: State<'_, AppState>,
+    filters: LogFilters,
+    format: UsageExportFormat,
+    file_path: String,
+) -> Result<u64, AppError> {
+    let db = state.db.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        db.export_request_logs(&filters, format, &PathBuf::from(file_path))
+    })
+    .await
+    .map_err(|e| AppError::Message(format!("导出请求日志失败: {e}")))?
+}
+
+/// 导出指定时间范围的花费报告
+#[tauri::command]
+pub async fn export_usage_report(
+    state: State<'_, AppState>,
+    start_date: i64,
+    end_date: i64,
+    currency: Option<String>,
+    format: UsageExportFormat,
+    file_path: String,
+) -> Result<UsageReport, AppError> {
+    let db = state.db.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        db.export_usage_report(
+            start_date,
+            end_date,
+            currency.as_deref(),
+            format,
+            &PathBuf::from(file_path),
+        )
+    })
+    .await
+    .map_err(|e| AppError::Message(format!("导出用量报告失败: {e}")))?
+}
+
+/// 立即检查并生成到期的定期报告，返回新生成的文件路径
+#[tauri::command]
+pub async fn generate_scheduled_usage_reports(
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, AppError> {
+    let db = state.db.clone();
+    let paths = tauri::async_runtime::spawn_blocking(move || {
+        db.generate_scheduled_usage_reports(chrono::Local::now())
+    })
+    .await
+    .map_err(|e| AppError::Message(format!("生成定期报告失败: {e}")))??;
+    Ok(paths
+        .into_iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+/// 获取全部预算
+#[tauri::command]
+pub fn get_budgets(state: State<'_, AppState>) -> Result<Vec<UsageBudget>, AppError> {
+    state.db.get_budgets()
+}
+
+/// 新增或更新预算，返回保存后的预算
+#[tauri::command]
+pub fn save_budget(
+    state: State<'_, AppState>,
+    mut budget: UsageBudget,
+) -> Result<UsageBudget, AppError> {
+    if budget.id.trim().is_empty() {
+        budget.id = uuid::Uuid::new_v4().to_string();
+    }
+    state.db.save_budget(&mut budget)?;
+    Ok(budget)
+}
+
+/// 删除预算
+#[tauri::command]
+pub fn delete_budget(state: State<'_, AppState>, id: String) -> Result<bool, AppError> {
+    state.db.delete_budget(&id)
+}
+
+/// 获取全部预算的当前执行情况与月末预测
+#[tauri::command]
+pub fn get_budget_statuses(state: State<'_, AppState>) -> Result<Vec<BudgetStatus>, AppError> {
+    state.db.get_budget_statuses(chrono::Local::now())
+}
+
 /// 模型定价信息
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -206,4 +522,12 @@ pub struct ModelPricingInfo {
     pub output_cost_per_million: String,
     pub cache_read_cost_per_million: String,
     pub cache_creation_cost_per_million: String,
+    /// 生效时间（Unix 秒，0 表示不限）
+    pub effective_from: i64,
+    /// 失效时间（Unix 秒，不含）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_until: Option<i64>,
+    /// 按输入规模分档的价格
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiers: Vec<PricingTier>,
 }
@@ -283,6 +283,9 @@ impl Database {
             .map_err(|e| AppError::Database(e.to_string()))?;
 
         let mut tables = Vec::new();
+        // 触发器放在数据之后：否则回放请求日志时小时汇总触发器会先写入汇总行，
+        // 与随后导入的汇总数据主键冲突
+        let mut triggers = Vec::new();
         let mut rows = stmt
             .query([])
             .map_err(|e| AppError::Database(e.to_string()))?;
@@ -296,6 +299,10 @@ impl Database {
                 continue;
             }
 
+            if obj_type == "trigger" {
+                triggers.push(sql);
+                continue;
+            }
             output.push_str(&sql);
             output.push_str(";\n");
 
@@ -339,6 +346,11 @@ impl Database {
             }
         }
 
+        for sql in triggers {
+            output.push_str(&sql);
+            output.push_str(";\n");
+        }
+
         output.push_str("COMMIT;\nPRAGMA foreign_keys=ON;\n");
         Ok(output)
     }
@@ -0,0 +1,136 @@
+//! 花费预算 DAO
+//!
+//! 预算定义与告警记录（每个周期已通知的最高阈值）
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::services::budget::{BudgetPeriod, UsageBudget};
+use rusqlite::OptionalExtension;
+
+impl Database {
+    /// 获取全部预算（全局预算在前）
+    pub fn get_budgets(&self) -> Result<Vec<UsageBudget>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, name, app_type, period, limit_amount, currency, thresholds,
+                        enabled, created_at, updated_at
+                 FROM usage_budgets
+                 ORDER BY app_type IS NOT NULL, app_type ASC, created_at ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let budgets = stmt
+            .query_map([], |row| {
+                let period: String = row.get(3)?;
+                let thresholds: String = row.get(6)?;
+                Ok(UsageBudget {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    app_type: row.get(2)?,
+                    period: BudgetPeriod::parse(&period).unwrap_or_default(),
+                    limit_amount: row.get(4)?,
+                    currency: row.get(5)?,
+                    thresholds: serde_json::from_str(&thresholds).unwrap_or_default(),
+                    enabled: row.get(7)?,
+                    created_at: row.get(8)?,
+                    updated_at: row.get(9)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(budgets)
+    }
+
+    /// 新增或更新预算
+    ///
+    /// 更新时清空告警记录，按新的限额与阈值重新通知
+    pub fn save_budget(&self, budget: &mut UsageBudget) -> Result<(), AppError> {
+        budget.normalize()?;
+        let now = chrono::Utc::now().timestamp();
+        if budget.created_at == 0 {
+            budget.created_at = now;
+        }
+        budget.updated_at = now;
+        let thresholds = serde_json::to_string(&budget.thresholds)
+            .map_err(|e| AppError::JsonSerialize { source: e })?;
+
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT INTO usage_budgets (
+                id, name, app_type, period, limit_amount, currency, thresholds,
+                enabled, created_at, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                app_type = excluded.app_type,
+                period = excluded.period,
+                limit_amount = excluded.limit_amount,
+                currency = excluded.currency,
+                thresholds = excluded.thresholds,
+                enabled = excluded.enabled,
+                updated_at = excluded.updated_at,
+                alert_period = NULL,
+                alert_threshold = NULL",
+            rusqlite::params![
+                budget.id,
+                budget.name,
+                budget.app_type,
+                budget.period.as_str(),
+                budget.limit_amount,
+                budget.currency,
+                thresholds,
+                budget.enabled,
+                budget.created_at,
+                budget.updated_at,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 删除预算
+    pub fn delete_budget(&self, id: &str) -> Result<bool, AppError> {
+        let conn = lock_conn!(self.conn);
+        let affected = conn
+            .execute("DELETE FROM usage_budgets WHERE id = ?1", [id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(affected > 0)
+    }
+
+    /// 已通知的 (周期, 最高阈值)
+    pub(crate) fn get_budget_alert_state(
+        &self,
+        id: &str,
+    ) -> Result<Option<(String, u32)>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let state: Option<(Option<String>, Option<u32>)> = conn
+            .query_row(
+                "SELECT alert_period, alert_threshold FROM usage_budgets WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(state.and_then(|(period, threshold)| period.zip(threshold)))
+    }
+
+    /// 记录本周期已通知的最高阈值
+    pub(crate) fn record_budget_alert(
+        &self,
+        id: &str,
+        period: &str,
+        threshold: u32,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "UPDATE usage_budgets SET alert_period = ?2, alert_threshold = ?3 WHERE id = ?1",
+            rusqlite::params![id, period, threshold],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+}
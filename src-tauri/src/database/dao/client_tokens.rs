@@ -0,0 +1,260 @@
+//! 客户端令牌 DAO
+//!
+//! 局域网共享代理时签发给客户端的令牌，仅保存哈希
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::client_auth::{ClientToken, ClientTokenUsage};
+use crate::proxy::hedging::HEDGE_CANCELLED_STATUS;
+use rusqlite::OptionalExtension;
+
+const CLIENT_TOKEN_COLUMNS: &str = "id, name, token_prefix, request_limit, cost_limit_usd,
+    quota_period, created_at, last_used_at, revoked_at";
+
+fn row_to_client_token(row: &rusqlite::Row) -> rusqlite::Result<ClientToken> {
+    Ok(ClientToken {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        token_prefix: row.get(2)?,
+        request_limit: row.get::<_, Option<i64>>(3)?.map(|v| v as u64),
+        cost_limit_usd: row.get(4)?,
+        quota_period: row.get(5)?,
+        created_at: row.get(6)?,
+        last_used_at: row.get(7)?,
+        revoked_at: row.get(8)?,
+    })
+}
+
+fn now_secs() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+impl Database {
+    /// 获取全部客户端令牌（含已吊销，按创建时间排序）
+    pub fn list_client_tokens(&self) -> Result<Vec<ClientToken>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {CLIENT_TOKEN_COLUMNS} FROM proxy_client_tokens ORDER BY created_at ASC, id ASC"
+            ))
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let tokens = stmt
+            .query_map([], row_to_client_token)
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(tokens)
+    }
+
+    /// 按 ID 获取客户端令牌
+    pub fn get_client_token(&self, id: &str) -> Result<Option<ClientToken>, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.query_row(
+            &format!("SELECT {CLIENT_TOKEN_COLUMNS} FROM proxy_client_tokens WHERE id = ?1"),
+            [id],
+            row_to_client_token,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 按令牌哈希查找客户端令牌
+    pub fn find_client_token_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<ClientToken>, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.query_row(
+            &format!(
+                "SELECT {CLIENT_TOKEN_COLUMNS} FROM proxy_client_tokens WHERE token_hash = ?1"
+            ),
+            [token_hash],
+            row_to_client_token,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 新增客户端令牌
+    pub fn insert_client_token(
+        &self,
+        token: &ClientToken,
+        token_hash: &str,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT INTO proxy_client_tokens (
+                id, name, token_hash, token_prefix, request_limit, cost_limit_usd,
+                quota_period, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                token.id,
+                token.name,
+                token_hash,
+                token.token_prefix,
+                token.request_limit.map(|v| v as i64),
+                token.cost_limit_usd,
+                token.quota_period,
+                token.created_at,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 更新令牌名称与配额（不影响令牌本身与吊销状态）
+    pub fn update_client_token(&self, token: &ClientToken) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        let affected = conn
+            .execute(
+                "UPDATE proxy_client_tokens
+                 SET name = ?2, request_limit = ?3, cost_limit_usd = ?4, quota_period = ?5
+                 WHERE id = ?1",
+                rusqlite::params![
+                    token.id,
+                    token.name,
+                    token.request_limit.map(|v| v as i64),
+                    token.cost_limit_usd,
+                    token.quota_period,
+                ],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if affected == 0 {
+            return Err(AppError::Database(format!(
+                "客户端令牌不存在: {}",
+                token.id
+            )));
+        }
+        Ok(())
+    }
+
+    /// 吊销客户端令牌（保留记录以便用量归属）
+    pub fn revoke_client_token(&self, id: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "UPDATE proxy_client_tokens SET revoked_at = ?2 WHERE id = ?1 AND revoked_at IS NULL",
+            rusqlite::params![id, now_secs()],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 记录令牌最近使用时间
+    pub fn touch_client_token(&self, id: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "UPDATE proxy_client_tokens SET last_used_at = ?2 WHERE id = ?1",
+            rusqlite::params![id, now_secs()],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 统计令牌自 since 起的用量
+    ///
+    /// 被取消的对冲请求由代理发起，既不计入请求数也不计入花费
+    pub fn get_client_token_usage(
+        &self,
+        id: &str,
+        since: i64,
+    ) -> Result<ClientTokenUsage, AppError> {
+        let conn = lock_conn!(self.conn);
+        let (requests, cost): (i64, f64) = conn
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
+                 FROM proxy_request_logs
+                 WHERE client_token_id = ?1 AND created_at >= ?2 AND status_code != ?3",
+                rusqlite::params![id, since, HEDGE_CANCELLED_STATUS as i64],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(ClientTokenUsage {
+            requests: requests as u64,
+            cost_usd: format!("{cost:.6}"),
+            period_start: since,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Database;
+    use crate::proxy::client_auth::{hash_secret, ClientToken};
+
+    fn token(id: &str) -> ClientToken {
+        ClientToken {
+            id: id.to_string(),
+            name: format!("client {id}"),
+            token_prefix: "ccs-abcdefgh".to_string(),
+            request_limit: Some(100),
+            cost_limit_usd: Some("5".to_string()),
+            quota_period: "daily".to_string(),
+            created_at: 1000,
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn test_client_token_lifecycle() {
+        let db = Database::memory().unwrap();
+        db.insert_client_token(&token("t1"), &hash_secret("ccs-secret"))
+            .unwrap();
+
+        let found = db
+            .find_client_token_by_hash(&hash_secret("ccs-secret"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, "t1");
+        assert_eq!(found.request_limit, Some(100));
+        assert!(db
+            .find_client_token_by_hash(&hash_secret("ccs-other"))
+            .unwrap()
+            .is_none());
+
+        let mut updated = found.clone();
+        updated.name = "renamed".to_string();
+        updated.request_limit = None;
+        db.update_client_token(&updated).unwrap();
+        let reloaded = db.get_client_token("t1").unwrap().unwrap();
+        assert_eq!(reloaded.name, "renamed");
+        assert_eq!(reloaded.request_limit, None);
+
+        db.revoke_client_token("t1").unwrap();
+        let revoked = db.get_client_token("t1").unwrap().unwrap();
+        assert!(revoked.revoked_at.is_some());
+        assert_eq!(db.list_client_tokens().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_client_token_usage() {
+        let db = Database::memory().unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            let rows = [
+                ("r1", "t1", 200, "1.5", 2000),
+                ("r2", "t1", 500, "0", 2000),
+                ("r3", "t1", 499, "0.7", 2000),
+                ("r4", "t1", 200, "9", 100),
+                ("r5", "t2", 200, "3", 2000),
+            ];
+            for (id, client, status, cost, created_at) in rows {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                        latency_ms, status_code, total_cost_usd, client_token_id, created_at)
+                     VALUES (?1, 'p1', 'claude', 'm', 10, ?2, ?3, ?4, ?5)",
+                    rusqlite::params![id, status, cost, client, created_at],
+                )
+                .unwrap();
+            }
+        }
+
+        let usage = db.get_client_token_usage("t1", 1000).unwrap();
+        assert_eq!(usage.requests, 2);
+        assert_eq!(usage.cost_usd, "1.500000");
+        assert_eq!(usage.period_start, 1000);
+    }
+}
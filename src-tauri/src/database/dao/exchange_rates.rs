@@ -0,0 +1,161 @@
+//! 汇率表 DAO
+//!
+//! 手动维护或从文件导入，汇率以“1 美元可兑换的该币种数量”存储
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::services::currency::{
+    find_exchange_rate, normalize_currency, parse_rate, ExchangeRate, BASE_CURRENCY,
+};
+use rust_decimal::Decimal;
+
+impl Database {
+    /// 获取全部汇率（按币种排序）
+    pub fn get_exchange_rates(&self) -> Result<Vec<ExchangeRate>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT currency, rate_per_usd, source, updated_at
+                 FROM exchange_rates
+                 ORDER BY currency ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rates = stmt
+            .query_map([], |row| {
+                Ok(ExchangeRate {
+                    currency: row.get(0)?,
+                    rate_per_usd: row.get(1)?,
+                    source: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rates)
+    }
+
+    /// 获取币种对美元的汇率（USD 恒为 1，未配置时返回 None）
+    pub fn get_exchange_rate(&self, currency: &str) -> Result<Option<Decimal>, AppError> {
+        let conn = lock_conn!(self.conn);
+        find_exchange_rate(&conn, currency)
+    }
+
+    /// 新增或更新汇率
+    pub fn save_exchange_rate(
+        &self,
+        currency: &str,
+        rate_per_usd: &str,
+        source: Option<&str>,
+    ) -> Result<ExchangeRate, AppError> {
+        let currency = normalize_currency(currency)?;
+        if currency == BASE_CURRENCY {
+            return Err(AppError::localized(
+                "error.baseCurrencyRate",
+                "USD 为基准币种，汇率固定为 1",
+                "USD is the base currency and always has a rate of 1",
+            ));
+        }
+        let rate = parse_rate(rate_per_usd)?;
+
+        let conn = lock_conn!(self.conn);
+        Self::upsert_exchange_rate(&conn, &currency, rate, source)
+    }
+
+    /// 从汇率文件批量导入（JSON / CSV），返回导入后的记录
+    pub fn import_exchange_rates(
+        &self,
+        content: &str,
+        source: &str,
+    ) -> Result<Vec<ExchangeRate>, AppError> {
+        let entries = crate::services::currency::parse_exchange_rate_file(content)?;
+
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        let imported = entries
+            .iter()
+            .map(|(currency, rate)| Self::upsert_exchange_rate(&tx, currency, *rate, Some(source)))
+            .collect::<Result<Vec<_>, _>>()?;
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(imported)
+    }
+
+    /// 删除汇率
+    pub fn delete_exchange_rate(&self, currency: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "DELETE FROM exchange_rates WHERE currency = ?1",
+            [currency.to_ascii_uppercase()],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    fn upsert_exchange_rate(
+        conn: &rusqlite::Connection,
+        currency: &str,
+        rate: Decimal,
+        source: Option<&str>,
+    ) -> Result<ExchangeRate, AppError> {
+        let rate = ExchangeRate {
+            currency: currency.to_string(),
+            rate_per_usd: rate.normalize().to_string(),
+            source: source.map(str::to_string),
+            updated_at: chrono::Utc::now().timestamp(),
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO exchange_rates (currency, rate_per_usd, source, updated_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                rate.currency,
+                rate.rate_per_usd,
+                rate.source,
+                rate.updated_at
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Database;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_exchange_rate_crud_and_import() {
+        let db = Database::memory().unwrap();
+
+        let saved = db.save_exchange_rate("cny", "7.20", None).unwrap();
+        assert_eq!(saved.currency, "CNY");
+        assert_eq!(saved.rate_per_usd, "7.2");
+        assert!(db.save_exchange_rate("USD", "1", None).is_err());
+        assert!(db.save_exchange_rate("EUR", "0", None).is_err());
+
+        let imported = db
+            .import_exchange_rates("currency,rate\nCNY,7.1\nEUR,0.9\n", "rates.csv")
+            .unwrap();
+        assert_eq!(imported.len(), 2);
+
+        let rates = db.get_exchange_rates().unwrap();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].currency, "CNY");
+        assert_eq!(rates[0].source.as_deref(), Some("rates.csv"));
+        assert_eq!(
+            db.get_exchange_rate("cny").unwrap(),
+            Some(Decimal::from_str("7.1").unwrap())
+        );
+        assert_eq!(db.get_exchange_rate("USD").unwrap(), Some(Decimal::ONE));
+
+        db.delete_exchange_rate("eur").unwrap();
+        assert_eq!(db.get_exchange_rate("EUR").unwrap(), None);
+    }
+}
@@ -0,0 +1,137 @@
+//! 请求/响应钩子 DAO
+//!
+//! 每个 app 独立的 JS 钩子列表，按 sort_index 顺序执行
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::hooks::ProxyHook;
+
+impl Database {
+    /// 获取指定 app 的钩子（按 sort_index 排序）
+    pub fn get_proxy_hooks(&self, app_type: &str) -> Result<Vec<ProxyHook>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, app_type, name, enabled, sort_index, provider_id, script, timeout_ms
+                 FROM proxy_hooks
+                 WHERE app_type = ?1
+                 ORDER BY sort_index ASC, id ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let hooks = stmt
+            .query_map([app_type], |row| {
+                Ok(ProxyHook {
+                    id: row.get(0)?,
+                    app_type: row.get(1)?,
+                    name: row.get(2)?,
+                    enabled: row.get(3)?,
+                    sort_index: row.get(4)?,
+                    provider_id: row.get(5)?,
+                    script: row.get(6)?,
+                    timeout_ms: row.get::<_, i64>(7)? as u64,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(hooks)
+    }
+
+    /// 新增或更新钩子
+    pub fn save_proxy_hook(&self, hook: &ProxyHook) -> Result<(), AppError> {
+        if hook.name.trim().is_empty() || hook.script.trim().is_empty() {
+            return Err(AppError::localized(
+                "error.invalidProxyHook",
+                "钩子必须包含名称和脚本",
+                "Hook requires a name and a script",
+            ));
+        }
+
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT OR REPLACE INTO proxy_hooks (
+                id, app_type, name, enabled, sort_index, provider_id, script, timeout_ms
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                hook.id,
+                hook.app_type,
+                hook.name,
+                hook.enabled,
+                hook.sort_index,
+                hook.provider_id.as_deref().filter(|id| !id.is_empty()),
+                hook.script,
+                hook.timeout_ms as i64,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 删除钩子
+    pub fn delete_proxy_hook(&self, id: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute("DELETE FROM proxy_hooks WHERE id = ?1", [id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Database;
+    use crate::provider::Provider;
+    use crate::proxy::hooks::ProxyHook;
+    use serde_json::json;
+
+    fn hook(id: &str, sort_index: i64, provider_id: Option<&str>) -> ProxyHook {
+        ProxyHook {
+            id: id.to_string(),
+            app_type: "claude".to_string(),
+            name: format!("hook {id}"),
+            enabled: true,
+            sort_index,
+            provider_id: provider_id.map(str::to_string),
+            script: "function onRequest(req) { return req; }".to_string(),
+            timeout_ms: 50,
+        }
+    }
+
+    #[test]
+    fn test_proxy_hooks_crud_and_cascade() {
+        let db = Database::memory().unwrap();
+        let provider = Provider::with_id("relay".to_string(), "Relay".to_string(), json!({}), None);
+        db.save_provider("claude", &provider).unwrap();
+
+        db.save_proxy_hook(&hook("b", 2, None)).unwrap();
+        db.save_proxy_hook(&hook("a", 1, Some("relay"))).unwrap();
+
+        let hooks = db.get_proxy_hooks("claude").unwrap();
+        assert_eq!(hooks.len(), 2);
+        assert_eq!(hooks[0].id, "a");
+        assert_eq!(hooks[0].provider_id.as_deref(), Some("relay"));
+        assert_eq!(hooks[0].timeout_ms, 50);
+        assert!(hooks[1].provider_id.is_none());
+        assert!(db.get_proxy_hooks("codex").unwrap().is_empty());
+
+        // 删除供应商时级联删除限定到该供应商的钩子，app 级钩子保留
+        db.delete_provider("claude", "relay").unwrap();
+        let hooks = db.get_proxy_hooks("claude").unwrap();
+        assert_eq!(hooks.len(), 1);
+        assert_eq!(hooks[0].id, "b");
+
+        db.delete_proxy_hook("b").unwrap();
+        assert!(db.get_proxy_hooks("claude").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_save_proxy_hook_validation() {
+        let db = Database::memory().unwrap();
+        let mut invalid = hook("x", 0, None);
+        invalid.script = "  ".to_string();
+        assert!(db.save_proxy_hook(&invalid).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Mock 上游 fixture DAO
+//!
+//! 每个 app 独立的 fixture 列表，按 sort_index 顺序匹配；可从请求日志录制
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::mock_upstream::MockFixture;
+use rusqlite::OptionalExtension;
+
+impl Database {
+    /// 获取指定 app 的 fixture（按 sort_index 排序）
+    pub fn get_mock_fixtures(&self, app_type: &str) -> Result<Vec<MockFixture>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, app_type, name, enabled, sort_index, model_pattern, prompt_contains,
+                        status_code, response_body, source_request_id
+                 FROM mock_fixtures
+                 WHERE app_type = ?1
+                 ORDER BY sort_index ASC, id ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let fixtures = stmt
+            .query_map([app_type], |row| {
+                Ok(MockFixture {
+                    id: row.get(0)?,
+                    app_type: row.get(1)?,
+                    name: row.get(2)?,
+                    enabled: row.get(3)?,
+                    sort_index: row.get(4)?,
+                    model_pattern: row.get(5)?,
+                    prompt_contains: row.get(6)?,
+                    status_code: row.get(7)?,
+                    response_body: row.get(8)?,
+                    source_request_id: row.get(9)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(fixtures)
+    }
+
+    /// 新增或更新 fixture
+    pub fn save_mock_fixture(&self, fixture: &MockFixture) -> Result<(), AppError> {
+        if fixture.name.trim().is_empty() || !(100..600).contains(&fixture.status_code) {
+            return Err(AppError::localized(
+                "error.invalidMockFixture",
+                "Mock fixture 必须包含名称和有效的状态码",
+                "Mock fixture requires a name and a valid status code",
+            ));
+        }
+
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT OR REPLACE INTO mock_fixtures (
+                id, app_type, name, enabled, sort_index, model_pattern, prompt_contains,
+                status_code, response_body, source_request_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![
+                fixture.id,
+                fixture.app_type,
+                fixture.name,
+                fixture.enabled,
+                fixture.sort_index,
+                fixture.model_pattern.as_deref().filter(|p| !p.is_empty()),
+                fixture.prompt_contains.as_deref().filter(|p| !p.is_empty()),
+                fixture.status_code,
+                fixture.response_body,
+                fixture.source_request_id,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 删除 fixture
+    pub fn delete_mock_fixture(&self, id: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute("DELETE FROM mock_fixtures WHERE id = ?1", [id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 从请求日志录制 fixture（模型精确匹配，排在该 app 现有 fixture 之后）
+    pub fn capture_mock_fixture(
+        &self,
+        request_id: &str,
+        id: &str,
+        name: &str,
+    ) -> Result<MockFixture, AppError> {
+        let fixture = {
+            let conn = lock_conn!(self.conn);
+            let source = conn
+                .query_row(
+                    "SELECT app_type, model, status_code, response_body, error_message
+                     FROM proxy_request_logs WHERE request_id = ?1",
+                    [request_id],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, u16>(2)?,
+                            row.get::<_, Option<String>>(3)?,
+                            row.get::<_, Option<String>>(4)?,
+                        ))
+                    },
+                )
+                .optional()
+                .map_err(|e| AppError::Database(e.to_string()))?;
+
+            let Some((app_type, model, status_code, response_body, error_message)) = source else {
+                return Err(AppError::localized(
+                    "error.mockFixtureSourceNotFound",
+                    format!("请求日志不存在: {request_id}"),
+                    format!("Request log not found: {request_id}"),
+                ));
+            };
+            let Some(response_body) = response_body.or(error_message) else {
+                return Err(AppError::localized(
+                    "error.mockFixtureSourceEmpty",
+                    "该请求日志未记录响应体，无法录制",
+                    "The request log has no recorded response body",
+                ));
+            };
+
+            let sort_index: i64 = conn
+                .query_row(
+                    "SELECT COALESCE(MAX(sort_index) + 1, 0) FROM mock_fixtures WHERE app_type = ?1",
+                    [&app_type],
+                    |row| row.get(0),
+                )
+                .map_err(|e| AppError::Database(e.to_string()))?;
+
+            MockFixture {
+                id: id.to_string(),
+                app_type,
+                name: name.to_string(),
+                enabled: true,
+                sort_index,
+                model_pattern: Some(model),
+                prompt_contains: None,
+                status_code,
+                response_body,
+                source_request_id: Some(request_id.to_string()),
+            }
+        };
+
+        self.save_mock_fixture(&fixture)?;
+        Ok(fixture)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Database;
+
+    #[test]
+    fn test_capture_and_crud_mock_fixtures() {
+        let db = Database::memory().unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                    latency_ms, status_code, response_body, created_at)
+                 VALUES ('r1', 'p1', 'claude', 'claude-sonnet-4', 10, 200, '{\"type\":\"message\"}', 1)",
+                [],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                    latency_ms, status_code, error_message, created_at)
+                 VALUES ('r2', 'p1', 'claude', 'claude-sonnet-4', 10, 429, 'rate limited', 2)",
+                [],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                    latency_ms, status_code, created_at)
+                 VALUES ('r3', 'p1', 'claude', 'm', 10, 200, 3)",
+                [],
+            )
+            .unwrap();
+        }
+
+        let first = db.capture_mock_fixture("r1", "f1", "ok").unwrap();
+        assert_eq!(first.model_pattern.as_deref(), Some("claude-sonnet-4"));
+        assert_eq!(first.sort_index, 0);
+
+        let second = db.capture_mock_fixture("r2", "f2", "429").unwrap();
+        assert_eq!(second.status_code, 429);
+        assert_eq!(second.response_body, "rate limited");
+        assert_eq!(second.sort_index, 1);
+
+        assert!(db.capture_mock_fixture("r3", "f3", "empty").is_err());
+        assert!(db.capture_mock_fixture("missing", "f4", "x").is_err());
+
+        let fixtures = db.get_mock_fixtures("claude").unwrap();
+        assert_eq!(fixtures.len(), 2);
+        assert_eq!(fixtures[0].source_request_id.as_deref(), Some("r1"));
+        assert!(db.get_mock_fixtures("codex").unwrap().is_empty());
+
+        db.delete_mock_fixture("f1").unwrap();
+        assert_eq!(db.get_mock_fixtures("claude").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_save_mock_fixture_validation() {
+        let db = Database::memory().unwrap();
+        let mut fixture = crate::proxy::mock_upstream::MockFixture {
+            id: "x".to_string(),
+            app_type: "claude".to_string(),
+            name: "x".to_string(),
+            enabled: true,
+            sort_index: 0,
+            model_pattern: None,
+            prompt_contains: None,
+            status_code: 200,
+            response_body: "hi".to_string(),
+            source_request_id: None,
+        };
+        assert!(db.save_mock_fixture(&fixture).is_ok());
+        fixture.status_code = 42;
+        assert!(db.save_mock_fixture(&fixture).is_err());
+    }
+}
@@ -2,12 +2,18 @@
 //!
 //! Database access operations for each domain
 
+pub mod budgets;
+pub mod client_tokens;
+pub mod exchange_rates;
 pub mod failover;
+pub mod hooks;
 pub mod mcp;
+pub mod mock_fixtures;
 pub mod omo;
 pub mod prompts;
 pub mod providers;
 pub mod proxy;
+pub mod routing_rules;
 pub mod settings;
 pub mod skills;
 pub mod stream_check;
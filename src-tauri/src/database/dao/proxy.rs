@@ -187,6 +187,63 @@ impl Database {
         Ok(())
     }
 
+    /// 获取负载均衡策略（仅在故障转移开启时生效）
+    pub async fn get_load_balance_strategy(
+        &self,
+        app_type: &str,
+    ) -> Result<LoadBalanceStrategy, AppError> {
+        let result: Result<String, rusqlite::Error> = {
+            let conn = lock_conn!(self.conn);
+            conn.query_row(
+                "SELECT load_balance_strategy FROM proxy_config WHERE app_type = ?1",
+                [app_type],
+                |row| row.get(0),
+            )
+        };
+
+        match result {
+            Ok(value) => Ok(value.parse().unwrap_or_else(|_| {
+                log::warn!("[{app_type}] 无效的负载均衡策略: {value}，回退到 priority");
+                LoadBalanceStrategy::Priority
+            })),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                self.init_proxy_config_rows().await?;
+                Ok(LoadBalanceStrategy::Priority)
+            }
+            Err(e) => Err(AppError::Database(e.to_string())),
+        }
+    }
+
+    /// 设置负载均衡策略
+    pub async fn set_load_balance_strategy(
+        &self,
+        app_type: &str,
+        value: &str,
+    ) -> Result<(), AppError> {
+        let strategy: LoadBalanceStrategy = value.trim().parse().map_err(|_| {
+            AppError::localized(
+                "error.invalidLoadBalanceStrategy",
+                format!("无效负载均衡策略: {value}"),
+                format!("Invalid load balance strategy: {value}"),
+            )
+        })?;
+
+        // 确保行存在
+        self.ensure_proxy_config_row_exists(app_type)?;
+
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "UPDATE proxy_config SET
+                load_balance_strategy = ?2,
+                updated_at = datetime('now')
+             WHERE app_type = ?1",
+            rusqlite::params![app_type, strategy.as_str()],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
     /// 获取应用级代理配置
     pub async fn get_proxy_config_for_app(
         &self,
@@ -913,4 +970,37 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_load_balance_strategy_round_trip_and_validation() -> Result<(), AppError> {
+        use crate::proxy::types::LoadBalanceStrategy;
+
+        let db = Database::memory()?;
+
+        let default = db.get_load_balance_strategy("claude").await?;
+        assert_eq!(default, LoadBalanceStrategy::Priority);
+
+        db.set_load_balance_strategy("claude", "least_latency")
+            .await?;
+        let updated = db.get_load_balance_strategy("claude").await?;
+        assert_eq!(updated, LoadBalanceStrategy::LeastLatency);
+
+        // 其他应用不受影响
+        let codex = db.get_load_balance_strategy("codex").await?;
+        assert_eq!(codex, LoadBalanceStrategy::Priority);
+
+        let err = db
+            .set_load_balance_strategy("claude", "random")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::Localized {
+                key: "error.invalidLoadBalanceStrategy",
+                ..
+            }
+        ));
+
+        Ok(())
+    }
 }
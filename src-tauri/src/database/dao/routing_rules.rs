@@ -0,0 +1,170 @@
+//! 模型路由规则 DAO
+//!
+//! 每个 app 独立的路由规则表，按 sort_index 顺序匹配
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::routing_rules::RoutingRule;
+
+impl Database {
+    /// 获取指定 app 的路由规则（按 sort_index 排序）
+    pub fn get_routing_rules(&self, app_type: &str) -> Result<Vec<RoutingRule>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, app_type, name, enabled, sort_index, model_pattern, thinking,
+                        min_request_bytes, max_request_bytes, header_name, header_value,
+                        provider_id, target_model
+                 FROM routing_rules
+                 WHERE app_type = ?1
+                 ORDER BY sort_index ASC, id ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rules = stmt
+            .query_map([app_type], |row| {
+                Ok(RoutingRule {
+                    id: row.get(0)?,
+                    app_type: row.get(1)?,
+                    name: row.get(2)?,
+                    enabled: row.get(3)?,
+                    sort_index: row.get(4)?,
+                    model_pattern: row.get(5)?,
+                    thinking: row.get(6)?,
+                    min_request_bytes: row.get::<_, Option<i64>>(7)?.map(|v| v as u64),
+                    max_request_bytes: row.get::<_, Option<i64>>(8)?.map(|v| v as u64),
+                    header_name: row.get(9)?,
+                    header_value: row.get(10)?,
+                    provider_id: row.get(11)?,
+                    target_model: row.get(12)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rules)
+    }
+
+    /// 新增或更新路由规则
+    pub fn save_routing_rule(&self, rule: &RoutingRule) -> Result<(), AppError> {
+        if rule.name.trim().is_empty() || rule.provider_id.trim().is_empty() {
+            return Err(AppError::localized(
+                "error.invalidRoutingRule",
+                "路由规则必须包含名称和目标供应商",
+                "Routing rule requires a name and a target provider",
+            ));
+        }
+        if let (Some(min), Some(max)) = (rule.min_request_bytes, rule.max_request_bytes) {
+            if min > max {
+                return Err(AppError::localized(
+                    "error.invalidRoutingRule",
+                    "请求大小下限不能大于上限",
+                    "Minimum request size cannot exceed the maximum",
+                ));
+            }
+        }
+
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT OR REPLACE INTO routing_rules (
+                id, app_type, name, enabled, sort_index, model_pattern, thinking,
+                min_request_bytes, max_request_bytes, header_name, header_value,
+                provider_id, target_model
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            rusqlite::params![
+                rule.id,
+                rule.app_type,
+                rule.name,
+                rule.enabled,
+                rule.sort_index,
+                rule.model_pattern,
+                rule.thinking,
+                rule.min_request_bytes.map(|v| v as i64),
+                rule.max_request_bytes.map(|v| v as i64),
+                rule.header_name,
+                rule.header_value,
+                rule.provider_id,
+                rule.target_model,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 删除路由规则
+    pub fn delete_routing_rule(&self, id: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute("DELETE FROM routing_rules WHERE id = ?1", [id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Database;
+    use crate::provider::Provider;
+    use crate::proxy::routing_rules::RoutingRule;
+    use serde_json::json;
+
+    fn rule(id: &str, sort_index: i64) -> RoutingRule {
+        RoutingRule {
+            id: id.to_string(),
+            app_type: "claude".to_string(),
+            name: format!("rule {id}"),
+            enabled: true,
+            sort_index,
+            model_pattern: Some("*haiku*".to_string()),
+            thinking: Some(false),
+            min_request_bytes: None,
+            max_request_bytes: Some(4096),
+            header_name: None,
+            header_value: None,
+            provider_id: "cheap".to_string(),
+            target_model: Some("glm-4.5-air".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_routing_rules_crud_and_cascade() {
+        let db = Database::memory().unwrap();
+        let provider = Provider::with_id("cheap".to_string(), "Cheap".to_string(), json!({}), None);
+        db.save_provider("claude", &provider).unwrap();
+
+        db.save_routing_rule(&rule("b", 2)).unwrap();
+        db.save_routing_rule(&rule("a", 1)).unwrap();
+
+        let rules = db.get_routing_rules("claude").unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].id, "a");
+        assert_eq!(rules[0].thinking, Some(false));
+        assert_eq!(rules[0].max_request_bytes, Some(4096));
+        assert_eq!(rules[0].target_model.as_deref(), Some("glm-4.5-air"));
+        assert!(db.get_routing_rules("codex").unwrap().is_empty());
+
+        db.delete_routing_rule("a").unwrap();
+        assert_eq!(db.get_routing_rules("claude").unwrap().len(), 1);
+
+        // 删除供应商时级联删除规则
+        db.delete_provider("claude", "cheap").unwrap();
+        assert!(db.get_routing_rules("claude").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_save_routing_rule_validation() {
+        let db = Database::memory().unwrap();
+        let provider = Provider::with_id("cheap".to_string(), "Cheap".to_string(), json!({}), None);
+        db.save_provider("claude", &provider).unwrap();
+
+        let mut invalid = rule("x", 0);
+        invalid.min_request_bytes = Some(10_000);
+        assert!(db.save_routing_rule(&invalid).is_err());
+
+        let mut invalid = rule("y", 0);
+        invalid.provider_id = String::new();
+        assert!(db.save_routing_rule(&invalid).is_err());
+    }
+}
@@ -98,6 +98,50 @@ impl Database {
         }
     }
 
+    // --- 上游 TLS 配置 ---
+
+    /// 获取全局上游 TLS 配置（额外 CA / mTLS 客户端证书）
+    pub fn get_upstream_tls_config(&self) -> Result<crate::provider::UpstreamTlsConfig, AppError> {
+        match self.get_setting("upstream_tls_config")? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析上游 TLS 配置失败: {e}"))),
+            None => Ok(crate::provider::UpstreamTlsConfig::default()),
+        }
+    }
+
+    /// 更新全局上游 TLS 配置
+    pub fn set_upstream_tls_config(
+        &self,
+        config: &crate::provider::UpstreamTlsConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化上游 TLS 配置失败: {e}")))?;
+        self.set_setting("upstream_tls_config", &json)
+    }
+
+    // --- 上游代理规则 ---
+
+    /// 获取上游代理规则（规则列表 / PAC）
+    pub fn get_upstream_proxy_rules(
+        &self,
+    ) -> Result<crate::proxy::upstream_rules::UpstreamProxyRules, AppError> {
+        match self.get_setting("upstream_proxy_rules")? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析上游代理规则失败: {e}"))),
+            None => Ok(crate::proxy::upstream_rules::UpstreamProxyRules::default()),
+        }
+    }
+
+    /// 更新上游代理规则
+    pub fn set_upstream_proxy_rules(
+        &self,
+        rules: &crate::proxy::upstream_rules::UpstreamProxyRules,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(rules)
+            .map_err(|e| AppError::Database(format!("序列化上游代理规则失败: {e}")))?;
+        self.set_setting("upstream_proxy_rules", &json)
+    }
+
     // --- 代理接管状态管理（已废弃，使用 proxy_config.enabled 替代）---
 
     /// 获取指定应用的代理接管状态
@@ -187,6 +231,163 @@ impl Database {
         self.set_setting("rectifier_config", &json)
     }
 
+    // --- 会话粘性路由配置 ---
+
+    /// 获取会话粘性路由配置
+    pub fn get_session_affinity_config(
+        &self,
+    ) -> Result<crate::proxy::types::SessionAffinityConfig, AppError> {
+        match self.get_setting("session_affinity_config")? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析会话粘性配置失败: {e}"))),
+            None => Ok(crate::proxy::types::SessionAffinityConfig::default()),
+        }
+    }
+
+    /// 更新会话粘性路由配置
+    pub fn set_session_affinity_config(
+        &self,
+        config: &crate::proxy::types::SessionAffinityConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化会话粘性配置失败: {e}")))?;
+        self.set_setting("session_affinity_config", &json)
+    }
+
+    // --- 上下文超长回退配置 ---
+
+    /// 获取上下文超长回退配置
+    pub fn get_context_overflow_config(
+        &self,
+    ) -> Result<crate::proxy::types::ContextOverflowConfig, AppError> {
+        match self.get_setting("context_overflow_config")? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析上下文超长回退配置失败: {e}"))),
+            None => Ok(crate::proxy::types::ContextOverflowConfig::default()),
+        }
+    }
+
+    /// 更新上下文超长回退配置
+    pub fn set_context_overflow_config(
+        &self,
+        config: &crate::proxy::types::ContextOverflowConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化上下文超长回退配置失败: {e}")))?;
+        self.set_setting("context_overflow_config", &json)
+    }
+
+    // --- 对冲请求配置 ---
+
+    /// 获取对冲请求配置
+    pub fn get_hedging_config(&self) -> Result<crate::proxy::types::HedgingConfig, AppError> {
+        match self.get_setting("hedging_config")? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析对冲请求配置失败: {e}"))),
+            None => Ok(crate::proxy::types::HedgingConfig::default()),
+        }
+    }
+
+    /// 更新对冲请求配置
+    pub fn set_hedging_config(
+        &self,
+        config: &crate::proxy::types::HedgingConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化对冲请求配置失败: {e}")))?;
+        self.set_setting("hedging_config", &json)
+    }
+
+    // --- 响应缓存配置 ---
+
+    /// 获取响应缓存配置
+    pub fn get_response_cache_config(
+        &self,
+    ) -> Result<crate::proxy::types::ResponseCacheConfig, AppError> {
+        match self.get_setting("response_cache_config")? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析响应缓存配置失败: {e}"))),
+            None => Ok(crate::proxy::types::ResponseCacheConfig::default()),
+        }
+    }
+
+    /// 更新响应缓存配置
+    pub fn set_response_cache_config(
+        &self,
+        config: &crate::proxy::types::ResponseCacheConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化响应缓存配置失败: {e}")))?;
+        self.set_setting("response_cache_config", &json)
+    }
+
+    // --- 密钥扫描配置 ---
+
+    /// 获取出站请求密钥扫描配置
+    pub fn get_secret_scan_config(
+        &self,
+    ) -> Result<crate::proxy::types::SecretScanConfig, AppError> {
+        match self.get_setting("secret_scan_config")? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析密钥扫描配置失败: {e}"))),
+            None => Ok(crate::proxy::types::SecretScanConfig::default()),
+        }
+    }
+
+    /// 更新出站请求密钥扫描配置
+    pub fn set_secret_scan_config(
+        &self,
+        config: &crate::proxy::types::SecretScanConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化密钥扫描配置失败: {e}")))?;
+        self.set_setting("secret_scan_config", &json)
+    }
+
+    // --- 客户端认证配置 ---
+
+    /// 获取客户端认证配置
+    pub fn get_client_auth_config(
+        &self,
+    ) -> Result<crate::proxy::types::ClientAuthConfig, AppError> {
+        match self.get_setting("client_auth_config")? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析客户端认证配置失败: {e}"))),
+            None => Ok(crate::proxy::types::ClientAuthConfig::default()),
+        }
+    }
+
+    /// 更新客户端认证配置
+    pub fn set_client_auth_config(
+        &self,
+        config: &crate::proxy::types::ClientAuthConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化客户端认证配置失败: {e}")))?;
+        self.set_setting("client_auth_config", &json)
+    }
+
+    // --- 代理 TLS 配置 ---
+
+    /// 获取代理监听 TLS 配置
+    pub fn get_proxy_tls_config(&self) -> Result<crate::proxy::types::ProxyTlsConfig, AppError> {
+        match self.get_setting("proxy_tls_config")? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析代理 TLS 配置失败: {e}"))),
+            None => Ok(crate::proxy::types::ProxyTlsConfig::default()),
+        }
+    }
+
+    /// 更新代理监听 TLS 配置
+    pub fn set_proxy_tls_config(
+        &self,
+        config: &crate::proxy::types::ProxyTlsConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化代理 TLS 配置失败: {e}")))?;
+        self.set_setting("proxy_tls_config", &json)
+    }
+
     // --- 日志配置 ---
 
     /// 获取日志配置
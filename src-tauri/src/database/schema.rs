@@ -6,6 +6,111 @@ use super::{lock_conn, Database, SCHEMA_VERSION};
 use crate::error::AppError;
 use rusqlite::Connection;
 
+/// usage_hourly_rollups 的写入列顺序（触发器与回填共用）
+const ROLLUP_COLUMNS: &str = "bucket_start, app_type, provider_id, model, status_class, source,
+    billing_currency, request_count, input_tokens, output_tokens, cache_read_tokens,
+    cache_creation_tokens, total_cost_usd, billing_cost, latency_ms";
+
+/// 小时汇总按主键累加的冲突处理子句
+const ROLLUP_UPSERT_SQL: &str =
+    "ON CONFLICT(bucket_start, app_type, provider_id, model, status_class, source, billing_currency)
+     DO UPDATE SET
+         request_count = request_count + excluded.request_count,
+         input_tokens = input_tokens + excluded.input_tokens,
+         output_tokens = output_tokens + excluded.output_tokens,
+         cache_read_tokens = cache_read_tokens + excluded.cache_read_tokens,
+         cache_creation_tokens = cache_creation_tokens + excluded.cache_creation_tokens,
+         total_cost_usd = total_cost_usd + excluded.total_cost_usd,
+         billing_cost = billing_cost + excluded.billing_cost,
+         latency_ms = latency_ms + excluded.latency_ms";
+
+/// 单条请求日志在小时汇总中的取值（`row` 为行引用，`sign` 为空或 `-`）
+fn rollup_values(row: &str, sign: &str) -> String {
+    format!(
+        "({row}.created_at / 3600) * 3600, {row}.app_type, {row}.provider_id,
+         {row}.model, {row}.status_code / 100, {row}.source,
+         COALESCE({row}.billing_currency, ''),
+         {sign}1, {sign}{row}.input_tokens, {sign}{row}.output_tokens,
+         {sign}{row}.cache_read_tokens, {sign}{row}.cache_creation_tokens,
+         {sign}CAST({row}.total_cost_usd AS REAL),
+         {sign}COALESCE(CAST({row}.billing_cost AS REAL), 0),
+         {sign}{row}.latency_ms"
+    )
+}
+
+/// model_pricing 表结构（v13+）
+///
+/// - effective_from / effective_until：生效时间段 [from, until)，Unix 秒；0 / NULL 表示不限
+/// - tiers：按输入规模分档的价格（JSON 数组，见 `PricingTier`）
+const MODEL_PRICING_TABLE_SQL: &str = "CREATE TABLE IF NOT EXISTS model_pricing (
+    model_id TEXT NOT NULL, display_name TEXT NOT NULL,
+    input_cost_per_million TEXT NOT NULL, output_cost_per_million TEXT NOT NULL,
+    cache_read_cost_per_million TEXT NOT NULL DEFAULT '0',
+    cache_creation_cost_per_million TEXT NOT NULL DEFAULT '0',
+    effective_from INTEGER NOT NULL DEFAULT 0,
+    effective_until INTEGER,
+    tiers TEXT,
+    PRIMARY KEY (model_id, effective_from)
+)";
+
+/// usage_budgets 表结构（v17+）
+///
+/// - app_type：NULL 表示全局预算，否则只统计该应用
+/// - thresholds：告警阈值百分比（JSON 数组，如 `[50,80,100]`）
+/// - alert_period / alert_threshold：已通知的周期与该周期内已越过的最高阈值，避免重复通知
+const USAGE_BUDGETS_TABLE_SQL: &str = "CREATE TABLE IF NOT EXISTS usage_budgets (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    app_type TEXT,
+    period TEXT NOT NULL DEFAULT 'monthly',
+    limit_amount TEXT NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'USD',
+    thresholds TEXT NOT NULL DEFAULT '[50,80,100]',
+    enabled INTEGER NOT NULL DEFAULT 1,
+    alert_period TEXT,
+    alert_threshold INTEGER,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+)";
+
+/// provider_balance_history 表结构（v18+）
+///
+/// 每次用量脚本查询成功时按套餐各记录一行，同一次查询的 recorded_at 相同
+const BALANCE_HISTORY_TABLE_SQL: &str = "CREATE TABLE IF NOT EXISTS provider_balance_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    provider_id TEXT NOT NULL,
+    app_type TEXT NOT NULL,
+    plan_name TEXT NOT NULL DEFAULT '',
+    remaining REAL,
+    used REAL,
+    total REAL,
+    unit TEXT,
+    recorded_at INTEGER NOT NULL
+)";
+
+const BALANCE_HISTORY_INDEX_SQL: &str = "CREATE INDEX IF NOT EXISTS idx_balance_history_provider
+    ON provider_balance_history(app_type, provider_id, recorded_at)";
+
+/// 长上下文（prompt 超过 200K）整单加价的默认分档
+const LONG_CONTEXT_TIERS: &[(&str, &str)] = &[
+    (
+        "claude-sonnet-4-5-20250929",
+        r#"[{"aboveInputTokens":200000,"inputCostPerMillion":"6","outputCostPerMillion":"22.5","cacheReadCostPerMillion":"0.60","cacheCreationCostPerMillion":"7.50"}]"#,
+    ),
+    (
+        "claude-sonnet-4-20250514",
+        r#"[{"aboveInputTokens":200000,"inputCostPerMillion":"6","outputCostPerMillion":"22.5","cacheReadCostPerMillion":"0.60","cacheCreationCostPerMillion":"7.50"}]"#,
+    ),
+    (
+        "gemini-3-pro-preview",
+        r#"[{"aboveInputTokens":200000,"inputCostPerMillion":"4","outputCostPerMillion":"18","cacheReadCostPerMillion":"0.4","cacheCreationCostPerMillion":"0"}]"#,
+    ),
+    (
+        "gemini-2.5-pro",
+        r#"[{"aboveInputTokens":200000,"inputCostPerMillion":"2.5","outputCostPerMillion":"15","cacheReadCostPerMillion":"0.25","cacheCreationCostPerMillion":"0"}]"#,
+    ),
+];
+
 impl Database {
     /// 创建所有数据库表
     pub(crate) fn create_tables(&self) -> Result<(), AppError> {
@@ -122,6 +227,7 @@ impl Database {
             circuit_min_requests INTEGER NOT NULL DEFAULT 10,
             default_cost_multiplier TEXT NOT NULL DEFAULT '1',
             pricing_model_source TEXT NOT NULL DEFAULT 'response',
+            load_balance_strategy TEXT NOT NULL DEFAULT 'priority',
             created_at TEXT NOT NULL DEFAULT (datetime('now')), updated_at TEXT NOT NULL DEFAULT (datetime('now'))
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
 
@@ -182,6 +288,12 @@ impl Database {
             provider_type TEXT, is_streaming INTEGER NOT NULL DEFAULT 0,
             cost_multiplier TEXT NOT NULL DEFAULT '1.0',
             request_body TEXT, response_body TEXT,
+            cache_hit_rate REAL, routing_rule TEXT,
+            from_cache INTEGER NOT NULL DEFAULT 0,
+            client_token_id TEXT,
+            rectifier TEXT,
+            billing_currency TEXT, billing_cost TEXT,
+            source TEXT NOT NULL DEFAULT 'proxy',
             created_at INTEGER NOT NULL
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
 
@@ -205,17 +317,9 @@ impl Database {
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
 
-        // 11. Model Pricing 表
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS model_pricing (
-            model_id TEXT PRIMARY KEY, display_name TEXT NOT NULL,
-            input_cost_per_million TEXT NOT NULL, output_cost_per_million TEXT NOT NULL,
-            cache_read_cost_per_million TEXT NOT NULL DEFAULT '0',
-            cache_creation_cost_per_million TEXT NOT NULL DEFAULT '0'
-        )",
-            [],
-        )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        // 11. Model Pricing 表（同一模型可按生效时间段保存多个版本）
+        conn.execute(MODEL_PRICING_TABLE_SQL, [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
 
         // 12. Stream Check Logs 表
         conn.execute("CREATE TABLE IF NOT EXISTS stream_check_logs (
@@ -232,6 +336,77 @@ impl Database {
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
 
+        // 13. Routing Rules 表（按模型/请求特征路由到指定供应商）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS routing_rules (
+                id TEXT PRIMARY KEY,
+                app_type TEXT NOT NULL,
+                name TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                sort_index INTEGER NOT NULL DEFAULT 0,
+                model_pattern TEXT,
+                thinking INTEGER,
+                min_request_bytes INTEGER,
+                max_request_bytes INTEGER,
+                header_name TEXT,
+                header_value TEXT,
+                provider_id TEXT NOT NULL,
+                target_model TEXT,
+                FOREIGN KEY (provider_id, app_type) REFERENCES providers(id, app_type) ON DELETE CASCADE
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_routing_rules_app
+             ON routing_rules(app_type, sort_index)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 14. Proxy Client Tokens 表（局域网共享代理时签发给客户端的令牌，仅保存哈希）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_client_tokens (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                token_hash TEXT NOT NULL UNIQUE,
+                token_prefix TEXT NOT NULL,
+                request_limit INTEGER,
+                cost_limit_usd TEXT,
+                quota_period TEXT NOT NULL DEFAULT 'daily',
+                created_at INTEGER NOT NULL,
+                last_used_at INTEGER,
+                revoked_at INTEGER
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 15. Proxy Hooks 表（按 app/供应商改写请求与响应的 JS 钩子）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_hooks (
+                id TEXT PRIMARY KEY,
+                app_type TEXT NOT NULL,
+                name TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                sort_index INTEGER NOT NULL DEFAULT 0,
+                provider_id TEXT,
+                script TEXT NOT NULL,
+                timeout_ms INTEGER NOT NULL DEFAULT 100,
+                FOREIGN KEY (provider_id, app_type) REFERENCES providers(id, app_type) ON DELETE CASCADE
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_proxy_hooks_app
+             ON proxy_hooks(app_type, sort_index)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
         // 注意：circuit_breaker_config 已合并到 proxy_config 表中
 
         // 16. Proxy Live Backup 表 (Live 配置备份)
@@ -243,6 +418,74 @@ impl Database {
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
 
+        // 17. Mock Fixtures 表（离线 Mock 上游返回的录制/预置响应）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS mock_fixtures (
+                id TEXT PRIMARY KEY,
+                app_type TEXT NOT NULL,
+                name TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                sort_index INTEGER NOT NULL DEFAULT 0,
+                model_pattern TEXT,
+                prompt_contains TEXT,
+                status_code INTEGER NOT NULL DEFAULT 200,
+                response_body TEXT NOT NULL,
+                source_request_id TEXT
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_mock_fixtures_app
+             ON mock_fixtures(app_type, sort_index)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 18. Exchange Rates 表（1 美元可兑换的各币种数量，USD 不入表）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS exchange_rates (
+                currency TEXT PRIMARY KEY,
+                rate_per_usd TEXT NOT NULL,
+                source TEXT,
+                updated_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 19. Transcript Import State 表（会话记录导入进度，按文件 mtime 增量导入）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS transcript_import_state (
+                path TEXT PRIMARY KEY,
+                app_type TEXT NOT NULL,
+                mtime INTEGER NOT NULL,
+                imported_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 20. Usage Hourly Rollups 表（按小时预聚合的请求日志，由触发器增量维护）
+        Self::create_usage_rollup_table(conn)?;
+        // 旧库的请求日志表缺少统计列时，触发器由迁移在补齐列后创建
+        if Self::has_column(conn, "proxy_request_logs", "source")?
+            && Self::has_column(conn, "proxy_request_logs", "billing_cost")?
+        {
+            Self::ensure_usage_rollup_triggers(conn)?;
+        }
+
+        // 21. Usage Budgets 表（全局 / 按应用的花费预算与告警阈值）
+        conn.execute(USAGE_BUDGETS_TABLE_SQL, [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 22. Provider Balance History 表（用量脚本查询到的余额时间序列）
+        conn.execute(BALANCE_HISTORY_TABLE_SQL, [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        conn.execute(BALANCE_HISTORY_INDEX_SQL, [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
         // 尝试添加 live_takeover_active 列到 proxy_config 表
         let _ = conn.execute(
             "ALTER TABLE proxy_config ADD COLUMN live_takeover_active INTEGER NOT NULL DEFAULT 0",
@@ -367,6 +610,66 @@ impl Database {
                         Self::migrate_v5_to_v6(conn)?;
                         Self::set_user_version(conn, 6)?;
                     }
+                    6 => {
+                        log::info!("迁移数据库从 v6 到 v7（添加负载均衡策略）");
+                        Self::migrate_v6_to_v7(conn)?;
+                        Self::set_user_version(conn, 7)?;
+                    }
+                    7 => {
+                        log::info!("迁移数据库从 v7 到 v8（添加缓存命中率）");
+                        Self::migrate_v7_to_v8(conn)?;
+                        Self::set_user_version(conn, 8)?;
+                    }
+                    8 => {
+                        log::info!("迁移数据库从 v8 到 v9（记录命中的路由规则）");
+                        Self::migrate_v8_to_v9(conn)?;
+                        Self::set_user_version(conn, 9)?;
+                    }
+                    9 => {
+                        log::info!("迁移数据库从 v9 到 v10（标记响应缓存命中）");
+                        Self::migrate_v9_to_v10(conn)?;
+                        Self::set_user_version(conn, 10)?;
+                    }
+                    10 => {
+                        log::info!("迁移数据库从 v10 到 v11（客户端令牌用量归属）");
+                        Self::migrate_v10_to_v11(conn)?;
+                        Self::set_user_version(conn, 11)?;
+                    }
+                    11 => {
+                        log::info!("迁移数据库从 v11 到 v12（记录整流事件）");
+                        Self::migrate_v11_to_v12(conn)?;
+                        Self::set_user_version(conn, 12)?;
+                    }
+                    12 => {
+                        log::info!("迁移数据库从 v12 到 v13（分档与按生效时间的模型定价）");
+                        Self::migrate_v12_to_v13(conn)?;
+                        Self::set_user_version(conn, 13)?;
+                    }
+                    13 => {
+                        log::info!("迁移数据库从 v13 到 v14（多币种计费）");
+                        Self::migrate_v13_to_v14(conn)?;
+                        Self::set_user_version(conn, 14)?;
+                    }
+                    14 => {
+                        log::info!("迁移数据库从 v14 到 v15（会话记录用量来源）");
+                        Self::migrate_v14_to_v15(conn)?;
+                        Self::set_user_version(conn, 15)?;
+                    }
+                    15 => {
+                        log::info!("迁移数据库从 v15 到 v16（使用统计小时汇总表）");
+                        Self::migrate_v15_to_v16(conn)?;
+                        Self::set_user_version(conn, 16)?;
+                    }
+                    16 => {
+                        log::info!("迁移数据库从 v16 到 v17（花费预算）");
+                        Self::migrate_v16_to_v17(conn)?;
+                        Self::set_user_version(conn, 17)?;
+                    }
+                    17 => {
+                        log::info!("迁移数据库从 v17 到 v18（余额历史）");
+                        Self::migrate_v17_to_v18(conn)?;
+                        Self::set_user_version(conn, 18)?;
+                    }
                     _ => {
                         return Err(AppError::Database(format!(
                             "未知的数据库版本 {version}，无法迁移到 {SCHEMA_VERSION}"
@@ -698,6 +1001,7 @@ impl Database {
             circuit_min_requests INTEGER NOT NULL DEFAULT 10,
             default_cost_multiplier TEXT NOT NULL DEFAULT '1',
             pricing_model_source TEXT NOT NULL DEFAULT 'response',
+            load_balance_strategy TEXT NOT NULL DEFAULT 'priority',
             created_at TEXT NOT NULL DEFAULT (datetime('now')), updated_at TEXT NOT NULL DEFAULT (datetime('now'))
         )", [])?;
 
@@ -935,6 +1239,295 @@ impl Database {
         Ok(())
     }
 
+    /// v6 -> v7 迁移：为 proxy_config 添加负载均衡策略列
+    fn migrate_v6_to_v7(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "load_balance_strategy",
+                "TEXT NOT NULL DEFAULT 'priority'",
+            )?;
+        }
+
+        log::info!("v6 -> v7 迁移完成：已添加负载均衡策略字段");
+        Ok(())
+    }
+
+    /// v7 -> v8 迁移：为 proxy_request_logs 添加缓存命中率列
+    fn migrate_v7_to_v8(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(conn, "proxy_request_logs", "cache_hit_rate", "REAL")?;
+        }
+
+        log::info!("v7 -> v8 迁移完成：已添加缓存命中率字段");
+        Ok(())
+    }
+
+    /// v8 -> v9 迁移：为 proxy_request_logs 添加命中路由规则列
+    fn migrate_v8_to_v9(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(conn, "proxy_request_logs", "routing_rule", "TEXT")?;
+        }
+
+        log::info!("v8 -> v9 迁移完成：已添加路由规则字段");
+        Ok(())
+    }
+
+    /// v9 -> v10 迁移：为 proxy_request_logs 添加响应缓存命中标记列
+    fn migrate_v9_to_v10(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_request_logs",
+                "from_cache",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+        }
+
+        log::info!("v9 -> v10 迁移完成：已添加响应缓存命中字段");
+        Ok(())
+    }
+
+    /// v10 -> v11 迁移：为 proxy_request_logs 添加客户端令牌列（按令牌归属用量与配额统计）
+    fn migrate_v10_to_v11(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(conn, "proxy_request_logs", "client_token_id", "TEXT")?;
+            conn.execute(
+                "CREATE INDEX IF NOT EXISTS idx_request_logs_client_token
+                 ON proxy_request_logs(client_token_id, created_at)",
+                [],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        log::info!("v10 -> v11 迁移完成：已添加客户端令牌字段");
+        Ok(())
+    }
+
+    /// v11 -> v12 迁移：为 proxy_request_logs 添加整流事件列（如上下文超长回退）
+    fn migrate_v11_to_v12(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(conn, "proxy_request_logs", "rectifier", "TEXT")?;
+        }
+
+        log::info!("v11 -> v12 迁移完成：已添加整流事件字段");
+        Ok(())
+    }
+
+    /// v12 -> v13 迁移：model_pricing 主键改为 (model_id, effective_from)，
+    /// 新增生效时间段与按输入规模分档的价格，并为已知的长上下文模型补充分档
+    fn migrate_v12_to_v13(conn: &Connection) -> Result<(), AppError> {
+        if !Self::table_exists(conn, "model_pricing")? {
+            return Ok(());
+        }
+
+        if !Self::has_column(conn, "model_pricing", "effective_from")? {
+            conn.execute("ALTER TABLE model_pricing RENAME TO model_pricing_v12", [])
+                .map_err(|e| AppError::Database(format!("重命名模型定价表失败: {e}")))?;
+            conn.execute(MODEL_PRICING_TABLE_SQL, [])
+                .map_err(|e| AppError::Database(format!("创建模型定价表失败: {e}")))?;
+            conn.execute(
+                "INSERT INTO model_pricing (
+                    model_id, display_name, input_cost_per_million, output_cost_per_million,
+                    cache_read_cost_per_million, cache_creation_cost_per_million
+                )
+                SELECT model_id, display_name, input_cost_per_million, output_cost_per_million,
+                       cache_read_cost_per_million, cache_creation_cost_per_million
+                FROM model_pricing_v12",
+                [],
+            )
+            .map_err(|e| AppError::Database(format!("迁移模型定价数据失败: {e}")))?;
+            conn.execute("DROP TABLE model_pricing_v12", [])
+                .map_err(|e| AppError::Database(format!("删除旧模型定价表失败: {e}")))?;
+        }
+
+        for (model_id, tiers) in LONG_CONTEXT_TIERS {
+            conn.execute(
+                "UPDATE model_pricing SET tiers = ?2
+                 WHERE model_id = ?1 AND effective_from = 0 AND tiers IS NULL",
+                rusqlite::params![model_id, tiers],
+            )
+            .map_err(|e| AppError::Database(format!("更新分档定价失败: {e}")))?;
+        }
+
+        log::info!("v12 -> v13 迁移完成：模型定价支持分档与生效时间段");
+        Ok(())
+    }
+
+    /// v13 -> v14 迁移：为 proxy_request_logs 添加计费币种与计费金额列
+    fn migrate_v13_to_v14(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(conn, "proxy_request_logs", "billing_currency", "TEXT")?;
+            Self::add_column_if_missing(conn, "proxy_request_logs", "billing_cost", "TEXT")?;
+        }
+
+        log::info!("v13 -> v14 迁移完成：已添加计费币种字段");
+        Ok(())
+    }
+
+    /// v14 -> v15 迁移：请求日志区分代理与会话记录来源，新增会话记录导入进度表
+    fn migrate_v14_to_v15(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_request_logs",
+                "source",
+                "TEXT NOT NULL DEFAULT 'proxy'",
+            )?;
+        }
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS transcript_import_state (
+                path TEXT PRIMARY KEY,
+                app_type TEXT NOT NULL,
+                mtime INTEGER NOT NULL,
+                imported_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        log::info!("v14 -> v15 迁移完成：已添加用量来源字段");
+        Ok(())
+    }
+
+    /// v15 -> v16 迁移：创建小时汇总表与维护触发器，并从现有请求日志回填
+    fn migrate_v15_to_v16(conn: &Connection) -> Result<(), AppError> {
+        Self::create_usage_rollup_table(conn)?;
+        if !Self::table_exists(conn, "proxy_request_logs")? {
+            return Ok(());
+        }
+        Self::ensure_usage_rollup_triggers(conn)?;
+
+        conn.execute("DELETE FROM usage_hourly_rollups", [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        let rows = conn
+            .execute(
+                &format!(
+                    "INSERT INTO usage_hourly_rollups ({ROLLUP_COLUMNS})
+                     SELECT (created_at / 3600) * 3600, app_type, provider_id, model,
+                            status_code / 100, source, COALESCE(billing_currency, ''),
+                            COUNT(*), SUM(input_tokens), SUM(output_tokens),
+                            SUM(cache_read_tokens), SUM(cache_creation_tokens),
+                            SUM(CAST(total_cost_usd AS REAL)),
+                            SUM(COALESCE(CAST(billing_cost AS REAL), 0)), SUM(latency_ms)
+                     FROM proxy_request_logs
+                     GROUP BY 1, 2, 3, 4, 5, 6, 7"
+                ),
+                [],
+            )
+            .map_err(|e| AppError::Database(format!("回填小时汇总失败: {e}")))?;
+
+        log::info!("v15 -> v16 迁移完成：已回填 {rows} 条小时汇总");
+        Ok(())
+    }
+
+    /// v16 -> v17 迁移：新增花费预算表
+    fn migrate_v16_to_v17(conn: &Connection) -> Result<(), AppError> {
+        conn.execute(USAGE_BUDGETS_TABLE_SQL, [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        log::info!("v16 -> v17 迁移完成：已添加花费预算表");
+        Ok(())
+    }
+
+    /// v17 -> v18 迁移：新增供应商余额历史表
+    fn migrate_v17_to_v18(conn: &Connection) -> Result<(), AppError> {
+        conn.execute(BALANCE_HISTORY_TABLE_SQL, [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        conn.execute(BALANCE_HISTORY_INDEX_SQL, [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        log::info!("v17 -> v18 迁移完成：已添加余额历史表");
+        Ok(())
+    }
+
+    /// 小时汇总表：每行为一个小时内同一 app / 供应商 / 模型 / 状态类别 / 来源 / 计费币种的累计值
+    ///
+    /// 清理请求日志时保留汇总，长期统计不受日志保留期限影响
+    fn create_usage_rollup_table(conn: &Connection) -> Result<(), AppError> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_hourly_rollups (
+                bucket_start INTEGER NOT NULL,
+                app_type TEXT NOT NULL,
+                provider_id TEXT NOT NULL,
+                model TEXT NOT NULL,
+                status_class INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                billing_currency TEXT NOT NULL DEFAULT '',
+                request_count INTEGER NOT NULL DEFAULT 0,
+                input_tokens INTEGER NOT NULL DEFAULT 0,
+                output_tokens INTEGER NOT NULL DEFAULT 0,
+                cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+                cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+                total_cost_usd REAL NOT NULL DEFAULT 0,
+                billing_cost REAL NOT NULL DEFAULT 0,
+                latency_ms INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (bucket_start, app_type, provider_id, model, status_class, source, billing_currency)
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 请求日志写入 / 更新时同步小时汇总（更新时先扣除旧值再累加新值；删除不回写）
+    fn ensure_usage_rollup_triggers(conn: &Connection) -> Result<(), AppError> {
+        let upsert = |row: &str, sign: &str| {
+            format!(
+                "INSERT INTO usage_hourly_rollups ({ROLLUP_COLUMNS})
+                 VALUES ({})
+                 {ROLLUP_UPSERT_SQL};",
+                rollup_values(row, sign)
+            )
+        };
+
+        conn.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS trg_request_logs_rollup_insert
+                 AFTER INSERT ON proxy_request_logs
+                 BEGIN
+                     {}
+                 END",
+                upsert("NEW", "")
+            ),
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        conn.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS trg_request_logs_rollup_update
+                 AFTER UPDATE ON proxy_request_logs
+                 BEGIN
+                     {}
+                     {}
+                 END",
+                upsert("OLD", "-"),
+                upsert("NEW", "")
+            ),
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 从小时汇总中扣除一条请求日志（删除重复计费的记录前调用；普通的日志清理不回写汇总）
+    pub(crate) fn retract_usage_rollup(
+        conn: &Connection,
+        request_id: &str,
+    ) -> Result<(), AppError> {
+        conn.execute(
+            &format!(
+                "INSERT INTO usage_hourly_rollups ({ROLLUP_COLUMNS})
+                 SELECT {} FROM proxy_request_logs WHERE request_id = ?1
+                 {ROLLUP_UPSERT_SQL}",
+                rollup_values("proxy_request_logs", "-")
+            ),
+            [request_id],
+        )
+        .map_err(|e| AppError::Database(format!("更新小时汇总失败: {e}")))?;
+        Ok(())
+    }
+
     /// 插入默认模型定价数据
     /// 格式: (model_id, display_name, input, output, cache_read, cache_creation)
     /// 注意: model_id 使用短横线格式（如 claude-haiku-4-5），与 API 返回的模型名称标准化后一致
@@ -1275,18 +1868,23 @@ impl Database {
         ];
 
         for (model_id, display_name, input, output, cache_read, cache_creation) in pricing_data {
+            let tiers = LONG_CONTEXT_TIERS
+                .iter()
+                .find(|(id, _)| *id == model_id)
+                .map(|(_, tiers)| *tiers);
             conn.execute(
                 "INSERT OR IGNORE INTO model_pricing (
                     model_id, display_name, input_cost_per_million, output_cost_per_million,
-                    cache_read_cost_per_million, cache_creation_cost_per_million
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    cache_read_cost_per_million, cache_creation_cost_per_million, tiers
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
                 rusqlite::params![
                     model_id,
                     display_name,
                     input,
                     output,
                     cache_read,
-                    cache_creation
+                    cache_creation,
+                    tiers
                 ],
             )
             .map_err(|e| AppError::Database(format!("插入模型定价失败: {e}")))?;
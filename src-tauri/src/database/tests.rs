@@ -629,3 +629,59 @@ fn schema_model_pricing_is_seeded_on_init() {
         gemini_count
     );
 }
+
+#[test]
+fn schema_migration_v13_rebuilds_model_pricing_with_versions_and_tiers() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+    conn.execute_batch(
+        r#"
+        CREATE TABLE model_pricing (
+            model_id TEXT PRIMARY KEY, display_name TEXT NOT NULL,
+            input_cost_per_million TEXT NOT NULL, output_cost_per_million TEXT NOT NULL,
+            cache_read_cost_per_million TEXT NOT NULL DEFAULT '0',
+            cache_creation_cost_per_million TEXT NOT NULL DEFAULT '0'
+        );
+        INSERT INTO model_pricing VALUES
+            ('claude-sonnet-4-5-20250929', 'Claude Sonnet 4.5', '3', '15', '0.30', '3.75'),
+            ('custom-model', 'Custom', '1', '2', '0', '0');
+        "#,
+    )
+    .expect("seed v12 model_pricing");
+    Database::set_user_version(&conn, 12).expect("set user_version=12");
+
+    Database::create_tables_on_conn(&conn).expect("create tables");
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (input, tiers): (String, Option<String>) = conn
+        .query_row(
+            "SELECT input_cost_per_million, tiers FROM model_pricing
+             WHERE model_id = 'claude-sonnet-4-5-20250929' AND effective_from = 0",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("migrated sonnet pricing");
+    assert_eq!(input, "3");
+    assert!(tiers.is_some_and(|t| t.contains("200000")));
+
+    // 主键改为 (model_id, effective_from)：同一模型可保存多个生效版本
+    conn.execute(
+        "INSERT INTO model_pricing (model_id, display_name, input_cost_per_million,
+            output_cost_per_million, effective_from)
+         VALUES ('custom-model', 'Custom', '2', '4', 1000)",
+        [],
+    )
+    .expect("insert second version");
+    let versions: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM model_pricing WHERE model_id = 'custom-model'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("count versions");
+    assert_eq!(versions, 2);
+
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
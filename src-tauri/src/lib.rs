@@ -25,6 +25,7 @@ mod services;
 mod session_manager;
 mod settings;
 mod store;
+mod time_util;
 mod tray;
 mod usage_script;
 
@@ -698,6 +699,7 @@ pub fn run() {
                 let db = &app.state::<AppState>().db;
                 if let Ok(log_config) = db.get_log_config() {
                     log::set_max_level(log_config.to_level_filter());
+                    crate::proxy::otel::apply_config(&log_config.otlp);
                     log::info!(
                         "已加载日志配置: enabled={}, level={}",
                         log_config.enabled,
@@ -715,6 +717,32 @@ pub fn run() {
                 let db = &app.state::<AppState>().db;
                 let proxy_url = db.get_global_proxy_url().ok().flatten();
 
+                // 上游 TLS 配置需先于客户端初始化应用；证书失效时仅记录错误
+                match db.get_upstream_tls_config() {
+                    Ok(tls_config) if tls_config.enabled => {
+                        if let Err(e) = crate::proxy::http_client::apply_tls_config(Some(&tls_config))
+                        {
+                            log::error!(
+                                "[GlobalProxy] [GP-014] Failed to apply saved upstream TLS config: {e}"
+                            );
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!("[GlobalProxy] Failed to load upstream TLS config: {e}"),
+                }
+
+                match db.get_upstream_proxy_rules() {
+                    Ok(rules) if rules.enabled => {
+                        if let Err(e) = crate::proxy::http_client::apply_proxy_rules(Some(&rules)) {
+                            log::error!(
+                                "[GlobalProxy] [GP-016] Failed to apply saved upstream proxy rules: {e}"
+                            );
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!("[GlobalProxy] Failed to load upstream proxy rules: {e}"),
+                }
+
                 if let Err(e) = crate::proxy::http_client::init(proxy_url.as_deref()) {
                     log::error!(
                         "[GlobalProxy] [GP-005] Failed to initialize with saved config: {e}"
@@ -773,8 +801,11 @@ pub fn run() {
                 if let Err(e) = state.db.periodic_backup_if_needed() {
                     log::warn!("Periodic backup failed on startup: {e}");
                 }
+                if let Err(e) = state.db.generate_scheduled_usage_reports(chrono::Local::now()) {
+                    log::warn!("[USG-006] 生成定期用量报告失败: {e}");
+                }
 
-                // Periodic backup timer: check every hour while the app is running
+                // Periodic backup / usage report timer: check every hour while the app is running
                 let db_for_timer = state.db.clone();
                 tauri::async_runtime::spawn(async move {
                     let mut interval =
@@ -785,6 +816,33 @@ pub fn run() {
                         if let Err(e) = db_for_timer.periodic_backup_if_needed() {
                             log::warn!("Periodic backup timer failed: {e}");
                         }
+                        if let Err(e) =
+                            db_for_timer.generate_scheduled_usage_reports(chrono::Local::now())
+                        {
+                            log::warn!("[USG-006] 生成定期用量报告失败: {e}");
+                        }
+                    }
+                });
+
+                // 预算阈值检查：每分钟一次，越过阈值时通知前端与托盘
+                let budget_app = app_handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+                    loop {
+                        interval.tick().await;
+                        let db = budget_app.state::<AppState>().db.clone();
+                        // 统计查询会持有数据库锁，放到阻塞线程池中执行，避免占用异步运行时线程
+                        let result = tauri::async_runtime::spawn_blocking(move || {
+                            db.check_budget_thresholds(chrono::Local::now())
+                        })
+                        .await;
+                        match result {
+                            Ok(Ok(check)) => {
+                                crate::services::budget::notify_budget_alerts(&budget_app, &check)
+                            }
+                            Ok(Err(e)) => log::warn!("[USG-007] 检查预算阈值失败: {e}"),
+                            Err(e) => log::warn!("[USG-007] 预算阈值检查任务异常: {e}"),
+                        }
                     }
                 });
             });
@@ -853,6 +911,16 @@ pub fn run() {
             commands::save_settings,
             commands::get_rectifier_config,
             commands::set_rectifier_config,
+            commands::get_session_affinity_config,
+            commands::set_session_affinity_config,
+            commands::get_context_overflow_config,
+            commands::set_context_overflow_config,
+            commands::get_hedging_config,
+            commands::set_hedging_config,
+            commands::get_response_cache_config,
+            commands::set_response_cache_config,
+            commands::get_secret_scan_config,
+            commands::set_secret_scan_config,
             commands::get_log_config,
             commands::set_log_config,
             commands::restart_app,
@@ -872,6 +940,8 @@ pub fn run() {
             commands::validate_mcp_command,
             // usage query
             commands::queryProviderUsage,
+            commands::get_balance_history,
+            commands::get_balance_forecast,
             commands::testUsageScript,
             // New MCP via config.json (SSOT)
             commands::get_mcp_config,
@@ -957,6 +1027,10 @@ pub fn run() {
             commands::get_proxy_status,
             commands::get_proxy_config,
             commands::update_proxy_config,
+            commands::get_proxy_tls_config,
+            commands::update_proxy_tls_config,
+            commands::get_proxy_ca_cert,
+            commands::export_proxy_ca_cert,
             // Global & Per-App Config
             commands::get_global_proxy_config,
             commands::update_global_proxy_config,
@@ -966,6 +1040,8 @@ pub fn run() {
             commands::set_default_cost_multiplier,
             commands::get_pricing_model_source,
             commands::set_pricing_model_source,
+            commands::get_load_balance_strategy,
+            commands::set_load_balance_strategy,
             commands::is_proxy_running,
             commands::is_live_takeover_active,
             commands::switch_proxy_provider,
@@ -982,6 +1058,26 @@ pub fn run() {
             commands::remove_from_failover_queue,
             commands::get_auto_failover_enabled,
             commands::set_auto_failover_enabled,
+            // Routing rules
+            commands::get_routing_rules,
+            commands::save_routing_rule,
+            commands::delete_routing_rule,
+            // Proxy hooks
+            commands::get_proxy_hooks,
+            commands::save_proxy_hook,
+            commands::delete_proxy_hook,
+            // Mock upstream
+            commands::get_mock_fixtures,
+            commands::save_mock_fixture,
+            commands::delete_mock_fixture,
+            commands::capture_mock_fixture,
+            commands::get_client_auth_config,
+            commands::set_client_auth_config,
+            commands::list_client_tokens,
+            commands::create_client_token,
+            commands::update_client_token,
+            commands::revoke_client_token,
+            commands::get_client_token_usage,
             // Usage statistics
             commands::get_usage_summary,
             commands::get_usage_trends,
@@ -989,11 +1085,30 @@ pub fn run() {
             commands::get_model_stats,
             commands::get_request_logs,
             commands::get_request_detail,
+            commands::get_session_cache_stats,
+            commands::get_session_usage_stats,
+            commands::get_project_usage_stats,
+            commands::get_latency_stats,
+            commands::get_latency_trends,
             commands::get_available_filters,
             commands::get_model_pricing,
             commands::update_model_pricing,
             commands::delete_model_pricing,
+            commands::preview_pricing_catalog,
+            commands::apply_pricing_catalog,
             commands::check_provider_limits,
+            commands::get_exchange_rates,
+            commands::save_exchange_rate,
+            commands::delete_exchange_rate,
+            commands::import_exchange_rates,
+            commands::import_transcript_usage,
+            commands::export_request_logs,
+            commands::export_usage_report,
+            commands::generate_scheduled_usage_reports,
+            commands::get_budgets,
+            commands::save_budget,
+            commands::delete_budget,
+            commands::get_budget_statuses,
             commands::delete_request_logs_by_date,
             commands::count_request_logs_by_date,
             // Stream health check
@@ -1033,6 +1148,11 @@ pub fn run() {
             // Global upstream proxy
             commands::get_global_proxy_url,
             commands::set_global_proxy_url,
+            commands::get_upstream_tls_config,
+            commands::set_upstream_tls_config,
+            commands::get_upstream_proxy_rules,
+            commands::set_upstream_proxy_rules,
+            commands::test_upstream_proxy_rules,
             commands::test_proxy_url,
             commands::get_upstream_proxy_status,
             commands::scan_local_proxies,
@@ -191,6 +191,101 @@ pub struct ProviderProxyConfig {
     pub proxy_password: Option<String>,
 }
 
+/// 上游 TLS 配置（额外信任的 CA 证书与 mTLS 客户端证书）
+///
+/// 既用作全局配置，也可作为供应商单独配置（启用时覆盖全局配置）
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct UpstreamTlsConfig {
+    /// 是否启用（false 时使用全局配置/内置根证书）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 额外信任的 CA 证书文件（PEM，可包含多个证书）
+    #[serde(rename = "caCertPaths", default, skip_serializing_if = "Vec::is_empty")]
+    pub ca_cert_paths: Vec<String>,
+    /// 是否禁用内置根证书，仅信任 caCertPaths 中的 CA
+    #[serde(rename = "disableSystemRoots", default)]
+    pub disable_system_roots: bool,
+    /// mTLS 客户端证书文件（PEM）
+    #[serde(rename = "clientCertPath", skip_serializing_if = "Option::is_none")]
+    pub client_cert_path: Option<String>,
+    /// mTLS 客户端私钥文件（PEM）
+    #[serde(rename = "clientKeyPath", skip_serializing_if = "Option::is_none")]
+    pub client_key_path: Option<String>,
+}
+
+/// Mock 上游故障类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MockFaultKind {
+    /// 返回 429
+    RateLimit,
+    /// 返回 500
+    ServerError,
+    /// 挂起直到请求超时
+    Timeout,
+    /// 响应体发送到一半时断开连接
+    Disconnect,
+}
+
+/// Mock 上游故障注入规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MockFault {
+    pub kind: MockFaultKind,
+    /// 触发概率（0~1）
+    #[serde(default)]
+    pub rate: f64,
+}
+
+/// Mock 上游配置（启用后代理不请求真实上游，而是返回录制/预置的响应）
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct MockUpstreamConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 返回响应头前的延迟（毫秒）
+    #[serde(rename = "latencyMs", default)]
+    pub latency_ms: u64,
+    /// 流式响应相邻 SSE 事件之间的延迟（毫秒）
+    #[serde(rename = "chunkDelayMs", default)]
+    pub chunk_delay_ms: u64,
+    /// 故障注入规则（按顺序判定，命中第一条即生效）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faults: Vec<MockFault>,
+}
+
+/// 计费币种的汇率基准
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum BillingRateBasis {
+    /// 按本地汇率表把美元标价换算为计费币种
+    #[default]
+    Exchange,
+    /// 固定比例：每 1 美元标价收取 fixedRate 单位计费币种（如“1 元 = 1 美元”填 1）
+    Fixed,
+}
+
+/// 供应商计费币种配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BillingCurrencyConfig {
+    /// ISO 4217 币种代码（如 CNY）
+    pub currency: String,
+    #[serde(rename = "rateBasis", default)]
+    pub rate_basis: BillingRateBasis,
+    /// 固定比例（rateBasis 为 fixed 时必填）
+    #[serde(rename = "fixedRate", skip_serializing_if = "Option::is_none")]
+    pub fixed_rate: Option<String>,
+}
+
+/// 低余额告警配置（余额单位与用量脚本返回的 unit 一致）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct BalanceAlertConfig {
+    /// 剩余额度低于该值时通知
+    #[serde(rename = "lowThreshold", skip_serializing_if = "Option::is_none")]
+    pub low_threshold: Option<f64>,
+    /// 余额耗尽时在故障转移队列中降到末尾
+    #[serde(rename = "demoteWhenExhausted", default)]
+    pub demote_when_exhausted: bool,
+}
+
 /// 供应商元数据
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ProviderMeta {
@@ -224,12 +319,27 @@ pub struct ProviderMeta {
     /// 每月消费限额（USD）
     #[serde(rename = "limitMonthlyUsd", skip_serializing_if = "Option::is_none")]
     pub limit_monthly_usd: Option<String>,
+    /// 低余额告警与耗尽降级（基于用量脚本查询结果）
+    #[serde(rename = "balanceAlert", skip_serializing_if = "Option::is_none")]
+    pub balance_alert: Option<BalanceAlertConfig>,
+    /// 计费币种（未设置视为按美元标价计费）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing: Option<BillingCurrencyConfig>,
+    /// 加权轮询权重（负载均衡策略为 weighted 时生效，未设置视为 1，0 表示仅作为故障转移备选）
+    #[serde(rename = "loadBalanceWeight", skip_serializing_if = "Option::is_none")]
+    pub load_balance_weight: Option<u32>,
     /// 供应商单独的模型测试配置
     #[serde(rename = "testConfig", skip_serializing_if = "Option::is_none")]
     pub test_config: Option<ProviderTestConfig>,
     /// 供应商单独的代理配置
     #[serde(rename = "proxyConfig", skip_serializing_if = "Option::is_none")]
     pub proxy_config: Option<ProviderProxyConfig>,
+    /// 供应商单独的上游 TLS 配置
+    #[serde(rename = "tlsConfig", skip_serializing_if = "Option::is_none")]
+    pub tls_config: Option<UpstreamTlsConfig>,
+    /// 离线 Mock 上游配置（用于本地测试 CLI 集成与故障转移）
+    #[serde(rename = "mockUpstream", skip_serializing_if = "Option::is_none")]
+    pub mock_upstream: Option<MockUpstreamConfig>,
     /// Claude API 格式（仅 Claude 供应商使用）
     /// - "anthropic": 原生 Anthropic Messages API，直接透传
     /// - "openai_chat": OpenAI Chat Completions 格式，需要转换
@@ -667,6 +777,25 @@ mod tests {
         assert!(value.get("pricingModelSource").is_none());
     }
 
+    #[test]
+    fn provider_meta_deserializes_tls_config() {
+        let meta: ProviderMeta = serde_json::from_value(json!({
+            "tlsConfig": {
+                "enabled": true,
+                "caCertPaths": ["/etc/corp/root.pem"],
+                "clientCertPath": "/etc/corp/client.pem",
+                "clientKeyPath": "/etc/corp/client.key"
+            }
+        }))
+        .expect("deserialize ProviderMeta");
+
+        let tls = meta.tls_config.expect("tls config");
+        assert!(tls.enabled);
+        assert_eq!(tls.ca_cert_paths, vec!["/etc/corp/root.pem".to_string()]);
+        assert!(!tls.disable_system_roots);
+        assert_eq!(tls.client_key_path.as_deref(), Some("/etc/corp/client.key"));
+    }
+
     #[test]
     fn provider_with_id_populates_defaults() {
         let settings_config = json!({
@@ -0,0 +1,363 @@
+//! 客户端认证模块
+//!
+//! 代理监听在 `0.0.0.0` 与团队共享时，通过签发的客户端令牌控制访问：
+//! - 令牌只在创建时返回一次明文，数据库仅保存 SHA-256 哈希
+//! - 每个路由都经过 [`require_client_token`] 中间件校验
+//! - 通过校验的请求在扩展中携带 [`ClientIdentity`]，用量按令牌归属写入请求日志
+//! - 支持按日/按月的请求数与成本配额，以及随时吊销
+//! - 开启认证或监听非回环地址时，拒绝来自其他网页的跨域请求（[`reject_cross_origin`]）
+
+use crate::proxy::{server::ProxyState, ProxyError};
+use crate::time_util::{self, period_start};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Local;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+/// 令牌前缀（便于识别与密钥扫描）
+const TOKEN_PREFIX: &str = "ccs-";
+
+/// 应用自身 WebView 的来源，始终允许
+const APP_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+];
+
+/// 列表中展示的令牌前缀长度
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+/// 配额周期：按自然日
+pub const QUOTA_PERIOD_DAILY: &str = time_util::PERIOD_DAILY;
+/// 配额周期：按自然月
+pub const QUOTA_PERIOD_MONTHLY: &str = time_util::PERIOD_MONTHLY;
+
+fn default_quota_period() -> String {
+    QUOTA_PERIOD_DAILY.to_string()
+}
+
+/// 客户端令牌（不含明文）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientToken {
+    pub id: String,
+    pub name: String,
+    /// 令牌明文的前几位，用于在列表中辨认
+    pub token_prefix: String,
+    /// 每周期最大请求数（None 表示不限）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_limit: Option<u64>,
+    /// 每周期最大成本（USD，None 表示不限）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_limit_usd: Option<String>,
+    /// 配额周期：daily / monthly
+    #[serde(default = "default_quota_period")]
+    pub quota_period: String,
+    pub created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<i64>,
+}
+
+/// 新签发的令牌（明文仅返回这一次）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedClientToken {
+    pub token: ClientToken,
+    pub secret: String,
+}
+
+/// 令牌在当前配额周期内的用量
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientTokenUsage {
+    pub requests: u64,
+    pub cost_usd: String,
+    /// 当前周期起点（Unix 秒）
+    pub period_start: i64,
+}
+
+/// 通过认证的客户端（写入请求扩展）
+#[derive(Debug, Clone)]
+pub struct ClientIdentity {
+    pub token_id: String,
+    pub name: String,
+}
+
+/// 生成令牌明文
+pub fn generate_secret() -> String {
+    format!(
+        "{TOKEN_PREFIX}{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// 计算令牌哈希（数据库只保存哈希）
+pub fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// 展示用前缀
+pub fn display_prefix(secret: &str) -> String {
+    secret.chars().take(DISPLAY_PREFIX_LEN).collect()
+}
+
+/// 校验配额周期取值
+pub fn is_valid_quota_period(period: &str) -> bool {
+    matches!(period, QUOTA_PERIOD_DAILY | QUOTA_PERIOD_MONTHLY)
+}
+
+/// 从请求头提取客户端出示的令牌（Authorization Bearer / x-api-key / x-goog-api-key）
+///
+/// 各 CLI 把 API Key 放在不同的请求头中，客户端只需把令牌配置为 API Key 即可
+pub fn presented_secret(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            v.strip_prefix("Bearer ")
+                .or_else(|| v.strip_prefix("bearer "))
+        });
+    bearer
+        .or_else(|| headers.get("x-api-key").and_then(|v| v.to_str().ok()))
+        .or_else(|| headers.get("x-goog-api-key").and_then(|v| v.to_str().ok()))
+        .map(str::trim)
+        .filter(|v| v.starts_with(TOKEN_PREFIX))
+}
+
+/// 检查令牌是否超出配额，返回超限说明
+pub fn quota_violation(token: &ClientToken, usage: &ClientTokenUsage) -> Option<String> {
+    if let Some(limit) = token.request_limit {
+        if usage.requests >= limit {
+            return Some(format!(
+                "令牌 {} 已达到{}请求上限 ({limit})",
+                token.name,
+                period_label(&token.quota_period)
+            ));
+        }
+    }
+    if let Some(limit) = token
+        .cost_limit_usd
+        .as_deref()
+        .and_then(|v| Decimal::from_str(v).ok())
+    {
+        let used = Decimal::from_str(&usage.cost_usd).unwrap_or(Decimal::ZERO);
+        if used >= limit {
+            return Some(format!(
+                "令牌 {} 已达到{}成本上限 (${limit})",
+                token.name,
+                period_label(&token.quota_period)
+            ));
+        }
+    }
+    None
+}
+
+fn period_label(period: &str) -> &'static str {
+    if period == QUOTA_PERIOD_MONTHLY {
+        "本月"
+    } else {
+        "今日"
+    }
+}
+
+/// 是否为回环监听地址
+fn is_loopback_address(address: &str) -> bool {
+    address.eq_ignore_ascii_case("localhost")
+        || address
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// 是否允许该跨域来源
+///
+/// 仅监听回环地址且未开启客户端认证时保持宽松；否则只允许应用自身的来源，
+/// 避免任意网页借用户浏览器访问共享代理。读取认证配置失败时按严格处理。
+pub fn origin_allowed(db: &crate::database::Database, listen_address: &str, origin: &str) -> bool {
+    if APP_ORIGINS.contains(&origin) {
+        return true;
+    }
+    let auth_enabled = db
+        .get_client_auth_config()
+        .map_or(true, |config| config.enabled);
+    is_loopback_address(listen_address) && !auth_enabled
+}
+
+/// 跨域来源校验中间件（位于 CORS 之外，预检请求同样被拒绝）
+pub async fn reject_cross_origin(
+    State(state): State<ProxyState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(origin) = request.headers().get("origin") else {
+        return next.run(request).await;
+    };
+    let origin = origin.to_str().unwrap_or_default().to_string();
+    let listen_address = state.config.read().await.listen_address.clone();
+    if origin_allowed(&state.db, &listen_address, &origin) {
+        return next.run(request).await;
+    }
+    log::warn!("[AUTH-004] 拒绝跨域请求: {origin} -> {}", request.uri());
+    ProxyError::OriginNotAllowed(origin).into_response()
+}
+
+/// 客户端认证中间件
+///
+/// 未开启认证时直接放行（/health 始终放行）；开启后：
+/// - 本机非浏览器请求在 allow_loopback 时可免令牌（带 Origin 头的浏览器请求不豁免）
+/// - 其余请求必须携带有效且未吊销的令牌，并且未超出配额
+pub async fn require_client_token(
+    State(state): State<ProxyState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let config = state.db.get_client_auth_config().unwrap_or_default();
+    if !config.enabled || request.uri().path() == "/health" {
+        return next.run(request).await;
+    }
+
+    let secret = presented_secret(request.headers()).map(str::to_string);
+    let Some(secret) = secret else {
+        let is_loopback = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .is_some_and(|ConnectInfo(addr)| addr.ip().is_loopback());
+        let from_browser = request.headers().contains_key("origin");
+        if config.allow_loopback && is_loopback && !from_browser {
+            return next.run(request).await;
+        }
+        log::warn!("[AUTH-001] 拒绝未携带客户端令牌的请求: {}", request.uri());
+        return ProxyError::AuthError("缺少客户端令牌".to_string()).into_response();
+    };
+
+    let token = match state.db.find_client_token_by_hash(&hash_secret(&secret)) {
+        Ok(Some(token)) if token.revoked_at.is_none() => token,
+        Ok(_) => {
+            log::warn!(
+                "[AUTH-002] 拒绝无效或已吊销的客户端令牌: {}",
+                display_prefix(&secret)
+            );
+            return ProxyError::AuthError("客户端令牌无效或已吊销".to_string()).into_response();
+        }
+        Err(e) => return ProxyError::DatabaseError(e.to_string()).into_response(),
+    };
+
+    if token.request_limit.is_some() || token.cost_limit_usd.is_some() {
+        let since = period_start(&token.quota_period, Local::now());
+        match state.db.get_client_token_usage(&token.id, since) {
+            Ok(usage) => {
+                if let Some(reason) = quota_violation(&token, &usage) {
+                    log::warn!("[AUTH-003] {reason}");
+                    return ProxyError::QuotaExceeded(reason).into_response();
+                }
+            }
+            Err(e) => return ProxyError::DatabaseError(e.to_string()).into_response(),
+        }
+    }
+
+    if let Err(e) = state.db.touch_client_token(&token.id) {
+        log::debug!("更新客户端令牌使用时间失败: {e}");
+    }
+
+    request.extensions_mut().insert(ClientIdentity {
+        token_id: token.id,
+        name: token.name,
+    });
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn token(request_limit: Option<u64>, cost_limit_usd: Option<&str>) -> ClientToken {
+        ClientToken {
+            id: "t1".to_string(),
+            name: "alice".to_string(),
+            token_prefix: "ccs-0123".to_string(),
+            request_limit,
+            cost_limit_usd: cost_limit_usd.map(str::to_string),
+            quota_period: QUOTA_PERIOD_DAILY.to_string(),
+            created_at: 0,
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    fn usage(requests: u64, cost_usd: &str) -> ClientTokenUsage {
+        ClientTokenUsage {
+            requests,
+            cost_usd: cost_usd.to_string(),
+            period_start: 0,
+        }
+    }
+
+    #[test]
+    fn test_generate_and_hash_secret() {
+        let secret = generate_secret();
+        assert!(secret.starts_with(TOKEN_PREFIX));
+        assert_eq!(secret.len(), TOKEN_PREFIX.len() + 64);
+        assert_ne!(secret, generate_secret());
+
+        let hash = hash_secret(&secret);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_secret(&secret));
+        assert_eq!(display_prefix(&secret).len(), DISPLAY_PREFIX_LEN);
+    }
+
+    #[test]
+    fn test_presented_secret_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(presented_secret(&headers), None);
+
+        // 非本系统签发的 key（如真实的 Anthropic key）不视为客户端令牌
+        headers.insert("x-api-key", HeaderValue::from_static("sk-ant-xxx"));
+        assert_eq!(presented_secret(&headers), None);
+
+        headers.insert("x-goog-api-key", HeaderValue::from_static("ccs-goog"));
+        assert_eq!(presented_secret(&headers), Some("ccs-goog"));
+
+        headers.insert("x-api-key", HeaderValue::from_static("ccs-key"));
+        assert_eq!(presented_secret(&headers), Some("ccs-key"));
+
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer ccs-bearer"),
+        );
+        assert_eq!(presented_secret(&headers), Some("ccs-bearer"));
+    }
+
+    #[test]
+    fn test_quota_violation() {
+        assert!(quota_violation(&token(None, None), &usage(1000, "99")).is_none());
+        assert!(quota_violation(&token(Some(10), None), &usage(9, "0")).is_none());
+        assert!(quota_violation(&token(Some(10), None), &usage(10, "0")).is_some());
+        assert!(quota_violation(&token(None, Some("5")), &usage(1, "4.99")).is_none());
+        assert!(quota_violation(&token(None, Some("5")), &usage(1, "5.00")).is_some());
+        // 无法解析的成本上限视为不限
+        assert!(quota_violation(&token(None, Some("abc")), &usage(1, "100")).is_none());
+    }
+
+    #[test]
+    fn test_quota_period() {
+        assert!(is_valid_quota_period("daily"));
+        assert!(is_valid_quota_period("monthly"));
+        assert!(!is_valid_quota_period("weekly"));
+    }
+}
@@ -0,0 +1,137 @@
+//! 上下文超长回退
+//!
+//! 识别 Anthropic / OpenAI / Gemini 格式的“上下文超长”错误，
+//! 并在配置了回退目标时改用长上下文模型或供应商重试一次。
+
+use super::model_mapper::{has_thinking_enabled, ModelMapping};
+use super::ProxyError;
+use crate::provider::Provider;
+use serde_json::Value;
+
+/// 各家上游表示上下文超长的错误特征（小写匹配）
+const OVERFLOW_PATTERNS: &[&str] = &[
+    // Anthropic: "prompt is too long: 210000 tokens > 200000 maximum"
+    "prompt is too long",
+    // Anthropic: "input length and `max_tokens` exceed context limit"
+    "exceed context limit",
+    // OpenAI: code "context_length_exceeded" / "This model's maximum context length is ..."
+    "context_length_exceeded",
+    "maximum context length",
+    // OpenAI Responses: "Your input exceeds the context window of this model"
+    "exceeds the context window",
+    // Gemini: "The input token count (N) exceeds the maximum number of tokens allowed (M)"
+    "exceeds the maximum number of tokens",
+];
+
+/// 已解析的回退目标
+#[derive(Debug, Clone)]
+pub struct ContextFallback {
+    /// 回退供应商（None 表示使用原供应商）
+    pub provider: Option<Provider>,
+    /// 回退模型（None 表示沿用目标供应商的模型映射）
+    pub model: Option<String>,
+}
+
+/// 判断上游错误是否为上下文超长
+///
+/// 只识别 400/413/422 类客户端错误，避免把 5xx 等供应商故障误判为超长
+pub fn is_context_overflow(error: &ProxyError) -> bool {
+    let ProxyError::UpstreamError {
+        status,
+        body: Some(body),
+    } = error
+    else {
+        return false;
+    };
+    if !matches!(status, 400 | 413 | 422) {
+        return false;
+    }
+    let body = body.to_lowercase();
+    OVERFLOW_PATTERNS
+        .iter()
+        .any(|pattern| body.contains(pattern))
+}
+
+/// 请求发往某供应商时实际使用的模型：显式改写优先，否则按供应商模型映射请求中的模型
+///
+/// 请求体没有 `model` 字段（Gemini 模型在 URI 中）且未改写时返回 None
+pub fn effective_model(
+    provider: &Provider,
+    body: &Value,
+    model_override: Option<&str>,
+) -> Option<String> {
+    if let Some(model) = model_override {
+        return Some(model.to_string());
+    }
+    let original = body.get("model").and_then(|m| m.as_str())?;
+    Some(ModelMapping::from_provider(provider).map_model(original, has_thinking_enabled(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upstream(status: u16, body: &str) -> ProxyError {
+        ProxyError::UpstreamError {
+            status,
+            body: Some(body.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_detects_overflow_across_formats() {
+        let cases = [
+            r#"{"type":"error","error":{"type":"invalid_request_error","message":"prompt is too long: 210000 tokens > 200000 maximum"}}"#,
+            r#"{"error":{"message":"This model's maximum context length is 128000 tokens.","type":"invalid_request_error","code":"context_length_exceeded"}}"#,
+            r#"{"error":{"message":"Your input exceeds the context window of this model.","code":"context_length_exceeded"}}"#,
+            r#"{"error":{"code":400,"message":"The input token count (1200000) exceeds the maximum number of tokens allowed (1048576).","status":"INVALID_ARGUMENT"}}"#,
+        ];
+        for body in cases {
+            assert!(is_context_overflow(&upstream(400, body)), "{body}");
+        }
+    }
+
+    #[test]
+    fn test_ignores_other_errors() {
+        assert!(!is_context_overflow(&upstream(
+            400,
+            r#"{"error":{"message":"Invalid 'signature' in 'thinking' block"}}"#
+        )));
+        // 5xx 不视为超长（即使消息里提到 context）
+        assert!(!is_context_overflow(&upstream(
+            500,
+            "maximum context length exceeded while proxying"
+        )));
+        assert!(!is_context_overflow(&ProxyError::UpstreamError {
+            status: 400,
+            body: None,
+        }));
+        assert!(!is_context_overflow(&ProxyError::Timeout(
+            "prompt is too long".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_effective_model_applies_override_then_mapping() {
+        let provider = Provider::with_id(
+            "p1".to_string(),
+            "P1".to_string(),
+            serde_json::json!({ "env": { "ANTHROPIC_MODEL": "mapped-model" } }),
+            None,
+        );
+        let body = serde_json::json!({ "model": "claude-sonnet-4-5" });
+
+        assert_eq!(
+            effective_model(&provider, &body, None).as_deref(),
+            Some("mapped-model")
+        );
+        assert_eq!(
+            effective_model(&provider, &body, Some("long-context")).as_deref(),
+            Some("long-context")
+        );
+        assert_eq!(
+            effective_model(&provider, &serde_json::json!({}), None),
+            None
+        );
+    }
+}
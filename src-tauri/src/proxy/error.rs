@@ -68,10 +68,21 @@ pub enum ProxyError {
     StreamIdleTimeout(u64),
 
     /// 认证错误
-    #[allow(dead_code)]
     #[error("认证失败: {0}")]
     AuthError(String),
 
+    /// 客户端令牌配额已用尽
+    #[error("配额已用尽: {0}")]
+    QuotaExceeded(String),
+
+    /// 请求中检测到疑似密钥且配置为拒绝
+    #[error("请求包含疑似密钥，已被代理拦截（{0}）。请移除后重试")]
+    SecretDetected(String),
+
+    /// 跨域来源不被允许（开启客户端认证或监听非回环地址时）
+    #[error("不允许的跨域来源: {0}")]
+    OriginNotAllowed(String),
+
     #[allow(dead_code)]
     #[error("内部错误: {0}")]
     Internal(String),
@@ -154,6 +165,11 @@ impl IntoResponse for ProxyError {
                         (StatusCode::GATEWAY_TIMEOUT, self.to_string())
                     }
                     ProxyError::AuthError(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
+                    ProxyError::QuotaExceeded(_) => {
+                        (StatusCode::TOO_MANY_REQUESTS, self.to_string())
+                    }
+                    ProxyError::SecretDetected(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+                    ProxyError::OriginNotAllowed(_) => (StatusCode::FORBIDDEN, self.to_string()),
                     ProxyError::Internal(_) => {
                         (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
                     }
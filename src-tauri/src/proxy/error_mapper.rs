@@ -12,6 +12,7 @@ use super::ProxyError;
 /// - 连接失败：502 Bad Gateway
 /// - 无可用 Provider：503 Service Unavailable
 /// - 重试耗尽：503 Service Unavailable
+/// - 检测到密钥被拦截：400 Bad Request
 /// - 其他错误：500 Internal Server Error
 pub fn map_proxy_error_to_status(error: &ProxyError) -> u16 {
     match error {
@@ -45,6 +46,9 @@ pub fn map_proxy_error_to_status(error: &ProxyError) -> u16 {
         // 转换错误：500 Internal Server Error
         ProxyError::TransformError(_) => 500,
 
+        // 请求中检测到密钥被拦截：400 Bad Request
+        ProxyError::SecretDetected(_) => 400,
+
         // 其他未知错误：500 Internal Server Error
         _ => 500,
     }
@@ -4,15 +4,24 @@
 
 use super::{
     body_filter::filter_private_params_with_whitelist,
+    context_overflow::{effective_model, is_context_overflow, ContextFallback},
     error::*,
+    error_mapper::{get_error_message, map_proxy_error_to_status},
     failover_switch::FailoverSwitchManager,
+    hedging::HedgeOptions,
+    hooks::{apply_request_hooks, hook_info, HookRunner, ProxyHook},
+    log_codes::{hook as log_hook, sec as log_sec},
+    metrics,
+    mock_upstream::{MockFixture, MockRequest, MockWireFormat},
+    otel::{AttemptReason, AttemptSpan, RequestTrace},
     provider_router::ProviderRouter,
     providers::{get_adapter, ProviderAdapter, ProviderType},
+    secret_scanner::{summarize, SecretScanOutcome},
     thinking_budget_rectifier::{rectify_thinking_budget, should_rectify_thinking_budget},
     thinking_rectifier::{
         normalize_thinking_type, rectify_anthropic_request, should_rectify_thinking_signature,
     },
-    types::{ProxyStatus, RectifierConfig},
+    types::{ProxyStatus, RectifierConfig, SecretScanAction},
     ProxyError,
 };
 use crate::{app_config::AppType, provider::Provider};
@@ -64,6 +73,7 @@ const HEADER_BLACKLIST: &[&str] = &[
     "x-b3-spanid",
     "x-b3-parentspanid",
     "x-b3-sampled",
+    // W3C Trace Context 单独处理（开启链路追踪时改写为尝试 span）
     "traceparent",
     "tracestate",
     // anthropic 特定头单独处理，避免重复
@@ -77,6 +87,8 @@ const HEADER_BLACKLIST: &[&str] = &[
 pub struct ForwardResult {
     pub response: Response,
     pub provider: Provider,
+    /// 成功响应前触发的整流器（写入请求日志）
+    pub rectifier: Option<&'static str>,
 }
 
 pub struct ForwardError {
@@ -99,6 +111,32 @@ pub struct RequestForwarder {
     rectifier_config: RectifierConfig,
     /// 非流式请求超时（秒）
     non_streaming_timeout: std::time::Duration,
+    /// 路由规则指定的模型改写：(provider_id, target_model)
+    model_override: Option<(String, String)>,
+    /// 对冲请求参数（未开启时为 None）
+    hedging: Option<HedgeOptions>,
+    /// 请求链路（未开启链路追踪或未被采样时为 None）
+    trace: Option<RequestTrace>,
+    /// 已启用的请求/响应钩子
+    hooks: Arc<Vec<ProxyHook>>,
+    /// 出站请求密钥扫描结果（由 RequestContext 扫描，未开启时为 None）
+    secret_scan: Option<Arc<SecretScanOutcome>>,
+    /// 上下文超长回退目标（未配置时为 None）
+    context_fallback: Option<ContextFallback>,
+    /// Mock 上游 fixture（链路中没有启用 Mock 的供应商时为空）
+    mock_fixtures: Arc<Vec<MockFixture>>,
+}
+
+/// 首次尝试（可能经过对冲）的结果
+struct AttemptOutcome<'a> {
+    result: Result<Response, ProxyError>,
+    /// 产生 result 的供应商
+    provider: &'a Provider,
+    used_half_open_permit: bool,
+    /// 参与对冲的备选供应商（后续故障转移时跳过）
+    hedged_provider_id: Option<String>,
+    /// 备选供应商在首个供应商仍未返回时胜出（非故障转移，不切换当前供应商）
+    hedge_won: bool,
 }
 
 impl RequestForwarder {
@@ -114,6 +152,13 @@ impl RequestForwarder {
         _streaming_first_byte_timeout: u64,
         _streaming_idle_timeout: u64,
         rectifier_config: RectifierConfig,
+        model_override: Option<(String, String)>,
+        hedging: Option<HedgeOptions>,
+        trace: Option<RequestTrace>,
+        hooks: Arc<Vec<ProxyHook>>,
+        secret_scan: Option<Arc<SecretScanOutcome>>,
+        context_fallback: Option<ContextFallback>,
+        mock_fixtures: Arc<Vec<MockFixture>>,
     ) -> Self {
         Self {
             router,
@@ -124,6 +169,13 @@ impl RequestForwarder {
             current_provider_id_at_start,
             rectifier_config,
             non_streaming_timeout: std::time::Duration::from_secs(non_streaming_timeout),
+            model_override,
+            hedging,
+            trace,
+            hooks,
+            secret_scan,
+            context_fallback,
+            mock_fixtures,
         }
     }
 
@@ -154,6 +206,16 @@ impl RequestForwarder {
             });
         }
 
+        // 处理出站请求中的疑似密钥（所有供应商尝试共用同一扫描结果）
+        if let Some(scan) = &self.secret_scan {
+            Self::apply_secret_scan(scan, adapter.name(), &mut body).map_err(|error| {
+                ForwardError {
+                    error,
+                    provider: None,
+                }
+            })?;
+        }
+
         let mut last_error = None;
         let mut last_provider = None;
         let mut attempted_providers = 0usize;
@@ -165,8 +227,15 @@ impl RequestForwarder {
         // 单 Provider 场景下跳过熔断器检查（故障转移关闭时）
         let bypass_circuit_breaker = providers.len() == 1;
 
+        // 已作为对冲备选请求过的供应商（不再重复请求）
+        let mut hedged_provider_id: Option<String> = None;
+
         // 依次尝试每个供应商
-        for provider in providers.iter() {
+        for (index, provider) in providers.iter().enumerate() {
+            if hedged_provider_id.as_deref() == Some(provider.id.as_str()) {
+                continue;
+            }
+
             // 发起请求前先获取熔断器放行许可（HalfOpen 会占用探测名额）
             // 单 Provider 场景下跳过此检查，避免熔断器阻塞所有请求
             let (allowed, used_half_open_permit) = if bypass_circuit_breaker {
@@ -195,10 +264,54 @@ impl RequestForwarder {
             }
 
             // 转发请求（每个 Provider 只尝试一次，重试由客户端控制）
-            match self
-                .forward(provider, endpoint, &body, &headers, adapter.as_ref())
+            // 首次尝试且请求适合对冲时，慢响应会触发对下一个供应商的对冲请求
+            let hedge_eligible = attempted_providers == 1
+                && self
+                    .hedging
+                    .as_ref()
+                    .is_some_and(|hedging| hedging.is_eligible(endpoint, &body));
+            let outcome = if hedge_eligible {
+                self.forward_hedged(
+                    app_type_str,
+                    provider,
+                    used_half_open_permit,
+                    &providers[index + 1..],
+                    endpoint,
+                    &body,
+                    &headers,
+                    adapter.as_ref(),
+                )
                 .await
-            {
+            } else {
+                let reason = if attempted_providers == 1 {
+                    AttemptReason::Initial
+                } else {
+                    AttemptReason::Failover
+                };
+                AttemptOutcome {
+                    result: self
+                        .forward(
+                            provider,
+                            endpoint,
+                            &body,
+                            &headers,
+                            adapter.as_ref(),
+                            reason,
+                        )
+                        .await,
+                    provider,
+                    used_half_open_permit,
+                    hedged_provider_id: None,
+                    hedge_won: false,
+                }
+            };
+            if outcome.hedged_provider_id.is_some() {
+                hedged_provider_id = outcome.hedged_provider_id;
+            }
+            let provider = outcome.provider;
+            let used_half_open_permit = outcome.used_half_open_permit;
+
+            match outcome.result {
                 Ok(response) => {
                     // 成功：记录成功并更新熔断器
                     let _ = self
@@ -226,10 +339,12 @@ impl RequestForwarder {
                         let mut status = self.status.write().await;
                         status.success_requests += 1;
                         status.last_error = None;
-                        let should_switch =
-                            self.current_provider_id_at_start.as_str() != provider.id.as_str();
+                        // 对冲胜出不是故障转移：首个供应商并未失败，不切换当前供应商
+                        let should_switch = !outcome.hedge_won
+                            && self.current_provider_id_at_start.as_str() != provider.id.as_str();
                         if should_switch {
                             status.failover_count += 1;
+                            metrics::global().record_failover(app_type_str, &provider.id);
 
                             // 异步触发供应商切换，更新 UI/托盘，并把“当前供应商”同步为实际使用的 provider
                             let fm = self.failover_manager.clone();
@@ -253,9 +368,41 @@ impl RequestForwarder {
                     return Ok(ForwardResult {
                         response,
                         provider: provider.clone(),
+                        rectifier: None,
                     });
                 }
                 Err(e) => {
+                    // 上下文超长：改用长上下文模型/供应商重试一次（不计入熔断器，结果直接返回）
+                    // 回退目标与本次尝试相同时重试没有意义，按普通错误处理
+                    if let Some(fallback) = self.context_fallback.as_ref().filter(|fallback| {
+                        is_context_overflow(&e)
+                            && !self.is_same_fallback_target(
+                                app_type_str,
+                                fallback,
+                                provider,
+                                &body,
+                            )
+                    }) {
+                        self.router
+                            .release_permit_neutral(
+                                &provider.id,
+                                app_type_str,
+                                used_half_open_permit,
+                            )
+                            .await;
+                        return self
+                            .forward_context_fallback(
+                                app_type_str,
+                                provider,
+                                fallback,
+                                endpoint,
+                                &body,
+                                &headers,
+                                adapter.as_ref(),
+                            )
+                            .await;
+                    }
+
                     // 检测是否需要触发整流器（仅 Claude/ClaudeAuth 供应商）
                     let provider_type = ProviderType::from_app_type_and_config(app_type, provider);
                     let is_anthropic_provider = matches!(
@@ -318,7 +465,14 @@ impl RequestForwarder {
 
                                 // 使用同一供应商重试（不计入熔断器）
                                 match self
-                                    .forward(provider, endpoint, &body, &headers, adapter.as_ref())
+                                    .forward(
+                                        provider,
+                                        endpoint,
+                                        &body,
+                                        &headers,
+                                        adapter.as_ref(),
+                                        AttemptReason::RectifierSignature,
+                                    )
                                     .await
                                 {
                                     Ok(response) => {
@@ -355,6 +509,8 @@ impl RequestForwarder {
                                                     != provider.id.as_str();
                                             if should_switch {
                                                 status.failover_count += 1;
+                                                metrics::global()
+                                                    .record_failover(app_type_str, &provider.id);
 
                                                 // 异步触发供应商切换，更新 UI/托盘
                                                 let fm = self.failover_manager.clone();
@@ -380,6 +536,8 @@ impl RequestForwarder {
                                         return Ok(ForwardResult {
                                             response,
                                             provider: provider.clone(),
+                                            rectifier: AttemptReason::RectifierSignature
+                                                .rectifier(),
                                         });
                                     }
                                     Err(retry_err) => {
@@ -509,7 +667,14 @@ impl RequestForwarder {
 
                             // 使用同一供应商重试（不计入熔断器）
                             match self
-                                .forward(provider, endpoint, &body, &headers, adapter.as_ref())
+                                .forward(
+                                    provider,
+                                    endpoint,
+                                    &body,
+                                    &headers,
+                                    adapter.as_ref(),
+                                    AttemptReason::RectifierBudget,
+                                )
                                 .await
                             {
                                 Ok(response) => {
@@ -543,6 +708,8 @@ impl RequestForwarder {
                                                 != provider.id.as_str();
                                         if should_switch {
                                             status.failover_count += 1;
+                                            metrics::global()
+                                                .record_failover(app_type_str, &provider.id);
                                             let fm = self.failover_manager.clone();
                                             let ah = self.app_handle.clone();
                                             let pid = provider.id.clone();
@@ -564,6 +731,7 @@ impl RequestForwarder {
                                     return Ok(ForwardResult {
                                         response,
                                         provider: provider.clone(),
+                                        rectifier: AttemptReason::RectifierBudget.rectifier(),
                                     });
                                 }
                                 Err(retry_err) => {
@@ -653,6 +821,7 @@ impl RequestForwarder {
 
                     // 分类错误
                     let category = self.categorize_proxy_error(&e);
+                    metrics::global().record_error(app_type_str, &provider.id, category);
 
                     match category {
                         ErrorCategory::Retryable => {
@@ -734,7 +903,368 @@ impl RequestForwarder {
         })
     }
 
-    /// 转发单个请求（使用适配器）
+    /// 上下文超长后用回退目标重试一次
+    ///
+    /// 回退是单次请求级别的处理：不经过熔断器，也不切换当前供应商
+    #[allow(clippy::too_many_arguments)]
+    async fn forward_context_fallback(
+        &self,
+        app_type_str: &str,
+        provider: &Provider,
+        fallback: &ContextFallback,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+    ) -> Result<ForwardResult, ForwardError> {
+        let (target, target_model) = self.fallback_target(fallback, provider);
+        log::info!(
+            "[{app_type_str}] [RECT-020] 上下文超长，回退到供应商 {}{} 重试",
+            target.name,
+            target_model
+                .map(|m| format!("（模型 {m}）"))
+                .unwrap_or_default()
+        );
+
+        let reason = AttemptReason::ContextFallback;
+        match self
+            .forward_as(
+                target,
+                endpoint,
+                body,
+                headers,
+                adapter,
+                reason,
+                target_model,
+            )
+            .await
+        {
+            Ok(response) => {
+                log::info!("[{app_type_str}] [RECT-021] 上下文超长回退重试成功");
+                {
+                    let mut status = self.status.write().await;
+                    status.success_requests += 1;
+                    status.last_error = None;
+                    if status.total_requests > 0 {
+                        status.success_rate =
+                            (status.success_requests as f32 / status.total_requests as f32) * 100.0;
+                    }
+                }
+                Ok(ForwardResult {
+                    response,
+                    provider: target.clone(),
+                    rectifier: reason.rectifier(),
+                })
+            }
+            Err(retry_err) => {
+                log::warn!("[{app_type_str}] [RECT-022] 上下文超长回退重试仍失败: {retry_err}");
+                let mut status = self.status.write().await;
+                status.failed_requests += 1;
+                status.last_error = Some(retry_err.to_string());
+                if status.total_requests > 0 {
+                    status.success_rate =
+                        (status.success_requests as f32 / status.total_requests as f32) * 100.0;
+                }
+                Err(ForwardError {
+                    error: retry_err,
+                    provider: Some(target.clone()),
+                })
+            }
+        }
+    }
+
+    /// 上下文超长回退的目标供应商与模型
+    fn fallback_target<'a>(
+        &'a self,
+        fallback: &'a ContextFallback,
+        provider: &'a Provider,
+    ) -> (&'a Provider, Option<&'a str>) {
+        let target = fallback.provider.as_ref().unwrap_or(provider);
+        let target_model = fallback
+            .model
+            .as_deref()
+            .or_else(|| self.target_model_for(target));
+        (target, target_model)
+    }
+
+    /// 回退目标是否就是刚失败的尝试（同一供应商且实际发送的模型相同）
+    fn is_same_fallback_target(
+        &self,
+        app_type_str: &str,
+        fallback: &ContextFallback,
+        provider: &Provider,
+        body: &Value,
+    ) -> bool {
+        let (target, target_model) = self.fallback_target(fallback, provider);
+        let same = target.id == provider.id
+            && effective_model(target, body, target_model)
+                == effective_model(provider, body, self.target_model_for(provider));
+        if same {
+            log::info!(
+                "[{app_type_str}] [RECT-024] 上下文超长回退目标与已尝试的供应商 {} 及模型相同，跳过回退",
+                provider.name
+            );
+        }
+        same
+    }
+
+    /// 首次尝试（带对冲）
+    ///
+    /// 先向首个供应商发起请求；若其在近期延迟分位数内未返回，则向队列中下一个
+    /// 可用供应商发起对冲请求，取先成功者并取消另一个。两次尝试都会记录：
+    /// - 胜出方：返回给调用方，由响应处理流程按实际供应商记录用量
+    /// - 被取消方：释放熔断器许可（不计失败），记 499 日志并按估算的输入 token 计费
+    /// - 失败方：记录熔断器失败并写入错误日志
+    #[allow(clippy::too_many_arguments)]
+    async fn forward_hedged<'a>(
+        &self,
+        app_type_str: &str,
+        primary: &'a Provider,
+        primary_permit: bool,
+        candidates: &'a [Provider],
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+    ) -> AttemptOutcome<'a> {
+        let primary_future = self.forward(
+            primary,
+            endpoint,
+            body,
+            headers,
+            adapter,
+            AttemptReason::Initial,
+        );
+        tokio::pin!(primary_future);
+
+        let single = |result| AttemptOutcome {
+            result,
+            provider: primary,
+            used_half_open_permit: primary_permit,
+            hedged_provider_id: None,
+            hedge_won: false,
+        };
+
+        let Some(hedging) = self.hedging.as_ref() else {
+            return single(primary_future.await);
+        };
+        let Some(delay) = hedging.delay_for(&primary.id) else {
+            return single(primary_future.await);
+        };
+
+        let start = std::time::Instant::now();
+        tokio::select! {
+            result = &mut primary_future => return single(result),
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        // 选择下一个放行的供应商作为对冲目标
+        let mut backup = None;
+        for candidate in candidates {
+            let permit = self
+                .router
+                .allow_provider_request(&candidate.id, app_type_str)
+                .await;
+            if permit.allowed {
+                backup = Some((candidate, permit.used_half_open_permit));
+                break;
+            }
+        }
+        let Some((backup, backup_permit)) = backup else {
+            return single(primary_future.await);
+        };
+
+        log::info!(
+            "[{}] [FWD-003] Provider {} 超过 {}ms 未响应，向 {} 发起对冲请求",
+            app_type_str,
+            primary.name,
+            delay.as_millis(),
+            backup.name
+        );
+
+        let backup_start = std::time::Instant::now();
+        let backup_future = self.forward(
+            backup,
+            endpoint,
+            body,
+            headers,
+            adapter,
+            AttemptReason::Hedge,
+        );
+        tokio::pin!(backup_future);
+
+        let hedged_provider_id = Some(backup.id.clone());
+        let (primary_first, first_result) = tokio::select! {
+            result = &mut primary_future => (true, result),
+            result = &mut backup_future => (false, result),
+        };
+
+        match (primary_first, first_result) {
+            (true, Ok(response)) => {
+                // 首个供应商先返回：取消对冲请求
+                self.cancel_hedge_attempt(
+                    app_type_str,
+                    backup,
+                    backup_permit,
+                    primary,
+                    body,
+                    backup_start,
+                )
+                .await;
+                AttemptOutcome {
+                    result: Ok(response),
+                    provider: primary,
+                    used_half_open_permit: primary_permit,
+                    hedged_provider_id,
+                    hedge_won: false,
+                }
+            }
+            (false, Ok(response)) => {
+                log::info!(
+                    "[{}] [FWD-004] 对冲请求胜出: {} ({}ms)",
+                    app_type_str,
+                    backup.name,
+                    start.elapsed().as_millis()
+                );
+                self.cancel_hedge_attempt(
+                    app_type_str,
+                    primary,
+                    primary_permit,
+                    backup,
+                    body,
+                    start,
+                )
+                .await;
+                AttemptOutcome {
+                    result: Ok(response),
+                    provider: backup,
+                    used_half_open_permit: backup_permit,
+                    hedged_provider_id,
+                    hedge_won: true,
+                }
+            }
+            (true, Err(primary_err)) => {
+                // 首个供应商失败：等待对冲请求
+                match backup_future.await {
+                    Ok(response) => {
+                        self.fail_hedge_attempt(
+                            app_type_str,
+                            primary,
+                            primary_permit,
+                            &primary_err,
+                            start,
+                        )
+                        .await;
+                        AttemptOutcome {
+                            result: Ok(response),
+                            provider: backup,
+                            used_half_open_permit: backup_permit,
+                            hedged_provider_id,
+                            hedge_won: false,
+                        }
+                    }
+                    Err(backup_err) => {
+                        // 都失败：对冲方在此记录，首个供应商的错误交给调用方按常规流程处理
+                        self.fail_hedge_attempt(
+                            app_type_str,
+                            backup,
+                            backup_permit,
+                            &backup_err,
+                            backup_start,
+                        )
+                        .await;
+                        AttemptOutcome {
+                            result: Err(primary_err),
+                            provider: primary,
+                            used_half_open_permit: primary_permit,
+                            hedged_provider_id,
+                            hedge_won: false,
+                        }
+                    }
+                }
+            }
+            (false, Err(backup_err)) => {
+                // 对冲请求失败：继续等待首个供应商
+                self.fail_hedge_attempt(
+                    app_type_str,
+                    backup,
+                    backup_permit,
+                    &backup_err,
+                    backup_start,
+                )
+                .await;
+                AttemptOutcome {
+                    result: primary_future.await,
+                    provider: primary,
+                    used_half_open_permit: primary_permit,
+                    hedged_provider_id,
+                    hedge_won: false,
+                }
+            }
+        }
+    }
+
+    /// 取消落败的对冲尝试：释放熔断器许可（不计失败）并记录 499 日志（按估算的输入 token 计费）
+    async fn cancel_hedge_attempt(
+        &self,
+        app_type_str: &str,
+        loser: &Provider,
+        used_half_open_permit: bool,
+        winner: &Provider,
+        body: &Value,
+        started_at: std::time::Instant,
+    ) {
+        self.router
+            .release_permit_neutral(&loser.id, app_type_str, used_half_open_permit)
+            .await;
+        if let Some(hedging) = self.hedging.as_ref() {
+            hedging
+                .log_cancelled(
+                    loser,
+                    self.target_model_for(loser),
+                    body,
+                    format!("对冲请求已取消：{} 先返回响应", winner.name),
+                    started_at.elapsed().as_millis() as u64,
+                )
+                .await;
+        }
+    }
+
+    /// 记录失败的对冲尝试：计入熔断器并写入错误日志
+    async fn fail_hedge_attempt(
+        &self,
+        app_type_str: &str,
+        provider: &Provider,
+        used_half_open_permit: bool,
+        error: &ProxyError,
+        started_at: std::time::Instant,
+    ) {
+        let _ = self
+            .router
+            .record_result(
+                &provider.id,
+                app_type_str,
+                used_half_open_permit,
+                false,
+                Some(error.to_string()),
+            )
+            .await;
+        metrics::global().record_error(
+            app_type_str,
+            &provider.id,
+            self.categorize_proxy_error(error),
+        );
+        if let Some(hedging) = self.hedging.as_ref() {
+            hedging.log_attempt(
+                &provider.id,
+                map_proxy_error_to_status(error),
+                get_error_message(error),
+                started_at.elapsed().as_millis() as u64,
+            );
+        }
+    }
+
+    /// 转发单个请求（开启链路追踪时记录为一次上游尝试）
     async fn forward(
         &self,
         provider: &Provider,
@@ -742,9 +1272,158 @@ impl RequestForwarder {
         body: &Value,
         headers: &axum::http::HeaderMap,
         adapter: &dyn ProviderAdapter,
+        reason: AttemptReason,
     ) -> Result<Response, ProxyError> {
-        // 使用适配器提取 base_url
-        let base_url = adapter.extract_base_url(provider)?;
+        let target_model = self.target_model_for(provider);
+        self.forward_as(
+            provider,
+            endpoint,
+            body,
+            headers,
+            adapter,
+            reason,
+            target_model,
+        )
+        .await
+    }
+
+    /// 以指定目标模型转发单个请求（目标模型优先于供应商自身的模型映射）
+    #[allow(clippy::too_many_arguments)]
+    async fn forward_as(
+        &self,
+        provider: &Provider,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+        reason: AttemptReason,
+        target_model: Option<&str>,
+    ) -> Result<Response, ProxyError> {
+        // 被取消（future 被丢弃）时 span 随之释放并记为 cancelled
+        let span = self
+            .trace
+            .as_ref()
+            .map(|trace| trace.start_attempt(provider, reason));
+        let result = self
+            .send_upstream(
+                provider,
+                endpoint,
+                body,
+                headers,
+                adapter,
+                target_model,
+                span.as_ref(),
+            )
+            .await;
+        if let Some(span) = span {
+            match &result {
+                Ok(response) => span.end_ok(response.status().as_u16()),
+                Err(e) => span.end_err(
+                    map_proxy_error_to_status(e),
+                    self.categorize_proxy_error(e),
+                    &get_error_message(e),
+                ),
+            }
+        }
+        result
+    }
+
+    /// 按配置处理请求体中的疑似密钥：告警、原地脱敏或拒绝
+    ///
+    /// 日志只包含检测器名称与 JSON 路径，不包含密钥本身
+    fn apply_secret_scan(
+        scan: &SecretScanOutcome,
+        tag: &str,
+        body: &mut Value,
+    ) -> Result<(), ProxyError> {
+        let action = scan.action;
+        if scan.detections.is_empty() {
+            return Ok(());
+        }
+
+        let summary = summarize(&scan.detections);
+        if action == SecretScanAction::Block {
+            log::warn!(
+                "[{tag}] [{}] 请求包含疑似密钥，已拦截: {summary}",
+                log_sec::BLOCKED
+            );
+            return Err(ProxyError::SecretDetected(summary));
+        }
+        if let Some(redacted) = &scan.redacted {
+            *body = redacted.clone();
+        }
+        log::warn!(
+            "[{tag}] [{}] 请求包含疑似密钥（{}）: {summary}",
+            log_sec::DETECTED,
+            if action == SecretScanAction::Redact {
+                "已脱敏"
+            } else {
+                "仅告警"
+            }
+        );
+        Ok(())
+    }
+
+    /// 执行作用于该供应商的 onRequest 钩子；没有钩子时原样返回
+    async fn apply_request_hooks(
+        &self,
+        provider: &Provider,
+        adapter: &dyn ProviderAdapter,
+        body: Value,
+        headers: &axum::http::HeaderMap,
+    ) -> (Value, axum::http::HeaderMap) {
+        if !self.hooks.iter().any(|h| h.applies_to(&provider.id)) {
+            return (body, headers.clone());
+        }
+
+        let hooks = self.hooks.clone();
+        let info = hook_info(
+            &adapter.name().to_ascii_lowercase(),
+            &provider.id,
+            &provider.name,
+            body.get("model")
+                .and_then(|m| m.as_str())
+                .unwrap_or_default(),
+        );
+        let provider_id = provider.id.clone();
+        let fallback = (body.clone(), headers.clone());
+        let headers = headers.clone();
+        tokio::task::spawn_blocking(move || match HookRunner::load(&hooks, &provider_id, info) {
+            Some(runner) => apply_request_hooks(&runner, body, headers),
+            None => (body, headers),
+        })
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!("[{}] 请求钩子执行中断: {e}", log_hook::RUN_FAILED);
+            fallback
+        })
+    }
+
+    /// 路由规则指定的目标模型（仅对规则选中的供应商生效）
+    fn target_model_for(&self, provider: &Provider) -> Option<&str> {
+        self.model_override
+            .as_ref()
+            .filter(|(provider_id, _)| *provider_id == provider.id)
+            .map(|(_, model)| model.as_str())
+    }
+
+    /// 发送单个上游请求（使用适配器）
+    #[allow(clippy::too_many_arguments)]
+    async fn send_upstream(
+        &self,
+        provider: &Provider,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+        target_model: Option<&str>,
+        attempt: Option<&AttemptSpan>,
+    ) -> Result<Response, ProxyError> {
+        // Gemini 的模型在 URI 中：改写 models/{model} 段
+        let rewritten_endpoint = target_model
+            .filter(|_| body.get("model").is_none())
+            .and_then(|model| rewrite_endpoint_model(endpoint, model));
+        let endpoint = rewritten_endpoint.as_deref().unwrap_or(endpoint);
 
         // 检查是否需要格式转换
         let needs_transform = adapter.needs_transform(provider);
@@ -756,12 +1435,16 @@ impl RequestForwarder {
                 endpoint
             };
 
-        // 使用适配器构建 URL
-        let url = adapter.build_url(&base_url, effective_endpoint);
-
         // 应用模型映射（独立于格式转换）
-        let (mapped_body, _original_model, _mapped_model) =
+        let (mut mapped_body, _original_model, _mapped_model) =
             super::model_mapper::apply_model_mapping(body.clone(), provider);
+        if let Some(model) = target_model {
+            if let Some(obj) = mapped_body.as_object_mut() {
+                if obj.contains_key("model") {
+                    obj.insert("model".to_string(), Value::String(model.to_string()));
+                }
+            }
+        }
 
         // 与 CCH 对齐：请求前不做 thinking 主动改写（仅保留兼容入口）
         let mapped_body = normalize_thinking_type(mapped_body);
@@ -777,9 +1460,55 @@ impl RequestForwarder {
         // 默认使用空白名单，过滤所有 _ 前缀字段
         let filtered_body = filter_private_params_with_whitelist(request_body, &[]);
 
-        // 获取 HTTP 客户端：优先使用供应商单独代理配置，否则使用全局客户端
+        // 执行 onRequest 钩子（在沙箱中同步执行，放到阻塞线程避免占用异步工作线程）
+        let (filtered_body, hooked_headers) = self
+            .apply_request_hooks(provider, adapter, filtered_body, headers)
+            .await;
+        let headers = &hooked_headers;
+
+        // 离线 Mock 上游：不发出真实请求，直接返回录制/预置的响应
+        if let Some(mock) = super::mock_upstream::enabled_config(provider) {
+            let model = filtered_body
+                .get("model")
+                .and_then(|v| v.as_str())
+                .or(target_model)
+                .or_else(|| endpoint_model(effective_endpoint))
+                .unwrap_or("");
+            let request = MockRequest {
+                format: MockWireFormat::detect(adapter.name(), effective_endpoint),
+                model,
+                body: &filtered_body,
+                streaming: filtered_body
+                    .get("stream")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false)
+                    || effective_endpoint.contains("streamGenerateContent"),
+            };
+            log::info!(
+                "[{}] >>> Mock 上游: {} (model={model})",
+                adapter.name(),
+                provider.name
+            );
+            return super::mock_upstream::respond(
+                mock,
+                &self.mock_fixtures,
+                request,
+                self.non_streaming_timeout,
+            )
+            .await;
+        }
+
+        // 使用适配器提取 base_url 并构建 URL
+        let base_url = adapter.extract_base_url(provider)?;
+        let url = adapter.build_url(&base_url, effective_endpoint);
+
+        // 获取 HTTP 客户端：优先使用供应商单独代理/TLS 配置，否则使用全局客户端
         let proxy_config = provider.meta.as_ref().and_then(|m| m.proxy_config.as_ref());
-        let client = super::http_client::get_for_provider(proxy_config);
+        let tls_config = provider.meta.as_ref().and_then(|m| m.tls_config.as_ref());
+        let client = super::http_client::get_for_provider(proxy_config, tls_config)
+            .map_err(ProxyError::ConfigError)?;
+        // 上游代理规则需要 DNS / PAC 时在阻塞线程池中预先求值，避免客户端回调阻塞
+        super::http_client::prepare_proxy_rules(&url).await;
         let mut request = client.post(&url);
 
         // 只有当 timeout > 0 时才设置请求超时
@@ -836,6 +1565,16 @@ impl RequestForwarder {
             }
         }
 
+        // W3C Trace Context：链路追踪开启时指向本次尝试 span，否则原样透传客户端的值
+        if let Some(span) = attempt {
+            request = request.header("traceparent", span.traceparent());
+        } else if let Some(traceparent) = headers.get("traceparent") {
+            request = request.header("traceparent", traceparent);
+        }
+        if let Some(tracestate) = headers.get("tracestate") {
+            request = request.header("tracestate", tracestate);
+        }
+
         // 禁用压缩，避免 gzip 流式响应解析错误
         // 参考 CCH: undici 在连接提前关闭时会对不完整的 gzip 流抛出错误
         request = request.header("accept-encoding", "identity");
@@ -927,3 +1666,47 @@ fn extract_error_message(error: &ProxyError) -> Option<String> {
         _ => Some(error.to_string()),
     }
 }
+
+/// 端点中 `models/{model}` 段里模型名的位置
+fn endpoint_model_span(endpoint: &str) -> Option<(usize, usize)> {
+    let start = endpoint.find("models/")? + "models/".len();
+    let end = endpoint[start..]
+        .find([':', '/', '?'])
+        .map_or(endpoint.len(), |i| start + i);
+    Some((start, end))
+}
+
+/// 读取端点中的模型名（Gemini 模型在 URI 中）
+fn endpoint_model(endpoint: &str) -> Option<&str> {
+    endpoint_model_span(endpoint).map(|(start, end)| &endpoint[start..end])
+}
+
+/// 改写端点中的 `models/{model}` 段（Gemini 模型在 URI 中）
+///
+/// 端点中没有模型段时返回 None
+fn rewrite_endpoint_model(endpoint: &str, model: &str) -> Option<String> {
+    let (start, end) = endpoint_model_span(endpoint)?;
+    Some(format!("{}{model}{}", &endpoint[..start], &endpoint[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_endpoint_model() {
+        assert_eq!(
+            rewrite_endpoint_model(
+                "/v1beta/models/gemini-2.5-pro:streamGenerateContent?alt=sse",
+                "gemini-2.5-flash"
+            )
+            .as_deref(),
+            Some("/v1beta/models/gemini-2.5-flash:streamGenerateContent?alt=sse")
+        );
+        assert_eq!(rewrite_endpoint_model("/v1/messages", "m"), None);
+        assert_eq!(
+            endpoint_model("/v1beta/models/gemini-2.5-pro:generateContent"),
+            Some("gemini-2.5-pro")
+        );
+    }
+}
@@ -5,14 +5,26 @@
 use crate::app_config::AppType;
 use crate::provider::Provider;
 use crate::proxy::{
+    client_auth::ClientIdentity,
+    context_overflow::ContextFallback,
     extract_session_id,
     forwarder::RequestForwarder,
+    hedging::HedgeOptions,
+    hooks::{hook_info, HookRunner, ProxyHook},
+    log_codes::{hook as log_hook, mock as log_mock, rt as log_rt},
+    mock_upstream::{self, MockFixture},
+    model_mapper::{has_thinking_enabled, ModelMapping},
+    otel::RequestTrace,
+    response_cache::{cache_key, CachedResponse, ResponseCacheTicket},
+    routing_rules::{find_matching_rule, RoutingRequest},
+    secret_scanner::{SecretScanOutcome, SecretScanner},
     server::ProxyState,
     types::{AppProxyConfig, RectifierConfig},
     ProxyError,
 };
 use axum::http::HeaderMap;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// 流式超时配置
 #[derive(Debug, Clone, Copy)]
@@ -57,13 +69,35 @@ pub struct RequestContext {
     pub app_type: AppType,
     /// Session ID（从客户端请求提取或新生成）
     pub session_id: String,
+    /// Session ID 是否由客户端提供（仅客户端提供的 ID 参与会话粘性路由）
+    pub session_client_provided: bool,
     /// 整流器配置
     pub rectifier_config: RectifierConfig,
     pub request_body: Option<String>,
+    /// 命中的路由规则名称（写入请求日志）
+    pub routing_rule: Option<String>,
+    /// 路由规则指定的模型改写：(provider_id, target_model)，仅对该供应商生效
+    pub model_override: Option<(String, String)>,
+    /// 响应缓存凭据（开启缓存且请求可缓存时由 lookup_response_cache 设置）
+    response_cache: Option<ResponseCacheTicket>,
+    /// 请求链路（未开启 OTLP 导出或未被采样时为 None）
+    pub trace: Option<RequestTrace>,
+    /// 客户端令牌 ID（启用客户端认证且请求携带令牌时设置，用于用量归属）
+    pub client_token_id: Option<String>,
+    /// 已启用的请求/响应钩子（按 sort_index 排序）
+    pub hooks: Arc<Vec<ProxyHook>>,
+    /// 出站请求密钥扫描结果（未开启时为 None）
+    pub secret_scan: Option<Arc<SecretScanOutcome>>,
+    /// 上下文超长回退目标（未开启或未配置时为 None）
+    context_fallback: Option<ContextFallback>,
+    /// 成功响应前触发的整流器（由转发结果设置，写入请求日志）
+    pub rectifier: Option<&'static str>,
+    /// Mock 上游 fixture（仅当链路中有启用 Mock 的供应商时加载）
+    mock_fixtures: Arc<Vec<MockFixture>>,
 }
 
 impl RequestContext {
-    /// 创建请求上下文
+    /// 创建请求上下文（模型名从请求体的 `model` 字段提取）
     ///
     /// # Arguments
     /// * `state` - 代理服务器状态
@@ -82,6 +116,37 @@ impl RequestContext {
         app_type: AppType,
         tag: &'static str,
         app_type_str: &'static str,
+    ) -> Result<Self, ProxyError> {
+        // 从请求体提取模型名称
+        let request_model = body
+            .get("model")
+            .and_then(|m| m.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        Self::new_with_model(
+            state,
+            body,
+            headers,
+            app_type,
+            tag,
+            app_type_str,
+            request_model,
+        )
+        .await
+    }
+
+    /// 创建请求上下文（显式指定模型名，如 Gemini 的模型在 URI 中）
+    ///
+    /// 模型名在选择供应商之前确定，以便参与路由规则匹配
+    pub async fn new_with_model(
+        state: &ProxyState,
+        body: &serde_json::Value,
+        headers: &HeaderMap,
+        app_type: AppType,
+        tag: &'static str,
+        app_type_str: &'static str,
+        request_model: String,
     ) -> Result<Self, ProxyError> {
         let start_time = Instant::now();
 
@@ -98,18 +163,22 @@ impl RequestContext {
         let current_provider_id =
             crate::settings::get_current_provider(&app_type).unwrap_or_default();
 
-        // 从请求体提取模型名称
-        let request_model = body
-            .get("model")
-            .and_then(|m| m.as_str())
-            .unwrap_or("unknown")
-            .to_string();
-
         // 提取 Session ID
         let session_result = extract_session_id(headers, body, app_type_str);
         let session_id = session_result.session_id.clone();
 
-        let request_body = serde_json::to_string(body).ok();
+        // 扫描一次出站请求，转发时直接复用结果
+        let secret_scan =
+            SecretScanner::from_config(&state.db.get_secret_scan_config().unwrap_or_default())
+                .map(|scanner| Arc::new(scanner.inspect(body)));
+
+        // 路由按原始请求体大小匹配；请求日志中保存脱敏后的副本
+        let original_body = serde_json::to_string(body).ok();
+        let request_bytes = original_body.as_ref().map_or(0, |b| b.len() as u64);
+        let request_body = match secret_scan.as_ref().and_then(|scan| scan.redacted.as_ref()) {
+            Some(redacted) => serde_json::to_string(redacted).ok(),
+            None => original_body,
+        };
 
         log::debug!(
             "[{}] Session ID: {} (from {:?}, client_provided: {})",
@@ -119,25 +188,137 @@ impl RequestContext {
             session_result.client_provided
         );
 
+        // 匹配路由规则（在常规队列之前）
+        let routing_request = RoutingRequest {
+            model: &request_model,
+            has_thinking: has_thinking_enabled(body),
+            request_bytes,
+            headers,
+        };
+        let routing_rules = state
+            .db
+            .get_routing_rules(app_type_str)
+            .unwrap_or_else(|e| {
+                log::warn!("[{}] [{}] 读取路由规则失败: {e}", tag, log_rt::LOAD_FAILED);
+                Vec::new()
+            });
+        let matched_rule = find_matching_rule(&routing_rules, &routing_request);
+
+        let hooks = state
+            .db
+            .get_proxy_hooks(app_type_str)
+            .map(|hooks| hooks.into_iter().filter(|h| h.enabled).collect())
+            .unwrap_or_else(|e| {
+                log::warn!("[{}] [{}] 读取钩子失败: {e}", tag, log_hook::LOAD_FAILED);
+                Vec::new()
+            });
+        let rule_provider = match matched_rule {
+            Some(rule) => match state.db.get_provider_by_id(&rule.provider_id, app_type_str) {
+                Ok(Some(provider)) => {
+                    log::info!(
+                        "[{}] [{}] 命中路由规则 '{}' → 供应商 {}{}",
+                        tag,
+                        log_rt::RULE_MATCHED,
+                        rule.name,
+                        provider.name,
+                        rule.target_model
+                            .as_deref()
+                            .map(|m| format!("，模型改写为 {m}"))
+                            .unwrap_or_default()
+                    );
+                    Some(provider)
+                }
+                _ => {
+                    log::warn!(
+                        "[{}] [{}] 路由规则 '{}' 的目标供应商 {} 不存在，忽略",
+                        tag,
+                        log_rt::PROVIDER_NOT_FOUND,
+                        rule.name,
+                        rule.provider_id
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
         // 使用共享的 ProviderRouter 选择 Provider（熔断器状态跨请求保持）
         // 注意：只在这里调用一次，结果传递给 forwarder，避免重复消耗 HalfOpen 名额
-        let providers = state
+        let selected = state
             .provider_router
-            .select_providers(app_type_str)
-            .await
-            .map_err(|e| match e {
+            .select_providers(
+                app_type_str,
+                Some(&request_model),
+                session_result
+                    .client_provided
+                    .then_some(session_id.as_str()),
+            )
+            .await;
+
+        // 命中规则时：规则供应商排在首位，常规队列作为回退（常规队列不可用时仅使用规则供应商）
+        let providers = match (rule_provider.clone(), selected) {
+            (Some(rule_provider), Ok(mut providers)) => {
+                providers.retain(|p| p.id != rule_provider.id);
+                providers.insert(0, rule_provider);
+                providers
+            }
+            (Some(rule_provider), Err(_)) => vec![rule_provider],
+            (None, result) => result.map_err(|e| match e {
                 crate::error::AppError::AllProvidersCircuitOpen => {
                     ProxyError::AllProvidersCircuitOpen
                 }
                 crate::error::AppError::NoProvidersConfigured => ProxyError::NoProvidersConfigured,
                 _ => ProxyError::DatabaseError(e.to_string()),
-            })?;
+            })?,
+        };
+
+        let (routing_rule, model_override) = match (matched_rule, rule_provider) {
+            (Some(rule), Some(provider)) => (
+                Some(rule.name.clone()),
+                rule.target_model
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| (provider.id, m.to_string())),
+            ),
+            _ => (None, None),
+        };
 
         let provider = providers
             .first()
             .cloned()
             .ok_or(ProxyError::NoAvailableProvider)?;
 
+        let context_fallback = Self::resolve_context_fallback(state, tag, app_type_str);
+
+        let uses_mock = providers
+            .iter()
+            .chain(context_fallback.as_ref().and_then(|f| f.provider.as_ref()))
+            .any(|p| mock_upstream::enabled_config(p).is_some());
+        let mock_fixtures = if uses_mock {
+            state
+                .db
+                .get_mock_fixtures(app_type_str)
+                .unwrap_or_else(|e| {
+                    log::warn!(
+                        "[{}] [{}] 读取 Mock fixture 失败: {e}",
+                        tag,
+                        log_mock::LOAD_FAILED
+                    );
+                    Vec::new()
+                })
+        } else {
+            Vec::new()
+        };
+
+        let trace = RequestTrace::start(headers, app_type_str, &request_model);
+        if let Some(trace) = trace.as_ref() {
+            trace.set_attribute("session.id", &session_id);
+            if let Some(rule) = routing_rule.as_deref() {
+                trace.set_attribute("cc_switch.routing_rule", rule);
+            }
+        }
+
         log::debug!(
             "[{}] Provider: {}, model: {}, failover chain: {} providers, session: {}",
             tag,
@@ -158,8 +339,53 @@ impl RequestContext {
             app_type_str,
             app_type,
             session_id,
+            session_client_provided: session_result.client_provided,
             rectifier_config,
             request_body,
+            routing_rule,
+            model_override,
+            response_cache: None,
+            trace,
+            client_token_id: None,
+            hooks: Arc::new(hooks),
+            secret_scan,
+            context_fallback,
+            rectifier: None,
+            mock_fixtures: Arc::new(mock_fixtures),
+        })
+    }
+
+    /// 读取当前 app 的上下文超长回退目标（回退供应商不存在时忽略该配置）
+    fn resolve_context_fallback(
+        state: &ProxyState,
+        tag: &str,
+        app_type_str: &str,
+    ) -> Option<ContextFallback> {
+        let config = state.db.get_context_overflow_config().unwrap_or_default();
+        if !config.enabled {
+            return None;
+        }
+        let target = config.targets.get(app_type_str)?;
+        let provider = match target.provider_id.as_deref() {
+            Some(id) => match state.db.get_provider_by_id(id, app_type_str) {
+                Ok(Some(provider)) => Some(provider),
+                _ => {
+                    log::warn!(
+                        "[{}] [RECT-023] 上下文超长回退供应商 {} 不存在，忽略",
+                        tag,
+                        id
+                    );
+                    return None;
+                }
+            },
+            None => None,
+        };
+        if provider.is_none() && target.model.is_none() {
+            return None;
+        }
+        Some(ContextFallback {
+            provider,
+            model: target.model.clone(),
         })
     }
 
@@ -167,21 +393,18 @@ impl RequestContext {
     ///
     /// Gemini API 的模型名称在 URI 中，格式如：
     /// `/v1beta/models/gemini-pro:generateContent`
-    pub fn with_model_from_uri(mut self, uri: &axum::http::Uri) -> Self {
+    pub fn model_from_uri(uri: &axum::http::Uri) -> String {
         let endpoint = uri
             .path_and_query()
             .map(|pq| pq.as_str())
             .unwrap_or(uri.path());
 
-        self.request_model = endpoint
-            .split('/')
-            .find(|s| s.starts_with("models/"))
-            .and_then(|s| s.strip_prefix("models/"))
-            .map(|s| s.split(':').next().unwrap_or(s))
+        endpoint
+            .split_once("models/")
+            .and_then(|(_, rest)| rest.split([':', '/', '?']).next())
+            .filter(|s| !s.is_empty())
             .unwrap_or("unknown")
-            .to_string();
-
-        self
+            .to_string()
     }
 
     /// 创建 RequestForwarder
@@ -220,9 +443,130 @@ impl RequestContext {
             first_byte_timeout,
             idle_timeout,
             self.rectifier_config.clone(),
+            self.model_override.clone(),
+            self.hedge_options(state),
+            self.trace.clone(),
+            self.hooks.clone(),
+            self.secret_scan.clone(),
+            self.context_fallback.clone(),
+            self.mock_fixtures.clone(),
         )
     }
 
+    /// 为实际响应的供应商加载响应钩子（没有适用的钩子时返回 None）
+    ///
+    /// 加载会编译并执行钩子脚本，在阻塞线程池中进行
+    pub async fn response_hook_runner(&self) -> Option<HookRunner> {
+        if !self.hooks.iter().any(|h| h.applies_to(&self.provider.id)) {
+            return None;
+        }
+        let hooks = self.hooks.clone();
+        let provider_id = self.provider.id.clone();
+        let info = hook_info(
+            self.app_type_str,
+            &self.provider.id,
+            &self.provider.name,
+            &self.request_model,
+        );
+        tokio::task::spawn_blocking(move || HookRunner::load(&hooks, &provider_id, info))
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("[{}] 响应钩子加载中断: {e}", log_hook::LOAD_FAILED);
+                None
+            })
+    }
+
+    /// 构建对冲请求参数（未开启或没有备选供应商时返回 None）
+    fn hedge_options(&self, state: &ProxyState) -> Option<HedgeOptions> {
+        if self.providers.len() < 2 {
+            return None;
+        }
+        let config = state.db.get_hedging_config().unwrap_or_default();
+        if !config.enabled {
+            return None;
+        }
+        Some(HedgeOptions {
+            config,
+            db: state.db.clone(),
+            app_type: self.app_type_str.to_string(),
+            request_model: self.request_model.clone(),
+            session_id: self.session_id.clone(),
+            routing_rule: self.routing_rule.clone(),
+            client_token_id: self.client_token_id.clone(),
+        })
+    }
+
+    /// 查询响应缓存
+    ///
+    /// 缓存键使用首选供应商映射后的模型；未命中时保留凭据，供响应成功后写入。
+    /// 仅可缓存的请求计入 ProxyStatus 的命中/未命中次数。
+    pub async fn lookup_response_cache(
+        &mut self,
+        state: &ProxyState,
+        endpoint: &str,
+        body: &serde_json::Value,
+    ) -> Option<Arc<CachedResponse>> {
+        let config = state.db.get_response_cache_config().unwrap_or_default();
+        if !config.enabled {
+            return None;
+        }
+
+        let primary = self.providers.first()?;
+        let mapped_model = match &self.model_override {
+            Some((provider_id, model)) if *provider_id == primary.id => model.clone(),
+            _ => {
+                let mapping = ModelMapping::from_provider(primary);
+                if mapping.has_mapping() {
+                    mapping.map_model(&self.request_model, has_thinking_enabled(body))
+                } else {
+                    self.request_model.clone()
+                }
+            }
+        };
+        let key = cache_key(self.app_type_str, &mapped_model, endpoint, body)?;
+
+        let hit = state
+            .response_cache
+            .get(&key, Duration::from_secs(config.ttl_seconds));
+        {
+            let mut status = state.status.write().await;
+            if hit.is_some() {
+                status.cache_hits += 1;
+            } else {
+                status.cache_misses += 1;
+            }
+        }
+
+        if hit.is_none() {
+            self.response_cache = Some(ResponseCacheTicket {
+                key,
+                provider_id: primary.id.clone(),
+                config,
+            });
+        }
+        hit
+    }
+
+    /// 获取可用于写入缓存的凭据（仅当响应来自计算缓存键的首选供应商时）
+    pub fn response_cache_ticket(&self) -> Option<&ResponseCacheTicket> {
+        self.response_cache
+            .as_ref()
+            .filter(|ticket| ticket.provider_id == self.provider.id)
+    }
+
+    /// 请求成功后将会话绑定到实际使用的供应商（会话粘性路由）
+    ///
+    /// 单供应商（故障转移关闭）时无需绑定
+    pub fn pin_session(&self, state: &ProxyState) {
+        if self.session_client_provided && self.providers.len() > 1 {
+            state.provider_router.pin_session(
+                self.app_type_str,
+                &self.session_id,
+                &self.provider.id,
+            );
+        }
+    }
+
     /// 获取 Provider 列表（用于故障转移）
     ///
     /// 返回在创建上下文时已选择的 providers，避免重复调用 select_providers()
@@ -236,6 +580,23 @@ impl RequestContext {
         self.start_time.elapsed().as_millis() as u64
     }
 
+    /// 将请求归属到认证中间件识别出的客户端
+    pub fn set_client(&mut self, client: Option<ClientIdentity>) {
+        if let Some(client) = client {
+            if let Some(trace) = &self.trace {
+                trace.set_attribute("cc_switch.client", &client.name);
+            }
+            self.client_token_id = Some(client.token_id);
+        }
+    }
+
+    /// 在请求链路上记录返回给客户端的状态码（未开启链路追踪时为空操作）
+    pub fn record_trace_response(&self, status_code: u16) {
+        if let Some(trace) = self.trace.as_ref() {
+            trace.record_response(status_code, &self.provider.id);
+        }
+    }
+
     /// 获取流式超时配置
     ///
     /// 配置生效规则：
@@ -258,3 +619,24 @@ impl RequestContext {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_from_uri() {
+        let uri: axum::http::Uri = "/v1beta/models/gemini-2.5-pro:generateContent"
+            .parse()
+            .unwrap();
+        assert_eq!(RequestContext::model_from_uri(&uri), "gemini-2.5-pro");
+
+        let uri: axum::http::Uri = "/v1beta/models/gemini-2.5-flash:streamGenerateContent?alt=sse"
+            .parse()
+            .unwrap();
+        assert_eq!(RequestContext::model_from_uri(&uri), "gemini-2.5-flash");
+
+        let uri: axum::http::Uri = "/v1/messages".parse().unwrap();
+        assert_eq!(RequestContext::model_from_uri(&uri), "unknown");
+    }
+}
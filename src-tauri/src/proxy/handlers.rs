@@ -8,20 +8,25 @@
 //! - Claude 的格式转换逻辑保留在此文件（用于 OpenRouter 旧接口回退）
 
 use super::{
+    client_auth::ClientIdentity,
     error_mapper::{get_error_message, map_proxy_error_to_status},
     handler_config::{
         CLAUDE_PARSER_CONFIG, CODEX_PARSER_CONFIG, GEMINI_PARSER_CONFIG, OPENAI_PARSER_CONFIG,
     },
     handler_context::RequestContext,
+    otel::trace_stream,
     providers::{get_adapter, streaming::create_anthropic_sse_stream, transform},
-    response_processor::{create_logged_passthrough_stream, process_response, SseUsageCollector},
+    response_processor::{
+        create_logged_passthrough_stream, process_response, serve_cached_response,
+        SseUsageCollector,
+    },
     server::ProxyState,
     types::*,
     usage::parser::TokenUsage,
     ProxyError,
 };
 use crate::app_config::AppType;
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension, Json};
 use serde_json::{json, Value};
 
 // ============================================================================
@@ -45,6 +50,19 @@ pub async fn get_status(State(state): State<ProxyState>) -> Result<Json<ProxySta
     Ok(Json(status))
 }
 
+/// Prometheus 指标（文本格式）
+pub async fn metrics(State(state): State<ProxyState>) -> impl IntoResponse {
+    let status = state.status.read().await.clone();
+    let breakers = state.provider_router.circuit_breaker_snapshot().await;
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            super::metrics::CONTENT_TYPE,
+        )],
+        super::metrics::global().render(&status, &breakers),
+    )
+}
+
 // ============================================================================
 // Claude API 处理器（包含格式转换逻辑）
 // ============================================================================
@@ -57,16 +75,25 @@ pub async fn get_status(State(state): State<ProxyState>) -> Result<Json<ProxySta
 pub async fn handle_messages(
     State(state): State<ProxyState>,
     headers: axum::http::HeaderMap,
+    client: Option<Extension<ClientIdentity>>,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
     let mut ctx =
         RequestContext::new(&state, &body, &headers, AppType::Claude, "Claude", "claude").await?;
+    ctx.set_client(client.map(|Extension(c)| c));
 
     let is_stream = body
         .get("stream")
         .and_then(|s| s.as_bool())
         .unwrap_or(false);
 
+    if let Some(cached) = ctx
+        .lookup_response_cache(&state, "/v1/messages", &body)
+        .await
+    {
+        return serve_cached_response(&cached, &ctx, &state);
+    }
+
     // 转发请求
     let forwarder = ctx.create_forwarder(&state);
     let result = match forwarder
@@ -90,6 +117,8 @@ pub async fn handle_messages(
     };
 
     ctx.provider = result.provider;
+    ctx.rectifier = result.rectifier;
+    ctx.pin_session(&state);
     let response = result.response;
 
     // 检查是否需要格式转换（OpenRouter 等中转服务）
@@ -116,6 +145,7 @@ async fn handle_claude_transform(
     is_stream: bool,
 ) -> Result<axum::response::Response, ProxyError> {
     let status = response.status();
+    ctx.record_trace_response(status.as_u16());
 
     if is_stream {
         // 流式响应转换 (OpenAI SSE → Anthropic SSE)
@@ -131,6 +161,9 @@ async fn handle_claude_transform(
             let status_code = status.as_u16();
             let start_time = ctx.start_time;
             let request_body = ctx.request_body.clone();
+            let routing_rule = ctx.routing_rule.clone();
+            let client_token_id = ctx.client_token_id.clone();
+            let rectifier = ctx.rectifier;
 
             SseUsageCollector::new(
                 start_time,
@@ -156,6 +189,8 @@ async fn handle_claude_transform(
                         let provider_id = provider_id.clone();
                         let model = model.clone();
                         let request_body = request_body.clone();
+                        let routing_rule = routing_rule.clone();
+                        let client_token_id = client_token_id.clone();
                         // For streaming responses, if there is a merged output text, it should be used as the response body first.
                         let final_body = if let Some(ref combined) = combined_output {
                             Some(combined.clone())
@@ -175,6 +210,9 @@ async fn handle_claude_transform(
                                 first_token_ms,
                                 true,
                                 status_code,
+                                routing_rule,
+                                client_token_id,
+                                rectifier,
                                 request_body,
                                 final_body,
                             )
@@ -211,7 +249,7 @@ async fn handle_claude_transform(
             axum::http::HeaderValue::from_static("keep-alive"),
         );
 
-        let body = axum::body::Body::from_stream(logged_stream);
+        let body = axum::body::Body::from_stream(trace_stream(logged_stream, ctx.trace.clone()));
         return Ok((headers, body).into_response());
     }
 
@@ -245,6 +283,9 @@ async fn handle_claude_transform(
 
         let request_model = ctx.request_model.clone();
         let request_body = ctx.request_body.clone();
+        let routing_rule = ctx.routing_rule.clone();
+        let client_token_id = ctx.client_token_id.clone();
+        let rectifier = ctx.rectifier;
         let app_type = ctx.app_type_str;
         let response_body = serde_json::to_string(&anthropic_response).ok();
         tokio::spawn({
@@ -263,6 +304,9 @@ async fn handle_claude_transform(
                     None, // first_token_ms
                     false,
                     status.as_u16(),
+                    routing_rule,
+                    client_token_id,
+                    rectifier,
                     request_body,
                     response_body,
                 )
@@ -304,16 +348,25 @@ async fn handle_claude_transform(
 pub async fn handle_chat_completions(
     State(state): State<ProxyState>,
     headers: axum::http::HeaderMap,
+    client: Option<Extension<ClientIdentity>>,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
     let mut ctx =
         RequestContext::new(&state, &body, &headers, AppType::Codex, "Codex", "codex").await?;
+    ctx.set_client(client.map(|Extension(c)| c));
 
     let is_stream = body
         .get("stream")
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
 
+    if let Some(cached) = ctx
+        .lookup_response_cache(&state, "/chat/completions", &body)
+        .await
+    {
+        return serve_cached_response(&cached, &ctx, &state);
+    }
+
     let forwarder = ctx.create_forwarder(&state);
     let result = match forwarder
         .forward_with_retry(
@@ -336,6 +389,8 @@ pub async fn handle_chat_completions(
     };
 
     ctx.provider = result.provider;
+    ctx.rectifier = result.rectifier;
+    ctx.pin_session(&state);
     let response = result.response;
 
     process_response(response, &ctx, &state, &OPENAI_PARSER_CONFIG).await
@@ -345,16 +400,22 @@ pub async fn handle_chat_completions(
 pub async fn handle_responses(
     State(state): State<ProxyState>,
     headers: axum::http::HeaderMap,
+    client: Option<Extension<ClientIdentity>>,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
     let mut ctx =
         RequestContext::new(&state, &body, &headers, AppType::Codex, "Codex", "codex").await?;
+    ctx.set_client(client.map(|Extension(c)| c));
 
     let is_stream = body
         .get("stream")
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
 
+    if let Some(cached) = ctx.lookup_response_cache(&state, "/responses", &body).await {
+        return serve_cached_response(&cached, &ctx, &state);
+    }
+
     let forwarder = ctx.create_forwarder(&state);
     let result = match forwarder
         .forward_with_retry(
@@ -377,6 +438,8 @@ pub async fn handle_responses(
     };
 
     ctx.provider = result.provider;
+    ctx.rectifier = result.rectifier;
+    ctx.pin_session(&state);
     let response = result.response;
 
     process_response(response, &ctx, &state, &CODEX_PARSER_CONFIG).await
@@ -391,12 +454,21 @@ pub async fn handle_gemini(
     State(state): State<ProxyState>,
     uri: axum::http::Uri,
     headers: axum::http::HeaderMap,
+    client: Option<Extension<ClientIdentity>>,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
     // Gemini 的模型名称在 URI 中
-    let mut ctx = RequestContext::new(&state, &body, &headers, AppType::Gemini, "Gemini", "gemini")
-        .await?
-        .with_model_from_uri(&uri);
+    let mut ctx = RequestContext::new_with_model(
+        &state,
+        &body,
+        &headers,
+        AppType::Gemini,
+        "Gemini",
+        "gemini",
+        RequestContext::model_from_uri(&uri),
+    )
+    .await?;
+    ctx.set_client(client.map(|Extension(c)| c));
 
     // 提取完整的路径和查询参数
     let endpoint = uri
@@ -409,6 +481,10 @@ pub async fn handle_gemini(
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
 
+    if let Some(cached) = ctx.lookup_response_cache(&state, endpoint, &body).await {
+        return serve_cached_response(&cached, &ctx, &state);
+    }
+
     let forwarder = ctx.create_forwarder(&state);
     let result = match forwarder
         .forward_with_retry(
@@ -431,6 +507,8 @@ pub async fn handle_gemini(
     };
 
     ctx.provider = result.provider;
+    ctx.rectifier = result.rectifier;
+    ctx.pin_session(&state);
     let response = result.response;
 
     process_response(response, &ctx, &state, &GEMINI_PARSER_CONFIG).await
@@ -448,10 +526,11 @@ fn log_forward_error(
 ) {
     use super::usage::logger::UsageLogger;
 
-    let logger = UsageLogger::new(&state.db);
+    let logger = UsageLogger::new(&state.db).with_client_token(ctx.client_token_id.clone());
     let status_code = map_proxy_error_to_status(error);
     let error_message = get_error_message(error);
     let request_id = uuid::Uuid::new_v4().to_string();
+    ctx.record_trace_response(status_code);
 
     if let Err(e) = logger.log_error_with_context(
         request_id,
@@ -464,6 +543,7 @@ fn log_forward_error(
         is_streaming,
         Some(ctx.session_id.clone()),
         None,
+        ctx.routing_rule.clone(),
     ) {
         log::warn!("记录失败请求日志失败: {e}");
     }
@@ -482,12 +562,17 @@ async fn log_usage(
     first_token_ms: Option<u64>,
     is_streaming: bool,
     status_code: u16,
+    routing_rule: Option<String>,
+    client_token_id: Option<String>,
+    rectifier: Option<&'static str>,
     request_body: Option<String>,
     response_body: Option<String>,
 ) {
     use super::usage::logger::UsageLogger;
 
-    let logger = UsageLogger::new(&state.db);
+    let logger = UsageLogger::new(&state.db)
+        .with_client_token(client_token_id)
+        .with_rectifier(rectifier);
 
     let (multiplier, pricing_model_source) =
         logger.resolve_pricing_config(provider_id, app_type).await;
@@ -516,6 +601,7 @@ async fn log_usage(
         is_streaming,
         request_body,
         response_body,
+        routing_rule,
     ) {
         log::warn!("[USG-001] 记录使用量失败: {e}");
     }
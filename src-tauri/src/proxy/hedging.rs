@@ -0,0 +1,326 @@
+//! 对冲请求模块
+//!
+//! 小体积非流式请求（标题生成、摘要、count_tokens 等）的感知延迟往往由单个慢中转决定。
+//! 开启对冲后，若首个供应商在近期延迟分位数内仍未响应，转发器会向故障转移队列中的
+//! 下一个供应商并发发起同样的请求，取先成功者并取消另一个。
+//!
+//! 落败的一方同样写入请求日志（归属到它自己的供应商）。被取消的请求记为 499，
+//! 上游已收到完整请求，因此按估算的输入 token 计费，花费报表据此体现对冲的额外成本；
+//! 胜出方的用量由正常的响应处理流程按实际供应商记录。
+
+use crate::database::Database;
+use crate::provider::Provider;
+use crate::proxy::model_mapper::ModelMapping;
+use crate::proxy::types::HedgingConfig;
+use crate::proxy::usage::calculator::CostCalculator;
+use crate::proxy::usage::logger::{RequestLog, UsageLogger};
+use crate::proxy::usage::parser::TokenUsage;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 计算延迟分位数时使用的样本数
+const LATENCY_SAMPLE_SIZE: u32 = 50;
+
+/// 样本不足时不对冲（无法判断"慢"）
+const MIN_LATENCY_SAMPLES: usize = 5;
+
+/// 被取消的对冲请求使用的状态码（与 nginx 的 "client closed request" 一致）
+pub const HEDGE_CANCELLED_STATUS: u16 = 499;
+
+/// 对冲请求参数（仅在配置开启且存在备选供应商时由 RequestContext 注入转发器）
+pub struct HedgeOptions {
+    pub config: HedgingConfig,
+    pub db: Arc<Database>,
+    pub app_type: String,
+    pub request_model: String,
+    pub session_id: String,
+    pub routing_rule: Option<String>,
+    pub client_token_id: Option<String>,
+}
+
+impl HedgeOptions {
+    /// 请求是否适合对冲：非流式且请求体不超过大小上限
+    pub fn is_eligible(&self, endpoint: &str, body: &Value) -> bool {
+        if body
+            .get("stream")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+        {
+            return false;
+        }
+        // Gemini 的流式请求体现在端点中（streamGenerateContent / alt=sse）
+        if endpoint.contains("stream") || endpoint.contains("alt=sse") {
+            return false;
+        }
+        serde_json::to_vec(body)
+            .map(|bytes| bytes.len() as u64 <= self.config.max_request_bytes)
+            .unwrap_or(false)
+    }
+
+    /// 计算对首个供应商的等待时间
+    ///
+    /// 取该供应商近期成功非流式请求延迟的分位数，且不低于 min_delay_ms；
+    /// 样本不足时返回 None（不对冲）
+    pub fn delay_for(&self, provider_id: &str) -> Option<Duration> {
+        let mut samples = match self.db.get_recent_latency_samples(
+            provider_id,
+            &self.app_type,
+            LATENCY_SAMPLE_SIZE,
+        ) {
+            Ok(samples) => samples,
+            Err(e) => {
+                log::warn!(
+                    "[{}] 读取供应商 {provider_id} 延迟样本失败: {e}",
+                    self.app_type
+                );
+                return None;
+            }
+        };
+        if samples.len() < MIN_LATENCY_SAMPLES {
+            return None;
+        }
+
+        let percentile = latency_percentile(&mut samples, self.config.latency_percentile)?;
+        Some(Duration::from_millis(
+            percentile.max(self.config.min_delay_ms),
+        ))
+    }
+
+    /// 记录失败的对冲请求，归属到该请求实际使用的供应商
+    pub fn log_attempt(
+        &self,
+        provider_id: &str,
+        status_code: u16,
+        error_message: String,
+        latency_ms: u64,
+    ) {
+        let logger = UsageLogger::new(&self.db).with_client_token(self.client_token_id.clone());
+        let request_id = uuid::Uuid::new_v4().to_string();
+
+        if let Err(e) = logger.log_error_with_context(
+            request_id,
+            provider_id.to_string(),
+            self.app_type.clone(),
+            self.request_model.clone(),
+            status_code,
+            error_message,
+            latency_ms,
+            false,
+            Some(self.session_id.clone()),
+            None,
+            self.routing_rule.clone(),
+        ) {
+            log::warn!("[USG-001] 记录对冲请求日志失败: {e}");
+        }
+    }
+
+    /// 记录被取消的对冲请求
+    ///
+    /// 按请求体约 4 字符/token 估算输入 token，并按该供应商的模型映射与倍率计算输入成本
+    pub async fn log_cancelled(
+        &self,
+        provider: &Provider,
+        target_model: Option<&str>,
+        body: &Value,
+        error_message: String,
+        latency_ms: u64,
+    ) {
+        let logger = UsageLogger::new(&self.db).with_client_token(self.client_token_id.clone());
+        let model = target_model.map(str::to_string).unwrap_or_else(|| {
+            let mapping = ModelMapping::from_provider(provider);
+            if mapping.has_mapping() {
+                mapping.map_model(&self.request_model, false)
+            } else {
+                self.request_model.clone()
+            }
+        });
+        let usage = TokenUsage {
+            input_tokens: estimate_input_tokens(body),
+            ..Default::default()
+        };
+
+        let (cost_multiplier, _) = logger
+            .resolve_pricing_config(&provider.id, &self.app_type)
+            .await;
+        let pricing = match logger.get_model_pricing(&model) {
+            Ok(pricing) => pricing,
+            Err(e) => {
+                log::warn!("[USG-002] 查询模型定价失败 ({model}): {e}");
+                None
+            }
+        };
+        let cost = CostCalculator::try_calculate(
+            &usage,
+            pricing
+                .as_ref()
+                .map(|p| p.for_prompt_tokens(usage.prompt_tokens(&self.app_type))),
+            cost_multiplier,
+        );
+
+        let log = RequestLog {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            provider_id: provider.id.clone(),
+            app_type: self.app_type.clone(),
+            model,
+            request_model: self.request_model.clone(),
+            usage,
+            cost,
+            latency_ms,
+            first_token_ms: None,
+            status_code: HEDGE_CANCELLED_STATUS,
+            error_message: Some(error_message),
+            session_id: Some(self.session_id.clone()),
+            provider_type: None,
+            is_streaming: false,
+            cost_multiplier: cost_multiplier.to_string(),
+            request_body: None,
+            response_body: None,
+            routing_rule: self.routing_rule.clone(),
+            from_cache: false,
+            client_token_id: self.client_token_id.clone(),
+            rectifier: None,
+        };
+        if let Err(e) = logger.log_request(&log) {
+            log::warn!("[USG-001] 记录对冲请求日志失败: {e}");
+        }
+    }
+}
+
+/// 按约 4 字符/token 估算请求体的输入 token 数
+fn estimate_input_tokens(body: &Value) -> u32 {
+    let bytes = serde_json::to_vec(body).map(|b| b.len()).unwrap_or(0);
+    (bytes / 4).max(1).min(u32::MAX as usize) as u32
+}
+
+/// 最近秩法计算分位数（percentile 会被限制在 1-99）
+pub fn latency_percentile(samples: &mut [u64], percentile: u8) -> Option<u64> {
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_unstable();
+    let percentile = percentile.clamp(1, 99) as usize;
+    let rank = (percentile * samples.len()).div_ceil(100);
+    Some(samples[rank.saturating_sub(1)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn options(db: Arc<Database>) -> HedgeOptions {
+        HedgeOptions {
+            config: HedgingConfig {
+                enabled: true,
+                latency_percentile: 90,
+                min_delay_ms: 200,
+                max_request_bytes: 256,
+            },
+            db,
+            app_type: "claude".to_string(),
+            request_model: "claude-haiku-4-5".to_string(),
+            session_id: "s1".to_string(),
+            routing_rule: None,
+            client_token_id: None,
+        }
+    }
+
+    #[test]
+    fn test_latency_percentile() {
+        let mut samples: Vec<u64> = (1..=10).map(|v| v * 100).collect();
+        assert_eq!(latency_percentile(&mut samples, 90), Some(900));
+        assert_eq!(latency_percentile(&mut samples, 50), Some(500));
+        assert_eq!(latency_percentile(&mut samples, 1), Some(100));
+        // 超出范围按 99 处理
+        assert_eq!(latency_percentile(&mut samples, 255), Some(1000));
+        assert_eq!(latency_percentile(&mut [], 90), None);
+    }
+
+    #[test]
+    fn test_is_eligible() {
+        let opts = options(Arc::new(Database::memory().unwrap()));
+
+        assert!(opts.is_eligible("/v1/messages/count_tokens", &json!({"model": "m"})));
+        assert!(!opts.is_eligible("/v1/messages", &json!({"model": "m", "stream": true})));
+        assert!(!opts.is_eligible(
+            "/v1beta/models/gemini-2.5-flash:streamGenerateContent?alt=sse",
+            &json!({})
+        ));
+        let large = json!({"model": "m", "messages": "x".repeat(1024)});
+        assert!(!opts.is_eligible("/v1/messages", &large));
+    }
+
+    #[test]
+    fn test_delay_uses_percentile_with_floor() {
+        let db = Arc::new(Database::memory().unwrap());
+        let opts = options(db.clone());
+
+        let insert = |id: &str, latency: i64, streaming: bool| {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                    latency_ms, status_code, is_streaming, created_at)
+                 VALUES (?1, 'p1', 'claude', 'm', ?2, 200, ?3, 1000)",
+                rusqlite::params![id, latency, streaming],
+            )
+            .unwrap();
+        };
+
+        // 样本不足：不对冲
+        for i in 0..4 {
+            insert(&format!("r{i}"), 100, false);
+        }
+        insert("stream", 9000, true);
+        assert_eq!(opts.delay_for("p1"), None);
+
+        // 样本充足：P90 低于下限时取下限
+        insert("r4", 150, false);
+        assert_eq!(opts.delay_for("p1"), Some(Duration::from_millis(200)));
+
+        for i in 5..10 {
+            insert(&format!("r{i}"), 1000, false);
+        }
+        assert_eq!(opts.delay_for("p1"), Some(Duration::from_millis(1000)));
+    }
+
+    #[test]
+    fn test_cancelled_attempt_bills_estimated_input() {
+        let db = Arc::new(Database::memory().unwrap());
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "INSERT OR REPLACE INTO model_pricing (model_id, display_name, input_cost_per_million, output_cost_per_million)
+                 VALUES ('hedge-model', 'Hedge Model', '1000000', '1000000')",
+                [],
+            )
+            .unwrap();
+        }
+        let opts = options(db.clone());
+        let provider = Provider::with_id("p1".to_string(), "P1".to_string(), json!({}), None);
+        let body = json!({"model": "m", "messages": "x".repeat(400)});
+
+        tauri::async_runtime::block_on(opts.log_cancelled(
+            &provider,
+            Some("hedge-model"),
+            &body,
+            "cancelled".to_string(),
+            120,
+        ));
+
+        let conn = db.conn.lock().unwrap();
+        let (status, input_tokens, output_cost, total_cost): (i64, i64, String, String) = conn
+            .query_row(
+                "SELECT status_code, input_tokens, output_cost_usd, total_cost_usd
+                 FROM proxy_request_logs WHERE provider_id = 'p1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(status, HEDGE_CANCELLED_STATUS as i64);
+        assert_eq!(input_tokens, estimate_input_tokens(&body) as i64);
+        assert!(input_tokens > 100);
+        assert_eq!(output_cost.parse::<f64>().unwrap(), 0.0);
+        assert_eq!(total_cost.parse::<f64>().unwrap(), input_tokens as f64);
+    }
+}
@@ -0,0 +1,753 @@
+//! 请求/响应 JS 钩子模块
+//!
+//! 每个 app 维护一组用户脚本（可限定到单个供应商），在 QuickJS 沙箱中执行：
+//! 沙箱内没有网络与文件访问，每次调用有执行时间上限，运行时有内存上限。
+//! 脚本可以定义以下任意函数，未定义的阶段直接跳过：
+//! - `onRequest(request, info)`：改写发往上游的 `{ body, headers }`
+//! - `onResponse(response, info)`：检查/改写非流式响应 `{ status, headers, body }`
+//! - `onSseEvent(event, info)`：检查/改写单个 SSE 事件 `{ event, data }`，返回 `null` 丢弃该事件
+//!
+//! 返回 `undefined` 表示不修改。多个钩子按 sort_index 依次执行，前一个的输出是后一个的输入。
+//! 钩子报错或超时只记录日志并跳过，不影响请求本身。
+
+use super::log_codes::hook as log_hook;
+use bytes::Bytes;
+use futures::stream::{Stream, StreamExt};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use rquickjs::{Context, Ctx, Function, Runtime};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 单次调用的默认执行时间上限（毫秒）
+pub const DEFAULT_HOOK_TIMEOUT_MS: u64 = 100;
+/// 单次调用允许配置的最大执行时间（毫秒）
+pub const MAX_HOOK_TIMEOUT_MS: u64 = 5_000;
+/// 钩子运行时内存上限
+const HOOK_MEMORY_LIMIT: usize = 128 * 1024 * 1024;
+
+/// 请求/响应钩子
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyHook {
+    pub id: String,
+    pub app_type: String,
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub sort_index: i64,
+    /// 限定的供应商（为空时对该 app 的全部供应商生效）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
+    pub script: String,
+    /// 单次调用的执行时间上限（毫秒）
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_timeout_ms() -> u64 {
+    DEFAULT_HOOK_TIMEOUT_MS
+}
+
+impl ProxyHook {
+    /// 钩子是否作用于指定供应商
+    pub fn applies_to(&self, provider_id: &str) -> bool {
+        self.enabled
+            && self
+                .provider_id
+                .as_deref()
+                .filter(|id| !id.is_empty())
+                .is_none_or(|id| id == provider_id)
+    }
+}
+
+/// 钩子阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    Request,
+    Response,
+    SseEvent,
+}
+
+impl HookStage {
+    fn function_name(self) -> &'static str {
+        match self {
+            HookStage::Request => "onRequest",
+            HookStage::Response => "onResponse",
+            HookStage::SseEvent => "onSseEvent",
+        }
+    }
+
+    const ALL: [HookStage; 3] = [HookStage::Request, HookStage::Response, HookStage::SseEvent];
+}
+
+/// 钩子执行结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookOutcome {
+    /// 未修改
+    Keep,
+    /// 替换为新值
+    Replace(Value),
+    /// 丢弃（仅 SSE 事件）
+    Drop,
+}
+
+/// 已加载到沙箱中的钩子
+struct LoadedHook {
+    name: String,
+    timeout: Duration,
+    context: Context,
+    stages: Vec<HookStage>,
+}
+
+/// 钩子执行器
+///
+/// 持有一个 QuickJS 运行时，每个钩子使用独立的上下文（全局变量互不干扰）。
+/// 流式响应在整个流的生命周期内复用同一个执行器。
+pub struct HookRunner {
+    runtime: Runtime,
+    hooks: Vec<LoadedHook>,
+    /// 当前调用的截止时间（相对 base 的毫秒数）
+    deadline: Arc<AtomicU64>,
+    base: Instant,
+    /// 传给脚本的第二个参数（app、供应商、模型）
+    info: String,
+}
+
+impl HookRunner {
+    /// 加载作用于指定供应商的钩子；没有可用钩子时返回 None
+    pub fn load(hooks: &[ProxyHook], provider_id: &str, info: Value) -> Option<Self> {
+        let applicable: Vec<&ProxyHook> =
+            hooks.iter().filter(|h| h.applies_to(provider_id)).collect();
+        if applicable.is_empty() {
+            return None;
+        }
+
+        let mut runner = match Self::new(info.to_string()) {
+            Ok(runner) => runner,
+            Err(e) => {
+                log::warn!("[{}] {e}", log_hook::LOAD_FAILED);
+                return None;
+            }
+        };
+
+        for hook in applicable {
+            match runner.load_hook(hook) {
+                Ok(loaded) if !loaded.stages.is_empty() => runner.hooks.push(loaded),
+                Ok(_) => log::warn!(
+                    "[{}] 钩子 {} 未定义 onRequest/onResponse/onSseEvent，已忽略",
+                    log_hook::LOAD_FAILED,
+                    hook.name
+                ),
+                Err(e) => log::warn!(
+                    "[{}] 加载钩子 {} 失败: {e}",
+                    log_hook::LOAD_FAILED,
+                    hook.name
+                ),
+            }
+        }
+
+        if runner.hooks.is_empty() {
+            None
+        } else {
+            Some(runner)
+        }
+    }
+
+    /// 创建带内存上限与执行时间中断的运行时
+    fn new(info: String) -> Result<Self, String> {
+        let runtime = Runtime::new().map_err(|e| format!("Failed to create JS runtime: {e}"))?;
+        runtime.set_memory_limit(HOOK_MEMORY_LIMIT);
+
+        let base = Instant::now();
+        let deadline = Arc::new(AtomicU64::new(u64::MAX));
+        {
+            let deadline = deadline.clone();
+            runtime.set_interrupt_handler(Some(Box::new(move || {
+                base.elapsed().as_millis() as u64 > deadline.load(Ordering::Relaxed)
+            })));
+        }
+
+        Ok(Self {
+            runtime,
+            hooks: Vec::new(),
+            deadline,
+            base,
+            info,
+        })
+    }
+
+    fn load_hook(&self, hook: &ProxyHook) -> Result<LoadedHook, String> {
+        let context = Context::full(&self.runtime)
+            .map_err(|e| format!("Failed to create JS context: {e}"))?;
+        let timeout = Duration::from_millis(hook.timeout_ms.clamp(1, MAX_HOOK_TIMEOUT_MS));
+
+        self.arm(timeout);
+        let stages = context.with(|ctx| {
+            ctx.eval::<(), _>(hook.script.as_str())
+                .map_err(|e| describe_error(&ctx, e))?;
+            let globals = ctx.globals();
+            Ok::<_, String>(
+                HookStage::ALL
+                    .into_iter()
+                    .filter(|stage| {
+                        globals
+                            .get::<_, Option<Function>>(stage.function_name())
+                            .ok()
+                            .flatten()
+                            .is_some()
+                    })
+                    .collect(),
+            )
+        });
+        let timed_out = self.disarm();
+        let stages = stages.map_err(|e| {
+            if timed_out {
+                timeout_message(timeout)
+            } else {
+                e
+            }
+        })?;
+
+        Ok(LoadedHook {
+            name: hook.name.clone(),
+            timeout,
+            context,
+            stages,
+        })
+    }
+
+    /// 是否有钩子处理该阶段
+    pub fn handles(&self, stage: HookStage) -> bool {
+        self.hooks.iter().any(|h| h.stages.contains(&stage))
+    }
+
+    /// 依次执行各钩子的指定阶段
+    pub fn run(&self, stage: HookStage, input: Value) -> HookOutcome {
+        let mut current = input;
+        let mut replaced = false;
+
+        for hook in self.hooks.iter().filter(|h| h.stages.contains(&stage)) {
+            match self.call(hook, stage, &current) {
+                Ok(HookOutcome::Keep) => {}
+                Ok(HookOutcome::Replace(value)) => {
+                    current = value;
+                    replaced = true;
+                }
+                Ok(HookOutcome::Drop) => return HookOutcome::Drop,
+                Err(e) => log::warn!(
+                    "[{}] 钩子 {} 执行 {} 失败，已跳过: {e}",
+                    log_hook::RUN_FAILED,
+                    hook.name,
+                    stage.function_name()
+                ),
+            }
+        }
+
+        if replaced {
+            HookOutcome::Replace(current)
+        } else {
+            HookOutcome::Keep
+        }
+    }
+
+    fn call(
+        &self,
+        hook: &LoadedHook,
+        stage: HookStage,
+        input: &Value,
+    ) -> Result<HookOutcome, String> {
+        let input = input.to_string();
+
+        self.arm(hook.timeout);
+        let result = hook.context.with(|ctx| {
+            let function: Function = ctx
+                .globals()
+                .get(stage.function_name())
+                .map_err(|e| describe_error(&ctx, e))?;
+            let arg = ctx.json_parse(input).map_err(|e| describe_error(&ctx, e))?;
+            let info = ctx
+                .json_parse(self.info.as_str())
+                .map_err(|e| describe_error(&ctx, e))?;
+            let output: rquickjs::Value = function
+                .call((arg, info))
+                .map_err(|e| describe_error(&ctx, e))?;
+
+            if output.is_undefined() {
+                return Ok(HookOutcome::Keep);
+            }
+            if output.is_null() {
+                return Ok(HookOutcome::Drop);
+            }
+            let json = ctx
+                .json_stringify(output)
+                .map_err(|e| describe_error(&ctx, e))?
+                .ok_or_else(|| "hook returned a value that cannot be serialized".to_string())?
+                .to_string()
+                .map_err(|e| describe_error(&ctx, e))?;
+            serde_json::from_str(&json)
+                .map(HookOutcome::Replace)
+                .map_err(|e| format!("invalid hook output: {e}"))
+        });
+        let timed_out = self.disarm();
+
+        result.map_err(|e| {
+            if timed_out {
+                timeout_message(hook.timeout)
+            } else {
+                e
+            }
+        })
+    }
+
+    fn arm(&self, timeout: Duration) {
+        let deadline = self.base.elapsed() + timeout;
+        self.deadline
+            .store(deadline.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// 解除截止时间，返回本次调用是否已超时
+    fn disarm(&self) -> bool {
+        let deadline = self.deadline.swap(u64::MAX, Ordering::Relaxed);
+        self.base.elapsed().as_millis() as u64 > deadline
+    }
+}
+
+fn timeout_message(timeout: Duration) -> String {
+    format!("timed out after {}ms", timeout.as_millis())
+}
+
+/// 提取 JS 异常信息
+fn describe_error(ctx: &Ctx<'_>, error: rquickjs::Error) -> String {
+    if error.is_exception() {
+        let value = ctx.catch();
+        if let Some(exception) = value.as_exception() {
+            return exception
+                .message()
+                .unwrap_or_else(|| "unknown exception".to_string());
+        }
+        if let Some(message) = value.as_string().and_then(|s| s.to_string().ok()) {
+            return message;
+        }
+    }
+    error.to_string()
+}
+
+/// 钩子脚本的第二个参数
+pub fn hook_info(app_type: &str, provider_id: &str, provider_name: &str, model: &str) -> Value {
+    json!({
+        "app": app_type,
+        "providerId": provider_id,
+        "providerName": provider_name,
+        "model": model,
+    })
+}
+
+/// 校验钩子脚本：能在沙箱中加载，且至少定义了一个钩子函数
+pub fn validate_hook(hook: &ProxyHook) -> Result<(), String> {
+    let probe = ProxyHook {
+        enabled: true,
+        ..hook.clone()
+    };
+    let runner = HookRunner::new(Value::Null.to_string())?;
+    let loaded = runner.load_hook(&probe)?;
+    if loaded.stages.is_empty() {
+        return Err("Script must define onRequest, onResponse or onSseEvent".to_string());
+    }
+    Ok(())
+}
+
+// ============================================================================
+// 请求 / 非流式响应
+// ============================================================================
+
+fn headers_to_json(headers: &HeaderMap) -> Value {
+    let mut map = Map::new();
+    for (name, value) in headers {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        match map.get_mut(name.as_str()) {
+            Some(Value::String(existing)) => {
+                existing.push_str(", ");
+                existing.push_str(value);
+            }
+            _ => {
+                map.insert(name.as_str().to_string(), Value::String(value.to_string()));
+            }
+        }
+    }
+    Value::Object(map)
+}
+
+fn headers_from_json(value: &Value) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let Some(map) = value.as_object() else {
+        return headers;
+    };
+    for (name, value) in map {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Null => continue,
+            other => other.to_string(),
+        };
+        match (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            (Ok(name), Ok(value)) => {
+                headers.insert(name, value);
+            }
+            _ => log::warn!("[{}] 忽略无效的请求头: {name}", log_hook::RUN_FAILED),
+        }
+    }
+    headers
+}
+
+/// 执行 onRequest，返回改写后的请求体与请求头
+pub fn apply_request_hooks(
+    runner: &HookRunner,
+    body: Value,
+    headers: HeaderMap,
+) -> (Value, HeaderMap) {
+    if !runner.handles(HookStage::Request) {
+        return (body, headers);
+    }
+
+    let input = json!({ "body": body, "headers": headers_to_json(&headers) });
+    match runner.run(HookStage::Request, input) {
+        HookOutcome::Replace(mut output) => {
+            let new_headers = match output.get("headers") {
+                Some(value) => headers_from_json(value),
+                None => headers,
+            };
+            let new_body = match output.get_mut("body").map(Value::take) {
+                Some(value) => value,
+                None => body,
+            };
+            (new_body, new_headers)
+        }
+        HookOutcome::Keep | HookOutcome::Drop => (body, headers),
+    }
+}
+
+/// 改写后的非流式响应
+pub struct HookedResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+/// 执行 onResponse；未修改时返回 None
+pub fn apply_response_hooks(
+    runner: &HookRunner,
+    status: u16,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> Option<HookedResponse> {
+    if !runner.handles(HookStage::Response) {
+        return None;
+    }
+
+    let body_value = serde_json::from_slice::<Value>(body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).to_string()));
+    let input = json!({
+        "status": status,
+        "headers": headers_to_json(headers),
+        "body": body_value,
+    });
+
+    let HookOutcome::Replace(output) = runner.run(HookStage::Response, input) else {
+        return None;
+    };
+
+    let status = output
+        .get("status")
+        .and_then(Value::as_u64)
+        .filter(|s| (100..=599).contains(s))
+        .map(|s| s as u16)
+        .unwrap_or(status);
+    let mut headers = match output.get("headers") {
+        Some(value) => headers_from_json(value),
+        None => headers.clone(),
+    };
+    let body = match output.get("body") {
+        Some(Value::String(text)) => Bytes::from(text.clone()),
+        Some(value) => Bytes::from(value.to_string()),
+        None => body.clone(),
+    };
+    // 响应体长度可能已变化，交给 HTTP 层重新计算
+    headers.remove(reqwest::header::CONTENT_LENGTH);
+    headers.remove(reqwest::header::CONTENT_ENCODING);
+
+    Some(HookedResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+// ============================================================================
+// SSE 事件
+// ============================================================================
+
+/// 对单个 SSE 事件块执行 onSseEvent，返回要输出的字节（None 表示丢弃）
+fn apply_sse_event(runner: &HookRunner, block: &str) -> Option<String> {
+    let mut event_name: Option<String> = None;
+    let mut data_lines: Vec<&str> = Vec::new();
+    for line in block.lines() {
+        if let Some(name) = line.strip_prefix("event:") {
+            event_name = Some(name.trim().to_string());
+        } else if let Some(data) = line.strip_prefix("data:") {
+            data_lines.push(data.strip_prefix(' ').unwrap_or(data));
+        }
+    }
+    if event_name.is_none() && data_lines.is_empty() {
+        return Some(format!("{block}\n\n"));
+    }
+
+    let data = data_lines.join("\n");
+    let data_value =
+        serde_json::from_str::<Value>(&data).unwrap_or_else(|_| Value::String(data.clone()));
+    let input = json!({ "event": event_name, "data": data_value });
+
+    match runner.run(HookStage::SseEvent, input) {
+        HookOutcome::Keep => Some(format!("{block}\n\n")),
+        HookOutcome::Drop => None,
+        HookOutcome::Replace(output) => {
+            let mut text = String::new();
+            if let Some(name) = output.get("event").and_then(Value::as_str) {
+                text.push_str(&format!("event: {name}\n"));
+            }
+            let data = match output.get("data") {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(other) => other.to_string(),
+            };
+            for line in data.split('\n') {
+                text.push_str(&format!("data: {line}\n"));
+            }
+            text.push('\n');
+            Some(text)
+        }
+    }
+}
+
+/// 按 SSE 事件边界对流执行 onSseEvent
+///
+/// 应放在用量收集之后，保证用量统计基于上游原始事件。
+/// 按字节切分事件，只解码完整事件，避免多字节字符跨块时被替换为 U+FFFD；
+/// 钩子在阻塞线程池中执行，慢脚本不会占用异步工作线程。
+pub fn create_sse_hook_stream(
+    stream: impl Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+    runner: HookRunner,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+    let runner = Arc::new(runner);
+    async_stream::stream! {
+        let mut buffer: Vec<u8> = Vec::new();
+        tokio::pin!(stream);
+
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(bytes) => {
+                    buffer.extend_from_slice(&bytes);
+                    let Some(end) = find_last_event_boundary(&buffer) else {
+                        continue;
+                    };
+                    let complete: Vec<u8> = buffer.drain(..end + 2).collect();
+
+                    let hook_runner = runner.clone();
+                    let raw = complete.clone();
+                    let output = tokio::task::spawn_blocking(move || {
+                        String::from_utf8_lossy(&raw)
+                            .split("\n\n")
+                            .filter(|block| !block.is_empty())
+                            .filter_map(|block| apply_sse_event(&hook_runner, block))
+                            .collect::<String>()
+                            .into_bytes()
+                    })
+                    .await
+                    .unwrap_or_else(|e| {
+                        log::warn!("[{}] SSE 钩子执行中断: {e}", log_hook::RUN_FAILED);
+                        complete
+                    });
+                    if !output.is_empty() {
+                        yield Ok(Bytes::from(output));
+                    }
+                }
+                Err(e) => {
+                    yield Err(e);
+                    break;
+                }
+            }
+        }
+
+        // 流结束时残留的不完整事件原样输出
+        if !buffer.is_empty() {
+            yield Ok(Bytes::from(buffer));
+        }
+    }
+}
+
+/// 缓冲区中最后一个事件分隔符 `\n\n` 的起始位置
+fn find_last_event_boundary(buffer: &[u8]) -> Option<usize> {
+    buffer.windows(2).rposition(|pair| pair == b"\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(script: &str) -> ProxyHook {
+        ProxyHook {
+            id: "h1".to_string(),
+            app_type: "claude".to_string(),
+            name: "test hook".to_string(),
+            enabled: true,
+            sort_index: 0,
+            provider_id: None,
+            script: script.to_string(),
+            timeout_ms: 200,
+        }
+    }
+
+    fn runner(hooks: &[ProxyHook]) -> HookRunner {
+        HookRunner::load(hooks, "p1", hook_info("claude", "p1", "Provider", "m")).unwrap()
+    }
+
+    #[test]
+    fn test_request_hook_rewrites_body_and_headers() {
+        let hooks = [hook(
+            r#"function onRequest(req, info) {
+                req.body.system = "be brief (" + info.providerId + ")";
+                delete req.body.metadata;
+                req.headers["x-relay"] = "1";
+                delete req.headers["x-drop"];
+                return req;
+            }"#,
+        )];
+        let mut headers = HeaderMap::new();
+        headers.insert("x-drop", HeaderValue::from_static("yes"));
+        headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
+
+        let (body, headers) = apply_request_hooks(
+            &runner(&hooks),
+            json!({ "model": "m", "metadata": { "user_id": "u" } }),
+            headers,
+        );
+
+        assert_eq!(body, json!({ "model": "m", "system": "be brief (p1)" }));
+        assert_eq!(headers.get("x-relay").unwrap(), "1");
+        assert!(headers.get("x-drop").is_none());
+        assert_eq!(headers.get("anthropic-version").unwrap(), "2023-06-01");
+    }
+
+    #[test]
+    fn test_hooks_chain_in_order_and_skip_failures() {
+        let hooks = [
+            hook("function onRequest(req) { req.body.n = 1; return req; }"),
+            hook("function onRequest(req) { throw new Error('boom'); }"),
+            hook("function onRequest(req) { req.body.n += 1; return req; }"),
+        ];
+        let (body, _) = apply_request_hooks(&runner(&hooks), json!({}), HeaderMap::new());
+        assert_eq!(body, json!({ "n": 2 }));
+    }
+
+    #[test]
+    fn test_hook_timeout_is_enforced() {
+        let hooks = [
+            hook("function onRequest(req) { while (true) {} }"),
+            hook("function onRequest(req) { req.body.after = true; return req; }"),
+        ];
+        let started = Instant::now();
+        let (body, _) = apply_request_hooks(&runner(&hooks), json!({}), HeaderMap::new());
+        assert_eq!(body, json!({ "after": true }));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_response_hook() {
+        let hooks = [hook(
+            r#"function onResponse(res) {
+                res.body.content[0].text = res.body.content[0].text.toUpperCase();
+                return res;
+            }"#,
+        )];
+        let mut headers = HeaderMap::new();
+        headers.insert("content-length", HeaderValue::from_static("42"));
+        let body = Bytes::from(r#"{"content":[{"type":"text","text":"hi"}]}"#);
+
+        let hooked = apply_response_hooks(&runner(&hooks), 200, &headers, &body).unwrap();
+        assert_eq!(hooked.status, 200);
+        assert!(hooked.headers.get("content-length").is_none());
+        let value: Value = serde_json::from_slice(&hooked.body).unwrap();
+        assert_eq!(value["content"][0]["text"], "HI");
+    }
+
+    #[test]
+    fn test_sse_hook_stream() {
+        let hooks = [hook(
+            r#"function onSseEvent(ev) {
+                if (ev.event === "ping") return null;
+                if (ev.data && ev.data.delta) { ev.data.delta.text += "!"; return ev; }
+            }"#,
+        )];
+        let chunks: Vec<Result<Bytes, std::io::Error>> = vec![
+            Ok(Bytes::from(
+                "event: ping\ndata: {}\n\nevent: content_block_delta\n",
+            )),
+            Ok(Bytes::from(
+                "data: {\"delta\":{\"text\":\"a\"}}\n\ndata: [DONE]\n\n",
+            )),
+        ];
+        let stream = create_sse_hook_stream(futures::stream::iter(chunks), runner(&hooks));
+        let output: Vec<Bytes> =
+            tauri::async_runtime::block_on(stream.map(|chunk| chunk.unwrap()).collect::<Vec<_>>());
+        let text: String = output
+            .iter()
+            .map(|b| String::from_utf8_lossy(b).to_string())
+            .collect();
+
+        assert_eq!(
+            text,
+            "event: content_block_delta\ndata: {\"delta\":{\"text\":\"a!\"}}\n\ndata: [DONE]\n\n"
+        );
+    }
+
+    #[test]
+    fn test_sse_hook_stream_keeps_multibyte_chars_split_across_chunks() {
+        let hooks = [hook("function onSseEvent(ev) {}")];
+        let event = "data: {\"delta\":{\"text\":\"你好🙂\"}}\n\n".as_bytes();
+        // 在“你”与 emoji 的中间切分
+        let split_cjk = event.iter().position(|b| *b == 0xE4).unwrap() + 1;
+        let split_emoji = event.iter().position(|b| *b == 0xF0).unwrap() + 2;
+        let chunks: Vec<Result<Bytes, std::io::Error>> = vec![
+            Ok(Bytes::copy_from_slice(&event[..split_cjk])),
+            Ok(Bytes::copy_from_slice(&event[split_cjk..split_emoji])),
+            Ok(Bytes::copy_from_slice(&event[split_emoji..])),
+        ];
+        let stream = create_sse_hook_stream(futures::stream::iter(chunks), runner(&hooks));
+        let output: Vec<Bytes> =
+            tauri::async_runtime::block_on(stream.map(|chunk| chunk.unwrap()).collect::<Vec<_>>());
+
+        assert_eq!(output.concat(), event);
+    }
+
+    #[test]
+    fn test_provider_scope_and_validation() {
+        let mut scoped = hook("function onRequest(req) { return req; }");
+        scoped.provider_id = Some("other".to_string());
+        assert!(HookRunner::load(&[scoped], "p1", Value::Null).is_none());
+
+        assert!(validate_hook(&hook("function onSseEvent(ev) {}")).is_ok());
+        assert!(validate_hook(&hook("var x = 1;")).is_err());
+        assert!(validate_hook(&hook("function onRequest( {")).is_err());
+    }
+}
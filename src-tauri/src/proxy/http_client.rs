@@ -3,12 +3,16 @@
 //! 提供支持全局代理配置的 HTTP 客户端。
 //! 所有需要发送 HTTP 请求的模块都应使用此模块提供的客户端。
 
-use crate::provider::ProviderProxyConfig;
+use super::upstream_rules::{
+    ip_in_network, parse_cidr, ProxyDecision, ProxyRuleSet, UpstreamProxyRules,
+};
+use crate::provider::{ProviderProxyConfig, UpstreamTlsConfig};
 use once_cell::sync::OnceCell;
-use reqwest::Client;
+use reqwest::{Client, ClientBuilder};
+use std::collections::HashMap;
 use std::env;
 use std::net::IpAddr;
-use std::sync::RwLock;
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
 /// 全局 HTTP 客户端实例
@@ -17,6 +21,17 @@ static GLOBAL_CLIENT: OnceCell<RwLock<Client>> = OnceCell::new();
 /// 当前代理 URL（用于日志和状态查询）
 static CURRENT_PROXY_URL: OnceCell<RwLock<Option<String>>> = OnceCell::new();
 
+/// 全局上游 TLS 配置（未启用时为 None）
+static GLOBAL_TLS_CONFIG: OnceCell<RwLock<Option<UpstreamTlsConfig>>> = OnceCell::new();
+
+/// 上游代理规则（未启用时为 None）
+static PROXY_RULES: OnceCell<RwLock<Option<Arc<ProxyRuleSet>>>> = OnceCell::new();
+
+/// 供应商专用客户端缓存（key 为代理与 TLS 配置的序列化结果）
+///
+/// 全局代理或全局 TLS 配置变更时清空
+static PROVIDER_CLIENTS: OnceCell<Mutex<HashMap<String, Client>>> = OnceCell::new();
+
 /// CC Switch 代理服务器当前监听的端口
 static CC_SWITCH_PROXY_PORT: OnceCell<RwLock<u16>> = OnceCell::new();
 
@@ -129,6 +144,8 @@ pub fn apply_proxy(proxy_url: Option<&str>) -> Result<(), String> {
         *url = effective_url.map(|s| s.to_string());
     }
 
+    clear_provider_clients();
+
     log::info!(
         "[GlobalProxy] Applied: {}",
         effective_url
@@ -173,6 +190,8 @@ pub fn update_proxy(proxy_url: Option<&str>) -> Result<(), String> {
         *url = effective_url.map(|s| s.to_string());
     }
 
+    clear_provider_clients();
+
     log::info!(
         "[GlobalProxy] Updated: {}",
         effective_url
@@ -213,29 +232,256 @@ pub fn is_proxy_enabled() -> bool {
     get_current_proxy_url().is_some()
 }
 
-/// 构建 HTTP 客户端
-fn build_client(proxy_url: Option<&str>) -> Result<Client, String> {
-    let mut builder = Client::builder()
+/// 验证上游 TLS 配置（不应用）
+///
+/// 读取证书文件并尝试构建客户端，用于在持久化之前发现路径或格式错误。
+pub fn validate_tls_config(config: &UpstreamTlsConfig) -> Result<(), String> {
+    if !config.enabled {
+        return Ok(());
+    }
+    apply_tls(base_builder(), config)?
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {e}"))?;
+    Ok(())
+}
+
+/// 应用全局上游 TLS 配置
+///
+/// 验证通过后记录配置，并按当前代理重建全局客户端；
+/// 未启用的配置等同于清除全局 TLS 配置。
+pub fn apply_tls_config(config: Option<&UpstreamTlsConfig>) -> Result<(), String> {
+    let effective = config.filter(|c| c.enabled).cloned();
+    if let Some(config) = &effective {
+        validate_tls_config(config)?;
+    }
+
+    let enabled = effective.is_some();
+    match GLOBAL_TLS_CONFIG.get() {
+        Some(lock) => {
+            let mut current = lock.write().map_err(|e| {
+                log::error!("[GlobalProxy] [GP-012] Failed to acquire TLS config write lock: {e}");
+                "Failed to update TLS config: lock poisoned".to_string()
+            })?;
+            *current = effective;
+        }
+        None => {
+            let _ = GLOBAL_TLS_CONFIG.set(RwLock::new(effective));
+        }
+    }
+    clear_provider_clients();
+
+    // 启动阶段全局客户端尚未初始化，由 init 负责应用
+    if GLOBAL_CLIENT.get().is_some() {
+        apply_proxy(get_current_proxy_url().as_deref())?;
+    }
+
+    log::info!(
+        "[GlobalProxy] Upstream TLS config {}",
+        if enabled { "applied" } else { "cleared" }
+    );
+    Ok(())
+}
+
+/// 验证上游代理规则（不应用）
+pub fn validate_proxy_rules(config: &UpstreamProxyRules) -> Result<(), String> {
+    if config.enabled {
+        ProxyRuleSet::compile(config)?;
+    }
+    Ok(())
+}
+
+/// 应用上游代理规则
+///
+/// 规则对全局客户端以及未配置单独代理的供应商客户端生效，
+/// 按请求目标逐个求值；未启用的配置等同于清除规则。
+pub fn apply_proxy_rules(config: Option<&UpstreamProxyRules>) -> Result<(), String> {
+    let compiled = match config.filter(|c| c.enabled) {
+        Some(config) => Some(Arc::new(ProxyRuleSet::compile(config)?)),
+        None => None,
+    };
+
+    let enabled = compiled.is_some();
+    match PROXY_RULES.get() {
+        Some(lock) => {
+            let mut current = lock.write().map_err(|e| {
+                log::error!("[GlobalProxy] [GP-015] Failed to acquire proxy rules write lock: {e}");
+                "Failed to update proxy rules: lock poisoned".to_string()
+            })?;
+            *current = compiled;
+        }
+        None => {
+            let _ = PROXY_RULES.set(RwLock::new(compiled));
+        }
+    }
+    clear_provider_clients();
+
+    if GLOBAL_CLIENT.get().is_some() {
+        apply_proxy(get_current_proxy_url().as_deref())?;
+    }
+
+    log::info!(
+        "[GlobalProxy] Upstream proxy rules {}",
+        if enabled { "applied" } else { "cleared" }
+    );
+    Ok(())
+}
+
+/// 获取当前生效的上游代理规则
+fn proxy_rules() -> Option<Arc<ProxyRuleSet>> {
+    PROXY_RULES
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .and_then(|rules| rules.clone())
+}
+
+/// 获取当前生效的全局上游 TLS 配置
+fn global_tls_config() -> Option<UpstreamTlsConfig> {
+    GLOBAL_TLS_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .and_then(|config| config.clone())
+}
+
+/// 清空供应商专用客户端缓存
+fn clear_provider_clients() {
+    if let Some(mut clients) = PROVIDER_CLIENTS.get().and_then(|lock| lock.lock().ok()) {
+        clients.clear();
+    }
+}
+
+/// 客户端通用参数
+fn base_builder() -> ClientBuilder {
+    Client::builder()
         .timeout(Duration::from_secs(600))
         .connect_timeout(Duration::from_secs(30))
         .pool_max_idle_per_host(10)
-        .tcp_keepalive(Duration::from_secs(60));
+        .tcp_keepalive(Duration::from_secs(60))
+}
 
-    // 有代理地址则使用代理，否则跟随系统代理
-    if let Some(url) = proxy_url {
-        // 先验证 URL 格式和 scheme
-        let parsed = url::Url::parse(url)
-            .map_err(|e| format!("Invalid proxy URL '{}': {}", mask_url(url), e))?;
+/// 读取 PEM 文件
+fn read_pem(kind: &str, path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Failed to read {kind} '{path}': {e}"))
+}
 
-        let scheme = parsed.scheme();
-        if !["http", "https", "socks5", "socks5h"].contains(&scheme) {
-            return Err(format!(
-                "Invalid proxy scheme '{}' in URL '{}'. Supported: http, https, socks5, socks5h",
-                scheme,
-                mask_url(url)
-            ));
+/// 将上游 TLS 配置应用到客户端构建器
+///
+/// - 额外 CA：逐个文件加入信任列表（一个文件可包含多个证书）
+/// - 禁用内置根证书：此时必须至少配置一个 CA，否则任何 HTTPS 请求都会失败
+/// - mTLS：证书与私钥必须成对配置
+fn apply_tls(
+    mut builder: ClientBuilder,
+    config: &UpstreamTlsConfig,
+) -> Result<ClientBuilder, String> {
+    let ca_paths: Vec<&str> = config
+        .ca_cert_paths
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    for path in &ca_paths {
+        let pem = read_pem("CA bundle", path)?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA bundle '{path}': {e}"))?;
+        if certs.is_empty() {
+            return Err(format!("No certificates found in CA bundle '{path}'"));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    if config.disable_system_roots {
+        if ca_paths.is_empty() {
+            return Err(
+                "Disabling built-in root certificates requires at least one CA bundle".to_string(),
+            );
+        }
+        builder = builder.tls_built_in_root_certs(false);
+    }
+
+    let cert_path = config
+        .client_cert_path
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty());
+    let key_path = config
+        .client_key_path
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty());
+    match (cert_path, key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let mut pem = read_pem("client certificate", cert_path)?;
+            pem.push(b'\n');
+            pem.extend(read_pem("client key", key_path)?);
+            let identity = reqwest::Identity::from_pem(&pem)
+                .map_err(|e| format!("Invalid client certificate or key: {e}"))?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => {
+            return Err("Client certificate and key must be configured together".to_string());
         }
+    }
+
+    Ok(builder)
+}
 
+/// 构建 HTTP 客户端
+fn build_client(proxy_url: Option<&str>) -> Result<Client, String> {
+    let mut builder = configure_proxy(base_builder(), proxy_url)?;
+    if let Some(tls) = global_tls_config() {
+        builder = apply_tls(builder, &tls)?;
+    }
+
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {e}"))
+}
+
+/// 配置代理：启用代理规则时按请求求值；否则有代理地址则使用代理，再否则跟随系统代理
+fn configure_proxy(
+    mut builder: ClientBuilder,
+    proxy_url: Option<&str>,
+) -> Result<ClientBuilder, String> {
+    let parsed_url = match proxy_url {
+        Some(url) => {
+            // 先验证 URL 格式和 scheme
+            let parsed = url::Url::parse(url)
+                .map_err(|e| format!("Invalid proxy URL '{}': {}", mask_url(url), e))?;
+
+            let scheme = parsed.scheme();
+            if !["http", "https", "socks5", "socks5h"].contains(&scheme) {
+                return Err(format!(
+                    "Invalid proxy scheme '{}' in URL '{}'. Supported: http, https, socks5, socks5h",
+                    scheme,
+                    mask_url(url)
+                ));
+            }
+            Some(parsed)
+        }
+        None => None,
+    };
+
+    if let Some(rules) = proxy_rules() {
+        // 未命中规则时回退到全局代理；未设置全局代理时回退到环境变量中的系统代理
+        // 回调运行在异步工作线程上：优先读缓存，未预热的请求才在 block_in_place 中求值
+        builder = builder.proxy(reqwest::Proxy::custom(move |url| {
+            let decision = rules
+                .try_decide(url)
+                .unwrap_or_else(|| decide_blocking(&rules, url));
+            match decision {
+                ProxyDecision::Proxy(proxy) => Some(proxy),
+                ProxyDecision::Direct => None,
+                ProxyDecision::Default => parsed_url.clone().or_else(|| env_proxy_for(url)),
+            }
+        }));
+        log::debug!("[GlobalProxy] Upstream proxy rules configured");
+        return Ok(builder);
+    }
+
+    if let Some(url) = proxy_url {
         let proxy = reqwest::Proxy::all(url)
             .map_err(|e| format!("Invalid proxy URL '{}': {}", mask_url(url), e))?;
         builder = builder.proxy(proxy);
@@ -253,9 +499,97 @@ fn build_client(proxy_url: Option<&str>) -> Result<Client, String> {
         }
     }
 
-    builder
-        .build()
-        .map_err(|e| format!("Failed to build HTTP client: {e}"))
+    Ok(builder)
+}
+
+/// 预先为请求 URL 求值上游代理规则
+///
+/// 需要 DNS 解析或执行 PAC 的决策在阻塞线程池中完成并写入缓存，
+/// 之后 HTTP 客户端的代理回调直接命中缓存，不会阻塞异步工作线程。
+pub async fn prepare_proxy_rules(url: &str) {
+    let Some(rules) = proxy_rules() else {
+        return;
+    };
+    let Ok(url) = url::Url::parse(url) else {
+        return;
+    };
+    if rules.try_decide(&url).is_some() {
+        return;
+    }
+    if let Err(e) = tokio::task::spawn_blocking(move || rules.decide(&url)).await {
+        log::warn!("[ProxyRules] Failed to evaluate proxy rules: {e}");
+    }
+}
+
+/// 缓存未命中时同步求值；位于多线程运行时的工作线程上时先让出该线程
+fn decide_blocking(rules: &ProxyRuleSet, url: &url::Url) -> ProxyDecision {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| rules.decide(url))
+        }
+        _ => rules.decide(url),
+    }
+}
+
+/// 从环境变量读取请求目标对应的系统代理（跳过指向自身的代理与 NO_PROXY 中的主机）
+///
+/// 启用代理规则后 reqwest 不再自动读取系统代理，由此补上规则未命中时的回退。
+fn env_proxy_for(target: &url::Url) -> Option<url::Url> {
+    let no_proxy = env::var("NO_PROXY")
+        .or_else(|_| env::var("no_proxy"))
+        .unwrap_or_default();
+    if target
+        .host_str()
+        .is_some_and(|host| no_proxy_matches(&no_proxy, host))
+    {
+        return None;
+    }
+
+    let keys: &[&str] = if target.scheme() == "https" {
+        &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+    } else {
+        &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+    };
+
+    keys.iter()
+        .filter_map(|key| env::var(key).ok())
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+        .filter(|value| !proxy_points_to_loopback(value))
+        .and_then(|value| {
+            url::Url::parse(&value)
+                .ok()
+                .filter(|u| u.has_host())
+                .or_else(|| url::Url::parse(&format!("http://{value}")).ok())
+        })
+}
+
+/// 主机是否命中 NO_PROXY 列表（逗号分隔；`*` 匹配全部，域名同时匹配子域名，支持 IP 与 CIDR）
+fn no_proxy_matches(no_proxy: &str, host: &str) -> bool {
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+    let host_ip = host.parse::<IpAddr>().ok();
+
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().to_ascii_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+            if let Some(ip) = host_ip {
+                return parse_cidr(&entry)
+                    .is_ok_and(|(network, prefix)| ip_in_network(ip, network, prefix));
+            }
+            let domain = entry.trim_start_matches("*.").trim_start_matches('.');
+            host == domain
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
 }
 
 fn system_proxy_points_to_loopback() -> bool {
@@ -349,78 +683,100 @@ fn build_proxy_url_from_config(config: &ProviderProxyConfig) -> Option<String> {
     Some(format!("{proxy_type}://{host}:{port}"))
 }
 
-/// 根据供应商单独代理配置构建 HTTP 客户端
+/// 根据供应商单独的代理与 TLS 配置构建 HTTP 客户端
 ///
-/// 如果供应商配置了单独代理（enabled = true），则使用该代理构建客户端；
-/// 否则返回 None，调用方应使用全局客户端。
+/// 两者都未启用时返回 None，调用方应使用全局客户端。
+/// 仅启用 TLS 时沿用全局代理；仅启用代理时沿用全局 TLS 配置。
+/// TLS 配置错误直接返回错误，避免静默降级为默认信任链。
 ///
 /// # Arguments
 /// * `proxy_config` - 供应商的代理配置
-///
-/// # Returns
-/// 如果配置有效则返回 Some(Client)，否则返回 None
-pub fn build_client_for_provider(proxy_config: Option<&ProviderProxyConfig>) -> Option<Client> {
-    let config = proxy_config.filter(|c| c.enabled)?;
-
-    let proxy_url = build_proxy_url_from_config(config)?;
+/// * `tls_config` - 供应商的上游 TLS 配置
+pub fn build_client_for_provider(
+    proxy_config: Option<&ProviderProxyConfig>,
+    tls_config: Option<&UpstreamTlsConfig>,
+) -> Result<Option<Client>, String> {
+    let proxy_config = proxy_config.filter(|c| c.enabled);
+    let tls_config = tls_config.filter(|c| c.enabled);
+    if proxy_config.is_none() && tls_config.is_none() {
+        return Ok(None);
+    }
 
-    log::debug!(
-        "[ProviderProxy] Building client with proxy: {}",
-        mask_url(&proxy_url)
-    );
+    let cache_key = serde_json::to_string(&(proxy_config, tls_config)).unwrap_or_default();
+    let cache = PROVIDER_CLIENTS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(client) = cache
+        .lock()
+        .ok()
+        .and_then(|clients| clients.get(&cache_key).cloned())
+    {
+        return Ok(Some(client));
+    }
 
-    // 构建带代理的客户端
-    let proxy = match reqwest::Proxy::all(&proxy_url) {
-        Ok(p) => p,
-        Err(e) => {
-            log::error!(
-                "[ProviderProxy] Failed to create proxy from '{}': {}",
-                mask_url(&proxy_url),
-                e
-            );
-            return None;
+    let provider_proxy = proxy_config.and_then(|config| {
+        let proxy_url = build_proxy_url_from_config(config)?;
+        match reqwest::Proxy::all(&proxy_url) {
+            Ok(proxy) => Some((proxy, proxy_url)),
+            Err(e) => {
+                log::error!(
+                    "[ProviderProxy] Failed to create proxy from '{}': {}",
+                    mask_url(&proxy_url),
+                    e
+                );
+                None
+            }
         }
-    };
-
-    match Client::builder()
-        .timeout(Duration::from_secs(600))
-        .connect_timeout(Duration::from_secs(30))
-        .pool_max_idle_per_host(10)
-        .tcp_keepalive(Duration::from_secs(60))
-        .proxy(proxy)
-        .build()
-    {
-        Ok(client) => {
-            log::info!(
-                "[ProviderProxy] Client built with proxy: {}",
+    });
+
+    let mut builder = base_builder();
+    match provider_proxy {
+        Some((proxy, proxy_url)) => {
+            builder = builder.proxy(proxy);
+            log::debug!(
+                "[ProviderProxy] Building client with proxy: {}",
                 mask_url(&proxy_url)
             );
-            Some(client)
-        }
-        Err(e) => {
-            log::error!("[ProviderProxy] Failed to build client: {e}");
-            None
         }
+        // 单独代理无效且未配置 TLS：与全局客户端等价
+        None if tls_config.is_none() => return Ok(None),
+        None => builder = configure_proxy(builder, get_current_proxy_url().as_deref())?,
+    }
+
+    // 供应商单独 TLS 配置优先，否则沿用全局 TLS 配置
+    if let Some(tls) = tls_config.cloned().or_else(global_tls_config) {
+        builder = apply_tls(builder, &tls)?;
+    }
+
+    let client = builder
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {e}"))?;
+    log::info!(
+        "[ProviderProxy] Client built (proxy: {}, tls: {})",
+        proxy_config.is_some(),
+        tls_config.is_some()
+    );
+
+    if let Ok(mut clients) = cache.lock() {
+        clients.insert(cache_key, client.clone());
     }
+    Ok(Some(client))
 }
 
 /// 获取供应商专用的 HTTP 客户端
 ///
-/// 优先使用供应商单独代理配置，如果未启用则返回全局客户端。
+/// 优先使用供应商单独的代理与 TLS 配置，如果都未启用则返回全局客户端。
 ///
 /// # Arguments
 /// * `proxy_config` - 供应商的代理配置
+/// * `tls_config` - 供应商的上游 TLS 配置
 ///
 /// # Returns
-/// 返回适合该供应商的 HTTP 客户端
-pub fn get_for_provider(proxy_config: Option<&ProviderProxyConfig>) -> Client {
-    // 优先使用供应商单独代理
-    if let Some(client) = build_client_for_provider(proxy_config) {
-        return client;
-    }
-
+/// 返回适合该供应商的 HTTP 客户端；TLS 证书无法加载时返回错误
+pub fn get_for_provider(
+    proxy_config: Option<&ProviderProxyConfig>,
+    tls_config: Option<&UpstreamTlsConfig>,
+) -> Result<Client, String> {
     // 回退到全局客户端
-    get()
+    Ok(build_client_for_provider(proxy_config, tls_config)?.unwrap_or_else(get))
 }
 
 #[cfg(test)]
@@ -481,6 +837,103 @@ mod tests {
         assert!(result.is_err(), "Should reject invalid proxy scheme");
     }
 
+    /// 生成自签名证书与私钥 PEM 文件
+    fn write_self_signed(dir: &std::path::Path) -> (String, String) {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = rcgen::CertificateParams::new(vec!["gateway.internal".to_string()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        let cert_path = dir.join("cert.pem");
+        let key_path = dir.join("key.pem");
+        std::fs::write(&cert_path, cert.pem()).unwrap();
+        std::fs::write(&key_path, key.serialize_pem()).unwrap();
+        (
+            cert_path.to_string_lossy().to_string(),
+            key_path.to_string_lossy().to_string(),
+        )
+    }
+
+    #[test]
+    fn test_apply_tls_with_ca_and_client_identity() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert_path, key_path) = write_self_signed(dir.path());
+
+        let config = UpstreamTlsConfig {
+            enabled: true,
+            ca_cert_paths: vec![cert_path.clone()],
+            disable_system_roots: true,
+            client_cert_path: Some(cert_path),
+            client_key_path: Some(key_path),
+        };
+        assert!(validate_tls_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_apply_tls_rejects_invalid_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert_path, _) = write_self_signed(dir.path());
+
+        // 证书文件不存在
+        let missing = UpstreamTlsConfig {
+            enabled: true,
+            ca_cert_paths: vec![dir.path().join("missing.pem").to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        assert!(validate_tls_config(&missing).is_err());
+
+        // 禁用内置根证书但未提供 CA
+        let no_roots = UpstreamTlsConfig {
+            enabled: true,
+            disable_system_roots: true,
+            ..Default::default()
+        };
+        assert!(validate_tls_config(&no_roots).is_err());
+
+        // 只有客户端证书没有私钥
+        let half_identity = UpstreamTlsConfig {
+            enabled: true,
+            client_cert_path: Some(cert_path),
+            ..Default::default()
+        };
+        assert!(validate_tls_config(&half_identity).is_err());
+
+        // 未启用时不做校验
+        let disabled = UpstreamTlsConfig {
+            enabled: false,
+            ..no_roots
+        };
+        assert!(validate_tls_config(&disabled).is_ok());
+    }
+
+    #[test]
+    fn test_build_client_for_provider() {
+        // 均未启用时使用全局客户端
+        let disabled_tls = UpstreamTlsConfig::default();
+        assert!(build_client_for_provider(None, Some(&disabled_tls))
+            .unwrap()
+            .is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let (cert_path, _) = write_self_signed(dir.path());
+        let tls = UpstreamTlsConfig {
+            enabled: true,
+            ca_cert_paths: vec![cert_path],
+            ..Default::default()
+        };
+        assert!(build_client_for_provider(None, Some(&tls))
+            .unwrap()
+            .is_some());
+
+        // TLS 配置错误不应静默回退到全局客户端
+        let broken = UpstreamTlsConfig {
+            enabled: true,
+            ca_cert_paths: vec![dir.path().join("missing.pem").to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        assert!(build_client_for_provider(None, Some(&broken)).is_err());
+    }
+
     #[test]
     fn test_proxy_points_to_loopback() {
         // 设置 CC Switch 代理端口为 15721（默认值）
@@ -536,4 +989,20 @@ mod tests {
             std::env::remove_var(key);
         }
     }
+
+    #[test]
+    fn test_no_proxy_matches() {
+        let list = "localhost, .corp.example, *.internal, 10.0.0.0/8, ::1";
+        assert!(no_proxy_matches(list, "localhost"));
+        assert!(no_proxy_matches(list, "api.corp.example"));
+        assert!(no_proxy_matches(list, "corp.example"));
+        assert!(no_proxy_matches(list, "relay.internal"));
+        assert!(no_proxy_matches(list, "10.2.3.4"));
+        assert!(no_proxy_matches(list, "[::1]"));
+        assert!(!no_proxy_matches(list, "notcorp.example"));
+        assert!(!no_proxy_matches(list, "11.0.0.1"));
+        assert!(!no_proxy_matches(list, "api.anthropic.com"));
+        assert!(no_proxy_matches("*", "api.anthropic.com"));
+        assert!(!no_proxy_matches("", "api.anthropic.com"));
+    }
 }
@@ -0,0 +1,350 @@
+//! 负载均衡模块
+//!
+//! 在故障转移队列的基础上按策略重排供应商顺序：
+//! - priority：保持队列顺序（P1 → P2 → ...）
+//! - weighted：平滑加权轮询，选出本次首选，其余按队列顺序作为备选
+//! - least_latency：按近期流式请求的首字延迟升序
+//! - least_cost：按模型定价 × 成本倍率升序
+//!
+//! 负载均衡只改变顺序，不会增删供应商，熔断器过滤在此之前已完成。
+
+use crate::database::Database;
+use crate::provider::Provider;
+use crate::proxy::model_mapper::ModelMapping;
+use crate::proxy::types::LoadBalanceStrategy;
+use crate::services::usage_stats::find_model_pricing_row;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// 计算近期延迟时使用的样本数
+const LATENCY_SAMPLE_SIZE: u32 = 20;
+
+/// 负载均衡器
+///
+/// 持有加权轮询的运行时状态，key 格式与熔断器一致："app_type:provider_id"
+#[derive(Default)]
+pub struct LoadBalancer {
+    current_weights: Mutex<HashMap<String, i64>>,
+}
+
+impl LoadBalancer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按策略重排供应商列表
+    ///
+    /// `request_model` 仅用于 least_cost 策略，缺失时按原顺序返回
+    pub async fn order(
+        &self,
+        db: &Database,
+        app_type: &str,
+        strategy: LoadBalanceStrategy,
+        providers: Vec<Provider>,
+        request_model: Option<&str>,
+    ) -> Vec<Provider> {
+        if providers.len() <= 1 {
+            return providers;
+        }
+
+        match strategy {
+            LoadBalanceStrategy::Priority => providers,
+            LoadBalanceStrategy::Weighted => self.order_weighted(app_type, providers),
+            LoadBalanceStrategy::LeastLatency => order_by_latency(db, app_type, providers),
+            LoadBalanceStrategy::LeastCost => match request_model {
+                Some(model) => order_by_cost(db, app_type, providers, model).await,
+                None => providers,
+            },
+        }
+    }
+
+    /// 平滑加权轮询（与 nginx 相同的算法）
+    ///
+    /// 每次选择时所有供应商的 current_weight 加上各自权重，选出最大者，
+    /// 再将其 current_weight 减去总权重。被选中者排在首位，其余保持队列顺序。
+    fn order_weighted(&self, app_type: &str, mut providers: Vec<Provider>) -> Vec<Provider> {
+        let weights: Vec<i64> = providers.iter().map(provider_weight).collect();
+        let total: i64 = weights.iter().sum();
+        if total == 0 {
+            return providers;
+        }
+
+        let mut current = match self.current_weights.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                log::warn!("[{app_type}] 加权轮询状态锁获取失败: {e}，回退到队列顺序");
+                return providers;
+            }
+        };
+
+        let mut selected: Option<(usize, i64)> = None;
+        for (idx, provider) in providers.iter().enumerate() {
+            let entry = current
+                .entry(format!("{app_type}:{}", provider.id))
+                .or_insert(0);
+            *entry += weights[idx];
+            if weights[idx] > 0 && selected.is_none_or(|(_, best)| *entry > best) {
+                selected = Some((idx, *entry));
+            }
+        }
+
+        let Some((selected_idx, _)) = selected else {
+            return providers;
+        };
+        if let Some(entry) = current.get_mut(&format!("{app_type}:{}", providers[selected_idx].id))
+        {
+            *entry -= total;
+        }
+        drop(current);
+
+        let chosen = providers.remove(selected_idx);
+        providers.insert(0, chosen);
+        providers
+    }
+}
+
+fn provider_weight(provider: &Provider) -> i64 {
+    provider
+        .meta
+        .as_ref()
+        .and_then(|m| m.load_balance_weight)
+        .unwrap_or(1) as i64
+}
+
+/// 按近期首字延迟升序排序
+///
+/// 没有样本的供应商视为延迟 0（乐观探测），确保新加入的供应商能获得流量。
+fn order_by_latency(db: &Database, app_type: &str, providers: Vec<Provider>) -> Vec<Provider> {
+    let ids: Vec<&str> = providers.iter().map(|p| p.id.as_str()).collect();
+    let latencies = db
+        .get_recent_provider_latencies(&ids, app_type, LATENCY_SAMPLE_SIZE)
+        .unwrap_or_else(|e| {
+            log::warn!("[{app_type}] 读取供应商延迟失败: {e}");
+            HashMap::new()
+        });
+
+    let mut scored: Vec<(f64, Provider)> = providers
+        .into_iter()
+        .map(|provider| {
+            let latency = latencies.get(&provider.id).copied().unwrap_or(0.0);
+            (latency, provider)
+        })
+        .collect();
+
+    // 稳定排序：延迟相同时保持队列顺序
+    scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+    scored.into_iter().map(|(_, p)| p).collect()
+}
+
+/// 按有效单价升序排序
+///
+/// 有效单价 = (输入单价 + 输出单价) × 成本倍率，模型按供应商自身的模型映射计算。
+/// 找不到定价的供应商排在最后（保持队列顺序）。
+async fn order_by_cost(
+    db: &Database,
+    app_type: &str,
+    providers: Vec<Provider>,
+    request_model: &str,
+) -> Vec<Provider> {
+    let default_multiplier = match db.get_default_cost_multiplier(app_type).await {
+        Ok(value) => Decimal::from_str(&value).unwrap_or(Decimal::ONE),
+        Err(e) => {
+            log::warn!("[{app_type}] 获取默认倍率失败: {e}");
+            Decimal::ONE
+        }
+    };
+
+    let mut scored: Vec<(Option<Decimal>, Provider)> = providers
+        .into_iter()
+        .map(|provider| {
+            let cost = effective_unit_cost(db, &provider, request_model, default_multiplier);
+            (cost, provider)
+        })
+        .collect();
+
+    scored.sort_by(|a, b| match (&a.0, &b.0) {
+        (Some(x), Some(y)) => x.cmp(y),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    scored.into_iter().map(|(_, p)| p).collect()
+}
+
+fn effective_unit_cost(
+    db: &Database,
+    provider: &Provider,
+    request_model: &str,
+    default_multiplier: Decimal,
+) -> Option<Decimal> {
+    let mapping = ModelMapping::from_provider(provider);
+    let model = if mapping.has_mapping() {
+        mapping.map_model(request_model, false)
+    } else {
+        request_model.to_string()
+    };
+
+    let pricing = {
+        let conn = db.conn.lock().ok()?;
+        find_model_pricing_row(&conn, &model, chrono::Utc::now().timestamp())
+            .ok()
+            .flatten()?
+    };
+    let input = pricing.input_cost_per_million;
+    let output = pricing.output_cost_per_million;
+
+    let multiplier = provider
+        .meta
+        .as_ref()
+        .and_then(|m| m.cost_multiplier.as_deref())
+        .and_then(|v| Decimal::from_str(v).ok())
+        .unwrap_or(default_multiplier);
+
+    Some((input + output) * multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::ProviderMeta;
+    use serde_json::json;
+
+    fn provider_with_weight(id: &str, weight: Option<u32>) -> Provider {
+        let mut provider = Provider::with_id(id.to_string(), id.to_string(), json!({}), None);
+        provider.meta = Some(ProviderMeta {
+            load_balance_weight: weight,
+            ..Default::default()
+        });
+        provider
+    }
+
+    fn first_ids(lb: &LoadBalancer, providers: &[Provider], rounds: usize) -> Vec<String> {
+        (0..rounds)
+            .map(|_| {
+                lb.order_weighted("claude", providers.to_vec())[0]
+                    .id
+                    .clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_weighted_round_robin_distribution() {
+        let lb = LoadBalancer::new();
+        let providers = vec![
+            provider_with_weight("a", Some(3)),
+            provider_with_weight("b", Some(1)),
+        ];
+
+        let picks = first_ids(&lb, &providers, 8);
+        assert_eq!(picks.iter().filter(|id| *id == "a").count(), 6);
+        assert_eq!(picks.iter().filter(|id| *id == "b").count(), 2);
+        // 平滑加权：不会连续 3 次以上选中同一个
+        assert_ne!(picks[..4], ["a", "a", "a", "a"]);
+    }
+
+    #[test]
+    fn test_weighted_keeps_others_as_fallback() {
+        let lb = LoadBalancer::new();
+        let providers = vec![
+            provider_with_weight("a", Some(1)),
+            provider_with_weight("b", Some(0)),
+            provider_with_weight("c", Some(5)),
+        ];
+
+        let ordered = lb.order_weighted("claude", providers);
+        let ids: Vec<&str> = ordered.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_zero_weight_never_selected_first() {
+        let lb = LoadBalancer::new();
+        let providers = vec![
+            provider_with_weight("a", Some(0)),
+            provider_with_weight("b", None),
+        ];
+
+        let picks = first_ids(&lb, &providers, 5);
+        assert!(picks.iter().all(|id| id == "b"));
+    }
+
+    #[tokio::test]
+    async fn test_least_latency_orders_by_recent_latency() {
+        let db = Database::memory().unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            // 非流式请求的总延迟不参与比较，否则 slow 的平均值会被拉低到 fast 之下
+            for (id, provider, latency, first_token) in [
+                ("r1", "slow", 900, Some(600)),
+                ("r2", "fast", 2000, Some(300)),
+                ("r3", "slow", 50, None),
+                ("r4", "slow", 50, None),
+            ] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                        latency_ms, first_token_ms, status_code, created_at)
+                     VALUES (?1, ?2, 'claude', 'm', ?3, ?4, 200, 1000)",
+                    rusqlite::params![id, provider, latency, first_token],
+                )
+                .unwrap();
+            }
+        }
+
+        let providers = vec![
+            provider_with_weight("slow", None),
+            provider_with_weight("fast", None),
+        ];
+        let ordered = LoadBalancer::new()
+            .order(
+                &db,
+                "claude",
+                LoadBalanceStrategy::LeastLatency,
+                providers,
+                None,
+            )
+            .await;
+        let ids: Vec<&str> = ordered.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["fast", "slow"]);
+    }
+
+    #[tokio::test]
+    async fn test_least_cost_uses_multiplier_and_skips_unknown_pricing() {
+        let db = Database::memory().unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "INSERT OR REPLACE INTO model_pricing (model_id, display_name, input_cost_per_million, output_cost_per_million)
+                 VALUES ('lb-model', 'LB Model', '1', '1')",
+                [],
+            )
+            .unwrap();
+        }
+
+        let mut expensive = provider_with_weight("expensive", None);
+        expensive.meta.as_mut().unwrap().cost_multiplier = Some("3".to_string());
+        let mut cheap = provider_with_weight("cheap", None);
+        cheap.meta.as_mut().unwrap().cost_multiplier = Some("0.5".to_string());
+        // 模型映射到无定价的模型：排在最后
+        let unknown = Provider::with_id(
+            "unknown".to_string(),
+            "unknown".to_string(),
+            json!({"env": {"ANTHROPIC_MODEL": "no-such-model"}}),
+            None,
+        );
+
+        let ordered = LoadBalancer::new()
+            .order(
+                &db,
+                "claude",
+                LoadBalanceStrategy::LeastCost,
+                vec![unknown, expensive, cheap],
+                Some("lb-model"),
+            )
+            .await;
+        let ids: Vec<&str> = ordered.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["cheap", "expensive", "unknown"]);
+    }
+}
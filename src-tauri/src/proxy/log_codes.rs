@@ -7,6 +7,8 @@
 //! - FO: Failover (故障转移)
 //! - RSP: Response (响应处理)
 //! - USG: Usage (使用量)
+//! - RT: Routing (路由规则)
+//! - OTL: OpenTelemetry (链路追踪导出)
 
 #![allow(dead_code)]
 
@@ -26,12 +28,16 @@ pub mod srv {
     pub const STOPPED: &str = "SRV-002";
     pub const STOP_TIMEOUT: &str = "SRV-003";
     pub const TASK_ERROR: &str = "SRV-004";
+    pub const TLS_CA_CREATED: &str = "SRV-005";
+    pub const TLS_HANDSHAKE: &str = "SRV-006";
 }
 
 /// 转发器日志码
 pub mod fwd {
     pub const PROVIDER_FAILED_RETRY: &str = "FWD-001";
     pub const ALL_PROVIDERS_FAILED: &str = "FWD-002";
+    pub const HEDGE_FIRED: &str = "FWD-003";
+    pub const HEDGE_WON: &str = "FWD-004";
 }
 
 /// 故障转移日志码
@@ -56,4 +62,41 @@ pub mod rsp {
 pub mod usg {
     pub const LOG_FAILED: &str = "USG-001";
     pub const PRICING_NOT_FOUND: &str = "USG-002";
+    pub const EXCHANGE_RATE_MISSING: &str = "USG-004";
+    pub const REPORT_FAILED: &str = "USG-006";
+    pub const BUDGET_ALERT: &str = "USG-007";
+    pub const BALANCE_ALERT: &str = "USG-008";
+}
+
+/// 路由规则日志码
+pub mod rt {
+    pub const RULE_MATCHED: &str = "RT-001";
+    pub const PROVIDER_NOT_FOUND: &str = "RT-002";
+    pub const LOAD_FAILED: &str = "RT-003";
+}
+
+/// 链路追踪导出日志码
+pub mod otl {
+    pub const EXPORT_FAILED: &str = "OTL-001";
+    pub const NO_RUNTIME: &str = "OTL-002";
+}
+
+/// 请求/响应钩子日志码
+pub mod hook {
+    pub const LOAD_FAILED: &str = "HOOK-001";
+    pub const RUN_FAILED: &str = "HOOK-002";
+}
+
+/// 密钥扫描日志码
+pub mod sec {
+    pub const DETECTED: &str = "SEC-001";
+    pub const BLOCKED: &str = "SEC-002";
+    pub const INVALID_PATTERN: &str = "SEC-003";
+}
+
+/// Mock 上游日志码
+pub mod mock {
+    pub const FIXTURE_MATCHED: &str = "MOCK-001";
+    pub const FAULT_INJECTED: &str = "MOCK-002";
+    pub const LOAD_FAILED: &str = "MOCK-003";
 }
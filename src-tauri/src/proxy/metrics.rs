@@ -0,0 +1,493 @@
+//! Prometheus 指标模块
+//!
+//! 进程内累计代理指标，由 `/metrics` 端点以 Prometheus 文本格式导出：
+//! - 请求数、错误数（按 ErrorCategory）、故障转移次数
+//! - 总延迟与首字延迟直方图
+//! - token 用量与成本
+//! - 抓取时再附加熔断器状态与响应缓存计数
+//!
+//! 请求级指标在写入 proxy_request_logs 时统一记录，保证与用量统计口径一致。
+
+use crate::proxy::circuit_breaker::{CircuitBreakerStats, CircuitState};
+use crate::proxy::error::ErrorCategory;
+use crate::proxy::types::ProxyStatus;
+use crate::proxy::usage::logger::RequestLog;
+use once_cell::sync::OnceCell;
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// 延迟直方图桶（秒）
+const LATENCY_BUCKETS: &[f64] = &[
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// Prometheus 文本格式的 Content-Type
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+static METRICS: OnceCell<ProxyMetrics> = OnceCell::new();
+
+/// 获取全局指标注册表
+pub fn global() -> &'static ProxyMetrics {
+    METRICS.get_or_init(ProxyMetrics::default)
+}
+
+/// (app, provider, model)
+type ModelKey = (String, String, String);
+
+#[derive(Clone)]
+struct Histogram {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; LATENCY_BUCKETS.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct MetricsInner {
+    /// (app, provider, model, status_code)
+    requests: BTreeMap<(String, String, String, u16), u64>,
+    /// (app, provider, category)
+    errors: BTreeMap<(String, String, &'static str), u64>,
+    /// (app, provider)：切换到该供应商的故障转移次数
+    failovers: BTreeMap<(String, String), u64>,
+    latency: BTreeMap<ModelKey, Histogram>,
+    first_token: BTreeMap<ModelKey, Histogram>,
+    /// (app, provider, model, type)
+    tokens: BTreeMap<(String, String, String, &'static str), u64>,
+    cost_usd: BTreeMap<ModelKey, f64>,
+}
+
+/// 代理指标注册表
+#[derive(Default)]
+pub struct ProxyMetrics {
+    inner: Mutex<MetricsInner>,
+}
+
+impl ProxyMetrics {
+    /// 记录一条请求日志（缓存命中不计入，单独由 ProxyStatus 导出）
+    pub fn record_request(&self, log: &RequestLog) {
+        if log.from_cache {
+            return;
+        }
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+
+        let key: ModelKey = (
+            log.app_type.clone(),
+            log.provider_id.clone(),
+            log.model.clone(),
+        );
+        *inner
+            .requests
+            .entry((key.0.clone(), key.1.clone(), key.2.clone(), log.status_code))
+            .or_insert(0) += 1;
+
+        inner
+            .latency
+            .entry(key.clone())
+            .or_default()
+            .observe(log.latency_ms as f64 / 1000.0);
+        if let Some(first_token_ms) = log.first_token_ms {
+            inner
+                .first_token
+                .entry(key.clone())
+                .or_default()
+                .observe(first_token_ms as f64 / 1000.0);
+        }
+
+        for (kind, value) in [
+            ("input", log.usage.input_tokens),
+            ("output", log.usage.output_tokens),
+            ("cache_read", log.usage.cache_read_tokens),
+            ("cache_creation", log.usage.cache_creation_tokens),
+        ] {
+            if value > 0 {
+                *inner
+                    .tokens
+                    .entry((key.0.clone(), key.1.clone(), key.2.clone(), kind))
+                    .or_insert(0) += value as u64;
+            }
+        }
+
+        if let Some(cost) = log.cost.as_ref().and_then(|c| c.total_cost.to_f64()) {
+            *inner.cost_usd.entry(key).or_insert(0.0) += cost;
+        }
+    }
+
+    /// 记录供应商错误
+    pub fn record_error(&self, app_type: &str, provider_id: &str, category: ErrorCategory) {
+        if let Ok(mut inner) = self.inner.lock() {
+            *inner
+                .errors
+                .entry((
+                    app_type.to_string(),
+                    provider_id.to_string(),
+                    category_label(category),
+                ))
+                .or_insert(0) += 1;
+        }
+    }
+
+    /// 记录故障转移（切换到 provider_id）
+    pub fn record_failover(&self, app_type: &str, provider_id: &str) {
+        if let Ok(mut inner) = self.inner.lock() {
+            *inner
+                .failovers
+                .entry((app_type.to_string(), provider_id.to_string()))
+                .or_insert(0) += 1;
+        }
+    }
+
+    /// 以 Prometheus 文本格式导出
+    ///
+    /// `breakers` 为 ("app_type:provider_id", 统计) 列表
+    pub fn render(
+        &self,
+        status: &ProxyStatus,
+        breakers: &[(String, CircuitBreakerStats)],
+    ) -> String {
+        let mut out = String::new();
+        let Ok(inner) = self.inner.lock() else {
+            return out;
+        };
+
+        header(
+            &mut out,
+            "cc_switch_requests_total",
+            "counter",
+            "Proxied requests",
+        );
+        for ((app, provider, model, status_code), value) in &inner.requests {
+            let code = status_code.to_string();
+            sample(
+                &mut out,
+                "cc_switch_requests_total",
+                &[
+                    ("app", app),
+                    ("provider", provider),
+                    ("model", model),
+                    ("status", &code),
+                ],
+                *value as f64,
+            );
+        }
+
+        header(
+            &mut out,
+            "cc_switch_errors_total",
+            "counter",
+            "Upstream errors by category",
+        );
+        for ((app, provider, category), value) in &inner.errors {
+            sample(
+                &mut out,
+                "cc_switch_errors_total",
+                &[("app", app), ("provider", provider), ("category", category)],
+                *value as f64,
+            );
+        }
+
+        header(
+            &mut out,
+            "cc_switch_failovers_total",
+            "counter",
+            "Failovers to provider",
+        );
+        for ((app, provider), value) in &inner.failovers {
+            sample(
+                &mut out,
+                "cc_switch_failovers_total",
+                &[("app", app), ("provider", provider)],
+                *value as f64,
+            );
+        }
+
+        histogram(
+            &mut out,
+            "cc_switch_request_duration_seconds",
+            "Total request latency",
+            &inner.latency,
+        );
+        histogram(
+            &mut out,
+            "cc_switch_first_token_seconds",
+            "Time to first token for streaming requests",
+            &inner.first_token,
+        );
+
+        header(
+            &mut out,
+            "cc_switch_tokens_total",
+            "counter",
+            "Tokens by type",
+        );
+        for ((app, provider, model, kind), value) in &inner.tokens {
+            sample(
+                &mut out,
+                "cc_switch_tokens_total",
+                &[
+                    ("app", app),
+                    ("provider", provider),
+                    ("model", model),
+                    ("type", kind),
+                ],
+                *value as f64,
+            );
+        }
+
+        header(
+            &mut out,
+            "cc_switch_cost_usd_total",
+            "counter",
+            "Cost in USD",
+        );
+        for ((app, provider, model), value) in &inner.cost_usd {
+            sample(
+                &mut out,
+                "cc_switch_cost_usd_total",
+                &[("app", app), ("provider", provider), ("model", model)],
+                *value,
+            );
+        }
+        drop(inner);
+
+        header(
+            &mut out,
+            "cc_switch_circuit_breaker_state",
+            "gauge",
+            "Circuit breaker state (0=closed, 1=half_open, 2=open)",
+        );
+        for (key, stats) in breakers {
+            let (app, provider) = key.split_once(':').unwrap_or(("", key));
+            let state = match stats.state {
+                CircuitState::Closed => 0.0,
+                CircuitState::HalfOpen => 1.0,
+                CircuitState::Open => 2.0,
+            };
+            sample(
+                &mut out,
+                "cc_switch_circuit_breaker_state",
+                &[("app", app), ("provider", provider)],
+                state,
+            );
+        }
+        header(
+            &mut out,
+            "cc_switch_circuit_breaker_consecutive_failures",
+            "gauge",
+            "Consecutive failures seen by the circuit breaker",
+        );
+        for (key, stats) in breakers {
+            let (app, provider) = key.split_once(':').unwrap_or(("", key));
+            sample(
+                &mut out,
+                "cc_switch_circuit_breaker_consecutive_failures",
+                &[("app", app), ("provider", provider)],
+                stats.consecutive_failures as f64,
+            );
+        }
+
+        header(
+            &mut out,
+            "cc_switch_response_cache_hits_total",
+            "counter",
+            "Response cache hits",
+        );
+        sample(
+            &mut out,
+            "cc_switch_response_cache_hits_total",
+            &[],
+            status.cache_hits as f64,
+        );
+        header(
+            &mut out,
+            "cc_switch_response_cache_misses_total",
+            "counter",
+            "Response cache misses",
+        );
+        sample(
+            &mut out,
+            "cc_switch_response_cache_misses_total",
+            &[],
+            status.cache_misses as f64,
+        );
+
+        out
+    }
+}
+
+fn category_label(category: ErrorCategory) -> &'static str {
+    match category {
+        ErrorCategory::Retryable => "retryable",
+        ErrorCategory::NonRetryable => "non_retryable",
+        ErrorCategory::ClientAbort => "client_abort",
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        out.push('{');
+        for (i, (key, val)) in labels.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{key}=\"{}\"", escape_label(val));
+        }
+        out.push('}');
+    }
+    let _ = writeln!(out, " {value}");
+}
+
+fn histogram(out: &mut String, name: &str, help: &str, series: &BTreeMap<ModelKey, Histogram>) {
+    header(out, name, "histogram", help);
+    for ((app, provider, model), hist) in series {
+        let base = [
+            ("app", app.as_str()),
+            ("provider", provider),
+            ("model", model),
+        ];
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(&hist.buckets) {
+            let le = bound.to_string();
+            let mut labels = base.to_vec();
+            labels.push(("le", &le));
+            sample(out, &format!("{name}_bucket"), &labels, *count as f64);
+        }
+        let mut labels = base.to_vec();
+        labels.push(("le", "+Inf"));
+        sample(out, &format!("{name}_bucket"), &labels, hist.count as f64);
+        sample(out, &format!("{name}_sum"), &base, hist.sum);
+        sample(out, &format!("{name}_count"), &base, hist.count as f64);
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::usage::calculator::CostBreakdown;
+    use crate::proxy::usage::parser::TokenUsage;
+    use rust_decimal::Decimal;
+
+    fn request_log(status_code: u16, latency_ms: u64, from_cache: bool) -> RequestLog {
+        RequestLog {
+            request_id: "r".to_string(),
+            provider_id: "p1".to_string(),
+            app_type: "claude".to_string(),
+            model: "claude-\"x\"".to_string(),
+            request_model: "m".to_string(),
+            usage: TokenUsage {
+                input_tokens: 100,
+                output_tokens: 20,
+                ..Default::default()
+            },
+            cost: Some(CostBreakdown {
+                input_cost: Decimal::ZERO,
+                output_cost: Decimal::ZERO,
+                cache_read_cost: Decimal::ZERO,
+                cache_creation_cost: Decimal::ZERO,
+                total_cost: Decimal::new(15, 1),
+            }),
+            latency_ms,
+            first_token_ms: Some(300),
+            status_code,
+            error_message: None,
+            session_id: None,
+            provider_type: None,
+            is_streaming: true,
+            cost_multiplier: "1".to_string(),
+            request_body: None,
+            response_body: None,
+            routing_rule: None,
+            from_cache,
+            client_token_id: None,
+            rectifier: None,
+        }
+    }
+
+    #[test]
+    fn test_render_prometheus_text() {
+        let metrics = ProxyMetrics::default();
+        metrics.record_request(&request_log(200, 800, false));
+        metrics.record_request(&request_log(200, 4000, false));
+        metrics.record_request(&request_log(200, 10, true));
+        metrics.record_error("claude", "p1", ErrorCategory::Retryable);
+        metrics.record_failover("claude", "p2");
+
+        let status = ProxyStatus {
+            cache_hits: 3,
+            ..Default::default()
+        };
+        let breakers = vec![(
+            "claude:p1".to_string(),
+            CircuitBreakerStats {
+                state: CircuitState::Open,
+                consecutive_failures: 4,
+                consecutive_successes: 0,
+                total_requests: 10,
+                failed_requests: 4,
+            },
+        )];
+        let text = metrics.render(&status, &breakers);
+
+        let labels = r#"app="claude",provider="p1",model="claude-\"x\"""#;
+        assert!(text.contains(&format!(
+            "cc_switch_requests_total{{{labels},status=\"200\"}} 2"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_request_duration_seconds_bucket{{{labels},le=\"1\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} 2"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_request_duration_seconds_sum{{{labels}}} 4.8"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_tokens_total{{{labels},type=\"input\"}} 200"
+        )));
+        assert!(text.contains(&format!("cc_switch_cost_usd_total{{{labels}}} 3")));
+        assert!(text.contains(
+            "cc_switch_errors_total{app=\"claude\",provider=\"p1\",category=\"retryable\"} 1"
+        ));
+        assert!(text.contains("cc_switch_failovers_total{app=\"claude\",provider=\"p2\"} 1"));
+        assert!(text.contains("cc_switch_circuit_breaker_state{app=\"claude\",provider=\"p1\"} 2"));
+        assert!(text.contains("cc_switch_response_cache_hits_total 3"));
+        assert!(text.contains("# TYPE cc_switch_first_token_seconds histogram"));
+    }
+}
@@ -0,0 +1,1202 @@
+//! 离线 Mock 上游
+//!
+//! 供应商启用 `mockUpstream` 后，代理不再请求真实上游，而是按匹配规则返回录制或预置的响应：
+//! - 非流式请求：录制的原生响应体原样返回，其余按请求格式合成
+//! - 流式请求：合成带 usage 的 SSE 事件流（Anthropic / OpenAI Chat / Responses / Gemini）
+//!
+//! 同时支持延迟与故障注入（429/500/超时/流中断），便于在本地演练故障转移与熔断。
+
+use super::log_codes::mock as log_mock;
+use super::routing_rules::glob_match;
+use super::ProxyError;
+use crate::provider::{MockFaultKind, MockUpstreamConfig, Provider};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Mock 上游请求日志的来源标记（不计成本，不计入真实用量）
+pub const MOCK_SOURCE: &str = "mock";
+
+/// 未命中任何 fixture 时返回的文本
+const DEFAULT_MOCK_TEXT: &str = "This is a mock response from CC Switch.";
+
+/// 每个 SSE 文本增量的字符数
+const STREAM_CHUNK_CHARS: usize = 24;
+
+/// 超时故障在未设置请求超时时的挂起时长（与 HTTP 客户端默认超时一致）
+const DEFAULT_HANG: Duration = Duration::from_secs(600);
+
+/// Mock 响应 fixture（每个 app 独立，按 sort_index 顺序匹配）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockFixture {
+    pub id: String,
+    pub app_type: String,
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub sort_index: i64,
+    /// 模型匹配（glob，为空时匹配所有模型）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_pattern: Option<String>,
+    /// 请求体需包含的文本（为空时不限制）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_contains: Option<String>,
+    #[serde(default = "default_status_code")]
+    pub status_code: u16,
+    /// 响应体：原生 JSON 响应、请求日志中记录的响应，或纯文本
+    pub response_body: String,
+    /// 录制来源的请求 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_request_id: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_status_code() -> u16 {
+    200
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+impl MockFixture {
+    /// fixture 是否匹配当前请求
+    pub fn matches(&self, model: &str, body_text: &str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if let Some(pattern) = non_empty(&self.model_pattern) {
+            if !glob_match(pattern, model) {
+                return false;
+            }
+        }
+        if let Some(needle) = non_empty(&self.prompt_contains) {
+            if !body_text.contains(needle) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 上游响应格式（由 adapter 与实际请求的端点决定）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockWireFormat {
+    Anthropic,
+    OpenAiChat,
+    OpenAiResponses,
+    Gemini,
+}
+
+impl MockWireFormat {
+    pub fn detect(adapter_name: &str, endpoint: &str) -> Self {
+        if adapter_name == "Gemini" {
+            Self::Gemini
+        } else if endpoint.contains("/chat/completions") {
+            Self::OpenAiChat
+        } else if endpoint.contains("/responses") {
+            Self::OpenAiResponses
+        } else {
+            Self::Anthropic
+        }
+    }
+
+    /// 响应体是否已是该格式的原生响应（可原样返回）
+    fn is_native(self, body: &Value) -> bool {
+        match self {
+            Self::Anthropic => body.get("type").and_then(Value::as_str) == Some("message"),
+            Self::OpenAiChat => body.get("choices").is_some(),
+            Self::OpenAiResponses => body.get("object").and_then(Value::as_str) == Some("response"),
+            Self::Gemini => body.get("candidates").is_some(),
+        }
+    }
+}
+
+/// 一次 Mock 请求
+pub struct MockRequest<'a> {
+    pub format: MockWireFormat,
+    pub model: &'a str,
+    pub body: &'a Value,
+    pub streaming: bool,
+}
+
+/// Mock 响应的 token 用量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct MockUsage {
+    input: u64,
+    output: u64,
+    cache_read: u64,
+    cache_creation: u64,
+}
+
+/// 供应商已启用的 Mock 上游配置（未启用时为 None）
+pub fn enabled_config(provider: &Provider) -> Option<&MockUpstreamConfig> {
+    provider
+        .meta
+        .as_ref()
+        .and_then(|m| m.mock_upstream.as_ref())
+        .filter(|m| m.enabled)
+}
+
+/// Mock 响应中的工具调用
+#[derive(Debug, Clone, PartialEq)]
+struct MockToolCall {
+    id: String,
+    name: String,
+    input: Value,
+}
+
+/// Mock 响应内容：文本与工具调用
+#[derive(Debug, Clone, PartialEq)]
+struct MockContent {
+    text: String,
+    tool_calls: Vec<MockToolCall>,
+}
+
+impl MockContent {
+    fn from_text(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            tool_calls: Vec::new(),
+        }
+    }
+
+    /// 是否输出文本块（有文本，或没有任何工具调用时）
+    fn has_text(&self) -> bool {
+        !self.text.is_empty() || self.tool_calls.is_empty()
+    }
+
+    fn stop_reason(&self, text_reason: &'static str, tool_reason: &'static str) -> &'static str {
+        if self.tool_calls.is_empty() {
+            text_reason
+        } else {
+            tool_reason
+        }
+    }
+}
+
+/// 生成 Mock 上游响应
+///
+/// `timeout` 为本次请求的超时设置，超时故障挂起该时长后返回超时错误
+pub async fn respond(
+    config: &MockUpstreamConfig,
+    fixtures: &[MockFixture],
+    request: MockRequest<'_>,
+    timeout: Duration,
+) -> Result<reqwest::Response, ProxyError> {
+    let fault = roll_fault(config);
+    if let Some(kind) = fault {
+        log::info!("[{}] Mock 上游注入故障: {kind:?}", log_mock::FAULT_INJECTED);
+    }
+
+    if config.latency_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(config.latency_ms)).await;
+    }
+
+    match fault {
+        Some(MockFaultKind::RateLimit) => {
+            return Err(ProxyError::UpstreamError {
+                status: 429,
+                body: Some(error_body(request.format, 429).to_string()),
+            })
+        }
+        Some(MockFaultKind::ServerError) => {
+            return Err(ProxyError::UpstreamError {
+                status: 500,
+                body: Some(error_body(request.format, 500).to_string()),
+            })
+        }
+        Some(MockFaultKind::Timeout) => {
+            let hang = if timeout.is_zero() {
+                DEFAULT_HANG
+            } else {
+                timeout
+            };
+            tokio::time::sleep(hang).await;
+            return Err(ProxyError::Timeout("Mock 上游请求超时".to_string()));
+        }
+        _ => {}
+    }
+    let disconnect = fault == Some(MockFaultKind::Disconnect);
+
+    let body_text = request.body.to_string();
+    let fixture = fixtures
+        .iter()
+        .find(|fixture| fixture.matches(request.model, &body_text));
+    match fixture {
+        Some(fixture) => log::info!(
+            "[{}] Mock 上游命中 fixture '{}' (model={})",
+            log_mock::FIXTURE_MATCHED,
+            fixture.name,
+            request.model
+        ),
+        None => log::info!(
+            "[{}] Mock 上游未命中 fixture，返回默认响应 (model={})",
+            log_mock::FIXTURE_MATCHED,
+            request.model
+        ),
+    }
+
+    let status = fixture.map_or(200, |f| f.status_code);
+    if !(200..300).contains(&status) {
+        return Err(ProxyError::UpstreamError {
+            status,
+            body: fixture.map(|f| f.response_body.clone()),
+        });
+    }
+
+    let recorded = fixture.map(|f| {
+        serde_json::from_str::<Value>(&f.response_body)
+            .unwrap_or_else(|_| Value::String(f.response_body.clone()))
+    });
+    let content = recorded
+        .as_ref()
+        .map(extract_content)
+        .unwrap_or_else(|| MockContent::from_text(DEFAULT_MOCK_TEXT));
+    let usage = recorded
+        .as_ref()
+        .and_then(extract_usage)
+        .unwrap_or_else(|| estimate_usage(&body_text, &content));
+
+    let (content_type, frames) = if request.streaming {
+        (
+            "text/event-stream",
+            render_sse(request.format, request.model, &content, usage),
+        )
+    } else {
+        let body = match recorded {
+            Some(body) if request.format.is_native(&body) => body,
+            _ => render_json(request.format, request.model, &content, usage),
+        };
+        let bytes = body.to_string().into_bytes();
+        // 非流式断连：只发送前一半响应体
+        let mid = bytes.len() / 2;
+        let frames = vec![
+            Bytes::copy_from_slice(&bytes[..mid]),
+            Bytes::copy_from_slice(&bytes[mid..]),
+        ];
+        ("application/json", frames)
+    };
+
+    let cut = disconnect.then(|| (frames.len() / 2).max(1));
+    let delay = if request.streaming {
+        Duration::from_millis(config.chunk_delay_ms)
+    } else {
+        Duration::ZERO
+    };
+    let stream = async_stream::stream! {
+        for (index, frame) in frames.into_iter().enumerate() {
+            if cut == Some(index) {
+                yield Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionReset,
+                    "mock upstream disconnected",
+                ));
+                return;
+            }
+            if index > 0 && !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            yield Ok::<_, std::io::Error>(frame);
+        }
+    };
+
+    let response = axum::http::Response::builder()
+        .status(status)
+        .header("content-type", content_type)
+        .header("x-cc-switch-mock", "1")
+        .body(reqwest::Body::wrap_stream(stream))
+        .map_err(|e| ProxyError::Internal(e.to_string()))?;
+    Ok(reqwest::Response::from(response))
+}
+
+/// 按顺序判定故障规则，返回命中的故障
+fn roll_fault(config: &MockUpstreamConfig) -> Option<MockFaultKind> {
+    config
+        .faults
+        .iter()
+        .find(|fault| fault.rate > 0.0 && fastrand::f64() < fault.rate)
+        .map(|fault| fault.kind)
+}
+
+/// 各格式的错误响应体
+fn error_body(format: MockWireFormat, status: u16) -> Value {
+    let (anthropic_type, openai_type, gemini_status, message) = if status == 429 {
+        (
+            "rate_limit_error",
+            "rate_limit_exceeded",
+            "RESOURCE_EXHAUSTED",
+            "Mock upstream rate limit exceeded",
+        )
+    } else {
+        (
+            "api_error",
+            "server_error",
+            "INTERNAL",
+            "Mock upstream internal error",
+        )
+    };
+    match format {
+        MockWireFormat::Anthropic => json!({
+            "type": "error",
+            "error": { "type": anthropic_type, "message": message }
+        }),
+        MockWireFormat::OpenAiChat | MockWireFormat::OpenAiResponses => json!({
+            "error": { "message": message, "type": openai_type, "code": null }
+        }),
+        MockWireFormat::Gemini => json!({
+            "error": { "code": status, "message": message, "status": gemini_status }
+        }),
+    }
+}
+
+/// 从录制的响应体中提取文本与工具调用（兼容各家原生格式、请求日志格式与纯文本）
+fn extract_content(body: &Value) -> MockContent {
+    let tool_calls = extract_tool_calls(body);
+    let text = extract_text(body).unwrap_or_else(|| {
+        if tool_calls.is_empty() {
+            DEFAULT_MOCK_TEXT.to_string()
+        } else {
+            String::new()
+        }
+    });
+    MockContent { text, tool_calls }
+}
+
+fn extract_text(body: &Value) -> Option<String> {
+    fn join(parts: Option<&Vec<Value>>, key: &str) -> Option<String> {
+        let text: String = parts?
+            .iter()
+            .filter_map(|part| part.get(key).and_then(Value::as_str))
+            .collect();
+        (!text.is_empty()).then_some(text)
+    }
+
+    if let Some(text) = body.as_str() {
+        return Some(text.to_string());
+    }
+    // Anthropic: content[].text；请求日志（流式）: content.text
+    if let Some(text) = join(body.get("content").and_then(Value::as_array), "text") {
+        return Some(text);
+    }
+    if let Some(text) = body
+        .pointer("/content/text")
+        .and_then(Value::as_str)
+        .filter(|t| !t.is_empty())
+    {
+        return Some(text.to_string());
+    }
+    // OpenAI Chat
+    if let Some(text) = body
+        .pointer("/choices/0/message/content")
+        .and_then(Value::as_str)
+        .filter(|t| !t.is_empty())
+    {
+        return Some(text.to_string());
+    }
+    // OpenAI Responses
+    if let Some(output) = body.get("output").and_then(Value::as_array) {
+        let text: String = output
+            .iter()
+            .filter_map(|item| join(item.get("content").and_then(Value::as_array), "text"))
+            .collect();
+        if !text.is_empty() {
+            return Some(text);
+        }
+    }
+    // Gemini
+    join(
+        body.pointer("/candidates/0/content/parts")
+            .and_then(Value::as_array),
+        "text",
+    )
+}
+
+/// 从录制的响应体中提取工具调用
+fn extract_tool_calls(body: &Value) -> Vec<MockToolCall> {
+    let field = |value: &Value, key: &str| value.get(key).and_then(Value::as_str).map(String::from);
+    // OpenAI 系列的 arguments 为 JSON 字符串
+    let arguments = |value: Option<&Value>| match value {
+        Some(Value::String(raw)) => serde_json::from_str(raw).unwrap_or_else(|_| json!({})),
+        Some(value) => value.clone(),
+        None => json!({}),
+    };
+
+    // Anthropic: content[] 中的 tool_use 块；请求日志（流式）: content.tool_use[]
+    let blocks = body.get("content").and_then(|content| {
+        content
+            .as_array()
+            .or_else(|| content.get("tool_use").and_then(Value::as_array))
+    });
+    if let Some(blocks) = blocks {
+        return blocks
+            .iter()
+            .filter(|block| block.get("type").and_then(Value::as_str) == Some("tool_use"))
+            .filter_map(|block| {
+                Some(MockToolCall {
+                    id: field(block, "id").unwrap_or_else(|| mock_id("toolu_mock_")),
+                    name: field(block, "name")?,
+                    input: block.get("input").cloned().unwrap_or_else(|| json!({})),
+                })
+            })
+            .collect();
+    }
+    // OpenAI Chat
+    if let Some(calls) = body
+        .pointer("/choices/0/message/tool_calls")
+        .and_then(Value::as_array)
+    {
+        return calls
+            .iter()
+            .filter_map(|call| {
+                Some(MockToolCall {
+                    id: field(call, "id").unwrap_or_else(|| mock_id("call_mock_")),
+                    name: call.pointer("/function/name")?.as_str()?.to_string(),
+                    input: arguments(call.pointer("/function/arguments")),
+                })
+            })
+            .collect();
+    }
+    // OpenAI Responses
+    if let Some(output) = body.get("output").and_then(Value::as_array) {
+        return output
+            .iter()
+            .filter(|item| item.get("type").and_then(Value::as_str) == Some("function_call"))
+            .filter_map(|item| {
+                Some(MockToolCall {
+                    id: field(item, "call_id").unwrap_or_else(|| mock_id("call_mock_")),
+                    name: field(item, "name")?,
+                    input: arguments(item.get("arguments")),
+                })
+            })
+            .collect();
+    }
+    // Gemini
+    if let Some(parts) = body
+        .pointer("/candidates/0/content/parts")
+        .and_then(Value::as_array)
+    {
+        return parts
+            .iter()
+            .filter_map(|part| part.get("functionCall"))
+            .filter_map(|call| {
+                Some(MockToolCall {
+                    id: mock_id("call_mock_"),
+                    name: field(call, "name")?,
+                    input: call.get("args").cloned().unwrap_or_else(|| json!({})),
+                })
+            })
+            .collect();
+    }
+    Vec::new()
+}
+
+/// 从录制的响应体中提取用量
+fn extract_usage(body: &Value) -> Option<MockUsage> {
+    let number = |value: &Value, key: &str| value.get(key).and_then(Value::as_u64);
+
+    if let Some(meta) = body.get("usageMetadata") {
+        return Some(MockUsage {
+            input: number(meta, "promptTokenCount").unwrap_or(0),
+            output: number(meta, "candidatesTokenCount").unwrap_or(0),
+            cache_read: number(meta, "cachedContentTokenCount").unwrap_or(0),
+            cache_creation: 0,
+        });
+    }
+    let usage = body.get("usage")?;
+    if let Some(prompt) = number(usage, "prompt_tokens") {
+        let cached = usage
+            .pointer("/prompt_tokens_details/cached_tokens")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        return Some(MockUsage {
+            input: prompt.saturating_sub(cached),
+            output: number(usage, "completion_tokens").unwrap_or(0),
+            cache_read: cached,
+            cache_creation: 0,
+        });
+    }
+    Some(MockUsage {
+        input: number(usage, "input_tokens")?,
+        output: number(usage, "output_tokens").unwrap_or(0),
+        cache_read: number(usage, "cache_read_input_tokens")
+            .or_else(|| number(usage, "cache_read_tokens"))
+            .unwrap_or(0),
+        cache_creation: number(usage, "cache_creation_input_tokens")
+            .or_else(|| number(usage, "cache_creation_tokens"))
+            .unwrap_or(0),
+    })
+}
+
+/// 按约 4 字符/token 估算用量
+fn estimate_usage(request_text: &str, content: &MockContent) -> MockUsage {
+    let output_chars = content.text.chars().count()
+        + content
+            .tool_calls
+            .iter()
+            .map(|call| call.name.len() + call.input.to_string().len())
+            .sum::<usize>();
+    MockUsage {
+        input: (request_text.len() as u64 / 4).max(1),
+        output: (output_chars as u64 / 4).max(1),
+        ..Default::default()
+    }
+}
+
+fn mock_id(prefix: &str) -> String {
+    format!("{prefix}{}", uuid::Uuid::new_v4().simple())
+}
+
+fn now_secs() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn chunk_text(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(STREAM_CHUNK_CHARS)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+fn openai_usage(usage: MockUsage) -> Value {
+    json!({
+        "prompt_tokens": usage.input + usage.cache_read,
+        "completion_tokens": usage.output,
+        "total_tokens": usage.input + usage.cache_read + usage.output,
+        "prompt_tokens_details": { "cached_tokens": usage.cache_read }
+    })
+}
+
+fn responses_body(id: &str, model: &str, content: &MockContent, usage: MockUsage) -> Value {
+    let mut output = Vec::new();
+    if content.has_text() {
+        output.push(json!({
+            "id": format!("msg_{id}"),
+            "type": "message",
+            "status": "completed",
+            "role": "assistant",
+            "content": [{ "type": "output_text", "text": content.text, "annotations": [] }]
+        }));
+    }
+    output.extend(content.tool_calls.iter().map(responses_function_call));
+    json!({
+        "id": id,
+        "object": "response",
+        "created_at": now_secs(),
+        "status": "completed",
+        "model": model,
+        "output": output,
+        "usage": {
+            "input_tokens": usage.input + usage.cache_read,
+            "input_tokens_details": { "cached_tokens": usage.cache_read },
+            "output_tokens": usage.output,
+            "total_tokens": usage.input + usage.cache_read + usage.output
+        }
+    })
+}
+
+fn responses_function_call(call: &MockToolCall) -> Value {
+    json!({
+        "id": format!("fc_{}", call.id),
+        "type": "function_call",
+        "status": "completed",
+        "call_id": call.id,
+        "name": call.name,
+        "arguments": call.input.to_string()
+    })
+}
+
+fn openai_tool_call(call: &MockToolCall) -> Value {
+    json!({
+        "id": call.id,
+        "type": "function",
+        "function": { "name": call.name, "arguments": call.input.to_string() }
+    })
+}
+
+/// Gemini 响应的 parts：文本在前，工具调用在后
+fn gemini_parts(text: Option<&str>, tool_calls: &[MockToolCall]) -> Vec<Value> {
+    text.map(|text| json!({ "text": text }))
+        .into_iter()
+        .chain(
+            tool_calls
+                .iter()
+                .map(|call| json!({ "functionCall": { "name": call.name, "args": call.input } })),
+        )
+        .collect()
+}
+
+fn gemini_usage(usage: MockUsage) -> Value {
+    json!({
+        "promptTokenCount": usage.input,
+        "candidatesTokenCount": usage.output,
+        "cachedContentTokenCount": usage.cache_read,
+        "totalTokenCount": usage.input + usage.output
+    })
+}
+
+/// 合成非流式响应
+fn render_json(
+    format: MockWireFormat,
+    model: &str,
+    content: &MockContent,
+    usage: MockUsage,
+) -> Value {
+    let text = content.has_text().then_some(content.text.as_str());
+    match format {
+        MockWireFormat::Anthropic => {
+            let blocks: Vec<Value> = text
+                .map(|text| json!({ "type": "text", "text": text }))
+                .into_iter()
+                .chain(content.tool_calls.iter().map(|call| {
+                    json!({ "type": "tool_use", "id": call.id, "name": call.name, "input": call.input })
+                }))
+                .collect();
+            json!({
+                "id": mock_id("msg_mock_"),
+                "type": "message",
+                "role": "assistant",
+                "model": model,
+                "content": blocks,
+                "stop_reason": content.stop_reason("end_turn", "tool_use"),
+                "stop_sequence": null,
+                "usage": {
+                    "input_tokens": usage.input,
+                    "output_tokens": usage.output,
+                    "cache_read_input_tokens": usage.cache_read,
+                    "cache_creation_input_tokens": usage.cache_creation
+                }
+            })
+        }
+        MockWireFormat::OpenAiChat => {
+            let mut message = json!({ "role": "assistant", "content": text });
+            if !content.tool_calls.is_empty() {
+                message["tool_calls"] = content.tool_calls.iter().map(openai_tool_call).collect();
+            }
+            json!({
+                "id": mock_id("chatcmpl-mock-"),
+                "object": "chat.completion",
+                "created": now_secs(),
+                "model": model,
+                "choices": [{
+                    "index": 0,
+                    "message": message,
+                    "finish_reason": content.stop_reason("stop", "tool_calls")
+                }],
+                "usage": openai_usage(usage)
+            })
+        }
+        MockWireFormat::OpenAiResponses => {
+            responses_body(&mock_id("resp_mock_"), model, content, usage)
+        }
+        MockWireFormat::Gemini => json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": gemini_parts(text, &content.tool_calls) },
+                "finishReason": "STOP",
+                "index": 0
+            }],
+            "usageMetadata": gemini_usage(usage),
+            "modelVersion": model
+        }),
+    }
+}
+
+/// 合成 SSE 事件流（每个元素为一个完整事件）
+fn render_sse(
+    format: MockWireFormat,
+    model: &str,
+    content: &MockContent,
+    usage: MockUsage,
+) -> Vec<Bytes> {
+    let named = |event: &str, data: Value| Bytes::from(format!("event: {event}\ndata: {data}\n\n"));
+    let data = |data: Value| Bytes::from(format!("data: {data}\n\n"));
+    let chunks = chunk_text(&content.text);
+
+    match format {
+        MockWireFormat::Anthropic => {
+            let mut frames = vec![named(
+                "message_start",
+                json!({
+                    "type": "message_start",
+                    "message": {
+                        "id": mock_id("msg_mock_"),
+                        "type": "message",
+                        "role": "assistant",
+                        "model": model,
+                        "content": [],
+                        "stop_reason": null,
+                        "stop_sequence": null,
+                        "usage": {
+                            "input_tokens": usage.input,
+                            "output_tokens": 1,
+                            "cache_read_input_tokens": usage.cache_read,
+                            "cache_creation_input_tokens": usage.cache_creation
+                        }
+                    }
+                }),
+            )];
+            let mut index = 0;
+            if content.has_text() {
+                frames.push(named(
+                    "content_block_start",
+                    json!({
+                        "type": "content_block_start",
+                        "index": index,
+                        "content_block": { "type": "text", "text": "" }
+                    }),
+                ));
+                frames.extend(chunks.iter().map(|chunk| {
+                    named(
+                        "content_block_delta",
+                        json!({
+                            "type": "content_block_delta",
+                            "index": index,
+                            "delta": { "type": "text_delta", "text": chunk }
+                        }),
+                    )
+                }));
+                frames.push(named(
+                    "content_block_stop",
+                    json!({ "type": "content_block_stop", "index": index }),
+                ));
+                index += 1;
+            }
+            for call in &content.tool_calls {
+                frames.push(named(
+                    "content_block_start",
+                    json!({
+                        "type": "content_block_start",
+                        "index": index,
+                        "content_block": { "type": "tool_use", "id": call.id, "name": call.name, "input": {} }
+                    }),
+                ));
+                frames.push(named(
+                    "content_block_delta",
+                    json!({
+                        "type": "content_block_delta",
+                        "index": index,
+                        "delta": { "type": "input_json_delta", "partial_json": call.input.to_string() }
+                    }),
+                ));
+                frames.push(named(
+                    "content_block_stop",
+                    json!({ "type": "content_block_stop", "index": index }),
+                ));
+                index += 1;
+            }
+            frames.push(named(
+                "message_delta",
+                json!({
+                    "type": "message_delta",
+                    "delta": {
+                        "stop_reason": content.stop_reason("end_turn", "tool_use"),
+                        "stop_sequence": null
+                    },
+                    "usage": { "output_tokens": usage.output }
+                }),
+            ));
+            frames.push(named("message_stop", json!({ "type": "message_stop" })));
+            frames
+        }
+        MockWireFormat::OpenAiChat => {
+            let id = mock_id("chatcmpl-mock-");
+            let created = now_secs();
+            let chunk = |delta: Value, finish_reason: Value| {
+                data(json!({
+                    "id": id,
+                    "object": "chat.completion.chunk",
+                    "created": created,
+                    "model": model,
+                    "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }]
+                }))
+            };
+            let mut frames = vec![chunk(
+                json!({ "role": "assistant", "content": "" }),
+                Value::Null,
+            )];
+            frames.extend(
+                chunks
+                    .iter()
+                    .map(|text| chunk(json!({ "content": text }), Value::Null)),
+            );
+            frames.extend(content.tool_calls.iter().enumerate().map(|(index, call)| {
+                let mut tool_call = openai_tool_call(call);
+                tool_call["index"] = json!(index);
+                chunk(json!({ "tool_calls": [tool_call] }), Value::Null)
+            }));
+            frames.push(chunk(
+                json!({}),
+                json!(content.stop_reason("stop", "tool_calls")),
+            ));
+            frames.push(data(json!({
+                "id": id,
+                "object": "chat.completion.chunk",
+                "created": created,
+                "model": model,
+                "choices": [],
+                "usage": openai_usage(usage)
+            })));
+            frames.push(Bytes::from_static(b"data: [DONE]\n\n"));
+            frames
+        }
+        MockWireFormat::OpenAiResponses => {
+            let id = mock_id("resp_mock_");
+            let item_id = format!("msg_{id}");
+            let mut in_progress = responses_body(&id, model, content, usage);
+            in_progress["status"] = json!("in_progress");
+            in_progress["output"] = json!([]);
+            in_progress["usage"] = Value::Null;
+
+            let mut frames = vec![named(
+                "response.created",
+                json!({ "type": "response.created", "response": in_progress }),
+            )];
+            let mut output_index = 0;
+            if content.has_text() {
+                frames.extend(chunks.iter().map(|chunk| {
+                    named(
+                        "response.output_text.delta",
+                        json!({
+                            "type": "response.output_text.delta",
+                            "item_id": item_id,
+                            "output_index": output_index,
+                            "content_index": 0,
+                            "delta": chunk
+                        }),
+                    )
+                }));
+                frames.push(named(
+                    "response.output_text.done",
+                    json!({
+                        "type": "response.output_text.done",
+                        "item_id": item_id,
+                        "output_index": output_index,
+                        "content_index": 0,
+                        "text": content.text
+                    }),
+                ));
+                output_index += 1;
+            }
+            for call in &content.tool_calls {
+                frames.push(named(
+                    "response.output_item.done",
+                    json!({
+                        "type": "response.output_item.done",
+                        "output_index": output_index,
+                        "item": responses_function_call(call)
+                    }),
+                ));
+                output_index += 1;
+            }
+            frames.push(named(
+                "response.completed",
+                json!({
+                    "type": "response.completed",
+                    "response": responses_body(&id, model, content, usage)
+                }),
+            ));
+            frames
+        }
+        MockWireFormat::Gemini => {
+            // 每个文本片段一个事件，工具调用放在最后一个事件中
+            let mut events: Vec<Vec<Value>> = chunks
+                .iter()
+                .map(|chunk| gemini_parts(Some(chunk), &[]))
+                .collect();
+            if !content.tool_calls.is_empty() {
+                let calls = gemini_parts(None, &content.tool_calls);
+                match events.last_mut() {
+                    Some(last) => last.extend(calls),
+                    None => events.push(calls),
+                }
+            }
+            if events.is_empty() {
+                events.push(gemini_parts(Some(""), &[]));
+            }
+
+            let last = events.len() - 1;
+            events
+                .into_iter()
+                .enumerate()
+                .map(|(index, parts)| {
+                    let mut candidate = json!({
+                        "content": { "role": "model", "parts": parts },
+                        "index": 0
+                    });
+                    let mut event = json!({ "modelVersion": model });
+                    if index == last {
+                        candidate["finishReason"] = json!("STOP");
+                        event["usageMetadata"] = gemini_usage(usage);
+                    }
+                    event["candidates"] = json!([candidate]);
+                    data(event)
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::MockFault;
+
+    fn fixture(model_pattern: Option<&str>, prompt_contains: Option<&str>) -> MockFixture {
+        MockFixture {
+            id: "f".to_string(),
+            app_type: "claude".to_string(),
+            name: "fixture".to_string(),
+            enabled: true,
+            sort_index: 0,
+            model_pattern: model_pattern.map(str::to_string),
+            prompt_contains: prompt_contains.map(str::to_string),
+            status_code: 200,
+            response_body: "hello".to_string(),
+            source_request_id: None,
+        }
+    }
+
+    fn parse_events(frames: &[Bytes]) -> Vec<Value> {
+        frames
+            .iter()
+            .filter_map(|frame| {
+                let text = std::str::from_utf8(frame).unwrap();
+                let data = text.lines().find_map(|l| l.strip_prefix("data: "))?;
+                serde_json::from_str(data).ok()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fixture_matching() {
+        let f = fixture(Some("claude-*"), Some("weather"));
+        assert!(f.matches("claude-sonnet-4", r#"{"q":"what's the weather"}"#));
+        assert!(!f.matches("gpt-5", r#"{"q":"what's the weather"}"#));
+        assert!(!f.matches("claude-sonnet-4", r#"{"q":"hi"}"#));
+        assert!(fixture(None, Some(" ")).matches("any", "{}"));
+
+        let mut disabled = fixture(None, None);
+        disabled.enabled = false;
+        assert!(!disabled.matches("any", "{}"));
+    }
+
+    #[test]
+    fn test_extract_from_recorded_bodies() {
+        let claude = json!({
+            "type": "message",
+            "content": [{ "type": "text", "text": "Hi" }, { "type": "text", "text": "!" }],
+            "usage": { "input_tokens": 10, "output_tokens": 2, "cache_read_input_tokens": 5 }
+        });
+        assert_eq!(extract_content(&claude).text, "Hi!");
+        assert_eq!(
+            extract_usage(&claude),
+            Some(MockUsage {
+                input: 10,
+                output: 2,
+                cache_read: 5,
+                cache_creation: 0
+            })
+        );
+
+        // 请求日志中流式响应的记录格式
+        let logged = json!({
+            "content": { "text": "streamed", "thinking": "", "tool_use": [] },
+            "usage": { "input_tokens": 3, "output_tokens": 4, "cache_read_tokens": 0, "cache_creation_tokens": 1 }
+        });
+        assert_eq!(extract_content(&logged).text, "streamed");
+        assert_eq!(extract_usage(&logged).unwrap().cache_creation, 1);
+
+        let chat = json!({
+            "choices": [{ "message": { "content": "ok" } }],
+            "usage": { "prompt_tokens": 12, "completion_tokens": 3, "prompt_tokens_details": { "cached_tokens": 2 } }
+        });
+        assert_eq!(extract_content(&chat).text, "ok");
+        assert_eq!(extract_usage(&chat).unwrap().input, 10);
+
+        let gemini = json!({
+            "candidates": [{ "content": { "parts": [{ "text": "g" }] } }],
+            "usageMetadata": { "promptTokenCount": 7, "candidatesTokenCount": 1 }
+        });
+        assert_eq!(extract_content(&gemini).text, "g");
+        assert_eq!(extract_usage(&gemini).unwrap().input, 7);
+
+        assert_eq!(
+            extract_content(&Value::String("plain".to_string())).text,
+            "plain"
+        );
+        assert!(extract_usage(&Value::String("plain".to_string())).is_none());
+    }
+
+    #[test]
+    fn test_anthropic_sse_carries_text_and_usage() {
+        let usage = MockUsage {
+            input: 100,
+            output: 20,
+            ..Default::default()
+        };
+        let text = "a".repeat(STREAM_CHUNK_CHARS * 2 + 1);
+        let events = parse_events(&render_sse(
+            MockWireFormat::Anthropic,
+            "claude-test",
+            &MockContent::from_text(&text),
+            usage,
+        ));
+
+        assert_eq!(events[0]["type"], "message_start");
+        assert_eq!(events[0]["message"]["usage"]["input_tokens"], 100);
+        let streamed: String = events
+            .iter()
+            .filter(|e| e["type"] == "content_block_delta")
+            .map(|e| e["delta"]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(streamed, text);
+        let delta = events
+            .iter()
+            .find(|e| e["type"] == "message_delta")
+            .unwrap();
+        assert_eq!(delta["usage"]["output_tokens"], 20);
+        assert_eq!(events.last().unwrap()["type"], "message_stop");
+    }
+
+    #[test]
+    fn test_openai_and_gemini_sse_end_with_usage() {
+        let usage = MockUsage {
+            input: 8,
+            output: 2,
+            cache_read: 4,
+            cache_creation: 0,
+        };
+        let chat = render_sse(
+            MockWireFormat::OpenAiChat,
+            "gpt-test",
+            &MockContent::from_text("hi"),
+            usage,
+        );
+        assert_eq!(chat.last().unwrap().as_ref(), b"data: [DONE]\n\n");
+        let events = parse_events(&chat);
+        assert_eq!(events.last().unwrap()["usage"]["prompt_tokens"], 12);
+
+        let responses = parse_events(&render_sse(
+            MockWireFormat::OpenAiResponses,
+            "gpt-test",
+            &MockContent::from_text("hi"),
+            usage,
+        ));
+        let completed = responses.last().unwrap();
+        assert_eq!(completed["type"], "response.completed");
+        assert_eq!(completed["response"]["usage"]["output_tokens"], 2);
+
+        let gemini = parse_events(&render_sse(
+            MockWireFormat::Gemini,
+            "gemini-test",
+            &MockContent::from_text(""),
+            usage,
+        ));
+        assert_eq!(gemini.len(), 1);
+        assert_eq!(gemini[0]["usageMetadata"]["promptTokenCount"], 8);
+    }
+
+    #[test]
+    fn test_tool_calls_survive_format_conversion() {
+        let recorded = json!({
+            "type": "message",
+            "content": [
+                { "type": "text", "text": "Reading" },
+                { "type": "tool_use", "id": "toolu_1", "name": "Read", "input": { "file_path": "a.rs" } }
+            ]
+        });
+        let content = extract_content(&recorded);
+        assert_eq!(content.text, "Reading");
+        assert_eq!(
+            content.tool_calls,
+            vec![MockToolCall {
+                id: "toolu_1".to_string(),
+                name: "Read".to_string(),
+                input: json!({ "file_path": "a.rs" }),
+            }]
+        );
+
+        // 只有工具调用时不回退到默认文本
+        let chat = json!({
+            "choices": [{ "message": { "content": null, "tool_calls": [
+                { "id": "call_1", "type": "function", "function": { "name": "Read", "arguments": "{\"file_path\":\"a.rs\"}" } }
+            ] } }]
+        });
+        let content = extract_content(&chat);
+        assert!(content.text.is_empty());
+        assert_eq!(content.tool_calls[0].input, json!({ "file_path": "a.rs" }));
+
+        let usage = MockUsage::default();
+        let body = render_json(MockWireFormat::Anthropic, "m", &content, usage);
+        assert_eq!(body["stop_reason"], "tool_use");
+        assert_eq!(body["content"][0]["type"], "tool_use");
+        assert_eq!(body["content"][0]["input"]["file_path"], "a.rs");
+
+        let events = parse_events(&render_sse(MockWireFormat::Anthropic, "m", &content, usage));
+        let delta = events
+            .iter()
+            .find(|e| e["delta"]["type"] == "input_json_delta")
+            .unwrap();
+        assert_eq!(delta["index"], 0);
+        assert_eq!(delta["delta"]["partial_json"], r#"{"file_path":"a.rs"}"#);
+
+        let responses = parse_events(&render_sse(
+            MockWireFormat::OpenAiResponses,
+            "m",
+            &content,
+            usage,
+        ));
+        let completed = responses.last().unwrap();
+        assert_eq!(completed["response"]["output"][0]["type"], "function_call");
+        assert_eq!(completed["response"]["output"][0]["call_id"], "call_1");
+
+        let gemini = render_json(MockWireFormat::Gemini, "m", &content, usage);
+        assert_eq!(
+            gemini["candidates"][0]["content"]["parts"][0]["functionCall"]["name"],
+            "Read"
+        );
+    }
+
+    #[test]
+    fn test_native_detection_and_format() {
+        assert_eq!(
+            MockWireFormat::detect("Claude", "/v1/chat/completions"),
+            MockWireFormat::OpenAiChat
+        );
+        assert_eq!(
+            MockWireFormat::detect("Codex", "/v1/responses"),
+            MockWireFormat::OpenAiResponses
+        );
+        assert_eq!(
+            MockWireFormat::detect("Gemini", "/v1beta/models/x:generateContent"),
+            MockWireFormat::Gemini
+        );
+        assert!(MockWireFormat::Anthropic.is_native(&json!({ "type": "message" })));
+        assert!(!MockWireFormat::Anthropic.is_native(&json!({ "content": { "text": "x" } })));
+    }
+
+    #[test]
+    fn test_fault_rates() {
+        let mut config = MockUpstreamConfig {
+            enabled: true,
+            faults: vec![MockFault {
+                kind: MockFaultKind::RateLimit,
+                rate: 0.0,
+            }],
+            ..Default::default()
+        };
+        assert!((0..100).all(|_| roll_fault(&config).is_none()));
+
+        config.faults.push(MockFault {
+            kind: MockFaultKind::Disconnect,
+            rate: 1.0,
+        });
+        assert!((0..100).all(|_| roll_fault(&config) == Some(MockFaultKind::Disconnect)));
+    }
+}
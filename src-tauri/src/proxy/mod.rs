@@ -4,6 +4,8 @@
 
 pub mod body_filter;
 pub mod circuit_breaker;
+pub mod client_auth;
+pub mod context_overflow;
 pub mod error;
 pub mod error_mapper;
 pub(crate) mod failover_switch;
@@ -12,18 +14,30 @@ pub mod handler_config;
 pub mod handler_context;
 mod handlers;
 mod health;
+pub mod hedging;
+pub mod hooks;
 pub mod http_client;
+pub mod load_balancer;
 pub mod log_codes;
+pub mod metrics;
+pub mod mock_upstream;
 pub mod model_mapper;
+pub mod otel;
 pub mod provider_router;
 pub mod providers;
+pub mod response_cache;
 pub mod response_handler;
 pub mod response_processor;
+pub mod routing_rules;
+pub mod secret_scanner;
 pub(crate) mod server;
 pub mod session;
+pub mod session_affinity;
 pub mod thinking_budget_rectifier;
 pub mod thinking_rectifier;
+pub mod tls;
 pub(crate) mod types;
+pub mod upstream_rules;
 pub mod usage;
 
 // 公开导出给外部使用（commands, services等模块需要）
@@ -0,0 +1,628 @@
+//! OpenTelemetry 链路追踪模块
+//!
+//! 以 OTLP/HTTP（JSON 编码）导出代理请求的链路：
+//! - 根 span：一次客户端请求（SERVER），流式响应附带 first_byte / last_byte 事件
+//! - 子 span：`forward_with_retry` 中的每次上游尝试（CLIENT），记录供应商、状态码、
+//!   尝试原因（首次/故障转移/对冲/整流重试）与错误分类
+//!
+//! 客户端携带的 `traceparent` 会被沿用（同一 trace id，父 span 为客户端 span，
+//! 采样决定跟随上游）；上游请求的 `traceparent` 指向对应的尝试 span。
+//! 根 span 在最后一个持有者（请求上下文、转发器、流式响应体）释放时结束并导出。
+
+use crate::provider::Provider;
+use crate::proxy::error::ErrorCategory;
+use crate::proxy::types::OtlpConfig;
+use axum::http::HeaderMap;
+use bytes::Bytes;
+use futures::stream::{Stream, StreamExt};
+use once_cell::sync::OnceCell;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+/// 单次上报的最大 span 数
+const MAX_BATCH_SPANS: usize = 512;
+
+/// 攒批等待时间
+const BATCH_DELAY: Duration = Duration::from_secs(2);
+
+/// 上报请求超时
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OTLP span kind
+const SPAN_KIND_SERVER: u8 = 2;
+const SPAN_KIND_CLIENT: u8 = 3;
+
+/// OTLP status code
+const STATUS_UNSET: u8 = 0;
+const STATUS_OK: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+
+static EXPORTER: OnceCell<RwLock<Option<Arc<OtlpExporter>>>> = OnceCell::new();
+
+/// 应用 OTLP 配置（启动时与日志配置更新时调用）
+///
+/// 已开始的请求继续使用旧的导出器，结束后旧导出器随之释放
+pub fn apply_config(config: &OtlpConfig) {
+    let exporter = config
+        .enabled
+        .then(|| Arc::new(OtlpExporter::new(config.clone())));
+    let lock = EXPORTER.get_or_init(|| RwLock::new(None));
+    if let Ok(mut guard) = lock.write() {
+        *guard = exporter;
+    }
+}
+
+fn current_exporter() -> Option<Arc<OtlpExporter>> {
+    EXPORTER
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .and_then(|guard| guard.clone())
+}
+
+/// 上游尝试原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptReason {
+    /// 故障转移队列中的首次尝试
+    Initial,
+    /// 前一个供应商失败后的故障转移
+    Failover,
+    /// 首个供应商响应慢时发起的对冲请求
+    Hedge,
+    /// thinking 签名整流后重试
+    RectifierSignature,
+    /// thinking budget 整流后重试
+    RectifierBudget,
+    /// 上下文超长后改用长上下文模型/供应商重试
+    ContextFallback,
+}
+
+impl AttemptReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Initial => "initial",
+            Self::Failover => "failover",
+            Self::Hedge => "hedge",
+            Self::RectifierSignature => "rectifier_signature",
+            Self::RectifierBudget => "rectifier_budget",
+            Self::ContextFallback => "context_fallback",
+        }
+    }
+
+    /// 触发重试的整流器（非整流重试返回 None）
+    pub fn rectifier(&self) -> Option<&'static str> {
+        match self {
+            Self::RectifierSignature => Some("thinking_signature"),
+            Self::RectifierBudget => Some("thinking_budget"),
+            Self::ContextFallback => Some("context_overflow"),
+            _ => None,
+        }
+    }
+}
+
+/// 解析 W3C traceparent：返回 (trace_id, parent_span_id, sampled)
+pub fn parse_traceparent(value: &str) -> Option<([u8; 16], [u8; 8], bool)> {
+    let mut parts = value.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let span_id = parts.next()?;
+    let flags = parts.next()?;
+    if version.len() != 2 || version == "ff" || trace_id.len() != 32 || span_id.len() != 16 {
+        return None;
+    }
+    // 版本 00 不允许额外字段
+    if version == "00" && parts.next().is_some() {
+        return None;
+    }
+
+    let trace_id: [u8; 16] = decode_hex(trace_id)?.try_into().ok()?;
+    let span_id: [u8; 8] = decode_hex(span_id)?.try_into().ok()?;
+    let flags = u8::from_str_radix(flags.get(..2)?, 16).ok()?;
+    if trace_id == [0; 16] || span_id == [0; 8] {
+        return None;
+    }
+    Some((trace_id, span_id, flags & 0x01 == 0x01))
+}
+
+/// 按比例采样（与 TraceIdRatioBased 一致：取 trace id 低 8 字节）
+fn ratio_sampled(trace_id: &[u8; 16], ratio: f64) -> bool {
+    if ratio >= 1.0 {
+        return true;
+    }
+    if ratio <= 0.0 {
+        return false;
+    }
+    let mut low = [0u8; 8];
+    low.copy_from_slice(&trace_id[8..]);
+    let bound = (ratio * (1u64 << 63) as f64) as u64;
+    (u64::from_be_bytes(low) >> 1) < bound
+}
+
+fn new_trace_id() -> [u8; 16] {
+    *uuid::Uuid::new_v4().as_bytes()
+}
+
+fn new_span_id() -> [u8; 8] {
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&uuid::Uuid::new_v4().as_bytes()[8..]);
+    id
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+        .to_string()
+}
+
+fn string_attr(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+fn int_attr(key: &str, value: i64) -> Value {
+    json!({ "key": key, "value": { "intValue": value.to_string() } })
+}
+
+fn bool_attr(key: &str, value: bool) -> Value {
+    json!({ "key": key, "value": { "boolValue": value } })
+}
+
+fn span_event(name: &str) -> Value {
+    json!({ "timeUnixNano": unix_nanos(SystemTime::now()), "name": name })
+}
+
+#[derive(Default)]
+struct RootState {
+    attributes: Vec<Value>,
+    events: Vec<Value>,
+    status_code: Option<u16>,
+    /// 已结束的子 span
+    children: Vec<Value>,
+}
+
+struct TraceInner {
+    exporter: Arc<OtlpExporter>,
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    name: String,
+    start: SystemTime,
+    state: Mutex<RootState>,
+}
+
+impl Drop for TraceInner {
+    fn drop(&mut self) {
+        let state = std::mem::take(
+            self.state
+                .get_mut()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+
+        let mut attributes = state.attributes;
+        let status = match state.status_code {
+            Some(code) => {
+                attributes.push(int_attr("http.response.status_code", code as i64));
+                if code >= 400 {
+                    json!({ "code": STATUS_ERROR })
+                } else {
+                    json!({ "code": STATUS_UNSET })
+                }
+            }
+            None => json!({ "code": STATUS_UNSET }),
+        };
+
+        let mut root = json!({
+            "traceId": encode_hex(&self.trace_id),
+            "spanId": encode_hex(&self.span_id),
+            "name": self.name,
+            "kind": SPAN_KIND_SERVER,
+            "startTimeUnixNano": unix_nanos(self.start),
+            "endTimeUnixNano": unix_nanos(SystemTime::now()),
+            "attributes": attributes,
+            "events": state.events,
+            "status": status,
+        });
+        if let Some(parent) = self.parent_span_id {
+            root["parentSpanId"] = Value::String(encode_hex(&parent));
+        }
+
+        let mut spans = state.children;
+        spans.push(root);
+        self.exporter.export(spans);
+    }
+}
+
+/// 一次客户端请求的链路（根 span），克隆共享同一根 span
+#[derive(Clone)]
+pub struct RequestTrace {
+    inner: Arc<TraceInner>,
+}
+
+impl RequestTrace {
+    /// 为客户端请求开始链路；导出未开启或未被采样时返回 None
+    pub fn start(headers: &HeaderMap, app_type: &str, request_model: &str) -> Option<Self> {
+        let exporter = current_exporter()?;
+
+        let incoming = headers
+            .get("traceparent")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_traceparent);
+        let (trace_id, parent_span_id, sampled) = match incoming {
+            Some((trace_id, parent, sampled)) => (trace_id, Some(parent), sampled),
+            None => {
+                let trace_id = new_trace_id();
+                let sampled = ratio_sampled(&trace_id, exporter.config.sample_ratio);
+                (trace_id, None, sampled)
+            }
+        };
+        if !sampled {
+            return None;
+        }
+
+        let state = RootState {
+            attributes: vec![
+                string_attr("cc_switch.app", app_type),
+                string_attr("gen_ai.request.model", request_model),
+            ],
+            ..Default::default()
+        };
+        Some(Self {
+            inner: Arc::new(TraceInner {
+                exporter,
+                trace_id,
+                span_id: new_span_id(),
+                parent_span_id,
+                name: format!("proxy {app_type}"),
+                start: SystemTime::now(),
+                state: Mutex::new(state),
+            }),
+        })
+    }
+
+    fn with_state(&self, f: impl FnOnce(&mut RootState)) {
+        if let Ok(mut state) = self.inner.state.lock() {
+            f(&mut state);
+        }
+    }
+
+    /// 设置根 span 的字符串属性
+    pub fn set_attribute(&self, key: &str, value: &str) {
+        self.with_state(|state| state.attributes.push(string_attr(key, value)));
+    }
+
+    /// 记录返回给客户端的状态码与实际服务的供应商
+    pub fn record_response(&self, status_code: u16, provider_id: &str) {
+        self.with_state(|state| {
+            state.status_code = Some(status_code);
+            state
+                .attributes
+                .push(string_attr("cc_switch.provider.id", provider_id));
+        });
+    }
+
+    /// 添加根 span 事件（如 first_byte / last_byte）
+    pub fn add_event(&self, name: &str) {
+        self.with_state(|state| state.events.push(span_event(name)));
+    }
+
+    /// 开始一次上游尝试（子 span）
+    pub fn start_attempt(&self, provider: &Provider, reason: AttemptReason) -> AttemptSpan {
+        let mut attributes = vec![
+            string_attr("cc_switch.provider.id", &provider.id),
+            string_attr("cc_switch.provider.name", &provider.name),
+            string_attr("cc_switch.attempt.reason", reason.as_str()),
+        ];
+        if let Some(rectifier) = reason.rectifier() {
+            attributes.push(string_attr("cc_switch.rectifier", rectifier));
+        }
+        AttemptSpan {
+            trace: self.clone(),
+            span_id: new_span_id(),
+            start: SystemTime::now(),
+            attributes,
+            finished: false,
+        }
+    }
+}
+
+/// 上游尝试 span；未显式结束即被释放（如对冲落败被取消）时记为 cancelled
+pub struct AttemptSpan {
+    trace: RequestTrace,
+    span_id: [u8; 8],
+    start: SystemTime,
+    attributes: Vec<Value>,
+    finished: bool,
+}
+
+impl AttemptSpan {
+    /// 传递给上游的 traceparent（父 span 为本次尝试）
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-01",
+            encode_hex(&self.trace.inner.trace_id),
+            encode_hex(&self.span_id)
+        )
+    }
+
+    /// 上游返回成功响应
+    pub fn end_ok(mut self, status_code: u16) {
+        self.attributes
+            .push(int_attr("http.response.status_code", status_code as i64));
+        self.finish(json!({ "code": STATUS_OK }));
+    }
+
+    /// 上游尝试失败
+    pub fn end_err(mut self, status_code: u16, category: ErrorCategory, message: &str) {
+        self.attributes
+            .push(int_attr("http.response.status_code", status_code as i64));
+        self.attributes.push(string_attr(
+            "error.type",
+            match category {
+                ErrorCategory::Retryable => "retryable",
+                ErrorCategory::NonRetryable => "non_retryable",
+                ErrorCategory::ClientAbort => "client_abort",
+            },
+        ));
+        self.finish(json!({ "code": STATUS_ERROR, "message": message }));
+    }
+
+    fn finish(&mut self, status: Value) {
+        self.finished = true;
+        let span = json!({
+            "traceId": encode_hex(&self.trace.inner.trace_id),
+            "spanId": encode_hex(&self.span_id),
+            "parentSpanId": encode_hex(&self.trace.inner.span_id),
+            "name": "upstream attempt",
+            "kind": SPAN_KIND_CLIENT,
+            "startTimeUnixNano": unix_nanos(self.start),
+            "endTimeUnixNano": unix_nanos(SystemTime::now()),
+            "attributes": std::mem::take(&mut self.attributes),
+            "status": status,
+        });
+        self.trace.with_state(|state| state.children.push(span));
+    }
+}
+
+impl Drop for AttemptSpan {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.attributes.push(bool_attr("cc_switch.cancelled", true));
+            self.finish(json!({ "code": STATUS_UNSET }));
+        }
+    }
+}
+
+/// 包装流式响应体：记录 first_byte / last_byte 事件，并持有链路直至流结束
+pub fn trace_stream<S, E>(
+    stream: S,
+    trace: Option<RequestTrace>,
+) -> impl Stream<Item = Result<Bytes, E>> + Send
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Send + 'static,
+{
+    async_stream::stream! {
+        let mut stream = std::pin::pin!(stream);
+        let mut first = true;
+        while let Some(item) = stream.next().await {
+            if first {
+                first = false;
+                if let Some(trace) = trace.as_ref() {
+                    trace.add_event("first_byte");
+                }
+            }
+            yield item;
+        }
+        if let Some(trace) = trace.as_ref() {
+            trace.add_event("last_byte");
+        }
+    }
+}
+
+/// OTLP/HTTP 导出器：后台任务攒批上报
+pub struct OtlpExporter {
+    config: OtlpConfig,
+    sender: OnceCell<mpsc::UnboundedSender<Vec<Value>>>,
+}
+
+impl OtlpExporter {
+    fn new(config: OtlpConfig) -> Self {
+        Self {
+            config,
+            sender: OnceCell::new(),
+        }
+    }
+
+    /// 提交一条链路的 span（首次提交时在当前运行时启动上报任务）
+    fn export(&self, spans: Vec<Value>) {
+        let sender = self.sender.get_or_try_init(|| {
+            let handle = tokio::runtime::Handle::try_current()?;
+            let (tx, rx) = mpsc::unbounded_channel();
+            handle.spawn(run_export_loop(self.config.clone(), rx));
+            Ok::<_, tokio::runtime::TryCurrentError>(tx)
+        });
+        match sender {
+            Ok(sender) => {
+                let _ = sender.send(spans);
+            }
+            Err(_) => log::debug!("[OTL-002] 当前不在异步运行时中，丢弃链路数据"),
+        }
+    }
+}
+
+/// 构建 OTLP ExportTraceServiceRequest（JSON）
+fn build_payload(service_name: &str, spans: Vec<Value>) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    string_attr("service.name", service_name),
+                    string_attr("service.version", env!("CARGO_PKG_VERSION")),
+                ]
+            },
+            "scopeSpans": [{
+                "scope": { "name": "cc-switch.proxy", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }]
+        }]
+    })
+}
+
+async fn run_export_loop(config: OtlpConfig, mut rx: mpsc::UnboundedReceiver<Vec<Value>>) {
+    // 采集器通常在本机：不走全局代理
+    let client = match reqwest::Client::builder()
+        .no_proxy()
+        .timeout(EXPORT_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            log::warn!("[OTL-001] 创建 OTLP 客户端失败: {e}");
+            return;
+        }
+    };
+
+    while let Some(mut batch) = rx.recv().await {
+        let deadline = tokio::time::sleep(BATCH_DELAY);
+        tokio::pin!(deadline);
+        while batch.len() < MAX_BATCH_SPANS {
+            tokio::select! {
+                more = rx.recv() => match more {
+                    Some(spans) => batch.extend(spans),
+                    None => break,
+                },
+                _ = &mut deadline => break,
+            }
+        }
+
+        let span_count = batch.len();
+        let payload = build_payload(&config.service_name, batch);
+        match client.post(&config.endpoint).json(&payload).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                log::trace!("[OTL] 已上报 {span_count} 个 span");
+            }
+            Ok(resp) => log::warn!(
+                "[OTL-001] OTLP 上报失败: {} 返回 {}",
+                config.endpoint,
+                resp.status()
+            ),
+            Err(e) => log::warn!("[OTL-001] OTLP 上报失败: {}: {e}", config.endpoint),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_trace(exporter: Arc<OtlpExporter>) -> RequestTrace {
+        RequestTrace {
+            inner: Arc::new(TraceInner {
+                exporter,
+                trace_id: [0xab; 16],
+                span_id: [0x01; 8],
+                parent_span_id: None,
+                name: "proxy claude".to_string(),
+                start: SystemTime::now(),
+                state: Mutex::new(RootState::default()),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_parse_traceparent() {
+        let (trace_id, span_id, sampled) =
+            parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(encode_hex(&trace_id), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(encode_hex(&span_id), "00f067aa0ba902b7");
+        assert!(sampled);
+
+        let (_, _, sampled) =
+            parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00").unwrap();
+        assert!(!sampled);
+
+        // 全零 id、非法版本、长度错误
+        assert!(
+            parse_traceparent("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none()
+        );
+        assert!(
+            parse_traceparent("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").is_none()
+        );
+        assert!(parse_traceparent("00-4bf92f35-00f067aa0ba902b7-01").is_none());
+        assert!(parse_traceparent("garbage").is_none());
+    }
+
+    #[test]
+    fn test_ratio_sampled() {
+        let low = [0u8; 16];
+        let mut high = [0u8; 16];
+        high[8..].copy_from_slice(&[0xff; 8]);
+
+        assert!(ratio_sampled(&high, 1.0));
+        assert!(!ratio_sampled(&low, 0.0));
+        assert!(ratio_sampled(&low, 0.5));
+        assert!(!ratio_sampled(&high, 0.5));
+    }
+
+    #[test]
+    fn test_attempt_spans_are_children_of_root() {
+        let exporter = Arc::new(OtlpExporter::new(OtlpConfig::default()));
+        let trace = test_trace(exporter);
+        let provider =
+            Provider::with_id("p1".to_string(), "Provider 1".to_string(), json!({}), None);
+
+        let failed = trace.start_attempt(&provider, AttemptReason::Initial);
+        assert_eq!(
+            failed.traceparent(),
+            format!("00-{}-{}-01", "ab".repeat(16), encode_hex(&failed.span_id))
+        );
+        failed.end_err(502, ErrorCategory::Retryable, "bad gateway");
+        trace
+            .start_attempt(&provider, AttemptReason::RectifierSignature)
+            .end_ok(200);
+        // 未结束即释放：记为取消
+        drop(trace.start_attempt(&provider, AttemptReason::Hedge));
+
+        let state = trace.inner.state.lock().unwrap();
+        assert_eq!(state.children.len(), 3);
+        for child in &state.children {
+            assert_eq!(child["parentSpanId"], "01".repeat(8));
+            assert_eq!(child["traceId"], "ab".repeat(16));
+        }
+        assert_eq!(state.children[0]["status"]["code"], STATUS_ERROR);
+        assert!(state.children[1]["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&string_attr("cc_switch.rectifier", "thinking_signature")));
+        assert!(state.children[2]["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&bool_attr("cc_switch.cancelled", true)));
+    }
+
+    #[test]
+    fn test_build_payload_shape() {
+        let payload = build_payload("cc-switch", vec![json!({"name": "s"})]);
+        let resource_spans = &payload["resourceSpans"][0];
+        assert_eq!(
+            resource_spans["resource"]["attributes"][0],
+            string_attr("service.name", "cc-switch")
+        );
+        assert_eq!(resource_spans["scopeSpans"][0]["spans"][0]["name"], "s");
+    }
+}
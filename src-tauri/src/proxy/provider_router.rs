@@ -7,9 +7,13 @@ use crate::database::Database;
 use crate::error::AppError;
 use crate::provider::Provider;
 use crate::proxy::circuit_breaker::{AllowResult, CircuitBreaker, CircuitBreakerConfig};
+use crate::proxy::load_balancer::LoadBalancer;
+use crate::proxy::session_affinity::SessionAffinity;
+use crate::proxy::types::LoadBalanceStrategy;
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 /// 供应商路由器
@@ -18,6 +22,10 @@ pub struct ProviderRouter {
     db: Arc<Database>,
     /// 熔断器管理器 - key 格式: "app_type:provider_id"
     circuit_breakers: Arc<RwLock<HashMap<String, Arc<CircuitBreaker>>>>,
+    /// 负载均衡器 - 按策略重排故障转移队列
+    load_balancer: LoadBalancer,
+    /// 会话粘性表 - key 格式: "app_type:session_id"
+    session_affinity: SessionAffinity,
 }
 
 impl ProviderRouter {
@@ -26,6 +34,8 @@ impl ProviderRouter {
         Self {
             db,
             circuit_breakers: Arc::new(RwLock::new(HashMap::new())),
+            load_balancer: LoadBalancer::new(),
+            session_affinity: SessionAffinity::new(),
         }
     }
 
@@ -33,8 +43,16 @@ impl ProviderRouter {
     ///
     /// 返回按优先级排序的可用供应商列表：
     /// - 故障转移关闭时：仅返回当前供应商
-    /// - 故障转移开启时：仅使用故障转移队列，按队列顺序依次尝试（P1 → P2 → ...）
-    pub async fn select_providers(&self, app_type: &str) -> Result<Vec<Provider>, AppError> {
+    /// - 故障转移开启时：仅使用故障转移队列，按负载均衡策略排序（默认按队列顺序 P1 → P2 → ...）
+    ///
+    /// `request_model` 为客户端请求的模型名，用于 least_cost 策略估算单价；
+    /// `session_id` 为客户端提供的会话 ID，用于会话粘性路由（绑定的供应商排到首位）
+    pub async fn select_providers(
+        &self,
+        app_type: &str,
+        request_model: Option<&str>,
+        session_id: Option<&str>,
+    ) -> Result<Vec<Provider>, AppError> {
         let mut result = Vec::new();
         let mut total_providers = 0usize;
         let mut circuit_open_count = 0usize;
@@ -76,6 +94,32 @@ impl ProviderRouter {
                     circuit_open_count += 1;
                 }
             }
+
+            let strategy = self
+                .db
+                .get_load_balance_strategy(app_type)
+                .await
+                .unwrap_or_else(|e| {
+                    log::warn!("[{app_type}] 读取负载均衡策略失败: {e}，使用优先级顺序");
+                    LoadBalanceStrategy::Priority
+                });
+            if strategy != LoadBalanceStrategy::Priority {
+                result = self
+                    .load_balancer
+                    .order(&self.db, app_type, strategy, result, request_model)
+                    .await;
+                log::debug!(
+                    "[{app_type}] 负载均衡策略 {}，首选供应商: {}",
+                    strategy.as_str(),
+                    result.first().map(|p| p.id.as_str()).unwrap_or("-")
+                );
+            }
+
+            if let Some(session_id) = session_id {
+                self.apply_session_affinity(app_type, session_id, &mut result);
+            }
+
+            self.demote_exhausted_providers(app_type, &mut result);
         } else {
             // 故障转移关闭：仅使用当前供应商，跳过熔断器检查
             let current_id = AppType::from_str(app_type)
@@ -108,6 +152,90 @@ impl ProviderRouter {
         Ok(result)
     }
 
+    /// 会话粘性：把会话绑定的供应商移到首位
+    ///
+    /// 绑定的供应商已熔断或不在队列中时解除绑定，按原顺序故障转移，
+    /// 请求成功后由 `pin_session()` 重新绑定到实际使用的供应商。
+    fn apply_session_affinity(&self, app_type: &str, session_id: &str, result: &mut Vec<Provider>) {
+        let config = self.db.get_session_affinity_config().unwrap_or_default();
+        if !config.enabled {
+            return;
+        }
+
+        let ttl = Duration::from_secs(config.ttl_seconds);
+        let Some(pinned_id) = self.session_affinity.get(app_type, session_id, ttl) else {
+            return;
+        };
+
+        match result.iter().position(|p| p.id == pinned_id) {
+            Some(0) => {}
+            Some(idx) => {
+                let pinned = result.remove(idx);
+                result.insert(0, pinned);
+                log::debug!("[{app_type}] 会话 {session_id} 粘性命中供应商 {pinned_id}");
+            }
+            None => {
+                self.session_affinity.unpin(app_type, session_id);
+                log::info!(
+                    "[{app_type}] 会话 {session_id} 绑定的供应商 {pinned_id} 不可用（熔断或已移出队列），解除粘性"
+                );
+            }
+        }
+    }
+
+    /// 余额耗尽降级：开启 `demoteWhenExhausted` 且最近一次用量查询余额耗尽的供应商
+    /// 保持相对顺序移到队尾，仍可作为最后的兜底；查询记录过期后恢复原顺序
+    fn demote_exhausted_providers(&self, app_type: &str, result: &mut Vec<Provider>) {
+        let wants_demotion = |p: &Provider| {
+            p.meta
+                .as_ref()
+                .and_then(|m| m.balance_alert.as_ref())
+                .is_some_and(|c| c.demote_when_exhausted)
+        };
+        if !result.iter().any(wants_demotion) {
+            return;
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let exhausted = match self.db.get_exhausted_provider_ids(app_type, now) {
+            Ok(ids) if !ids.is_empty() => ids,
+            Ok(_) => return,
+            Err(e) => {
+                log::warn!("[{app_type}] 读取余额耗尽供应商失败: {e}");
+                return;
+            }
+        };
+
+        let (demoted, kept): (Vec<_>, Vec<_>) = std::mem::take(result)
+            .into_iter()
+            .partition(|p| wants_demotion(p) && exhausted.contains(&p.id));
+        if !demoted.is_empty() {
+            log::info!(
+                "[{app_type}] 余额耗尽，降级到队尾: {}",
+                demoted
+                    .iter()
+                    .map(|p| p.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        *result = kept;
+        result.extend(demoted);
+    }
+
+    /// 请求成功后绑定会话到实际使用的供应商（刷新 TTL）
+    pub fn pin_session(&self, app_type: &str, session_id: &str, provider_id: &str) {
+        let config = self.db.get_session_affinity_config().unwrap_or_default();
+        if config.enabled {
+            self.session_affinity.pin(
+                app_type,
+                session_id,
+                provider_id,
+                Duration::from_secs(config.ttl_seconds),
+            );
+        }
+    }
+
     /// 请求执行前获取熔断器“放行许可”
     ///
     /// - Closed：直接放行
@@ -218,6 +346,26 @@ impl ProviderRouter {
         }
     }
 
+    /// 获取所有已创建熔断器的状态快照（key 格式: "app_type:provider_id"，按 key 排序）
+    pub async fn circuit_breaker_snapshot(
+        &self,
+    ) -> Vec<(String, crate::proxy::circuit_breaker::CircuitBreakerStats)> {
+        let breakers: Vec<(String, Arc<CircuitBreaker>)> = {
+            let guard = self.circuit_breakers.read().await;
+            guard
+                .iter()
+                .map(|(key, breaker)| (key.clone(), breaker.clone()))
+                .collect()
+        };
+
+        let mut stats = Vec::with_capacity(breakers.len());
+        for (key, breaker) in breakers {
+            stats.push((key, breaker.get_stats().await));
+        }
+        stats.sort_by(|a, b| a.0.cmp(&b.0));
+        stats
+    }
+
     /// 获取或创建熔断器
     async fn get_or_create_circuit_breaker(&self, key: &str) -> Arc<CircuitBreaker> {
         // 先尝试读锁获取
@@ -334,7 +482,7 @@ mod tests {
         db.add_to_failover_queue("claude", "b").unwrap();
 
         let router = ProviderRouter::new(db.clone());
-        let providers = router.select_providers("claude").await.unwrap();
+        let providers = router.select_providers("claude", None, None).await.unwrap();
 
         assert_eq!(providers.len(), 1);
         assert_eq!(providers[0].id, "a");
@@ -367,7 +515,7 @@ mod tests {
         db.update_proxy_config_for_app(config).await.unwrap();
 
         let router = ProviderRouter::new(db.clone());
-        let providers = router.select_providers("claude").await.unwrap();
+        let providers = router.select_providers("claude", None, None).await.unwrap();
 
         assert_eq!(providers.len(), 2);
         // 故障转移开启时：仅按队列顺序选择（忽略当前供应商）
@@ -375,6 +523,57 @@ mod tests {
         assert_eq!(providers[1].id, "a");
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn test_exhausted_provider_demoted_to_queue_end() {
+        let _home = TempHome::new();
+        let db = Arc::new(Database::memory().unwrap());
+
+        let mut provider_a =
+            Provider::with_id("a".to_string(), "Provider A".to_string(), json!({}), None);
+        provider_a.sort_index = Some(1);
+        provider_a.meta = Some(crate::provider::ProviderMeta {
+            balance_alert: Some(crate::provider::BalanceAlertConfig {
+                low_threshold: None,
+                demote_when_exhausted: true,
+            }),
+            ..Default::default()
+        });
+        let mut provider_b =
+            Provider::with_id("b".to_string(), "Provider B".to_string(), json!({}), None);
+        provider_b.sort_index = Some(2);
+
+        db.save_provider("claude", &provider_a).unwrap();
+        db.save_provider("claude", &provider_b).unwrap();
+        db.add_to_failover_queue("claude", "a").unwrap();
+        db.add_to_failover_queue("claude", "b").unwrap();
+
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let exhausted = crate::provider::UsageData {
+            plan_name: None,
+            extra: None,
+            is_valid: Some(true),
+            invalid_message: None,
+            total: Some(10.0),
+            used: Some(10.0),
+            remaining: Some(0.0),
+            unit: Some("USD".to_string()),
+        };
+        let now = chrono::Utc::now().timestamp();
+        db.record_balance_snapshot("claude", "a", &[exhausted], now)
+            .unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        let providers = router.select_providers("claude", None, None).await.unwrap();
+
+        assert_eq!(providers.len(), 2);
+        assert_eq!(providers[0].id, "b");
+        assert_eq!(providers[1].id, "a");
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_failover_enabled_uses_queue_only_even_if_current_not_in_queue() {
@@ -399,7 +598,7 @@ mod tests {
         db.update_proxy_config_for_app(config).await.unwrap();
 
         let router = ProviderRouter::new(db.clone());
-        let providers = router.select_providers("claude").await.unwrap();
+        let providers = router.select_providers("claude", None, None).await.unwrap();
 
         assert_eq!(providers.len(), 1);
         assert_eq!(providers[0].id, "b");
@@ -442,12 +641,89 @@ mod tests {
             .await
             .unwrap();
 
-        let providers = router.select_providers("claude").await.unwrap();
+        let providers = router.select_providers("claude", None, None).await.unwrap();
         assert_eq!(providers.len(), 2);
 
         assert!(router.allow_provider_request("b", "claude").await.allowed);
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn test_session_affinity_pins_and_falls_back_when_circuit_open() {
+        let _home = TempHome::new();
+        let db = Arc::new(Database::memory().unwrap());
+
+        db.update_circuit_breaker_config(&CircuitBreakerConfig {
+            failure_threshold: 1,
+            timeout_seconds: 3600,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let provider_a =
+            Provider::with_id("a".to_string(), "Provider A".to_string(), json!({}), None);
+        let provider_b =
+            Provider::with_id("b".to_string(), "Provider B".to_string(), json!({}), None);
+        db.save_provider("claude", &provider_a).unwrap();
+        db.save_provider("claude", &provider_b).unwrap();
+        db.add_to_failover_queue("claude", "a").unwrap();
+        db.add_to_failover_queue("claude", "b").unwrap();
+
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+
+        // 会话绑定到 b：b 排到首位，其他会话不受影响
+        router.pin_session("claude", "session-1", "b");
+        let providers = router
+            .select_providers("claude", None, Some("session-1"))
+            .await
+            .unwrap();
+        assert_eq!(providers[0].id, "b");
+        assert_eq!(providers[1].id, "a");
+
+        let providers = router
+            .select_providers("claude", None, Some("session-2"))
+            .await
+            .unwrap();
+        assert_eq!(providers[0].id, "a");
+
+        // b 熔断：解除粘性，回退到队列顺序
+        router
+            .record_result("b", "claude", false, false, Some("fail".to_string()))
+            .await
+            .unwrap();
+        let providers = router
+            .select_providers("claude", None, Some("session-1"))
+            .await
+            .unwrap();
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].id, "a");
+        assert_eq!(
+            router
+                .session_affinity
+                .get("claude", "session-1", Duration::from_secs(300)),
+            None
+        );
+
+        // 关闭粘性后不再绑定
+        db.set_session_affinity_config(&crate::proxy::types::SessionAffinityConfig {
+            enabled: false,
+            ..Default::default()
+        })
+        .unwrap();
+        router.pin_session("claude", "session-3", "a");
+        assert_eq!(
+            router
+                .session_affinity
+                .get("claude", "session-3", Duration::from_secs(300)),
+            None
+        );
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_release_permit_neutral_frees_half_open_slot() {
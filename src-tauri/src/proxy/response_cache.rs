@@ -0,0 +1,392 @@
+//! 响应缓存模块
+//!
+//! Claude Code / Codex 会反复发送完全相同的小请求（标题生成、count_tokens、话题检测等）。
+//! 开启后按 (app, 映射后模型, 端点, 去除易变字段后的请求体) 的规范化哈希缓存上游响应：
+//! - 非流式响应原样返回，流式响应整体回放 SSE
+//! - 工具调用轮次（请求以工具结果结尾，或响应包含工具调用）不缓存
+//! - 按 TTL 过期，按条数与单条大小限制容量
+//!
+//! 缓存命中在 proxy_request_logs 中记为零成本条目（from_cache = 1）。
+
+use crate::proxy::types::ResponseCacheConfig;
+use axum::response::Response;
+use bytes::Bytes;
+use futures::stream::{Stream, StreamExt};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 计算缓存键前移除的易变字段（会话 ID、用户标识等，不影响响应内容）
+const VOLATILE_FIELDS: &[&str] = &["metadata", "user", "prompt_cache_key", "safety_identifier"];
+
+/// 响应中出现这些标记时视为工具调用轮次，不缓存
+const TOOL_CALL_MARKERS: &[&str] = &[
+    "\"tool_use\"",
+    "\"tool_calls\"",
+    "\"function_call\"",
+    "\"functionCall\"",
+];
+
+/// 缓存的上游响应
+pub struct CachedResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Bytes,
+    /// 原始响应的供应商（命中日志归属）
+    pub provider_id: String,
+    pub model: String,
+    pub is_streaming: bool,
+}
+
+impl CachedResponse {
+    /// 构建返回给客户端的响应
+    pub fn to_response(&self) -> Result<Response, axum::http::Error> {
+        let mut builder = Response::builder()
+            .status(self.status)
+            .header("x-cc-switch-cache", "hit");
+        if let Some(content_type) = &self.content_type {
+            builder = builder.header("content-type", content_type);
+        }
+        builder.body(axum::body::Body::from(self.body.clone()))
+    }
+}
+
+/// 请求级缓存凭据：查询缓存时生成，响应成功后用于写入
+///
+/// 键中的映射模型基于首选供应商计算，因此只有该供应商返回的响应才会写入
+#[derive(Clone)]
+pub struct ResponseCacheTicket {
+    pub key: String,
+    pub provider_id: String,
+    pub config: ResponseCacheConfig,
+}
+
+struct Entry {
+    response: Arc<CachedResponse>,
+    inserted_at: Instant,
+}
+
+/// 进程内响应缓存
+#[derive(Default)]
+pub struct ResponseCache {
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 查询缓存（过期条目会被移除）
+    pub fn get(&self, key: &str, ttl: Duration) -> Option<Arc<CachedResponse>> {
+        let mut entries = self.entries.lock().ok()?;
+        match entries.get(key) {
+            Some(entry) if entry.inserted_at.elapsed() <= ttl => Some(entry.response.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// 写入缓存
+    ///
+    /// 超出单条大小上限或包含工具调用的响应不会写入；容量已满时先清理过期条目，
+    /// 仍不足则淘汰最早写入的条目
+    pub fn insert(&self, key: String, response: CachedResponse, config: &ResponseCacheConfig) {
+        if config.max_entries == 0
+            || response.body.len() > config.max_entry_bytes
+            || contains_tool_call(&response.body)
+        {
+            return;
+        }
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        let ttl = Duration::from_secs(config.ttl_seconds);
+        entries.retain(|_, entry| entry.inserted_at.elapsed() <= ttl);
+        while entries.len() >= config.max_entries && !entries.contains_key(&key) {
+            let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.inserted_at)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+
+        entries.insert(
+            key,
+            Entry {
+                response: Arc::new(response),
+                inserted_at: Instant::now(),
+            },
+        );
+    }
+}
+
+/// 计算缓存键；工具调用轮次返回 None（不缓存）
+pub fn cache_key(
+    app_type: &str,
+    mapped_model: &str,
+    endpoint: &str,
+    body: &Value,
+) -> Option<String> {
+    if is_tool_use_turn(body) {
+        return None;
+    }
+
+    let mut normalized = body.clone();
+    if let Some(obj) = normalized.as_object_mut() {
+        for field in VOLATILE_FIELDS {
+            obj.remove(*field);
+        }
+    }
+    let canonical = canonicalize(&normalized).to_string();
+
+    let mut hasher = Sha256::new();
+    hasher.update(app_type.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(mapped_model.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(endpoint.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(canonical.as_bytes());
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// 请求是否为工具调用轮次（最后一条消息是工具结果）
+fn is_tool_use_turn(body: &Value) -> bool {
+    // Claude / OpenAI Chat Completions
+    if let Some(last) = body
+        .get("messages")
+        .and_then(|v| v.as_array())
+        .and_then(|m| m.last())
+    {
+        if last.get("role").and_then(|r| r.as_str()) == Some("tool") {
+            return true;
+        }
+        if let Some(blocks) = last.get("content").and_then(|c| c.as_array()) {
+            if blocks
+                .iter()
+                .any(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_result"))
+            {
+                return true;
+            }
+        }
+    }
+
+    // OpenAI Responses API
+    if let Some(last) = body
+        .get("input")
+        .and_then(|v| v.as_array())
+        .and_then(|items| items.last())
+    {
+        if last
+            .get("type")
+            .and_then(|t| t.as_str())
+            .is_some_and(|t| t.ends_with("_call_output"))
+        {
+            return true;
+        }
+    }
+
+    // Gemini
+    if let Some(parts) = body
+        .get("contents")
+        .and_then(|v| v.as_array())
+        .and_then(|c| c.last())
+        .and_then(|c| c.get("parts"))
+        .and_then(|p| p.as_array())
+    {
+        if parts.iter().any(|p| p.get("functionResponse").is_some()) {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn contains_tool_call(body: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(body);
+    TOOL_CALL_MARKERS.iter().any(|marker| text.contains(marker))
+}
+
+/// 递归按键名排序，保证相同内容得到相同的序列化结果
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(obj) => {
+            let mut keys: Vec<&String> = obj.keys().collect();
+            keys.sort();
+            let mut sorted = Map::new();
+            for key in keys {
+                sorted.insert(key.clone(), canonicalize(&obj[key]));
+            }
+            Value::Object(sorted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 包装流式响应：透传的同时收集完整 SSE，正常结束后写入缓存
+///
+/// 流出错或超出单条大小上限时放弃写入
+pub fn tee_stream_into_cache(
+    stream: impl Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+    cache: Arc<ResponseCache>,
+    ticket: ResponseCacheTicket,
+    template: CachedResponse,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+    async_stream::stream! {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut cacheable = true;
+
+        tokio::pin!(stream);
+        while let Some(chunk) = stream.next().await {
+            match &chunk {
+                Ok(bytes) if cacheable => {
+                    if buffer.len() + bytes.len() > ticket.config.max_entry_bytes {
+                        cacheable = false;
+                        buffer = Vec::new();
+                    } else {
+                        buffer.extend_from_slice(bytes);
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => cacheable = false,
+            }
+            yield chunk;
+        }
+
+        if cacheable && !buffer.is_empty() {
+            cache.insert(
+                ticket.key,
+                CachedResponse {
+                    body: Bytes::from(buffer),
+                    ..template
+                },
+                &ticket.config,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> ResponseCacheConfig {
+        ResponseCacheConfig {
+            enabled: true,
+            ttl_seconds: 60,
+            max_entries: 2,
+            max_entry_bytes: 64,
+        }
+    }
+
+    fn cached(body: &str) -> CachedResponse {
+        CachedResponse {
+            status: 200,
+            content_type: Some("application/json".to_string()),
+            body: Bytes::from(body.to_string()),
+            provider_id: "p1".to_string(),
+            model: "m".to_string(),
+            is_streaming: false,
+        }
+    }
+
+    #[test]
+    fn test_cache_key_ignores_volatile_fields_and_key_order() {
+        let a = json!({"model": "m", "max_tokens": 10, "metadata": {"user_id": "session_a"}});
+        let b = json!({"metadata": {"user_id": "session_b"}, "max_tokens": 10, "model": "m"});
+        let c = json!({"model": "m", "max_tokens": 20});
+
+        let key_a = cache_key("claude", "m", "/v1/messages", &a).unwrap();
+        assert_eq!(key_a, cache_key("claude", "m", "/v1/messages", &b).unwrap());
+        assert_ne!(key_a, cache_key("claude", "m", "/v1/messages", &c).unwrap());
+        assert_ne!(
+            key_a,
+            cache_key("claude", "other", "/v1/messages", &a).unwrap()
+        );
+        assert_ne!(key_a, cache_key("codex", "m", "/v1/messages", &a).unwrap());
+    }
+
+    #[test]
+    fn test_tool_use_turns_are_not_cacheable() {
+        let claude = json!({"messages": [
+            {"role": "user", "content": "hi"},
+            {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "t1"}]}
+        ]});
+        let openai = json!({"messages": [{"role": "tool", "content": "42"}]});
+        let responses = json!({"input": [{"type": "function_call_output", "output": "42"}]});
+        let gemini = json!({"contents": [{"parts": [{"functionResponse": {"name": "f"}}]}]});
+        for body in [claude, openai, responses, gemini] {
+            assert!(cache_key("claude", "m", "/", &body).is_none());
+        }
+
+        let plain = json!({"messages": [{"role": "user", "content": "hi"}]});
+        assert!(cache_key("claude", "m", "/", &plain).is_some());
+    }
+
+    #[test]
+    fn test_insert_respects_caps_and_ttl() {
+        let cache = ResponseCache::new();
+        let config = config();
+        let ttl = Duration::from_secs(60);
+
+        cache.insert("a".to_string(), cached("{}"), &config);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert("b".to_string(), cached("{}"), &config);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert("c".to_string(), cached("{}"), &config);
+        // 超出条数上限：淘汰最早写入的 a
+        assert!(cache.get("a", ttl).is_none());
+        assert!(cache.get("b", ttl).is_some());
+        assert!(cache.get("c", ttl).is_some());
+
+        // 超出单条大小上限或包含工具调用：不写入
+        cache.insert("big".to_string(), cached(&"x".repeat(100)), &config);
+        assert!(cache.get("big", ttl).is_none());
+        cache.insert(
+            "tool".to_string(),
+            cached(r#"{"stop_reason":"tool_use"}"#),
+            &config,
+        );
+        assert!(cache.get("tool", ttl).is_none());
+
+        // 过期
+        assert!(cache.get("c", Duration::ZERO).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tee_stream_into_cache() {
+        let cache = Arc::new(ResponseCache::new());
+        let ticket = ResponseCacheTicket {
+            key: "k".to_string(),
+            provider_id: "p1".to_string(),
+            config: config(),
+        };
+        let chunks: Vec<Result<Bytes, std::io::Error>> = vec![
+            Ok(Bytes::from_static(b"data: {\"a\":1}\n\n")),
+            Ok(Bytes::from_static(b"data: [DONE]\n\n")),
+        ];
+        let stream = tee_stream_into_cache(
+            futures::stream::iter(chunks),
+            cache.clone(),
+            ticket,
+            cached(""),
+        );
+        let collected: Vec<_> = stream.collect().await;
+        assert_eq!(collected.len(), 2);
+
+        let entry = cache.get("k", Duration::from_secs(60)).unwrap();
+        assert_eq!(&entry.body[..], b"data: {\"a\":1}\n\ndata: [DONE]\n\n");
+    }
+}
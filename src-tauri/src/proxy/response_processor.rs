@@ -5,6 +5,10 @@
 use super::{
     handler_config::UsageParserConfig,
     handler_context::{RequestContext, StreamingTimeoutConfig},
+    hooks::{apply_response_hooks, create_sse_hook_stream, HookStage},
+    log_codes::hook as log_hook,
+    otel::trace_stream,
+    response_cache::{tee_stream_into_cache, CachedResponse},
     server::ProxyState,
     usage::parser::TokenUsage,
     ProxyError,
@@ -52,7 +56,9 @@ pub async fn handle_streaming(
         status.as_u16(),
         format_headers(response.headers())
     );
+    ctx.record_trace_response(status.as_u16());
     let mut builder = axum::response::Response::builder().status(status);
+    let content_type = content_type_of(response.headers());
 
     // 复制响应头
     for (key, value) in response.headers() {
@@ -80,8 +86,39 @@ pub async fn handle_streaming(
     // 创建带日志和超时的透传流
     let logged_stream =
         create_logged_passthrough_stream(stream, ctx.tag, Some(usage_collector), timeout_config);
+    // 记录首/末字节事件，并让链路持续到流结束
+    let logged_stream = trace_stream(logged_stream, ctx.trace.clone());
+    // onSseEvent 钩子在用量收集之后执行，只影响客户端（及缓存）看到的事件
+    let logged_stream: std::pin::Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>> =
+        match ctx
+            .response_hook_runner()
+            .await
+            .filter(|runner| runner.handles(HookStage::SseEvent))
+        {
+            Some(runner) => Box::pin(create_sse_hook_stream(logged_stream, runner)),
+            None => Box::pin(logged_stream),
+        };
 
-    let body = axum::body::Body::from_stream(logged_stream);
+    // 可缓存的成功响应：透传的同时写入响应缓存
+    let body = match ctx.response_cache_ticket() {
+        Some(ticket) if status.is_success() => {
+            let template = CachedResponse {
+                status: status.as_u16(),
+                content_type,
+                body: Bytes::new(),
+                provider_id: ctx.provider.id.clone(),
+                model: ctx.request_model.clone(),
+                is_streaming: true,
+            };
+            axum::body::Body::from_stream(tee_stream_into_cache(
+                logged_stream,
+                state.response_cache.clone(),
+                ticket.clone(),
+                template,
+            ))
+        }
+        _ => axum::body::Body::from_stream(logged_stream),
+    };
     match builder.body(body) {
         Ok(resp) => resp,
         Err(e) => {
@@ -101,6 +138,7 @@ pub async fn handle_non_streaming(
 ) -> Result<Response, ProxyError> {
     let response_headers = response.headers().clone();
     let status = response.status();
+    ctx.record_trace_response(status.as_u16());
 
     // 读取响应体
     let body_bytes = response.bytes().await.map_err(|e| {
@@ -187,6 +225,50 @@ pub async fn handle_non_streaming(
         );
     }
 
+    // 执行 onResponse 钩子（用量已基于上游原始响应记录）
+    let (status, response_headers, body_bytes) = match ctx.response_hook_runner().await {
+        Some(runner) => {
+            let status_code = status.as_u16();
+            let headers = response_headers.clone();
+            let bytes = body_bytes.clone();
+            let hooked = tokio::task::spawn_blocking(move || {
+                apply_response_hooks(&runner, status_code, &headers, &bytes)
+            })
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("[{}] 响应钩子执行中断: {e}", log_hook::RUN_FAILED);
+                None
+            });
+            match hooked {
+                Some(hooked) => (
+                    reqwest::StatusCode::from_u16(hooked.status).unwrap_or(status),
+                    hooked.headers,
+                    hooked.body,
+                ),
+                None => (status, response_headers, body_bytes),
+            }
+        }
+        None => (status, response_headers, body_bytes),
+    };
+
+    // 可缓存的成功响应写入响应缓存
+    if let Some(ticket) = ctx.response_cache_ticket() {
+        if status.is_success() {
+            state.response_cache.insert(
+                ticket.key.clone(),
+                CachedResponse {
+                    status: status.as_u16(),
+                    content_type: content_type_of(&response_headers),
+                    body: body_bytes.clone(),
+                    provider_id: ctx.provider.id.clone(),
+                    model: ctx.request_model.clone(),
+                    is_streaming: false,
+                },
+                &ticket.config,
+            );
+        }
+    }
+
     // 构建响应
     let mut builder = axum::response::Response::builder().status(status);
     for (key, value) in response_headers.iter() {
@@ -200,6 +282,52 @@ pub async fn handle_non_streaming(
     })
 }
 
+/// 返回响应缓存命中的响应，并记录零成本请求日志
+pub fn serve_cached_response(
+    cached: &CachedResponse,
+    ctx: &RequestContext,
+    state: &ProxyState,
+) -> Result<Response, ProxyError> {
+    use super::usage::logger::UsageLogger;
+
+    log::info!(
+        "[{}] 响应缓存命中 (model={}, provider={})",
+        ctx.tag,
+        cached.model,
+        cached.provider_id
+    );
+
+    let logger = UsageLogger::new(&state.db).with_client_token(ctx.client_token_id.clone());
+    if let Err(e) = logger.log_cache_hit(
+        uuid::Uuid::new_v4().to_string(),
+        cached.provider_id.clone(),
+        ctx.app_type_str.to_string(),
+        cached.model.clone(),
+        ctx.latency_ms(),
+        cached.is_streaming,
+        Some(ctx.session_id.clone()),
+        ctx.routing_rule.clone(),
+    ) {
+        log::warn!("[USG-001] 记录缓存命中日志失败: {e}");
+    }
+    if let Some(trace) = ctx.trace.as_ref() {
+        trace.set_attribute("cc_switch.response_cache", "hit");
+    }
+    ctx.record_trace_response(cached.status);
+
+    cached.to_response().map_err(|e| {
+        log::error!("[{}] 构建缓存响应失败: {e}", ctx.tag);
+        ProxyError::Internal(format!("Failed to build cached response: {e}"))
+    })
+}
+
+fn content_type_of(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
 /// 通用响应处理入口
 ///
 /// 根据响应类型自动选择流式或非流式处理
@@ -935,6 +1063,9 @@ fn create_usage_collector(
     let start_time = ctx.start_time;
     let model_extractor = parser_config.model_extractor;
     let session_id = ctx.session_id.clone();
+    let routing_rule = ctx.routing_rule.clone();
+    let client_token_id = ctx.client_token_id.clone();
+    let rectifier = ctx.rectifier;
 
     SseUsageCollector::new(
         start_time,
@@ -975,6 +1106,8 @@ fn create_usage_collector(
             let state = state.clone();
             let provider_id = provider_id.clone();
             let session_id = session_id.clone();
+            let routing_rule = routing_rule.clone();
+            let client_token_id = client_token_id.clone();
             let request_model = request_model.clone();
             let request_body = request_body.clone();
 
@@ -991,6 +1124,9 @@ fn create_usage_collector(
                     true, // is_streaming
                     status_code,
                     Some(session_id),
+                    routing_rule,
+                    client_token_id,
+                    rectifier,
                     request_body,
                     final_body,
                 )
@@ -1023,6 +1159,9 @@ fn spawn_log_usage(
     let request_model = request_model.to_string();
     let latency_ms = ctx.latency_ms();
     let session_id = ctx.session_id.clone();
+    let routing_rule = ctx.routing_rule.clone();
+    let client_token_id = ctx.client_token_id.clone();
+    let rectifier = ctx.rectifier;
 
     tokio::spawn(async move {
         log_usage_internal(
@@ -1037,6 +1176,9 @@ fn spawn_log_usage(
             is_streaming,
             status_code,
             Some(session_id),
+            routing_rule,
+            client_token_id,
+            rectifier,
             request_body,
             response_body,
         )
@@ -1058,12 +1200,17 @@ async fn log_usage_internal(
     is_streaming: bool,
     status_code: u16,
     session_id: Option<String>,
+    routing_rule: Option<String>,
+    client_token_id: Option<String>,
+    rectifier: Option<&'static str>,
     request_body: Option<String>,
     response_body: Option<String>,
 ) {
     use super::usage::logger::UsageLogger;
 
-    let logger = UsageLogger::new(&state.db);
+    let logger = UsageLogger::new(&state.db)
+        .with_client_token(client_token_id)
+        .with_rectifier(rectifier);
     let (multiplier, pricing_model_source) =
         logger.resolve_pricing_config(provider_id, app_type).await;
     let pricing_model = if pricing_model_source == "request" {
@@ -1100,6 +1247,7 @@ async fn log_usage_internal(
         is_streaming,
         request_body,
         response_body,
+        routing_rule,
     ) {
         log::warn!("[USG-001] 记录使用量失败: {e}");
     }
@@ -1246,6 +1394,7 @@ mod tests {
     use crate::provider::ProviderMeta;
     use crate::proxy::failover_switch::FailoverSwitchManager;
     use crate::proxy::provider_router::ProviderRouter;
+    use crate::proxy::response_cache::ResponseCache;
     use crate::proxy::types::{ProxyConfig, ProxyStatus};
     use rust_decimal::Decimal;
     use std::collections::HashMap;
@@ -1263,6 +1412,7 @@ mod tests {
             provider_router: Arc::new(ProviderRouter::new(db.clone())),
             app_handle: None,
             failover_manager: Arc::new(FailoverSwitchManager::new(db)),
+            response_cache: Arc::new(ResponseCache::new()),
         }
     }
 
@@ -1336,6 +1486,9 @@ mod tests {
             false,
             200,
             None,
+            None, // routing_rule
+            None, // client_token_id
+            None, // rectifier
             None, // request_body
             None, // response_body
         )
@@ -1397,6 +1550,9 @@ mod tests {
             false,
             200,
             None,
+            None, // routing_rule
+            None, // client_token_id
+            None, // rectifier
             None, // request_body
             None, // response_body
         )
@@ -0,0 +1,254 @@
+//! 模型路由规则模块
+//!
+//! 每个 app 维护一张规则表，按 sort_index 顺序匹配请求：
+//! - 模型名 glob（`*` / `?`，不区分大小写）
+//! - 是否启用 thinking
+//! - 请求体大小区间（字节）
+//! - 请求头（只给名称时仅要求存在，给值时按 glob 匹配）
+//!
+//! 所有已设置的条件同时满足才算命中。命中的规则把目标供应商放到故障转移链首位，
+//! 可选地把请求模型改写为 target_model，其余供应商照常作为回退。
+
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+/// 路由规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingRule {
+    pub id: String,
+    pub app_type: String,
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub sort_index: i64,
+    /// 请求模型 glob，如 `claude-*-haiku-*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_pattern: Option<String>,
+    /// thinking 条件：Some(true) 仅匹配开启 thinking 的请求，Some(false) 仅匹配未开启的
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_request_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_name: Option<String>,
+    /// 请求头值 glob；为空时只要求请求头存在
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_value: Option<String>,
+    /// 命中后使用的供应商
+    pub provider_id: String,
+    /// 命中后改写的模型名（为空时保持原模型，仍走供应商自身的模型映射）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_model: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 参与规则匹配的请求特征
+pub struct RoutingRequest<'a> {
+    pub model: &'a str,
+    pub has_thinking: bool,
+    pub request_bytes: u64,
+    pub headers: &'a HeaderMap,
+}
+
+impl RoutingRule {
+    /// 判断规则是否命中请求
+    pub fn matches(&self, req: &RoutingRequest<'_>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        if let Some(pattern) = non_empty(&self.model_pattern) {
+            if !glob_match(pattern, req.model) {
+                return false;
+            }
+        }
+
+        if let Some(thinking) = self.thinking {
+            if thinking != req.has_thinking {
+                return false;
+            }
+        }
+
+        if self
+            .min_request_bytes
+            .is_some_and(|min| req.request_bytes < min)
+        {
+            return false;
+        }
+        if self
+            .max_request_bytes
+            .is_some_and(|max| req.request_bytes > max)
+        {
+            return false;
+        }
+
+        if let Some(name) = non_empty(&self.header_name) {
+            let Some(value) = req.headers.get(name).and_then(|v| v.to_str().ok()) else {
+                return false;
+            };
+            if let Some(pattern) = non_empty(&self.header_value) {
+                if !glob_match(pattern, value) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// 按顺序返回第一条命中的规则
+pub fn find_matching_rule<'a>(
+    rules: &'a [RoutingRule],
+    req: &RoutingRequest<'_>,
+) -> Option<&'a RoutingRule> {
+    rules.iter().find(|rule| rule.matches(req))
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// 简单 glob 匹配（`*` 匹配任意长度，`?` 匹配单个字符，不区分大小写）
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0usize, 0usize);
+    // 最近一次 `*` 的位置，以及它当前吞到的文本位置（用于回溯）
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn rule(id: &str) -> RoutingRule {
+        RoutingRule {
+            id: id.to_string(),
+            app_type: "claude".to_string(),
+            name: id.to_string(),
+            enabled: true,
+            sort_index: 0,
+            model_pattern: None,
+            thinking: None,
+            min_request_bytes: None,
+            max_request_bytes: None,
+            header_name: None,
+            header_value: None,
+            provider_id: "p1".to_string(),
+            target_model: None,
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("claude-*-haiku-*", "claude-3-5-haiku-20241022"));
+        assert!(glob_match("*haiku*", "Claude-Haiku-4-5"));
+        assert!(glob_match("gpt-?o", "gpt-4o"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("claude-opus-*", "claude-sonnet-4"));
+        assert!(!glob_match("gpt-?o", "gpt-4.1o"));
+    }
+
+    #[test]
+    fn test_rule_conditions_are_combined() {
+        let headers = HeaderMap::new();
+        let req = RoutingRequest {
+            model: "claude-haiku-4-5",
+            has_thinking: false,
+            request_bytes: 2_000,
+            headers: &headers,
+        };
+
+        let mut r = rule("haiku");
+        r.model_pattern = Some("*haiku*".to_string());
+        assert!(r.matches(&req));
+
+        r.thinking = Some(true);
+        assert!(!r.matches(&req));
+
+        r.thinking = Some(false);
+        r.max_request_bytes = Some(1_000);
+        assert!(!r.matches(&req));
+
+        r.max_request_bytes = None;
+        r.min_request_bytes = Some(1_000);
+        assert!(r.matches(&req));
+
+        r.enabled = false;
+        assert!(!r.matches(&req));
+    }
+
+    #[test]
+    fn test_header_condition() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-route", HeaderValue::from_static("background-task"));
+        let req = RoutingRequest {
+            model: "any",
+            has_thinking: false,
+            request_bytes: 0,
+            headers: &headers,
+        };
+
+        let mut r = rule("header");
+        r.header_name = Some("x-route".to_string());
+        assert!(r.matches(&req));
+
+        r.header_value = Some("background*".to_string());
+        assert!(r.matches(&req));
+
+        r.header_value = Some("interactive".to_string());
+        assert!(!r.matches(&req));
+
+        r.header_name = Some("x-missing".to_string());
+        r.header_value = None;
+        assert!(!r.matches(&req));
+    }
+
+    #[test]
+    fn test_find_matching_rule_uses_order() {
+        let headers = HeaderMap::new();
+        let req = RoutingRequest {
+            model: "claude-opus-4",
+            has_thinking: true,
+            request_bytes: 10,
+            headers: &headers,
+        };
+
+        let mut first = rule("sonnet");
+        first.model_pattern = Some("*sonnet*".to_string());
+        let mut second = rule("opus");
+        second.model_pattern = Some("*opus*".to_string());
+        let catch_all = rule("all");
+        let rules = vec![first, second, catch_all];
+
+        assert_eq!(find_matching_rule(&rules, &req).unwrap().id, "opus");
+    }
+}
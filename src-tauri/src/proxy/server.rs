@@ -3,8 +3,8 @@
 //! 基于Axum的HTTP服务器，处理代理请求
 
 use super::{
-    failover_switch::FailoverSwitchManager, handlers, log_codes::srv as log_srv,
-    provider_router::ProviderRouter, types::*, ProxyError,
+    client_auth, failover_switch::FailoverSwitchManager, handlers, log_codes::srv as log_srv,
+    provider_router::ProviderRouter, response_cache::ResponseCache, types::*, ProxyError,
 };
 use crate::database::Database;
 use axum::{
@@ -16,7 +16,7 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::{oneshot, RwLock};
 use tokio::task::JoinHandle;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 
 /// 代理服务器状态（共享）
 #[derive(Clone)]
@@ -33,6 +33,8 @@ pub struct ProxyState {
     pub app_handle: Option<tauri::AppHandle>,
     /// 故障转移切换管理器
     pub failover_manager: Arc<FailoverSwitchManager>,
+    /// 响应缓存（进程内，代理重启后清空）
+    pub response_cache: Arc<ResponseCache>,
 }
 
 /// 代理HTTP服务器
@@ -64,6 +66,7 @@ impl ProxyServer {
             provider_router,
             app_handle,
             failover_manager,
+            response_cache: Arc::new(ResponseCache::new()),
         };
 
         Self {
@@ -85,6 +88,22 @@ impl ProxyServer {
                 .parse()
                 .map_err(|e| ProxyError::BindFailed(format!("无效的地址: {e}")))?;
 
+        // 开启 TLS 时先加载证书，证书无效则不启动
+        let tls_config = self.state.db.get_proxy_tls_config().unwrap_or_default();
+        let tls_acceptor = if tls_config.enabled {
+            Some(super::tls::build_acceptor(
+                &tls_config,
+                &self.config.listen_address,
+            )?)
+        } else {
+            None
+        };
+        let scheme = if tls_acceptor.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+
         // 创建关闭通道
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
 
@@ -96,7 +115,7 @@ impl ProxyServer {
             .await
             .map_err(|e| ProxyError::BindFailed(e.to_string()))?;
 
-        log::info!("[{}] 代理服务器启动于 {addr}", log_srv::STARTED);
+        log::info!("[{}] 代理服务器启动于 {scheme}://{addr}", log_srv::STARTED);
 
         // 更新全局代理端口，用于系统代理检测
         crate::proxy::http_client::set_proxy_port(self.config.listen_port);
@@ -109,6 +128,7 @@ impl ProxyServer {
         status.running = true;
         status.address = self.config.listen_address.clone();
         status.port = self.config.listen_port;
+        status.tls = tls_acceptor.is_some();
         drop(status);
 
         // 记录启动时间
@@ -117,12 +137,23 @@ impl ProxyServer {
         // 启动服务器
         let state = self.state.clone();
         let handle = tokio::spawn(async move {
-            axum::serve(listener, app)
-                .with_graceful_shutdown(async {
-                    shutdown_rx.await.ok();
-                })
-                .await
-                .ok();
+            // 客户端认证需要对端地址判断是否为本机请求
+            match tls_acceptor {
+                Some(acceptor) => {
+                    super::tls::serve_tls(listener, acceptor, app, shutdown_rx).await;
+                }
+                None => {
+                    axum::serve(
+                        listener,
+                        app.into_make_service_with_connect_info::<SocketAddr>(),
+                    )
+                    .with_graceful_shutdown(async {
+                        shutdown_rx.await.ok();
+                    })
+                    .await
+                    .ok();
+                }
+            }
 
             // 服务器停止后更新状态
             state.status.write().await.running = false;
@@ -206,8 +237,9 @@ impl ProxyServer {
     }
 
     fn build_router(&self) -> Router {
+        // 来源已由 reject_cross_origin 校验，这里只回显允许的来源
         let cors = CorsLayer::new()
-            .allow_origin(Any)
+            .allow_origin(AllowOrigin::mirror_request())
             .allow_methods(Any)
             .allow_headers(Any);
 
@@ -215,6 +247,7 @@ impl ProxyServer {
             // 健康检查
             .route("/health", get(handlers::health_check))
             .route("/status", get(handlers::get_status))
+            .route("/metrics", get(handlers::metrics))
             // Claude API (支持带前缀和不带前缀两种格式)
             .route("/v1/messages", post(handlers::handle_messages))
             .route("/claude/v1/messages", post(handlers::handle_messages))
@@ -242,7 +275,17 @@ impl ProxyServer {
             .route("/gemini/v1beta/*path", post(handlers::handle_gemini))
             // 提高默认请求体大小限制（避免 413 Payload Too Large）
             .layer(DefaultBodyLimit::max(200 * 1024 * 1024))
+            // 客户端令牌认证（位于 CORS 之内，预检请求不受影响）
+            .layer(axum::middleware::from_fn_with_state(
+                self.state.clone(),
+                client_auth::require_client_token,
+            ))
             .layer(cors)
+            // 跨域来源校验（位于 CORS 之外，不允许的来源连预检也被拒绝）
+            .layer(axum::middleware::from_fn_with_state(
+                self.state.clone(),
+                client_auth::reject_cross_origin,
+            ))
             .with_state(self.state.clone())
     }
 
@@ -269,3 +312,73 @@ impl ProxyServer {
             .await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use tower::Service;
+
+    fn preflight(origin: &str) -> Request<Body> {
+        Request::builder()
+            .method("OPTIONS")
+            .uri("/v1/messages")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn server(listen_address: &str) -> ProxyServer {
+        let config = ProxyConfig {
+            listen_address: listen_address.to_string(),
+            ..Default::default()
+        };
+        ProxyServer::new(config, Arc::new(Database::memory().unwrap()), None)
+    }
+
+    #[tokio::test]
+    async fn test_cross_origin_preflight_rejected_on_shared_listener() {
+        let mut router = server("0.0.0.0").build_router();
+
+        let response = router
+            .call(preflight("https://evil.example"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+
+        // 应用自身的 WebView 来源仍然允许
+        let response = router.call(preflight("tauri://localhost")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "tauri://localhost"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cross_origin_preflight_rejected_when_client_auth_enabled() {
+        let server = server("127.0.0.1");
+        let response = server
+            .build_router()
+            .call(preflight("https://evil.example"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut auth = server.state.db.get_client_auth_config().unwrap();
+        auth.enabled = true;
+        server.state.db.set_client_auth_config(&auth).unwrap();
+        let response = server
+            .build_router()
+            .call(preflight("https://evil.example"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
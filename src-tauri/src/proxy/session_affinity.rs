@@ -0,0 +1,98 @@
+//! 会话粘性路由模块
+//!
+//! 记录 "app_type:session_id" → provider_id 的映射，使同一会话在 TTL 内
+//! 持续命中同一个供应商，避免负载均衡或故障恢复把对话打散到不同中转，
+//! 导致上游 prompt cache 失效。
+//!
+//! 只记录客户端提供的 Session ID（新生成的随机 ID 每次都不同，没有意义）。
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 单个会话的粘性记录
+struct Pin {
+    provider_id: String,
+    last_used: Instant,
+}
+
+/// 会话粘性表
+#[derive(Default)]
+pub struct SessionAffinity {
+    pins: Mutex<HashMap<String, Pin>>,
+}
+
+impl SessionAffinity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 获取会话当前绑定的供应商（过期则移除并返回 None）
+    pub fn get(&self, app_type: &str, session_id: &str, ttl: Duration) -> Option<String> {
+        let key = format!("{app_type}:{session_id}");
+        let mut pins = self.pins.lock().ok()?;
+        match pins.get(&key) {
+            Some(pin) if pin.last_used.elapsed() <= ttl => Some(pin.provider_id.clone()),
+            Some(_) => {
+                pins.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// 绑定会话到供应商（刷新 TTL），顺带清理过期记录
+    pub fn pin(&self, app_type: &str, session_id: &str, provider_id: &str, ttl: Duration) {
+        let Ok(mut pins) = self.pins.lock() else {
+            return;
+        };
+        pins.retain(|_, pin| pin.last_used.elapsed() <= ttl);
+        pins.insert(
+            format!("{app_type}:{session_id}"),
+            Pin {
+                provider_id: provider_id.to_string(),
+                last_used: Instant::now(),
+            },
+        );
+    }
+
+    /// 解除会话绑定
+    pub fn unpin(&self, app_type: &str, session_id: &str) {
+        if let Ok(mut pins) = self.pins.lock() {
+            pins.remove(&format!("{app_type}:{session_id}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_and_get() {
+        let affinity = SessionAffinity::new();
+        let ttl = Duration::from_secs(60);
+
+        assert_eq!(affinity.get("claude", "s1", ttl), None);
+        affinity.pin("claude", "s1", "p1", ttl);
+        assert_eq!(affinity.get("claude", "s1", ttl), Some("p1".to_string()));
+        // 不同 app 互不影响
+        assert_eq!(affinity.get("codex", "s1", ttl), None);
+
+        affinity.pin("claude", "s1", "p2", ttl);
+        assert_eq!(affinity.get("claude", "s1", ttl), Some("p2".to_string()));
+
+        affinity.unpin("claude", "s1");
+        assert_eq!(affinity.get("claude", "s1", ttl), None);
+    }
+
+    #[test]
+    fn test_expired_pin_is_dropped() {
+        let affinity = SessionAffinity::new();
+        affinity.pin("claude", "s1", "p1", Duration::from_secs(60));
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(affinity.get("claude", "s1", Duration::ZERO), None);
+        assert_eq!(affinity.get("claude", "s1", Duration::from_secs(60)), None);
+    }
+}
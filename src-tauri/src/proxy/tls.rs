@@ -0,0 +1,480 @@
+//! 代理监听 TLS
+//!
+//! 代理对局域网开放时以 HTTPS 提供服务，避免提示词明文传输：
+//! - custom：加载用户提供的证书链与私钥（PEM）
+//! - selfSigned：首次使用时在应用配置目录生成本地 CA（可导出供客户端信任），
+//!   每次启动由该 CA 签发覆盖 localhost、回环地址与额外主机名的服务端证书。
+//!   CA 带名称约束，只能为这些主机签发证书；主机列表变化时重新生成 CA
+
+use super::{
+    log_codes::srv as log_srv,
+    types::{ProxyTlsConfig, TLS_CERT_SOURCE_CUSTOM, TLS_CERT_SOURCE_SELF_SIGNED},
+    ProxyError,
+};
+use axum::{extract::ConnectInfo, Router};
+use chrono::Datelike;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{conn::auto::Builder, graceful::GracefulShutdown},
+};
+use rcgen::{
+    BasicConstraints, CertificateParams, CidrSubnet, DistinguishedName, DnType,
+    ExtendedKeyUsagePurpose, GeneralSubtree, IsCa, KeyPair, KeyUsagePurpose, NameConstraints,
+};
+use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio_rustls::TlsAcceptor;
+
+const CA_COMMON_NAME: &str = "CC Switch Local Proxy CA";
+const CA_CERT_FILE: &str = "ca-cert.pem";
+const CA_KEY_FILE: &str = "ca-key.pem";
+/// 本地 CA 名称约束允许的主机（每行一个）
+const CA_HOSTS_FILE: &str = "ca-hosts.txt";
+
+/// 服务端证书有效期（天），每次启动重新签发
+const SERVER_CERT_VALID_DAYS: i64 = 365;
+
+/// TLS 握手超时，避免半开连接长期占用
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 关闭时等待存量连接结束的上限（需小于 ProxyServer::stop 的 5 秒超时）
+const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+fn tls_dir() -> PathBuf {
+    crate::config::get_app_config_dir().join("proxy-tls")
+}
+
+/// 本地 CA 证书路径
+pub fn ca_cert_path() -> PathBuf {
+    tls_dir().join(CA_CERT_FILE)
+}
+
+/// 本地 CA 的证书参数（主体固定，重建后与已保存的 CA 证书保持同一签发者）
+///
+/// 名称约束把 CA 限定在它服务的主机上：即使私钥泄露，也无法为其他域名签发受信任的证书
+fn ca_params(hosts: &[String]) -> CertificateParams {
+    let mut params = CertificateParams::default();
+    let mut dn = DistinguishedName::new();
+    dn.push(DnType::CommonName, CA_COMMON_NAME);
+    dn.push(DnType::OrganizationName, "CC Switch");
+    params.distinguished_name = dn;
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    params.name_constraints = Some(NameConstraints {
+        permitted_subtrees: hosts.iter().map(|host| host_subtree(host)).collect(),
+        excluded_subtrees: Vec::new(),
+    });
+    params.not_before = rcgen::date_time_ymd(2024, 1, 1);
+    params.not_after = rcgen::date_time_ymd(2044, 1, 1);
+    params
+}
+
+/// 单个主机对应的名称约束：IP 精确到单个地址，域名按 DNS 名称约束
+fn host_subtree(host: &str) -> GeneralSubtree {
+    match host.parse::<IpAddr>() {
+        Ok(ip) => {
+            let prefix = if ip.is_ipv4() { 32 } else { 128 };
+            GeneralSubtree::IpAddress(CidrSubnet::from_addr_prefix(ip, prefix))
+        }
+        Err(_) => GeneralSubtree::DnsName(host.to_ascii_lowercase()),
+    }
+}
+
+/// 写入私钥文件（仅当前用户可读：Unix 下为 0600，Windows 下移除继承的 ACL）
+fn write_private_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(contents.as_bytes())
+    }
+
+    #[cfg(windows)]
+    {
+        // 先创建空文件并收紧 ACL，再写入私钥内容（截断写入会保留 ACL）
+        std::fs::write(path, "")?;
+        restrict_to_current_user(path)?;
+        std::fs::write(path, contents)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        std::fs::write(path, contents)
+    }
+}
+
+/// 移除文件继承的 ACL，仅授予当前用户完全控制权限
+#[cfg(windows)]
+fn restrict_to_current_user(path: &Path) -> std::io::Result<()> {
+    use std::os::windows::process::CommandExt;
+
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let user = std::env::var("USERNAME").map_err(|_| std::io::Error::other("无法确定当前用户"))?;
+    let account = match std::env::var("USERDOMAIN") {
+        Ok(domain) if !domain.is_empty() => format!("{domain}\\{user}"),
+        _ => user,
+    };
+    let status = std::process::Command::new("icacls")
+        .arg(path)
+        .args(["/inheritance:r", "/grant:r"])
+        .arg(format!("{account}:F"))
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "设置私钥文件权限失败: {status}"
+        )))
+    }
+}
+
+/// 读取已保存 CA 名称约束允许的主机（文件缺失时为空，视为需要重新生成）
+fn read_ca_hosts(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .map(|contents| {
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 加载本地 CA 私钥及其名称约束允许的主机
+///
+/// CA 不存在或名称约束未覆盖 `hosts` 时重新生成（客户端需要重新信任新的 CA）
+fn load_or_create_ca(hosts: &[String]) -> Result<(KeyPair, Vec<String>), String> {
+    let dir = tls_dir();
+    let key_path = dir.join(CA_KEY_FILE);
+    let cert_path = dir.join(CA_CERT_FILE);
+    let hosts_path = dir.join(CA_HOSTS_FILE);
+
+    if key_path.exists() && cert_path.exists() {
+        let permitted = read_ca_hosts(&hosts_path);
+        if hosts
+            .iter()
+            .all(|host| permitted.iter().any(|p| p.eq_ignore_ascii_case(host)))
+        {
+            let pem = std::fs::read_to_string(&key_path)
+                .map_err(|e| format!("读取本地 CA 私钥失败: {e}"))?;
+            let key = KeyPair::from_pem(&pem).map_err(|e| format!("解析本地 CA 私钥失败: {e}"))?;
+            return Ok((key, permitted));
+        }
+        log::warn!(
+            "[{}] 本地代理 CA 未覆盖当前主机列表，将重新生成，客户端需重新信任",
+            log_srv::TLS_CA_CREATED
+        );
+    }
+
+    std::fs::create_dir_all(&dir).map_err(|e| format!("创建证书目录失败: {e}"))?;
+    let key = KeyPair::generate().map_err(|e| format!("生成 CA 私钥失败: {e}"))?;
+    let cert = ca_params(hosts)
+        .self_signed(&key)
+        .map_err(|e| format!("生成 CA 证书失败: {e}"))?;
+
+    write_private_file(&key_path, &key.serialize_pem())
+        .map_err(|e| format!("保存 CA 私钥失败: {e}"))?;
+    std::fs::write(&cert_path, cert.pem()).map_err(|e| format!("保存 CA 证书失败: {e}"))?;
+    std::fs::write(&hosts_path, hosts.join("\n"))
+        .map_err(|e| format!("保存 CA 主机列表失败: {e}"))?;
+
+    log::info!(
+        "[{}] 已生成本地代理 CA: {}",
+        log_srv::TLS_CA_CREATED,
+        cert_path.display()
+    );
+    Ok((key, hosts.to_vec()))
+}
+
+/// 获取覆盖 `hosts` 的本地 CA 证书（PEM），供用户导出并加入客户端信任
+pub fn ca_cert_pem(hosts: &[String]) -> Result<String, ProxyError> {
+    load_or_create_ca(hosts).map_err(ProxyError::ConfigError)?;
+    std::fs::read_to_string(ca_cert_path())
+        .map_err(|e| ProxyError::ConfigError(format!("读取本地 CA 证书失败: {e}")))
+}
+
+/// 自签证书覆盖的主机名：localhost、回环地址、具体监听地址与用户配置的额外主机
+pub fn server_cert_hosts(config: &ProxyTlsConfig, listen_address: &str) -> Vec<String> {
+    let mut hosts: Vec<String> = vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ];
+    let listen = listen_address.trim_start_matches('[').trim_end_matches(']');
+    let candidates = std::iter::once(listen)
+        .filter(|host| !matches!(*host, "0.0.0.0" | "::" | ""))
+        .chain(config.extra_hosts.iter().map(|h| h.trim()));
+    for host in candidates {
+        if !host.is_empty() && !hosts.iter().any(|h| h.eq_ignore_ascii_case(host)) {
+            hosts.push(host.to_string());
+        }
+    }
+    hosts
+}
+
+/// 由本地 CA 签发服务端证书
+fn issue_server_cert(
+    hosts: Vec<String>,
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>), String> {
+    let (ca_key, permitted) = load_or_create_ca(&hosts)?;
+    let ca_cert = ca_params(&permitted)
+        .self_signed(&ca_key)
+        .map_err(|e| format!("加载本地 CA 失败: {e}"))?;
+
+    let mut params = CertificateParams::new(hosts).map_err(|e| format!("无效的证书主机名: {e}"))?;
+    let mut dn = DistinguishedName::new();
+    dn.push(DnType::CommonName, "CC Switch Local Proxy");
+    params.distinguished_name = dn;
+    params.key_usages = vec![
+        KeyUsagePurpose::DigitalSignature,
+        KeyUsagePurpose::KeyEncipherment,
+    ];
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    params.use_authority_key_identifier_extension = true;
+    let today = chrono::Local::now().date_naive();
+    let (not_before, not_after) = (
+        today - chrono::Duration::days(1),
+        today + chrono::Duration::days(SERVER_CERT_VALID_DAYS),
+    );
+    params.not_before = rcgen::date_time_ymd(
+        not_before.year(),
+        not_before.month() as u8,
+        not_before.day() as u8,
+    );
+    params.not_after = rcgen::date_time_ymd(
+        not_after.year(),
+        not_after.month() as u8,
+        not_after.day() as u8,
+    );
+
+    let key = KeyPair::generate().map_err(|e| format!("生成服务端私钥失败: {e}"))?;
+    let cert = params
+        .signed_by(&key, &ca_cert, &ca_key)
+        .map_err(|e| format!("签发服务端证书失败: {e}"))?;
+
+    let key_der = PrivateKeyDer::try_from(key.serialize_der())
+        .map_err(|e| format!("序列化服务端私钥失败: {e}"))?;
+    Ok((vec![cert.der().clone(), ca_cert.der().clone()], key_der))
+}
+
+/// 加载用户提供的证书链与私钥
+fn load_custom_cert(
+    config: &ProxyTlsConfig,
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>), String> {
+    let cert_path = config
+        .cert_path
+        .as_deref()
+        .filter(|p| !p.trim().is_empty())
+        .ok_or("未配置证书文件路径")?;
+    let key_path = config
+        .key_path
+        .as_deref()
+        .filter(|p| !p.trim().is_empty())
+        .ok_or("未配置私钥文件路径")?;
+
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .map_err(|e| format!("读取证书文件失败 ({cert_path}): {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析证书文件失败 ({cert_path}): {e}"))?;
+    if certs.is_empty() {
+        return Err(format!("证书文件中没有证书: {cert_path}"));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| format!("读取私钥文件失败 ({key_path}): {e}"))?;
+    Ok((certs, key))
+}
+
+/// 根据配置构建 TLS 接收器（证书或私钥无效时返回配置错误，代理不会启动）
+pub fn build_acceptor(
+    config: &ProxyTlsConfig,
+    listen_address: &str,
+) -> Result<TlsAcceptor, ProxyError> {
+    let (certs, key) = match config.cert_source.as_str() {
+        TLS_CERT_SOURCE_CUSTOM => load_custom_cert(config),
+        TLS_CERT_SOURCE_SELF_SIGNED => issue_server_cert(server_cert_hosts(config, listen_address)),
+        other => Err(format!("未知的证书来源: {other}")),
+    }
+    .map_err(ProxyError::ConfigError)?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut server_config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| ProxyError::ConfigError(format!("TLS 协议配置失败: {e}")))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| ProxyError::ConfigError(format!("证书与私钥不匹配或无效: {e}")))?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// 以 HTTPS 提供服务，直到收到关闭信号
+///
+/// 与 `axum::serve` 一致：每个请求的扩展中携带 `ConnectInfo<SocketAddr>`，
+/// 关闭时停止接收新连接并等待存量连接结束（有上限）。
+pub async fn serve_tls(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    app: Router,
+    mut shutdown_rx: oneshot::Receiver<()>,
+) {
+    let builder = Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::warn!("[{}] 接收连接失败: {e}", log_srv::TASK_ERROR);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    continue;
+                }
+            },
+            _ = &mut shutdown_rx => break,
+        };
+
+        let acceptor = acceptor.clone();
+        let builder = builder.clone();
+        let watcher = graceful.watcher();
+        let app = app.clone();
+        let service = hyper::service::service_fn(move |mut request| {
+            request.extensions_mut().insert(ConnectInfo(peer));
+            tower::Service::call(&mut app.clone(), request)
+        });
+
+        tokio::spawn(async move {
+            let tls_stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        log::debug!("[{}] TLS 握手失败 ({peer}): {e}", log_srv::TLS_HANDSHAKE);
+                        return;
+                    }
+                    Err(_) => {
+                        log::debug!("[{}] TLS 握手超时 ({peer})", log_srv::TLS_HANDSHAKE);
+                        return;
+                    }
+                };
+
+            let connection = builder
+                .serve_connection(TokioIo::new(tls_stream), service)
+                .into_owned();
+            if let Err(e) = watcher.watch(connection).await {
+                log::debug!("HTTPS 连接结束 ({peer}): {e}");
+            }
+        });
+    }
+
+    drop(listener);
+    if tokio::time::timeout(GRACEFUL_SHUTDOWN_TIMEOUT, graceful.shutdown())
+        .await
+        .is_err()
+    {
+        log::warn!(
+            "[{}] 等待 HTTPS 连接关闭超时，强制结束",
+            log_srv::STOP_TIMEOUT
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::client::danger::ServerCertVerifier;
+
+    #[test]
+    fn test_server_cert_hosts() {
+        let config = ProxyTlsConfig {
+            extra_hosts: vec![
+                " 192.168.1.20 ".to_string(),
+                "LOCALHOST".to_string(),
+                "proxy.lan".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        let hosts = server_cert_hosts(&config, "0.0.0.0");
+        assert_eq!(
+            hosts,
+            vec!["localhost", "127.0.0.1", "::1", "192.168.1.20", "proxy.lan"]
+        );
+
+        let hosts = server_cert_hosts(&ProxyTlsConfig::default(), "10.0.0.5");
+        assert_eq!(hosts, vec!["localhost", "127.0.0.1", "::1", "10.0.0.5"]);
+    }
+
+    fn verify_leaf(ca_pem: &str, leaf: &rcgen::Certificate, host: &str) -> bool {
+        let ca_der = CertificateDer::from_pem_slice(ca_pem.as_bytes()).unwrap();
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(ca_der).unwrap();
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let verifier =
+            rustls::client::WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .unwrap();
+        let server_name = rustls::pki_types::ServerName::try_from(host.to_string()).unwrap();
+        verifier
+            .verify_server_cert(
+                leaf.der(),
+                &[],
+                &server_name,
+                &[],
+                rustls::pki_types::UnixTime::now(),
+            )
+            .is_ok()
+    }
+
+    #[test]
+    fn test_leaf_certificate_chains_to_ca() {
+        let hosts = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+        let ca_key = KeyPair::generate().unwrap();
+        let ca_pem = ca_params(&hosts).self_signed(&ca_key).unwrap().pem();
+
+        // 由同一私钥重建的 CA 签发的证书应能被已保存的 CA 证书验证
+        let rebuilt_ca = ca_params(&hosts).self_signed(&ca_key).unwrap();
+        let leaf_key = KeyPair::generate().unwrap();
+        let leaf = CertificateParams::new(hosts.clone())
+            .unwrap()
+            .signed_by(&leaf_key, &rebuilt_ca, &ca_key)
+            .unwrap();
+        assert!(verify_leaf(&ca_pem, &leaf, "localhost"));
+        assert!(verify_leaf(&ca_pem, &leaf, "127.0.0.1"));
+    }
+
+    #[test]
+    fn test_ca_name_constraints_reject_other_hosts() {
+        let hosts = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+        let ca_key = KeyPair::generate().unwrap();
+        let ca = ca_params(&hosts).self_signed(&ca_key).unwrap();
+        let ca_pem = ca.pem();
+
+        let leaf_key = KeyPair::generate().unwrap();
+        let leaf = CertificateParams::new(vec!["example.com".to_string(), "10.0.0.1".to_string()])
+            .unwrap()
+            .signed_by(&leaf_key, &ca, &ca_key)
+            .unwrap();
+        assert!(!verify_leaf(&ca_pem, &leaf, "example.com"));
+        assert!(!verify_leaf(&ca_pem, &leaf, "10.0.0.1"));
+    }
+}
@@ -89,6 +89,15 @@ pub struct ProxyStatus {
     /// 当前活跃的代理目标列表
     #[serde(default)]
     pub active_targets: Vec<ActiveTarget>,
+    /// 响应缓存命中次数
+    #[serde(default)]
+    pub cache_hits: u64,
+    /// 响应缓存未命中次数（仅统计可缓存的请求）
+    #[serde(default)]
+    pub cache_misses: u64,
+    /// 是否以 HTTPS 提供服务
+    #[serde(default)]
+    pub tls: bool,
 }
 
 /// 活跃的代理目标信息
@@ -194,6 +203,49 @@ pub struct AppProxyConfig {
     pub circuit_min_requests: u32,
 }
 
+/// 负载均衡策略（每个 app 独立，存储在 proxy_config.load_balance_strategy）
+///
+/// 仅在自动故障转移开启时生效：策略只决定队列中“首选”的顺序，
+/// 其余供应商仍作为故障转移候选，熔断器语义保持不变。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadBalanceStrategy {
+    /// 按故障转移队列顺序（P1 → P2 → ...）
+    #[default]
+    Priority,
+    /// 按供应商权重平滑加权轮询
+    Weighted,
+    /// 按近期延迟（优先首字延迟）从低到高
+    LeastLatency,
+    /// 按模型定价 × 成本倍率从低到高
+    LeastCost,
+}
+
+impl LoadBalanceStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoadBalanceStrategy::Priority => "priority",
+            LoadBalanceStrategy::Weighted => "weighted",
+            LoadBalanceStrategy::LeastLatency => "least_latency",
+            LoadBalanceStrategy::LeastCost => "least_cost",
+        }
+    }
+}
+
+impl std::str::FromStr for LoadBalanceStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "priority" => Ok(LoadBalanceStrategy::Priority),
+            "weighted" => Ok(LoadBalanceStrategy::Weighted),
+            "least_latency" => Ok(LoadBalanceStrategy::LeastLatency),
+            "least_cost" => Ok(LoadBalanceStrategy::LeastCost),
+            other => Err(format!("unknown load balance strategy: {other}")),
+        }
+    }
+}
+
 /// 整流器配置
 ///
 /// 存储在 settings 表中
@@ -233,6 +285,279 @@ impl Default for RectifierConfig {
     }
 }
 
+/// 上下文超长回退目标（供应商与模型至少设置一项）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextFallbackTarget {
+    /// 回退供应商（为空时使用原供应商）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
+    /// 回退模型（为空时沿用该供应商的模型映射）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// 上下文超长回退配置
+///
+/// 存储在 settings 表的 context_overflow_config 字段中（JSON 格式）。
+/// 上游返回“prompt is too long / context length exceeded”时，
+/// 用配置的长上下文模型或供应商重试一次，并在请求日志中记录 context_overflow 整流事件。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextOverflowConfig {
+    /// 总开关（默认关闭）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 按 app 配置的回退目标（键为 claude / codex / gemini）
+    #[serde(default)]
+    pub targets: std::collections::HashMap<String, ContextFallbackTarget>,
+}
+
+/// 会话粘性路由配置
+///
+/// 存储在 settings 表的 session_affinity_config 字段中（JSON 格式）。
+/// 同一会话在 TTL 内优先路由到上次成功的供应商，以保留上游 prompt cache。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionAffinityConfig {
+    /// 总开关（默认开启）
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 粘性有效期（秒），每次命中后刷新；默认 300 秒，与 Anthropic prompt cache 默认 TTL 一致
+    #[serde(default = "default_session_affinity_ttl")]
+    pub ttl_seconds: u64,
+}
+
+fn default_session_affinity_ttl() -> u64 {
+    300
+}
+
+impl Default for SessionAffinityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_seconds: default_session_affinity_ttl(),
+        }
+    }
+}
+
+/// 对冲请求配置
+///
+/// 存储在 settings 表的 hedging_config 字段中（JSON 格式）。
+/// 小体积非流式请求在首个供应商超过近期延迟分位数仍未响应时，
+/// 向故障转移队列中的下一个供应商并发请求，取先成功者。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HedgingConfig {
+    /// 总开关（默认关闭，对冲会增加上游请求量）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 触发对冲的延迟分位数（1-99），默认 P90
+    #[serde(default = "default_hedging_percentile")]
+    pub latency_percentile: u8,
+    /// 对冲等待时间下限（毫秒），避免样本偏低时过早对冲
+    #[serde(default = "default_hedging_min_delay_ms")]
+    pub min_delay_ms: u64,
+    /// 参与对冲的请求体大小上限（字节）
+    #[serde(default = "default_hedging_max_request_bytes")]
+    pub max_request_bytes: u64,
+}
+
+fn default_hedging_percentile() -> u8 {
+    90
+}
+
+fn default_hedging_min_delay_ms() -> u64 {
+    500
+}
+
+fn default_hedging_max_request_bytes() -> u64 {
+    32 * 1024
+}
+
+impl Default for HedgingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            latency_percentile: default_hedging_percentile(),
+            min_delay_ms: default_hedging_min_delay_ms(),
+            max_request_bytes: default_hedging_max_request_bytes(),
+        }
+    }
+}
+
+/// 响应缓存配置
+///
+/// 存储在 settings 表的 response_cache_config 字段中（JSON 格式）。
+/// 对完全相同的请求（忽略 metadata 等易变字段）直接返回缓存的上游响应，不计费。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseCacheConfig {
+    /// 总开关（默认关闭）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 缓存有效期（秒）
+    #[serde(default = "default_response_cache_ttl")]
+    pub ttl_seconds: u64,
+    /// 最多缓存的响应条数，超出时淘汰最早写入的条目
+    #[serde(default = "default_response_cache_max_entries")]
+    pub max_entries: usize,
+    /// 单条响应体大小上限（字节），超出不缓存
+    #[serde(default = "default_response_cache_max_entry_bytes")]
+    pub max_entry_bytes: usize,
+}
+
+fn default_response_cache_ttl() -> u64 {
+    600
+}
+
+fn default_response_cache_max_entries() -> usize {
+    256
+}
+
+fn default_response_cache_max_entry_bytes() -> usize {
+    256 * 1024
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_seconds: default_response_cache_ttl(),
+            max_entries: default_response_cache_max_entries(),
+            max_entry_bytes: default_response_cache_max_entry_bytes(),
+        }
+    }
+}
+
+/// 密钥扫描命中后的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretScanAction {
+    /// 仅记录日志，原样转发
+    #[default]
+    Warn,
+    /// 原地替换为 `[REDACTED:<检测器>]` 后转发
+    Redact,
+    /// 拒绝请求并向客户端返回错误
+    Block,
+}
+
+/// 用户自定义的密钥正则（带捕获组时只处理第一个捕获组）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomSecretPattern {
+    pub name: String,
+    pub pattern: String,
+}
+
+/// 出站请求密钥扫描配置
+///
+/// 存储在 settings 表的 secret_scan_config 字段中（JSON 格式）。
+/// 内置检测器：aws_access_key、aws_secret_key、github_token、private_key、jwt、high_entropy。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretScanConfig {
+    /// 总开关（默认关闭）
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub action: SecretScanAction,
+    /// 禁用的内置检测器名称
+    #[serde(default)]
+    pub disabled_detectors: Vec<String>,
+    #[serde(default)]
+    pub custom_patterns: Vec<CustomSecretPattern>,
+    /// 高熵检测阈值（每字符比特数，0-8）
+    #[serde(default = "default_secret_entropy_threshold")]
+    pub entropy_threshold: f64,
+}
+
+fn default_secret_entropy_threshold() -> f64 {
+    4.3
+}
+
+impl Default for SecretScanConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            action: SecretScanAction::default(),
+            disabled_detectors: Vec::new(),
+            custom_patterns: Vec::new(),
+            entropy_threshold: default_secret_entropy_threshold(),
+        }
+    }
+}
+
+/// 客户端认证配置
+///
+/// 存储在 settings 表的 client_auth_config 字段中（JSON 格式）。
+/// 开启后代理的每个路由都要求携带已签发的客户端令牌（Bearer / x-api-key / x-goog-api-key）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientAuthConfig {
+    /// 总开关（默认关闭）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 本机（回环地址）且非浏览器发起的请求可免令牌，便于本机接管的 CLI 继续使用
+    #[serde(default = "default_true")]
+    pub allow_loopback: bool,
+}
+
+impl Default for ClientAuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allow_loopback: true,
+        }
+    }
+}
+
+/// 代理监听 TLS 证书来源：自签 CA 签发
+pub const TLS_CERT_SOURCE_SELF_SIGNED: &str = "selfSigned";
+/// 代理监听 TLS 证书来源：用户提供的证书与私钥
+pub const TLS_CERT_SOURCE_CUSTOM: &str = "custom";
+
+fn default_tls_cert_source() -> String {
+    TLS_CERT_SOURCE_SELF_SIGNED.to_string()
+}
+
+/// 代理监听 TLS 配置
+///
+/// 存储在 settings 表的 proxy_tls_config 字段中（JSON 格式）。
+/// 开启后代理以 HTTPS 提供服务，接管写入的代理地址同步改为 `https://`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyTlsConfig {
+    /// 总开关（默认关闭）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 证书来源：selfSigned（本地 CA 自动签发）/ custom（用户提供 PEM 文件）
+    #[serde(default = "default_tls_cert_source")]
+    pub cert_source: String,
+    /// 证书链 PEM 文件路径（custom 模式）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert_path: Option<String>,
+    /// 私钥 PEM 文件路径（custom 模式）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_path: Option<String>,
+    /// 自签证书额外的主机名/IP（如局域网地址），localhost 与回环地址始终包含
+    #[serde(default)]
+    pub extra_hosts: Vec<String>,
+}
+
+impl Default for ProxyTlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_source: default_tls_cert_source(),
+            cert_path: None,
+            key_path: None,
+            extra_hosts: Vec::new(),
+        }
+    }
+}
+
 /// 日志配置
 ///
 /// 存储在 settings 表的 log_config 字段中（JSON 格式）
@@ -245,6 +570,9 @@ pub struct LogConfig {
     /// 日志级别: error, warn, info, debug, trace
     #[serde(default = "default_log_level")]
     pub level: String,
+    /// OpenTelemetry 链路追踪导出（OTLP/HTTP）
+    #[serde(default)]
+    pub otlp: OtlpConfig,
 }
 
 impl Default for LogConfig {
@@ -252,6 +580,50 @@ impl Default for LogConfig {
         Self {
             enabled: true,
             level: "info".to_string(),
+            otlp: OtlpConfig::default(),
+        }
+    }
+}
+
+fn default_otlp_endpoint() -> String {
+    "http://127.0.0.1:4318/v1/traces".to_string()
+}
+
+fn default_otlp_sample_ratio() -> f64 {
+    1.0
+}
+
+fn default_otlp_service_name() -> String {
+    "cc-switch".to_string()
+}
+
+/// OTLP 链路追踪导出配置
+///
+/// 每个代理请求生成一个根 span，每次上游尝试生成一个子 span
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtlpConfig {
+    /// 是否启用导出（默认关闭）
+    #[serde(default)]
+    pub enabled: bool,
+    /// OTLP/HTTP traces 端点（JSON 编码）
+    #[serde(default = "default_otlp_endpoint")]
+    pub endpoint: String,
+    /// 采样比例（0.0-1.0）；请求携带 traceparent 时沿用上游的采样决定
+    #[serde(default = "default_otlp_sample_ratio")]
+    pub sample_ratio: f64,
+    /// 上报的 service.name
+    #[serde(default = "default_otlp_service_name")]
+    pub service_name: String,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: default_otlp_endpoint(),
+            sample_ratio: default_otlp_sample_ratio(),
+            service_name: default_otlp_service_name(),
         }
     }
 }
@@ -323,6 +695,43 @@ mod tests {
         assert!(config.request_thinking_budget);
     }
 
+    #[test]
+    fn test_session_affinity_config_serde_default() {
+        let config: SessionAffinityConfig = serde_json::from_str("{}").unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.ttl_seconds, 300);
+
+        let config: SessionAffinityConfig =
+            serde_json::from_str(r#"{"enabled": false, "ttlSeconds": 3600}"#).unwrap();
+        assert!(!config.enabled);
+        assert_eq!(config.ttl_seconds, 3600);
+    }
+
+    #[test]
+    fn test_hedging_config_serde_default() {
+        let config: HedgingConfig = serde_json::from_str("{}").unwrap();
+        assert!(!config.enabled);
+        assert_eq!(config.latency_percentile, 90);
+        assert_eq!(config.min_delay_ms, 500);
+        assert_eq!(config.max_request_bytes, 32 * 1024);
+    }
+
+    #[test]
+    fn test_client_auth_config_serde_default() {
+        let config: ClientAuthConfig = serde_json::from_str("{}").unwrap();
+        assert!(!config.enabled);
+        assert!(config.allow_loopback);
+    }
+
+    #[test]
+    fn test_proxy_tls_config_serde_default() {
+        let config: ProxyTlsConfig = serde_json::from_str("{}").unwrap();
+        assert!(!config.enabled);
+        assert_eq!(config.cert_source, TLS_CERT_SOURCE_SELF_SIGNED);
+        assert!(config.cert_path.is_none());
+        assert!(config.extra_hosts.is_empty());
+    }
+
     #[test]
     fn test_log_config_default() {
         let config = LogConfig::default();
@@ -381,6 +790,7 @@ mod tests {
         let config = LogConfig {
             enabled: false,
             level: "debug".to_string(),
+            ..Default::default()
         };
         assert_eq!(config.to_level_filter(), log::LevelFilter::Off);
     }
@@ -390,10 +800,21 @@ mod tests {
         let config = LogConfig {
             enabled: true,
             level: "debug".to_string(),
+            ..Default::default()
         };
         let json = serde_json::to_string(&config).unwrap();
         let parsed: LogConfig = serde_json::from_str(&json).unwrap();
         assert!(parsed.enabled);
         assert_eq!(parsed.level, "debug");
     }
+
+    #[test]
+    fn test_otlp_config_serde_default() {
+        // 旧版 log_config 没有 otlp 字段
+        let config: LogConfig = serde_json::from_str(r#"{"enabled":true,"level":"warn"}"#).unwrap();
+        assert!(!config.otlp.enabled);
+        assert_eq!(config.otlp.endpoint, "http://127.0.0.1:4318/v1/traces");
+        assert_eq!(config.otlp.sample_ratio, 1.0);
+        assert_eq!(config.otlp.service_name, "cc-switch");
+    }
 }
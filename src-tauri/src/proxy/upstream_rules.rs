@@ -0,0 +1,855 @@
+//! 上游代理规则模块
+//!
+//! 按请求目标为每个出站请求选择代理，两种模式：
+//! - `rules`：有序规则列表（域名 / 域名后缀 / CIDR → 代理 URL 或 direct），首条命中生效
+//! - `pac`：PAC 脚本，调用 `FindProxyForURL(url, host)`，取返回值中第一个可用项
+//!
+//! 未命中规则（或 PAC 执行失败、超时）时回退到全局代理 / 系统代理。
+//!
+//! HTTP 客户端的代理回调运行在异步工作线程上，只读取缓存（[`ProxyRuleSet::try_decide`]）；
+//! 需要 DNS 解析或执行 PAC 的请求由转发器提前在阻塞线程池中求值并写入缓存。
+//! DNS 解析限时且按主机缓存；PAC 运行时跨请求复用，有执行时间与内存上限。
+//! 与浏览器一致，PAC 脚本看到的 URL 只保留协议、主机与端口，结果按此缓存。
+
+use super::routing_rules::glob_match;
+use once_cell::sync::Lazy;
+use rquickjs::{Context, Function, Runtime};
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::net::{IpAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use url::Url;
+
+pub const PROXY_RULE_MODE_RULES: &str = "rules";
+pub const PROXY_RULE_MODE_PAC: &str = "pac";
+
+pub const RULE_MATCH_DOMAIN: &str = "domain";
+pub const RULE_MATCH_DOMAIN_SUFFIX: &str = "domainSuffix";
+pub const RULE_MATCH_CIDR: &str = "cidr";
+
+/// 规则目标：直连
+pub const PROXY_TARGET_DIRECT: &str = "direct";
+
+/// PAC 结果缓存上限（按协议 + 主机 + 端口缓存，超出后整体清空）
+const PAC_CACHE_LIMIT: usize = 256;
+
+/// 单次 PAC 求值的执行时间上限
+const PAC_TIMEOUT: Duration = Duration::from_millis(1_000);
+/// PAC 运行时内存上限
+const PAC_MEMORY_LIMIT: usize = 32 * 1024 * 1024;
+
+/// 单次 DNS 解析的等待上限（超时视为无法解析）
+const DNS_TIMEOUT: Duration = Duration::from_millis(500);
+/// DNS 解析结果缓存时长（包括解析失败）
+const DNS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// 主机 → (解析时间, 地址列表)
+type DnsCache = HashMap<String, (Instant, Vec<IpAddr>)>;
+static DNS_CACHE: Lazy<Mutex<DnsCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 本机出口地址只探测一次
+static MY_IP_ADDRESS: Lazy<String> = Lazy::new(detect_my_ip_address);
+
+/// 上游代理规则配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamProxyRules {
+    #[serde(default)]
+    pub enabled: bool,
+    /// rules | pac
+    #[serde(default = "default_mode")]
+    pub mode: String,
+    /// 有序规则列表（mode = rules）
+    #[serde(default)]
+    pub rules: Vec<UpstreamProxyRule>,
+    /// PAC 脚本内容（mode = pac）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pac_script: Option<String>,
+}
+
+impl Default for UpstreamProxyRules {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: default_mode(),
+            rules: Vec::new(),
+            pac_script: None,
+        }
+    }
+}
+
+fn default_mode() -> String {
+    PROXY_RULE_MODE_RULES.to_string()
+}
+
+/// 单条上游代理规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamProxyRule {
+    /// domain | domainSuffix | cidr
+    pub match_type: String,
+    /// 域名、域名后缀（可带 `.` / `*.` 前缀）或 CIDR（如 `10.0.0.0/8`）
+    ///
+    /// CIDR 规则对域名目标先解析（限时并缓存），任一解析地址落在网段内即命中；
+    /// 解析失败时不命中。
+    pub pattern: String,
+    /// 代理 URL（http/https/socks5/socks5h）或 `direct`
+    pub proxy: String,
+}
+
+/// 代理选择结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProxyDecision {
+    /// 直连
+    Direct,
+    /// 使用指定代理
+    Proxy(Url),
+    /// 未命中，回退到全局代理 / 系统代理
+    Default,
+}
+
+/// 单条规则的匹配条件
+#[derive(Debug)]
+enum HostMatcher {
+    Domain(String),
+    Suffix(String),
+    Cidr(IpAddr, u8),
+}
+
+impl HostMatcher {
+    /// `resolved` 延迟解析域名，同一请求内最多解析一次；
+    /// `lookup` 返回 None 表示无法在不阻塞的前提下得到解析结果，此时匹配结果未知
+    fn matches(
+        &self,
+        host: &str,
+        resolved: &OnceCell<Option<Vec<IpAddr>>>,
+        lookup: fn(&str) -> Option<Vec<IpAddr>>,
+    ) -> Option<bool> {
+        match self {
+            HostMatcher::Domain(domain) => Some(host == domain),
+            HostMatcher::Suffix(suffix) => Some(
+                host == suffix
+                    || host
+                        .strip_suffix(suffix.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('.')),
+            ),
+            HostMatcher::Cidr(network, prefix) => resolved
+                .get_or_init(|| match host.parse::<IpAddr>() {
+                    Ok(ip) => Some(vec![ip]),
+                    Err(_) => lookup(host),
+                })
+                .as_ref()
+                .map(|ips| ips.iter().any(|ip| ip_in_network(*ip, *network, *prefix))),
+        }
+    }
+}
+
+/// 编译后的规则集
+#[derive(Debug)]
+enum Matcher {
+    Rules(Vec<(HostMatcher, ProxyDecision)>),
+    Pac {
+        script: String,
+        /// 复用的 PAC 运行时；执行失败后丢弃，下次求值时重建
+        engine: Mutex<Option<PacEngine>>,
+        cache: Mutex<HashMap<String, ProxyDecision>>,
+    },
+}
+
+/// 编译后的上游代理规则，供 HTTP 客户端按请求求值
+#[derive(Debug)]
+pub struct ProxyRuleSet {
+    matcher: Matcher,
+}
+
+impl ProxyRuleSet {
+    /// 校验并编译规则配置
+    pub fn compile(config: &UpstreamProxyRules) -> Result<Self, String> {
+        let matcher = match config.mode.as_str() {
+            PROXY_RULE_MODE_RULES => {
+                let mut rules = Vec::with_capacity(config.rules.len());
+                for (index, rule) in config.rules.iter().enumerate() {
+                    let matcher =
+                        parse_matcher(rule).map_err(|e| format!("Rule #{}: {e}", index + 1))?;
+                    let decision = parse_target(&rule.proxy)
+                        .map_err(|e| format!("Rule #{}: {e}", index + 1))?;
+                    rules.push((matcher, decision));
+                }
+                Matcher::Rules(rules)
+            }
+            PROXY_RULE_MODE_PAC => {
+                let script = config
+                    .pac_script
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .ok_or_else(|| "PAC script is empty".to_string())?
+                    .to_string();
+                // 用一个示例 URL 试跑，提前暴露语法错误或缺失 FindProxyForURL
+                let engine = PacEngine::load(&script)?;
+                engine.find_proxy("https://example.com/", "example.com")?;
+                Matcher::Pac {
+                    script,
+                    engine: Mutex::new(Some(engine)),
+                    cache: Mutex::new(HashMap::new()),
+                }
+            }
+            other => return Err(format!("Unsupported proxy rule mode '{other}'")),
+        };
+        Ok(Self { matcher })
+    }
+
+    /// 为请求 URL 选择代理（可能执行 DNS 解析或 PAC 脚本，需在阻塞线程中调用）
+    pub fn decide(&self, url: &Url) -> ProxyDecision {
+        self.evaluate(url, true).unwrap_or(ProxyDecision::Default)
+    }
+
+    /// 仅凭缓存为请求 URL 选择代理，不会阻塞
+    ///
+    /// 需要 DNS 解析或执行 PAC 而缓存未命中时返回 None
+    pub fn try_decide(&self, url: &Url) -> Option<ProxyDecision> {
+        self.evaluate(url, false)
+    }
+
+    fn evaluate(&self, url: &Url, blocking: bool) -> Option<ProxyDecision> {
+        let Some(host) = url.host_str() else {
+            return Some(ProxyDecision::Default);
+        };
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_ascii_lowercase();
+
+        match &self.matcher {
+            Matcher::Rules(rules) => {
+                let lookup: fn(&str) -> Option<Vec<IpAddr>> = if blocking {
+                    |host| Some(resolve_host(host))
+                } else {
+                    cached_host
+                };
+                let resolved = OnceCell::new();
+                for (matcher, decision) in rules {
+                    if matcher.matches(&host, &resolved, lookup)? {
+                        return Some(decision.clone());
+                    }
+                }
+                Some(ProxyDecision::Default)
+            }
+            Matcher::Pac {
+                script,
+                engine,
+                cache,
+            } => {
+                let pac_url = pac_url(url, &host);
+                if let Some(decision) = cache.lock().ok().and_then(|c| c.get(&pac_url).cloned()) {
+                    return Some(decision);
+                }
+                if !blocking {
+                    return None;
+                }
+                let decision = match run_pac(engine, script, &pac_url, &host) {
+                    Ok(result) => parse_pac_result(&result),
+                    Err(e) => {
+                        log::warn!("[ProxyRules] PAC evaluation failed for {host}: {e}");
+                        ProxyDecision::Default
+                    }
+                };
+                if let Ok(mut cache) = cache.lock() {
+                    if cache.len() >= PAC_CACHE_LIMIT {
+                        cache.clear();
+                    }
+                    cache.insert(pac_url, decision.clone());
+                }
+                Some(decision)
+            }
+        }
+    }
+
+    /// 规则中引用的全部代理 URL（去重，保持顺序）
+    pub fn proxy_urls(&self) -> Vec<Url> {
+        let mut urls: Vec<Url> = Vec::new();
+        if let Matcher::Rules(rules) = &self.matcher {
+            for (_, decision) in rules {
+                if let ProxyDecision::Proxy(url) = decision {
+                    if !urls.contains(url) {
+                        urls.push(url.clone());
+                    }
+                }
+            }
+        }
+        urls
+    }
+}
+
+fn parse_matcher(rule: &UpstreamProxyRule) -> Result<HostMatcher, String> {
+    let pattern = rule.pattern.trim().to_ascii_lowercase();
+    if pattern.is_empty() {
+        return Err("pattern is empty".to_string());
+    }
+    match rule.match_type.as_str() {
+        RULE_MATCH_DOMAIN => Ok(HostMatcher::Domain(pattern)),
+        RULE_MATCH_DOMAIN_SUFFIX => {
+            let suffix = pattern
+                .trim_start_matches("*.")
+                .trim_start_matches('.')
+                .to_string();
+            if suffix.is_empty() {
+                return Err(format!("invalid domain suffix '{}'", rule.pattern));
+            }
+            Ok(HostMatcher::Suffix(suffix))
+        }
+        RULE_MATCH_CIDR => parse_cidr(&pattern).map(|(ip, prefix)| HostMatcher::Cidr(ip, prefix)),
+        other => Err(format!("unsupported match type '{other}'")),
+    }
+}
+
+pub(super) fn parse_cidr(value: &str) -> Result<(IpAddr, u8), String> {
+    let (ip, prefix) = match value.split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (value, None),
+    };
+    let ip: IpAddr = ip.parse().map_err(|_| format!("invalid CIDR '{value}'"))?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(p) => p
+            .parse::<u8>()
+            .ok()
+            .filter(|p| *p <= max)
+            .ok_or_else(|| format!("invalid CIDR prefix in '{value}'"))?,
+        None => max,
+    };
+    Ok((ip, prefix))
+}
+
+pub(super) fn ip_in_network(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+/// 解析规则目标：`direct` 或代理 URL
+fn parse_target(value: &str) -> Result<ProxyDecision, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case(PROXY_TARGET_DIRECT) {
+        return Ok(ProxyDecision::Direct);
+    }
+    let url = Url::parse(value).map_err(|e| format!("invalid proxy URL: {e}"))?;
+    if !["http", "https", "socks5", "socks5h"].contains(&url.scheme()) {
+        return Err(format!(
+            "unsupported proxy scheme '{}'. Supported: http, https, socks5, socks5h",
+            url.scheme()
+        ));
+    }
+    if url.host_str().is_none() {
+        return Err("proxy URL has no host".to_string());
+    }
+    Ok(ProxyDecision::Proxy(url))
+}
+
+/// 解析 PAC 返回值，如 `PROXY a:8080; SOCKS5 b:1080; DIRECT`
+///
+/// 按顺序取第一个可识别的项；不支持的项（如 SOCKS4）跳过。
+fn parse_pac_result(result: &str) -> ProxyDecision {
+    for entry in result.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let mut parts = entry.split_whitespace();
+        let kind = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let target = parts.next();
+        let scheme = match kind.as_str() {
+            "DIRECT" => return ProxyDecision::Direct,
+            "PROXY" | "HTTP" => "http",
+            "HTTPS" => "https",
+            "SOCKS" | "SOCKS5" => "socks5",
+            _ => continue,
+        };
+        if let Some(url) = target.and_then(|t| Url::parse(&format!("{scheme}://{t}")).ok()) {
+            return ProxyDecision::Proxy(url);
+        }
+    }
+    ProxyDecision::Default
+}
+
+/// PAC 标准辅助函数中可用纯 JS 实现的部分
+const PAC_PRELUDE: &str = r#"
+function isPlainHostName(host) { return host.indexOf('.') < 0; }
+function dnsDomainIs(host, domain) {
+    return host.length >= domain.length &&
+        host.substring(host.length - domain.length) === domain;
+}
+function localHostOrDomainIs(host, hostdom) {
+    return host === hostdom || hostdom.lastIndexOf(host + '.', 0) === 0;
+}
+function dnsDomainLevels(host) { return host.split('.').length - 1; }
+function isResolvable(host) { return dnsResolve(host) !== null; }
+function convertAddr(ip) {
+    var parts = ip.split('.');
+    return ((parts[0] << 24) | (parts[1] << 16) | (parts[2] << 8) | parts[3]) >>> 0;
+}
+function isInNet(host, pattern, mask) {
+    var ip = /^\d+\.\d+\.\d+\.\d+$/.test(host) ? host : dnsResolve(host);
+    if (!ip) { return false; }
+    var m = convertAddr(mask);
+    return ((convertAddr(ip) & m) >>> 0) === ((convertAddr(pattern) & m) >>> 0);
+}
+"#;
+
+/// 传给 PAC 脚本的 URL：只保留协议、主机与端口（与浏览器一致，避免路径与查询参数泄露给脚本）
+fn pac_url(url: &Url, host: &str) -> String {
+    let host = if host.contains(':') {
+        format!("[{host}]")
+    } else {
+        host.to_string()
+    };
+    match url.port() {
+        Some(port) => format!("{}://{host}:{port}/", url.scheme()),
+        None => format!("{}://{host}/", url.scheme()),
+    }
+}
+
+/// 使用复用的 PAC 运行时求值，运行时缺失（或上次失败被丢弃）时重建
+fn run_pac(
+    engine: &Mutex<Option<PacEngine>>,
+    script: &str,
+    url: &str,
+    host: &str,
+) -> Result<String, String> {
+    let mut engine = engine
+        .lock()
+        .map_err(|_| "PAC engine lock poisoned".to_string())?;
+    if engine.is_none() {
+        *engine = Some(PacEngine::load(script)?);
+    }
+    let result = engine
+        .as_ref()
+        .map(|e| e.find_proxy(url, host))
+        .unwrap_or_else(|| Err("PAC engine unavailable".to_string()));
+    if result.is_err() {
+        // 超时或异常后脚本的全局状态不可信，下次重新加载
+        *engine = None;
+    }
+    result
+}
+
+/// 已加载 PAC 脚本的 QuickJS 运行时（带内存上限与执行时间中断）
+struct PacEngine {
+    context: Context,
+    /// 当前调用的截止时间（相对 base 的毫秒数）
+    deadline: Arc<AtomicU64>,
+    base: Instant,
+}
+
+impl std::fmt::Debug for PacEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacEngine").finish_non_exhaustive()
+    }
+}
+
+impl PacEngine {
+    /// 创建运行时、注册 PAC 辅助函数并执行脚本
+    fn load(script: &str) -> Result<Self, String> {
+        let runtime = Runtime::new().map_err(|e| format!("Failed to create JS runtime: {e}"))?;
+        runtime.set_memory_limit(PAC_MEMORY_LIMIT);
+        let base = Instant::now();
+        let deadline = Arc::new(AtomicU64::new(u64::MAX));
+        {
+            let deadline = deadline.clone();
+            runtime.set_interrupt_handler(Some(Box::new(move || {
+                base.elapsed().as_millis() as u64 > deadline.load(Ordering::Relaxed)
+            })));
+        }
+        let context =
+            Context::full(&runtime).map_err(|e| format!("Failed to create JS context: {e}"))?;
+        let engine = Self {
+            context,
+            deadline,
+            base,
+        };
+
+        engine.arm();
+        let loaded = engine.context.with(|ctx| {
+            let globals = ctx.globals();
+            let register_failed =
+                |e: rquickjs::Error| format!("Failed to register PAC helper: {e}");
+            globals
+                .set("dnsResolve", Function::new(ctx.clone(), dns_resolve))
+                .map_err(register_failed)?;
+            globals
+                .set("myIpAddress", Function::new(ctx.clone(), my_ip_address))
+                .map_err(register_failed)?;
+            globals
+                .set(
+                    "shExpMatch",
+                    Function::new(ctx.clone(), |text: String, pattern: String| {
+                        glob_match(&pattern, &text)
+                    }),
+                )
+                .map_err(register_failed)?;
+
+            ctx.eval::<(), _>(PAC_PRELUDE)
+                .map_err(|e| format!("Failed to load PAC helpers: {e}"))?;
+            ctx.eval::<(), _>(script)
+                .map_err(|e| format!("PAC script evaluation failed: {e}"))?;
+            globals
+                .get::<_, Function>("FindProxyForURL")
+                .map(|_| ())
+                .map_err(|_| "PAC script does not define FindProxyForURL".to_string())
+        });
+        if engine.disarm() {
+            return Err(timeout_message("PAC script evaluation"));
+        }
+        loaded.map(|_| engine)
+    }
+
+    /// 执行 FindProxyForURL（超过 `PAC_TIMEOUT` 时中断）
+    fn find_proxy(&self, url: &str, host: &str) -> Result<String, String> {
+        self.arm();
+        let result = self.context.with(|ctx| {
+            let find: Function = ctx
+                .globals()
+                .get("FindProxyForURL")
+                .map_err(|_| "PAC script does not define FindProxyForURL".to_string())?;
+            find.call::<_, String>((url, host))
+                .map_err(|e| format!("FindProxyForURL failed: {e}"))
+        });
+        if self.disarm() {
+            return Err(timeout_message("FindProxyForURL"));
+        }
+        result
+    }
+
+    fn arm(&self) {
+        let deadline = self.base.elapsed() + PAC_TIMEOUT;
+        self.deadline
+            .store(deadline.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// 解除截止时间，返回本次调用是否已超时
+    fn disarm(&self) -> bool {
+        let deadline = self.deadline.swap(u64::MAX, Ordering::Relaxed);
+        self.base.elapsed().as_millis() as u64 > deadline
+    }
+}
+
+fn timeout_message(what: &str) -> String {
+    format!("{what} timed out after {}ms", PAC_TIMEOUT.as_millis())
+}
+
+/// 读取未过期的 DNS 缓存（不触发解析）
+fn cached_host(host: &str) -> Option<Vec<IpAddr>> {
+    DNS_CACHE
+        .lock()
+        .ok()
+        .and_then(|c| c.get(host).cloned())
+        .filter(|(at, _)| at.elapsed() < DNS_CACHE_TTL)
+        .map(|(_, ips)| ips)
+}
+
+/// 解析主机地址：在独立线程中执行系统解析并限时等待，结果缓存 `DNS_CACHE_TTL`
+fn resolve_host(host: &str) -> Vec<IpAddr> {
+    if let Some(ips) = cached_host(host) {
+        return ips;
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let target = host.to_string();
+    std::thread::spawn(move || {
+        let ips: Vec<IpAddr> = (target.as_str(), 0)
+            .to_socket_addrs()
+            .map(|addrs| addrs.map(|addr| addr.ip()).collect())
+            .unwrap_or_default();
+        let _ = tx.send(ips);
+    });
+    let ips = rx.recv_timeout(DNS_TIMEOUT).unwrap_or_else(|_| {
+        log::debug!("[ProxyRules] DNS lookup for {host} timed out");
+        Vec::new()
+    });
+
+    if let Ok(mut cache) = DNS_CACHE.lock() {
+        if cache.len() >= PAC_CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(host.to_string(), (Instant::now(), ips.clone()));
+    }
+    ips
+}
+
+fn dns_resolve(host: String) -> Option<String> {
+    resolve_host(&host)
+        .into_iter()
+        .find(IpAddr::is_ipv4)
+        .map(|ip| ip.to_string())
+}
+
+fn my_ip_address() -> String {
+    MY_IP_ADDRESS.clone()
+}
+
+/// 本机出口地址（UDP connect 不发送数据包，只用于选路）
+fn detect_my_ip_address() -> String {
+    UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("8.8.8.8:53")?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|_| "127.0.0.1".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(match_type: &str, pattern: &str, proxy: &str) -> UpstreamProxyRule {
+        UpstreamProxyRule {
+            match_type: match_type.to_string(),
+            pattern: pattern.to_string(),
+            proxy: proxy.to_string(),
+        }
+    }
+
+    fn decide(rules: &ProxyRuleSet, url: &str) -> ProxyDecision {
+        rules.decide(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_rules_first_match_wins() {
+        let config = UpstreamProxyRules {
+            enabled: true,
+            rules: vec![
+                rule(RULE_MATCH_DOMAIN, "relay.corp.example", "direct"),
+                rule(
+                    RULE_MATCH_DOMAIN_SUFFIX,
+                    "*.corp.example",
+                    "http://10.0.0.1:3128",
+                ),
+                rule(RULE_MATCH_CIDR, "10.0.0.0/8", "direct"),
+                rule(
+                    RULE_MATCH_DOMAIN_SUFFIX,
+                    "anthropic.com",
+                    "socks5://127.0.0.1:1080",
+                ),
+            ],
+            ..Default::default()
+        };
+        let rules = ProxyRuleSet::compile(&config).unwrap();
+
+        assert_eq!(
+            decide(&rules, "https://relay.corp.example/v1"),
+            ProxyDecision::Direct
+        );
+        assert_eq!(
+            decide(&rules, "https://api.corp.example/v1"),
+            ProxyDecision::Proxy(Url::parse("http://10.0.0.1:3128").unwrap())
+        );
+        assert_eq!(
+            decide(&rules, "http://10.2.3.4:8080/"),
+            ProxyDecision::Direct
+        );
+        assert_eq!(
+            decide(&rules, "https://API.Anthropic.com/v1/messages"),
+            ProxyDecision::Proxy(Url::parse("socks5://127.0.0.1:1080").unwrap())
+        );
+        // 后缀匹配必须在标签边界上
+        assert_eq!(
+            decide(&rules, "https://notanthropic.com/"),
+            ProxyDecision::Default
+        );
+        assert_eq!(rules.proxy_urls().len(), 2);
+    }
+
+    #[test]
+    fn test_compile_rejects_invalid_rules() {
+        for bad in [
+            rule(RULE_MATCH_CIDR, "10.0.0.0/33", "direct"),
+            rule(RULE_MATCH_DOMAIN_SUFFIX, "*.", "direct"),
+            rule("regex", "foo", "direct"),
+            rule(RULE_MATCH_DOMAIN, "example.com", "ftp://127.0.0.1:21"),
+        ] {
+            let config = UpstreamProxyRules {
+                enabled: true,
+                rules: vec![bad],
+                ..Default::default()
+            };
+            assert!(ProxyRuleSet::compile(&config).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ipv6_cidr() {
+        assert!(ip_in_network(
+            "fd00::1".parse().unwrap(),
+            "fd00::".parse().unwrap(),
+            8
+        ));
+        assert!(!ip_in_network(
+            "2001:db8::1".parse().unwrap(),
+            "fd00::".parse().unwrap(),
+            8
+        ));
+        assert!(ip_in_network(
+            "1.2.3.4".parse().unwrap(),
+            "0.0.0.0".parse().unwrap(),
+            0
+        ));
+    }
+
+    #[test]
+    fn test_parse_pac_result() {
+        assert_eq!(parse_pac_result("DIRECT"), ProxyDecision::Direct);
+        assert_eq!(
+            parse_pac_result("SOCKS4 a:1080; PROXY proxy.local:8080; DIRECT"),
+            ProxyDecision::Proxy(Url::parse("http://proxy.local:8080").unwrap())
+        );
+        assert_eq!(
+            parse_pac_result("SOCKS5 127.0.0.1:1080"),
+            ProxyDecision::Proxy(Url::parse("socks5://127.0.0.1:1080").unwrap())
+        );
+        assert_eq!(parse_pac_result(""), ProxyDecision::Default);
+    }
+
+    #[test]
+    fn test_pac_script() {
+        let config = UpstreamProxyRules {
+            enabled: true,
+            mode: PROXY_RULE_MODE_PAC.to_string(),
+            pac_script: Some(
+                r#"function FindProxyForURL(url, host) {
+                    if (dnsDomainIs(host, ".corp.example") || isInNet(host, "10.0.0.0", "255.0.0.0")) {
+                        return "DIRECT";
+                    }
+                    if (shExpMatch(host, "*.openai.com")) {
+                        return "SOCKS5 127.0.0.1:1080";
+                    }
+                    return "PROXY 127.0.0.1:7890";
+                }"#
+                .to_string(),
+            ),
+            ..Default::default()
+        };
+        let rules = ProxyRuleSet::compile(&config).unwrap();
+
+        assert_eq!(
+            decide(&rules, "https://relay.corp.example/"),
+            ProxyDecision::Direct
+        );
+        assert_eq!(decide(&rules, "http://10.1.1.1/"), ProxyDecision::Direct);
+        assert_eq!(
+            decide(&rules, "https://api.openai.com/v1"),
+            ProxyDecision::Proxy(Url::parse("socks5://127.0.0.1:1080").unwrap())
+        );
+        assert_eq!(
+            decide(&rules, "https://api.anthropic.com/v1"),
+            ProxyDecision::Proxy(Url::parse("http://127.0.0.1:7890").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_pac_infinite_loop_is_interrupted() {
+        let config = UpstreamProxyRules {
+            enabled: true,
+            mode: PROXY_RULE_MODE_PAC.to_string(),
+            pac_script: Some("function FindProxyForURL(url, host) { while (true) {} }".to_string()),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let err = ProxyRuleSet::compile(&config).unwrap_err();
+        assert!(err.contains("timed out"), "{err}");
+        assert!(started.elapsed() < PAC_TIMEOUT * 3);
+    }
+
+    #[test]
+    fn test_cidr_rule_resolves_hostnames() {
+        let config = UpstreamProxyRules {
+            enabled: true,
+            rules: vec![rule(RULE_MATCH_CIDR, "127.0.0.0/8", "direct")],
+            ..Default::default()
+        };
+        let rules = ProxyRuleSet::compile(&config).unwrap();
+
+        assert_eq!(
+            decide(&rules, "http://localhost:8080/"),
+            ProxyDecision::Direct
+        );
+        assert_eq!(
+            decide(&rules, "https://unresolvable.invalid/"),
+            ProxyDecision::Default
+        );
+    }
+
+    #[test]
+    fn test_pac_without_entry_point_is_rejected() {
+        let config = UpstreamProxyRules {
+            enabled: true,
+            mode: PROXY_RULE_MODE_PAC.to_string(),
+            pac_script: Some("var x = 1;".to_string()),
+            ..Default::default()
+        };
+        assert!(ProxyRuleSet::compile(&config).is_err());
+    }
+
+    #[test]
+    fn test_pac_cache_warms_by_origin() {
+        let config = UpstreamProxyRules {
+            enabled: true,
+            mode: PROXY_RULE_MODE_PAC.to_string(),
+            pac_script: Some(
+                r#"function FindProxyForURL(url, host) {
+                    return url.indexOf("/v1") >= 0 ? "DIRECT" : "PROXY 127.0.0.1:7890";
+                }"#
+                .to_string(),
+            ),
+            ..Default::default()
+        };
+        let rules = ProxyRuleSet::compile(&config).unwrap();
+        let url = Url::parse("https://api.example.com/v1/messages?beta=true").unwrap();
+
+        // 未预热时不阻塞求值
+        assert_eq!(rules.try_decide(&url), None);
+        // 脚本只看到协议与主机，路径不影响结果
+        let proxy = ProxyDecision::Proxy(Url::parse("http://127.0.0.1:7890").unwrap());
+        assert_eq!(rules.decide(&url), proxy);
+        let other_path = Url::parse("https://api.example.com/v1/models").unwrap();
+        assert_eq!(rules.try_decide(&other_path), Some(proxy));
+        // 端口不同视为不同的来源
+        let other_port = Url::parse("https://api.example.com:8443/v1").unwrap();
+        assert_eq!(rules.try_decide(&other_port), None);
+    }
+
+    #[test]
+    fn test_cidr_rule_needs_warm_dns_cache() {
+        let config = UpstreamProxyRules {
+            enabled: true,
+            rules: vec![
+                rule(RULE_MATCH_DOMAIN, "api.example.com", "direct"),
+                rule(RULE_MATCH_CIDR, "10.0.0.0/8", "direct"),
+            ],
+            ..Default::default()
+        };
+        let rules = ProxyRuleSet::compile(&config).unwrap();
+
+        // 前面的规则已命中或目标本身是 IP 时无需解析
+        assert_eq!(
+            rules.try_decide(&Url::parse("https://api.example.com/").unwrap()),
+            Some(ProxyDecision::Direct)
+        );
+        assert_eq!(
+            rules.try_decide(&Url::parse("http://10.1.2.3/").unwrap()),
+            Some(ProxyDecision::Direct)
+        );
+        // 域名需要解析，缓存未命中时交给阻塞线程
+        let url = Url::parse("https://never-resolved.invalid/").unwrap();
+        assert_eq!(rules.try_decide(&url), None);
+        assert_eq!(rules.decide(&url), ProxyDecision::Default);
+        assert_eq!(rules.try_decide(&url), Some(ProxyDecision::Default));
+    }
+}
@@ -4,6 +4,7 @@
 
 use super::parser::TokenUsage;
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
 /// 成本明细
@@ -16,6 +17,20 @@ pub struct CostBreakdown {
     pub total_cost: Decimal,
 }
 
+impl CostBreakdown {
+    /// 按比例缩放各项成本（用于币种换算，保留 8 位小数）
+    pub fn scaled(&self, factor: Decimal) -> Self {
+        let scale = |value: Decimal| (value * factor).round_dp(8);
+        Self {
+            input_cost: scale(self.input_cost),
+            output_cost: scale(self.output_cost),
+            cache_read_cost: scale(self.cache_read_cost),
+            cache_creation_cost: scale(self.cache_creation_cost),
+            total_cost: scale(self.total_cost),
+        }
+    }
+}
+
 /// 模型定价信息
 #[derive(Debug, Clone)]
 pub struct ModelPricing {
@@ -23,6 +38,28 @@ pub struct ModelPricing {
     pub output_cost_per_million: Decimal,
     pub cache_read_cost_per_million: Decimal,
     pub cache_creation_cost_per_million: Decimal,
+    /// 按输入规模分档的价格（按阈值升序）
+    pub tiers: Vec<(u64, ModelPricing)>,
+}
+
+/// 按输入规模分档的价格（存储在 model_pricing.tiers 中的 JSON 格式）
+///
+/// 与 Anthropic / Gemini 的长上下文计费一致：prompt 总量超过阈值时，整个请求按该档计价
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingTier {
+    /// prompt token 总数（含缓存读写）超过该值时生效
+    pub above_input_tokens: u64,
+    pub input_cost_per_million: String,
+    pub output_cost_per_million: String,
+    #[serde(default = "zero_price")]
+    pub cache_read_cost_per_million: String,
+    #[serde(default = "zero_price")]
+    pub cache_creation_cost_per_million: String,
+}
+
+fn zero_price() -> String {
+    "0".to_string()
 }
 
 /// 成本计算器
@@ -41,6 +78,8 @@ impl CostCalculator {
     /// - cache_read_cost: cache_read_tokens × 缓存读取价格
     /// - 这样避免缓存部分被重复计费
     /// - total_cost: 各项成本之和 × 倍率（倍率只作用于最终总价）
+    ///
+    /// 只使用 `pricing` 的基础价格；分档定价需先通过 [`ModelPricing::for_prompt_tokens`] 选档
     pub fn calculate(
         usage: &TokenUsage,
         pricing: &ModelPricing,
@@ -98,8 +137,42 @@ impl ModelPricing {
             output_cost_per_million: Decimal::from_str(output)?,
             cache_read_cost_per_million: Decimal::from_str(cache_read)?,
             cache_creation_cost_per_million: Decimal::from_str(cache_creation)?,
+            tiers: Vec::new(),
         })
     }
+
+    /// 附加分档价格（阈值为 0 或重复时视为无效）
+    pub fn with_tiers(mut self, tiers: &[PricingTier]) -> Result<Self, String> {
+        let mut parsed = Vec::with_capacity(tiers.len());
+        for tier in tiers {
+            if tier.above_input_tokens == 0 {
+                return Err("分档阈值必须大于 0".to_string());
+            }
+            let pricing = Self::from_strings(
+                &tier.input_cost_per_million,
+                &tier.output_cost_per_million,
+                &tier.cache_read_cost_per_million,
+                &tier.cache_creation_cost_per_million,
+            )
+            .map_err(|e| format!("分档价格无效: {e}"))?;
+            parsed.push((tier.above_input_tokens, pricing));
+        }
+        parsed.sort_by_key(|(threshold, _)| *threshold);
+        if parsed.windows(2).any(|w| w[0].0 == w[1].0) {
+            return Err("分档阈值重复".to_string());
+        }
+        self.tiers = parsed;
+        Ok(self)
+    }
+
+    /// 按 prompt 总量选出适用的价格档（未超过任何阈值时为基础价格）
+    pub fn for_prompt_tokens(&self, prompt_tokens: u64) -> &ModelPricing {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|(threshold, _)| prompt_tokens > *threshold)
+            .map_or(self, |(_, pricing)| pricing)
+    }
 }
 
 #[cfg(test)]
@@ -192,4 +265,46 @@ mod tests {
         assert!(cost.total_cost > Decimal::ZERO);
         assert!(cost.total_cost.to_string().len() > 2); // 确保保留了小数位
     }
+
+    #[test]
+    fn test_tiered_pricing() {
+        let tier = |above: u64, input: &str, output: &str| PricingTier {
+            above_input_tokens: above,
+            input_cost_per_million: input.to_string(),
+            output_cost_per_million: output.to_string(),
+            cache_read_cost_per_million: zero_price(),
+            cache_creation_cost_per_million: zero_price(),
+        };
+        let pricing = ModelPricing::from_strings("3", "15", "0.3", "3.75")
+            .unwrap()
+            .with_tiers(&[tier(500_000, "9", "30"), tier(200_000, "6", "22.5")])
+            .unwrap();
+
+        // 阈值本身仍按基础价格，超过后整单按对应档位
+        assert_eq!(
+            pricing.for_prompt_tokens(200_000).input_cost_per_million,
+            Decimal::from(3)
+        );
+        assert_eq!(
+            pricing.for_prompt_tokens(200_001).output_cost_per_million,
+            Decimal::from_str("22.5").unwrap()
+        );
+        assert_eq!(
+            pricing.for_prompt_tokens(800_000).input_cost_per_million,
+            Decimal::from(9)
+        );
+
+        assert!(ModelPricing::from_strings("1", "1", "0", "0")
+            .unwrap()
+            .with_tiers(&[tier(0, "1", "1")])
+            .is_err());
+        assert!(ModelPricing::from_strings("1", "1", "0", "0")
+            .unwrap()
+            .with_tiers(&[tier(10, "1", "1"), tier(10, "2", "2")])
+            .is_err());
+        assert!(ModelPricing::from_strings("1", "1", "0", "0")
+            .unwrap()
+            .with_tiers(&[tier(10, "abc", "1")])
+            .is_err());
+    }
 }
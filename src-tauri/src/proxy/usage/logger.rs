@@ -4,6 +4,9 @@ use super::calculator::{CostBreakdown, CostCalculator, ModelPricing};
 use super::parser::TokenUsage;
 use crate::database::Database;
 use crate::error::AppError;
+use crate::provider::Provider;
+use crate::proxy::mock_upstream::{self, MOCK_SOURCE};
+use crate::services::currency::{self, BilledCost};
 use crate::services::usage_stats::find_model_pricing_row;
 use rust_decimal::Decimal;
 use std::{str::FromStr, time::SystemTime};
@@ -33,24 +36,73 @@ pub struct RequestLog {
     pub request_body: Option<String>,
     /// Response body (optional, for debugging and log viewing)
     pub response_body: Option<String>,
+    /// 命中的路由规则名称
+    pub routing_rule: Option<String>,
+    /// 是否由响应缓存直接返回（不计费）
+    pub from_cache: bool,
+    /// 发起请求的客户端令牌 ID（未启用客户端认证时为空）
+    pub client_token_id: Option<String>,
+    /// 触发的整流事件（thinking_signature / thinking_budget / context_overflow）
+    pub rectifier: Option<String>,
 }
 
 /// 使用量记录器
 pub struct UsageLogger<'a> {
     db: &'a Database,
+    client_token_id: Option<String>,
+    rectifier: Option<String>,
 }
 
 impl<'a> UsageLogger<'a> {
     pub fn new(db: &'a Database) -> Self {
-        Self { db }
+        Self {
+            db,
+            client_token_id: None,
+            rectifier: None,
+        }
+    }
+
+    /// 将后续记录归属到指定客户端令牌
+    pub fn with_client_token(mut self, client_token_id: Option<String>) -> Self {
+        self.client_token_id = client_token_id;
+        self
+    }
+
+    /// 为后续记录标注触发的整流事件
+    pub fn with_rectifier(mut self, rectifier: Option<&str>) -> Self {
+        self.rectifier = rectifier.map(str::to_string);
+        self
     }
 
     /// 记录成功的请求
+    ///
+    /// 启用 Mock 上游的供应商不产生真实消费：记为 `mock` 来源且成本为零
     pub fn log_request(&self, log: &RequestLog) -> Result<(), AppError> {
+        let provider = self
+            .db
+            .get_provider_by_id(&log.provider_id, &log.app_type)
+            .ok()
+            .flatten();
+        let is_mock = provider
+            .as_ref()
+            .is_some_and(|p| mock_upstream::enabled_config(p).is_some());
+        let source = if is_mock { MOCK_SOURCE } else { "proxy" };
+
+        let billed = log
+            .cost
+            .as_ref()
+            .filter(|_| !is_mock)
+            .and_then(|cost| self.resolve_billing(provider.as_ref()?, cost));
+        let cost = billed
+            .as_ref()
+            .map(|b| &b.usd)
+            .or(log.cost.as_ref())
+            .filter(|_| !is_mock);
+
         let conn = crate::database::lock_conn!(self.db.conn);
 
         let (input_cost, output_cost, cache_read_cost, cache_creation_cost, total_cost) =
-            if let Some(cost) = &log.cost {
+            if let Some(cost) = cost {
                 (
                     cost.input_cost.to_string(),
                     cost.output_cost.to_string(),
@@ -82,8 +134,9 @@ impl<'a> UsageLogger<'a> {
                 input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
                 input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
                 latency_ms, first_token_ms, status_code, error_message, session_id,
-                provider_type, is_streaming, cost_multiplier, request_body, response_body, created_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25)",
+                provider_type, is_streaming, cost_multiplier, request_body, response_body, cache_hit_rate, routing_rule, from_cache, created_at,
+                client_token_id, rectifier, billing_currency, billing_cost, source
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33)",
             rusqlite::params![
                 log.request_id,
                 log.provider_id,
@@ -109,11 +162,21 @@ impl<'a> UsageLogger<'a> {
                 log.cost_multiplier,
                 log.request_body,
                 log.response_body,
+                log.usage.cache_hit_rate(&log.app_type),
+                log.routing_rule,
+                log.from_cache as i64,
                 created_at,
+                log.client_token_id,
+                log.rectifier,
+                billed.as_ref().map(|b| b.currency.as_str()),
+                billed.as_ref().map(|b| b.amount.to_string()),
+                source,
             ],
         )
         .map_err(|e| AppError::Database(format!("记录请求日志失败: {e}")))?;
 
+        crate::proxy::metrics::global().record_request(log);
+
         Ok(())
     }
 
@@ -150,6 +213,10 @@ impl<'a> UsageLogger<'a> {
             cost_multiplier: "1.0".to_string(),
             request_body: None,
             response_body: None,
+            routing_rule: None,
+            from_cache: false,
+            client_token_id: self.client_token_id.clone(),
+            rectifier: self.rectifier.clone(),
         };
 
         self.log_request(&log)
@@ -171,6 +238,7 @@ impl<'a> UsageLogger<'a> {
         is_streaming: bool,
         session_id: Option<String>,
         provider_type: Option<String>,
+        routing_rule: Option<String>,
     ) -> Result<(), AppError> {
         let request_model = model.clone();
         let log = RequestLog {
@@ -191,23 +259,82 @@ impl<'a> UsageLogger<'a> {
             cost_multiplier: "1.0".to_string(),
             request_body: None,
             response_body: None,
+            routing_rule,
+            from_cache: false,
+            client_token_id: self.client_token_id.clone(),
+            rectifier: self.rectifier.clone(),
         };
 
         self.log_request(&log)
     }
 
-    /// 获取模型定价
-    pub fn get_model_pricing(&self, model_id: &str) -> Result<Option<ModelPricing>, AppError> {
-        let conn = crate::database::lock_conn!(self.db.conn);
-        let row = find_model_pricing_row(&conn, model_id)?;
-        match row {
-            Some((input, output, cache_read, cache_creation)) => {
-                ModelPricing::from_strings(&input, &output, &cache_read, &cache_creation)
-                    .map(Some)
-                    .map_err(|e| AppError::Database(format!("解析定价数据失败: {e}")))
+    /// 记录响应缓存命中（零用量、零成本）
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_cache_hit(
+        &self,
+        request_id: String,
+        provider_id: String,
+        app_type: String,
+        model: String,
+        latency_ms: u64,
+        is_streaming: bool,
+        session_id: Option<String>,
+        routing_rule: Option<String>,
+    ) -> Result<(), AppError> {
+        let request_model = model.clone();
+        let log = RequestLog {
+            request_id,
+            provider_id,
+            app_type,
+            model,
+            request_model,
+            usage: TokenUsage::default(),
+            cost: None,
+            latency_ms,
+            first_token_ms: None,
+            status_code: 200,
+            error_message: None,
+            session_id,
+            provider_type: None,
+            is_streaming,
+            cost_multiplier: "0".to_string(),
+            request_body: None,
+            response_body: None,
+            routing_rule,
+            from_cache: true,
+            client_token_id: self.client_token_id.clone(),
+            rectifier: self.rectifier.clone(),
+        };
+
+        self.log_request(&log)
+    }
+
+    /// 按供应商声明的计费币种折算成本（未声明或无法折算时返回 None）
+    fn resolve_billing(&self, provider: &Provider, cost: &CostBreakdown) -> Option<BilledCost> {
+        let config = provider.meta.as_ref()?.billing.as_ref()?;
+
+        let rate = match self.db.get_exchange_rate(&config.currency) {
+            Ok(rate) => rate,
+            Err(e) => {
+                log::warn!("[USG-004] 查询汇率失败 (currency={}): {e}", config.currency);
+                None
             }
-            None => Ok(None),
+        };
+        if rate.is_none() {
+            log::warn!(
+                "[USG-004] 汇率表缺少 {}，供应商 {} 的成本无法换算",
+                config.currency,
+                provider.id
+            );
         }
+
+        currency::apply_billing(cost, config, rate)
+    }
+
+    /// 获取模型当前生效的定价
+    pub fn get_model_pricing(&self, model_id: &str) -> Result<Option<ModelPricing>, AppError> {
+        let conn = crate::database::lock_conn!(self.db.conn);
+        find_model_pricing_row(&conn, model_id, chrono::Utc::now().timestamp())
     }
 
     /// 获取有效的倍率与计费模式来源（供应商优先，未配置则回退全局默认）
@@ -312,6 +439,7 @@ impl<'a> UsageLogger<'a> {
         is_streaming: bool,
         request_body: Option<String>,
         response_body: Option<String>,
+        routing_rule: Option<String>,
     ) -> Result<(), AppError> {
         let pricing = self.get_model_pricing(&pricing_model)?;
 
@@ -319,7 +447,11 @@ impl<'a> UsageLogger<'a> {
             log::warn!("[USG-002] 模型定价未找到，成本将记录为 0: {pricing_model}");
         }
 
-        let cost = CostCalculator::try_calculate(&usage, pricing.as_ref(), cost_multiplier);
+        // 长上下文等分档价格按本次请求的 prompt 总量选档
+        let pricing = pricing
+            .as_ref()
+            .map(|p| p.for_prompt_tokens(usage.prompt_tokens(&app_type)));
+        let cost = CostCalculator::try_calculate(&usage, pricing, cost_multiplier);
 
         let log = RequestLog {
             request_id,
@@ -339,6 +471,10 @@ impl<'a> UsageLogger<'a> {
             cost_multiplier: cost_multiplier.to_string(),
             request_body,
             response_body,
+            routing_rule,
+            from_cache: false,
+            client_token_id: self.client_token_id.clone(),
+            rectifier: self.rectifier.clone(),
         };
 
         self.log_request(&log)
@@ -391,6 +527,7 @@ mod tests {
             false,
             None, // request_body
             None, // response_body
+            None, // routing_rule
         )?;
 
         // 验证记录已插入
@@ -435,4 +572,70 @@ mod tests {
         assert_eq!(error, Some("Internal Server Error".to_string()));
         Ok(())
     }
+
+    #[test]
+    fn test_mock_provider_logs_are_marked_and_free() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let mut provider = Provider::with_id(
+            "mock-provider".to_string(),
+            "Mock".to_string(),
+            serde_json::json!({}),
+            None,
+        );
+        provider.meta = Some(crate::provider::ProviderMeta {
+            mock_upstream: Some(crate::provider::MockUpstreamConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        db.save_provider("claude", &provider)?;
+        {
+            let conn = crate::database::lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO model_pricing (model_id, display_name, input_cost_per_million, output_cost_per_million)
+                 VALUES ('test-model', 'Test Model', '3.0', '15.0')",
+                [],
+            )
+            .unwrap();
+        }
+
+        let usage = TokenUsage {
+            input_tokens: 1000,
+            output_tokens: 500,
+            ..Default::default()
+        };
+        UsageLogger::new(&db).log_with_calculation(
+            "req-mock".to_string(),
+            "mock-provider".to_string(),
+            "claude".to_string(),
+            "test-model".to_string(),
+            "test-model".to_string(),
+            "test-model".to_string(),
+            usage,
+            Decimal::from(1),
+            100,
+            None,
+            200,
+            None,
+            Some("claude".to_string()),
+            false,
+            None,
+            None,
+            None,
+        )?;
+
+        let conn = crate::database::lock_conn!(db.conn);
+        let (source, cost, input): (String, String, i64) = conn
+            .query_row(
+                "SELECT source, total_cost_usd, input_tokens FROM proxy_request_logs WHERE request_id = 'req-mock'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(source, MOCK_SOURCE);
+        assert_eq!(cost, "0");
+        assert_eq!(input, 1000);
+        Ok(())
+    }
 }
@@ -31,6 +31,30 @@ pub enum ApiType {
 }
 
 impl TokenUsage {
+    /// prompt 总 token 数（含缓存读写）
+    ///
+    /// Claude 的 input_tokens 不含缓存部分，需加上缓存读写；
+    /// Codex / Gemini 记录的是原始输入（已包含缓存命中部分）。
+    pub fn prompt_tokens(&self, app_type: &str) -> u64 {
+        let input = self.input_tokens as u64;
+        let cache_read = self.cache_read_tokens as u64;
+        let cache_creation = self.cache_creation_tokens as u64;
+        if app_type == "claude" {
+            input + cache_read + cache_creation
+        } else {
+            input.max(cache_read) + cache_creation
+        }
+    }
+
+    /// prompt cache 命中率（cache_read / prompt 总量），无 prompt token 时返回 None
+    pub fn cache_hit_rate(&self, app_type: &str) -> Option<f64> {
+        let prompt_tokens = self.prompt_tokens(app_type);
+        if prompt_tokens == 0 {
+            return None;
+        }
+        Some(self.cache_read_tokens as f64 / prompt_tokens as f64)
+    }
+
     /// 从 Claude API 非流式响应解析
     pub fn from_claude_response(body: &Value) -> Option<Self> {
         let usage = body.get("usage")?;
@@ -878,4 +902,28 @@ mod tests {
         assert_eq!(usage.output_tokens, 50);
         assert_eq!(usage.model, Some("gpt-4o".to_string()));
     }
+
+    #[test]
+    fn test_cache_hit_rate() {
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_read_tokens: 800,
+            cache_creation_tokens: 100,
+            model: None,
+        };
+        // Claude：input 不含缓存，prompt = 100 + 800 + 100
+        assert_eq!(usage.cache_hit_rate("claude"), Some(0.8));
+
+        // Codex：input 已包含缓存命中部分
+        let usage = TokenUsage {
+            input_tokens: 1000,
+            cache_read_tokens: 250,
+            cache_creation_tokens: 0,
+            ..usage
+        };
+        assert_eq!(usage.cache_hit_rate("codex"), Some(0.25));
+
+        assert_eq!(TokenUsage::default().cache_hit_rate("claude"), None);
+    }
 }
@@ -0,0 +1,574 @@
+//! 供应商余额历史与低余额告警
+//!
+//! 用量脚本每次查询成功后，把各套餐的剩余/已用/总额记录为时间序列，据此估算消耗速率
+//! （充值导致的余额上升不计入消耗）并预测耗尽时间。配置了低余额告警的供应商在余额
+//! 跌破阈值或耗尽时向前端发送 `provider-balance-low` 事件；开启耗尽降级后，
+//! 最近一次查询（不早于 [`EXHAUSTED_SNAPSHOT_MAX_AGE_SECS`]）余额耗尽的供应商在
+//! 故障转移队列中排到末尾。
+//!
+//! 套餐单位可能不同（如金额与次数），一次查询的总剩余额度只累计与首个有剩余额度的
+//! 有效套餐单位相同的套餐。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::provider::{Provider, UsageData, UsageResult};
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashSet;
+use tauri::{AppHandle, Emitter};
+
+/// 低余额事件
+pub const BALANCE_ALERT_EVENT: &str = "provider-balance-low";
+
+/// 估算消耗速率所用的历史窗口
+const BURN_RATE_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// 余额历史保留时长
+const HISTORY_RETENTION_SECS: i64 = 180 * 24 * 60 * 60;
+
+const DAY_SECS: f64 = 24.0 * 60.0 * 60.0;
+
+/// 耗尽降级依据的余额记录最长有效期，过期后不再降级，避免充值后一直排在队尾
+pub const EXHAUSTED_SNAPSHOT_MAX_AGE_SECS: i64 = 60 * 60;
+
+/// 只保留与同次查询首个有剩余额度的套餐单位相同的记录（`h` 为 provider_balance_history 别名）
+const PRIMARY_UNIT_FILTER: &str = "h.unit IS (
+    SELECT f.unit FROM provider_balance_history f
+    WHERE f.app_type = h.app_type AND f.provider_id = h.provider_id
+      AND f.recorded_at = h.recorded_at AND f.remaining IS NOT NULL
+    ORDER BY f.id ASC
+    LIMIT 1
+)";
+
+/// 单个套餐的一次余额记录
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceSnapshot {
+    pub provider_id: String,
+    pub app_type: String,
+    pub plan_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    pub recorded_at: i64,
+}
+
+/// 余额耗尽预测
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceForecast {
+    pub provider_id: String,
+    pub app_type: String,
+    /// 最近一次查询的剩余额度（各套餐之和）
+    pub remaining: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    pub recorded_at: i64,
+    /// 日均消耗，历史不足两次查询时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burn_rate_per_day: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runs_out_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runs_out_in_days: Option<f64>,
+}
+
+/// 低余额事件负载
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceAlert {
+    pub provider_id: String,
+    pub provider_name: String,
+    pub app_type: String,
+    pub remaining: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    pub exhausted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runs_out_in_days: Option<f64>,
+}
+
+/// 一次查询的总剩余额度及其单位
+///
+/// 只累计与首个有剩余额度的有效套餐单位相同的套餐，均未返回剩余时为空
+fn total_remaining(plans: &[UsageData]) -> Option<(f64, Option<&str>)> {
+    let valid = plans
+        .iter()
+        .filter(|plan| plan.is_valid != Some(false) && plan.remaining.is_some());
+    let unit = valid.clone().next()?.unit.as_deref();
+    let sum = valid
+        .filter(|plan| plan.unit.as_deref() == unit)
+        .filter_map(|plan| plan.remaining)
+        .sum();
+    Some((sum, unit))
+}
+
+/// 每秒消耗速率：只累计相邻两次查询间的余额下降，充值不抵扣消耗
+fn burn_rate(points: &[(i64, f64)]) -> Option<f64> {
+    let (first, last) = (points.first()?, points.last()?);
+    let span = last.0 - first.0;
+    if span <= 0 {
+        return None;
+    }
+    let burned: f64 = points
+        .windows(2)
+        .map(|pair| (pair[0].1 - pair[1].1).max(0.0))
+        .sum();
+    Some(burned / span as f64)
+}
+
+impl Database {
+    /// 记录一次查询的各有效套餐余额，并清理超过保留期的历史
+    pub fn record_balance_snapshot(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        plans: &[UsageData],
+        recorded_at: i64,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        for plan in plans.iter().filter(|plan| plan.is_valid != Some(false)) {
+            conn.execute(
+                "INSERT INTO provider_balance_history (
+                    provider_id, app_type, plan_name, remaining, used, total, unit, recorded_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    provider_id,
+                    app_type,
+                    plan.plan_name.as_deref().unwrap_or_default(),
+                    plan.remaining,
+                    plan.used,
+                    plan.total,
+                    plan.unit,
+                    recorded_at,
+                ],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+        conn.execute(
+            "DELETE FROM provider_balance_history WHERE recorded_at < ?1",
+            [recorded_at - HISTORY_RETENTION_SECS],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 获取供应商的余额历史（按时间升序）
+    pub fn get_balance_history(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        start: Option<i64>,
+        end: Option<i64>,
+    ) -> Result<Vec<BalanceSnapshot>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT provider_id, app_type, plan_name, remaining, used, total, unit, recorded_at
+                 FROM provider_balance_history
+                 WHERE app_type = ?1 AND provider_id = ?2
+                   AND (?3 IS NULL OR recorded_at >= ?3)
+                   AND (?4 IS NULL OR recorded_at <= ?4)
+                 ORDER BY recorded_at ASC, id ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let history = stmt
+            .query_map(params![app_type, provider_id, start, end], |row| {
+                Ok(BalanceSnapshot {
+                    provider_id: row.get(0)?,
+                    app_type: row.get(1)?,
+                    plan_name: row.get(2)?,
+                    remaining: row.get(3)?,
+                    used: row.get(4)?,
+                    total: row.get(5)?,
+                    unit: row.get(6)?,
+                    recorded_at: row.get(7)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(history)
+    }
+
+    /// 每次查询的总剩余额度及单位（按时间升序）
+    fn balance_points(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        since: i64,
+    ) -> Result<Vec<(i64, f64, Option<String>)>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let sql = format!(
+            "SELECT h.recorded_at, SUM(h.remaining), h.unit
+             FROM provider_balance_history h
+             WHERE h.app_type = ?1 AND h.provider_id = ?2 AND h.recorded_at >= ?3
+               AND h.remaining IS NOT NULL AND {PRIMARY_UNIT_FILTER}
+             GROUP BY h.recorded_at
+             ORDER BY h.recorded_at ASC"
+        );
+        let mut stmt = conn
+            .prepare(&sql)
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        let points = stmt
+            .query_map(params![app_type, provider_id, since], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(points)
+    }
+
+    /// 最近一次查询的总剩余额度
+    fn latest_balance(&self, app_type: &str, provider_id: &str) -> Result<Option<f64>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let sql = format!(
+            "SELECT SUM(h.remaining) FROM provider_balance_history h
+             WHERE h.app_type = ?1 AND h.provider_id = ?2 AND h.remaining IS NOT NULL
+               AND {PRIMARY_UNIT_FILTER}
+             GROUP BY h.recorded_at
+             ORDER BY h.recorded_at DESC
+             LIMIT 1"
+        );
+        conn.query_row(&sql, params![app_type, provider_id], |row| row.get(0))
+            .optional()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 按近 7 天的余额历史估算消耗速率与耗尽时间，窗口内没有记录时返回 None
+    pub fn get_balance_forecast(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        now: i64,
+    ) -> Result<Option<BalanceForecast>, AppError> {
+        let history = self.balance_points(app_type, provider_id, now - BURN_RATE_WINDOW_SECS)?;
+        let Some((recorded_at, remaining, unit)) = history.last().cloned() else {
+            return Ok(None);
+        };
+        // 套餐单位变化前后的余额不可比，只用与最近一次相同单位的记录估算速率
+        let points: Vec<(i64, f64)> = history
+            .into_iter()
+            .filter(|(_, _, point_unit)| *point_unit == unit)
+            .map(|(at, remaining, _)| (at, remaining))
+            .collect();
+
+        let rate = burn_rate(&points);
+        let seconds_left = rate
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| remaining.max(0.0) / rate);
+        Ok(Some(BalanceForecast {
+            provider_id: provider_id.to_string(),
+            app_type: app_type.to_string(),
+            remaining,
+            unit,
+            recorded_at,
+            burn_rate_per_day: rate.map(|rate| rate * DAY_SECS),
+            runs_out_at: seconds_left.map(|secs| recorded_at + secs as i64),
+            runs_out_in_days: seconds_left.map(|secs| secs / DAY_SECS),
+        }))
+    }
+
+    /// 最近一次查询余额已耗尽的供应商
+    ///
+    /// 记录早于 `now - EXHAUSTED_SNAPSHOT_MAX_AGE_SECS` 时视为过期：未开启自动查询的
+    /// 供应商充值后不会再有新记录，不能一直按耗尽处理
+    pub(crate) fn get_exhausted_provider_ids(
+        &self,
+        app_type: &str,
+        now: i64,
+    ) -> Result<HashSet<String>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let sql = format!(
+            "SELECT h.provider_id
+             FROM provider_balance_history h
+             JOIN (
+                 SELECT provider_id, MAX(recorded_at) AS latest
+                 FROM provider_balance_history
+                 WHERE app_type = ?1
+                 GROUP BY provider_id
+             ) l ON h.provider_id = l.provider_id AND h.recorded_at = l.latest
+             WHERE h.app_type = ?1 AND h.remaining IS NOT NULL AND l.latest >= ?2
+               AND {PRIMARY_UNIT_FILTER}
+             GROUP BY h.provider_id
+             HAVING SUM(h.remaining) <= 0"
+        );
+        let mut stmt = conn
+            .prepare(&sql)
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        let ids = stmt
+            .query_map(
+                params![app_type, now - EXHAUSTED_SNAPSHOT_MAX_AGE_SECS],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<HashSet<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(ids)
+    }
+
+    /// 记录用量脚本查询结果，余额新跌破阈值或刚耗尽时返回告警
+    pub fn record_usage_result(
+        &self,
+        app_type: &str,
+        provider: &Provider,
+        result: &UsageResult,
+        now: i64,
+    ) -> Result<Option<BalanceAlert>, AppError> {
+        let Some(plans) = result.data.as_deref().filter(|_| result.success) else {
+            return Ok(None);
+        };
+
+        let previous = self.latest_balance(app_type, &provider.id)?;
+        self.record_balance_snapshot(app_type, &provider.id, plans, now)?;
+
+        let Some(config) = provider
+            .meta
+            .as_ref()
+            .and_then(|m| m.balance_alert.as_ref())
+        else {
+            return Ok(None);
+        };
+        let Some((remaining, unit)) = total_remaining(plans) else {
+            return Ok(None);
+        };
+
+        let crossed_low = config
+            .low_threshold
+            .is_some_and(|t| remaining < t && previous.is_none_or(|p| p >= t));
+        let exhausted = remaining <= 0.0;
+        let just_exhausted = exhausted && previous.is_none_or(|p| p > 0.0);
+        if !crossed_low && !just_exhausted {
+            return Ok(None);
+        }
+
+        let forecast = self.get_balance_forecast(app_type, &provider.id, now)?;
+        Ok(Some(BalanceAlert {
+            provider_id: provider.id.clone(),
+            provider_name: provider.name.clone(),
+            app_type: app_type.to_string(),
+            remaining,
+            threshold: config.low_threshold,
+            unit: unit.map(str::to_string),
+            exhausted,
+            runs_out_in_days: forecast.and_then(|f| f.runs_out_in_days),
+        }))
+    }
+}
+
+/// 托盘告警键
+fn tray_alert_key(app_type: &str, provider_id: &str) -> String {
+    format!("balance:{app_type}:{provider_id}")
+}
+
+/// 托盘告警文本
+fn tray_message(alert: &BalanceAlert, language: &str) -> String {
+    let name = &alert.provider_name;
+    let remaining = format!(
+        "{:.2}{}",
+        alert.remaining,
+        alert
+            .unit
+            .as_deref()
+            .map(|unit| format!(" {unit}"))
+            .unwrap_or_default()
+    );
+    match (language, alert.exhausted) {
+        ("en", true) => format!("{name} balance exhausted"),
+        ("en", false) => format!("{name} balance low: {remaining}"),
+        ("ja", true) => format!("{name} の残高がなくなりました"),
+        ("ja", false) => format!("{name} の残高が少なくなっています: {remaining}"),
+        (_, true) => format!("{name} 余额已耗尽"),
+        (_, false) => format!("{name} 余额不足：{remaining}"),
+    }
+}
+
+/// 余额是否仍处于告警状态（低于阈值或已耗尽）；未配置告警或查询结果不含剩余额度时为 None
+fn still_low(provider: &Provider, result: &UsageResult) -> Option<bool> {
+    let plans = result.data.as_deref().filter(|_| result.success)?;
+    let (remaining, _) = total_remaining(plans)?;
+    let config = provider
+        .meta
+        .as_ref()
+        .and_then(|m| m.balance_alert.as_ref());
+    Some(config.is_some_and(|config| {
+        remaining <= 0.0 || config.low_threshold.is_some_and(|t| remaining < t)
+    }))
+}
+
+/// 向前端发送低余额事件，并在托盘提示中显示
+pub fn notify_balance_alert(app: &AppHandle, alert: &BalanceAlert) {
+    log::info!(
+        "[USG-008] 供应商 {} 余额{}：{}",
+        alert.provider_name,
+        if alert.exhausted {
+            "已耗尽"
+        } else {
+            "不足"
+        },
+        alert.remaining
+    );
+    if let Err(e) = app.emit(BALANCE_ALERT_EVENT, alert) {
+        log::warn!("[USG-008] 发送低余额事件失败: {e}");
+    }
+    let language = crate::settings::get_settings().language;
+    crate::tray::set_tray_alert(
+        app,
+        &tray_alert_key(&alert.app_type, &alert.provider_id),
+        Some(tray_message(alert, language.as_deref().unwrap_or("zh"))),
+    );
+}
+
+/// 余额恢复（充值或调整阈值、关闭告警）后清除该供应商的托盘告警
+///
+/// 查询失败或未返回剩余额度时无法判断，保持原状
+pub fn clear_recovered_balance_alert(
+    app: &AppHandle,
+    app_type: &str,
+    provider: &Provider,
+    result: &UsageResult,
+) {
+    if still_low(provider, result) == Some(false) {
+        crate::tray::set_tray_alert(app, &tray_alert_key(app_type, &provider.id), None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{BalanceAlertConfig, ProviderMeta};
+    use serde_json::json;
+
+    fn plan(remaining: f64) -> UsageData {
+        UsageData {
+            plan_name: Some("main".to_string()),
+            extra: None,
+            is_valid: Some(true),
+            invalid_message: None,
+            total: Some(100.0),
+            used: Some(100.0 - remaining),
+            remaining: Some(remaining),
+            unit: Some("USD".to_string()),
+        }
+    }
+
+    fn usage(remaining: f64) -> UsageResult {
+        UsageResult {
+            success: true,
+            data: Some(vec![plan(remaining)]),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_still_low_tracks_recovery() {
+        let mut provider = Provider::with_id("p1".into(), "Relay".into(), json!({}), None);
+        assert_eq!(still_low(&provider, &usage(0.0)), Some(false));
+
+        provider.meta = Some(ProviderMeta {
+            balance_alert: Some(BalanceAlertConfig {
+                low_threshold: Some(20.0),
+                demote_when_exhausted: false,
+            }),
+            ..Default::default()
+        });
+        assert_eq!(still_low(&provider, &usage(10.0)), Some(true));
+        assert_eq!(still_low(&provider, &usage(50.0)), Some(false));
+        let failed = UsageResult {
+            success: false,
+            data: None,
+            error: Some("timeout".to_string()),
+        };
+        assert_eq!(still_low(&provider, &failed), None);
+    }
+
+    #[test]
+    fn test_burn_rate_ignores_top_ups() {
+        let day = 24 * 60 * 60;
+        // 两天内消耗 30，中途充值 50 不抵扣消耗
+        let points = [(0, 100.0), (day, 80.0), (day + 1, 130.0), (2 * day, 120.0)];
+        let rate = burn_rate(&points).unwrap();
+        assert!((rate * DAY_SECS - 15.0).abs() < 1e-9);
+        assert!(burn_rate(&points[..1]).is_none());
+    }
+
+    #[test]
+    fn test_low_balance_alert_and_forecast() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let mut provider = Provider::with_id("p1".into(), "Relay".into(), json!({}), None);
+        provider.meta = Some(ProviderMeta {
+            balance_alert: Some(BalanceAlertConfig {
+                low_threshold: Some(20.0),
+                demote_when_exhausted: true,
+            }),
+            ..Default::default()
+        });
+        let day = 24 * 60 * 60;
+        let now = 100 * day;
+
+        assert!(db
+            .record_usage_result("claude", &provider, &usage(50.0), now - 2 * day)?
+            .is_none());
+        let alert = db
+            .record_usage_result("claude", &provider, &usage(10.0), now - day)?
+            .expect("跌破阈值时告警");
+        assert!(!alert.exhausted);
+        assert_eq!(alert.threshold, Some(20.0));
+        assert!((alert.runs_out_in_days.unwrap() - 0.25).abs() < 1e-9);
+
+        // 仍低于阈值但未耗尽，不重复告警
+        assert!(db
+            .record_usage_result("claude", &provider, &usage(5.0), now - day / 2)?
+            .is_none());
+        assert!(db.get_exhausted_provider_ids("claude", now)?.is_empty());
+
+        let alert = db
+            .record_usage_result("claude", &provider, &usage(0.0), now)?
+            .expect("耗尽时告警");
+        assert!(alert.exhausted);
+        assert!(db.get_exhausted_provider_ids("claude", now)?.contains("p1"));
+        // 记录过期后不再降级
+        assert!(db
+            .get_exhausted_provider_ids("claude", now + EXHAUSTED_SNAPSHOT_MAX_AGE_SECS + 1)?
+            .is_empty());
+
+        let forecast = db.get_balance_forecast("claude", "p1", now)?.unwrap();
+        assert_eq!(forecast.remaining, 0.0);
+        assert!((forecast.burn_rate_per_day.unwrap() - 25.0).abs() < 1e-9);
+        assert_eq!(db.get_balance_history("claude", "p1", None, None)?.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remaining_only_sums_matching_units() -> Result<(), AppError> {
+        let requests = UsageData {
+            plan_name: Some("requests".to_string()),
+            remaining: Some(500.0),
+            unit: Some("requests".to_string()),
+            ..plan(0.0)
+        };
+        let plans = vec![plan(0.0), requests, plan(3.0)];
+        assert_eq!(total_remaining(&plans), Some((3.0, Some("USD"))));
+
+        // 剩余次数不能抵消已耗尽的金额余额
+        let db = Database::memory()?;
+        db.record_balance_snapshot("claude", "p1", &plans, 100)?;
+        assert_eq!(db.latest_balance("claude", "p1")?, Some(3.0));
+        let forecast = db.get_balance_forecast("claude", "p1", 100)?.unwrap();
+        assert_eq!(forecast.remaining, 3.0);
+        assert_eq!(forecast.unit.as_deref(), Some("USD"));
+
+        db.record_balance_snapshot("claude", "p2", &plans[..2], 100)?;
+        assert!(db.get_exhausted_provider_ids("claude", 100)?.contains("p2"));
+        Ok(())
+    }
+}
@@ -0,0 +1,486 @@
+//! 全局 / 按应用的花费预算
+//!
+//! 供应商级限额只约束单个供应商，预算则汇总一个应用（或全部应用）在当日/当月的花费。
+//! 每个预算配置若干告警阈值（百分比），越过阈值时向前端发送 `budget-threshold-crossed`
+//! 事件并更新托盘提示；同一周期内每个阈值只通知一次。
+//! 月度预算按近 7 天（不足 7 天时为本月至今）的花费速率预测月末花费。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::services::usage_stats::{usage_rows_sql, CurrencyCost};
+use crate::time_util::{local_midnight, period_start};
+use chrono::{DateTime, Datelike, Local, Months};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+/// 预算阈值越过事件
+pub const BUDGET_ALERT_EVENT: &str = "budget-threshold-crossed";
+
+/// 托盘告警键前缀
+const TRAY_ALERT_PREFIX: &str = "budget:";
+
+/// 预测所用的速率窗口
+const FORECAST_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// 预算周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Daily,
+    #[default]
+    Monthly,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Monthly => "monthly",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "daily" => Some(Self::Daily),
+            "monthly" => Some(Self::Monthly),
+            _ => None,
+        }
+    }
+
+    /// 周期标识（用于告警去重）
+    fn key(self, now: DateTime<Local>) -> String {
+        match self {
+            Self::Daily => now.format("%Y-%m-%d").to_string(),
+            Self::Monthly => now.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// 预算定义
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBudget {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// 为空表示全局预算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_type: Option<String>,
+    #[serde(default)]
+    pub period: BudgetPeriod,
+    pub limit_amount: String,
+    #[serde(default = "default_budget_currency")]
+    pub currency: String,
+    /// 告警阈值（百分比，升序）
+    #[serde(default = "default_thresholds")]
+    pub thresholds: Vec<u32>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+fn default_budget_currency() -> String {
+    crate::services::currency::BASE_CURRENCY.to_string()
+}
+
+fn default_thresholds() -> Vec<u32> {
+    vec![50, 80, 100]
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl UsageBudget {
+    /// 校验并规范化：名称非空、限额为正、阈值去重排序
+    pub fn normalize(&mut self) -> Result<(), AppError> {
+        self.name = self.name.trim().to_string();
+        if self.name.is_empty() {
+            return Err(AppError::localized(
+                "error.invalidBudget",
+                "预算名称不能为空",
+                "Budget name is required",
+            ));
+        }
+        self.app_type = match self.app_type.as_deref().map(str::trim) {
+            Some(app) if !app.is_empty() => Some(
+                app.parse::<crate::app_config::AppType>()?
+                    .as_str()
+                    .to_string(),
+            ),
+            _ => None,
+        };
+        self.currency = crate::services::currency::normalize_currency(&self.currency)?;
+
+        match self.limit_amount.trim().parse::<f64>() {
+            Ok(limit) if limit > 0.0 && limit.is_finite() => {
+                self.limit_amount = self.limit_amount.trim().to_string();
+            }
+            _ => {
+                return Err(AppError::localized(
+                    "error.invalidBudget",
+                    format!("无效的预算金额: {}", self.limit_amount),
+                    format!("Invalid budget amount: {}", self.limit_amount),
+                ))
+            }
+        }
+
+        self.thresholds.sort_unstable();
+        self.thresholds.dedup();
+        if self.thresholds.is_empty() || self.thresholds.iter().any(|t| !(1..=1000).contains(t)) {
+            return Err(AppError::localized(
+                "error.invalidBudget",
+                "告警阈值必须为 1-1000 之间的百分比",
+                "Alert thresholds must be percentages between 1 and 1000",
+            ));
+        }
+        Ok(())
+    }
+
+    fn limit(&self) -> f64 {
+        self.limit_amount.parse().unwrap_or(0.0)
+    }
+}
+
+/// 预算当前周期的执行情况
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    #[serde(flatten)]
+    pub budget: UsageBudget,
+    pub period_start: i64,
+    pub period_end: i64,
+    /// 本周期已花费（预算币种）
+    pub spent: String,
+    /// 已用百分比
+    pub percent: f64,
+    /// 已越过的最高阈值
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reached_threshold: Option<u32>,
+    /// 月末花费预测（仅月度预算）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forecast_percent: Option<f64>,
+}
+
+/// 阈值越过事件负载
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetAlert {
+    pub budget_id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_type: Option<String>,
+    pub period: BudgetPeriod,
+    pub threshold: u32,
+    pub percent: f64,
+    pub spent: String,
+    pub limit_amount: String,
+    pub currency: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<String>,
+}
+
+/// 一次预算检查的结果
+#[derive(Debug, Clone, Default)]
+pub struct BudgetCheck {
+    /// 本周期内新越过阈值、需要通知的告警
+    pub alerts: Vec<BudgetAlert>,
+    /// 当前仍超过任一阈值的预算（用于托盘提示）
+    pub over_threshold: Vec<BudgetStatus>,
+}
+
+/// 下一个周期的起点（本地时间零点，Unix 秒）
+fn next_period_start(period: BudgetPeriod, now: DateTime<Local>) -> i64 {
+    let today = now.date_naive();
+    let next = match period {
+        BudgetPeriod::Daily => today.succ_opt(),
+        BudgetPeriod::Monthly => today
+            .with_day(1)
+            .and_then(|first| first.checked_add_months(Months::new(1))),
+    };
+    next.and_then(local_midnight)
+        .unwrap_or_else(|| now.timestamp())
+}
+
+impl Database {
+    /// `[start, end]` 内的花费（按报告币种，`app_type` 为 None 时统计全部应用）
+    fn spend_between(
+        &self,
+        start: i64,
+        end: i64,
+        currency: &str,
+        app_type: Option<&str>,
+    ) -> Result<f64, AppError> {
+        let conn = lock_conn!(self.conn);
+        let cost = CurrencyCost::resolve(&conn, Some(currency), "u.")?;
+        let sql = format!(
+            "SELECT COALESCE(SUM({cost_expr}), 0)
+             FROM ({rows}) u
+             WHERE (?1 IS NULL OR u.app_type = ?1)",
+            cost_expr = cost.expr,
+            rows = usage_rows_sql(Some(start), Some(end), true)
+        );
+        Ok(conn.query_row(&sql, params![app_type], |row| row.get(0))?)
+    }
+
+    /// 按近期趋势的花费速率预测月末花费
+    fn forecast_month_end(
+        &self,
+        budget: &UsageBudget,
+        month_start: i64,
+        month_end: i64,
+        now: i64,
+        spent: f64,
+    ) -> Result<f64, AppError> {
+        let window_start = month_start.max(now - FORECAST_WINDOW_SECS);
+        if now <= window_start {
+            return Ok(spent);
+        }
+        let trends = self.get_app_daily_trends(
+            Some(window_start),
+            Some(now),
+            Some(&budget.currency),
+            None,
+            budget.app_type.as_deref(),
+        )?;
+        let window_cost: f64 = trends
+            .iter()
+            .filter_map(|point| point.total_cost.parse::<f64>().ok())
+            .sum();
+        // 窗口过短时速率波动大，至少按 1 小时计
+        let rate = window_cost / (now - window_start).max(60 * 60) as f64;
+        Ok(spent + rate * (month_end - now).max(0) as f64)
+    }
+
+    /// 计算单个预算在当前周期的执行情况
+    pub fn get_budget_status(
+        &self,
+        budget: &UsageBudget,
+        now: DateTime<Local>,
+    ) -> Result<BudgetStatus, AppError> {
+        let start = period_start(budget.period.as_str(), now);
+        let end = next_period_start(budget.period, now);
+        let spent = self.spend_between(
+            start,
+            now.timestamp(),
+            &budget.currency,
+            budget.app_type.as_deref(),
+        )?;
+
+        let limit = budget.limit();
+        let percent = if limit > 0.0 {
+            spent / limit * 100.0
+        } else {
+            0.0
+        };
+        let reached_threshold = budget
+            .thresholds
+            .iter()
+            .copied()
+            .filter(|t| percent >= f64::from(*t))
+            .max();
+
+        let forecast = match budget.period {
+            BudgetPeriod::Monthly => {
+                Some(self.forecast_month_end(budget, start, end, now.timestamp(), spent)?)
+            }
+            BudgetPeriod::Daily => None,
+        };
+
+        Ok(BudgetStatus {
+            budget: budget.clone(),
+            period_start: start,
+            period_end: end - 1,
+            spent: format!("{spent:.6}"),
+            percent,
+            reached_threshold,
+            forecast: forecast.map(|f| format!("{f:.6}")),
+            forecast_percent: forecast.filter(|_| limit > 0.0).map(|f| f / limit * 100.0),
+        })
+    }
+
+    /// 计算全部预算的执行情况
+    pub fn get_budget_statuses(&self, now: DateTime<Local>) -> Result<Vec<BudgetStatus>, AppError> {
+        self.get_budgets()?
+            .iter()
+            .map(|budget| self.get_budget_status(budget, now))
+            .collect()
+    }
+
+    /// 检查已启用的预算，返回本周期内新越过阈值的告警并记录，避免重复通知
+    pub fn check_budget_thresholds(&self, now: DateTime<Local>) -> Result<BudgetCheck, AppError> {
+        let mut check = BudgetCheck::default();
+        for budget in self.get_budgets()?.into_iter().filter(|b| b.enabled) {
+            let status = match self.get_budget_status(&budget, now) {
+                Ok(status) => status,
+                Err(e) => {
+                    log::warn!("[USG-007] 计算预算 {} 失败: {e}", budget.name);
+                    continue;
+                }
+            };
+            let Some(threshold) = status.reached_threshold else {
+                continue;
+            };
+            check.over_threshold.push(status.clone());
+
+            let period_key = budget.period.key(now);
+            let notified = self.get_budget_alert_state(&budget.id)?;
+            if matches!(&notified, Some((period, last)) if *period == period_key && *last >= threshold)
+            {
+                continue;
+            }
+            self.record_budget_alert(&budget.id, &period_key, threshold)?;
+
+            log::info!(
+                "[USG-007] 预算 {} 已用 {:.1}%，越过 {threshold}% 阈值",
+                budget.name,
+                status.percent
+            );
+            check.alerts.push(BudgetAlert {
+                budget_id: budget.id.clone(),
+                name: budget.name.clone(),
+                app_type: budget.app_type.clone(),
+                period: budget.period,
+                threshold,
+                percent: status.percent,
+                spent: status.spent,
+                limit_amount: budget.limit_amount.clone(),
+                currency: budget.currency.clone(),
+                forecast: status.forecast,
+            });
+        }
+        Ok(check)
+    }
+}
+
+/// 托盘告警文本
+fn tray_message(status: &BudgetStatus, language: &str) -> String {
+    let name = &status.budget.name;
+    let percent = status.percent.round();
+    match language {
+        "en" => format!("budget \"{name}\" at {percent}%"),
+        "ja" => format!("予算「{name}」{percent}% 使用"),
+        _ => format!("预算「{name}」已用 {percent}%"),
+    }
+}
+
+/// 向前端发送新越过阈值的事件，并按仍超过阈值的预算刷新托盘告警
+///
+/// 进入新周期、调高限额或停用预算后，对应的托盘告警随之清除
+pub fn notify_budget_alerts(app: &AppHandle, check: &BudgetCheck) {
+    for alert in &check.alerts {
+        if let Err(e) = app.emit(BUDGET_ALERT_EVENT, alert) {
+            log::warn!("[USG-007] 发送预算告警事件失败: {e}");
+        }
+    }
+
+    let language = crate::settings::get_settings().language;
+    let language = language.as_deref().unwrap_or("zh");
+    crate::tray::replace_tray_alerts(
+        app,
+        TRAY_ALERT_PREFIX,
+        check.over_threshold.iter().map(|status| {
+            (
+                format!("{TRAY_ALERT_PREFIX}{}", status.budget.id),
+                tray_message(status, language),
+            )
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(app_type: Option<&str>, limit: &str) -> UsageBudget {
+        UsageBudget {
+            id: "b1".to_string(),
+            name: "team".to_string(),
+            app_type: app_type.map(str::to_string),
+            period: BudgetPeriod::Monthly,
+            limit_amount: limit.to_string(),
+            currency: "usd".to_string(),
+            thresholds: vec![100, 50, 80, 50],
+            enabled: true,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_budget_normalize() {
+        let mut valid = budget(Some("Claude"), "10");
+        valid.normalize().unwrap();
+        assert_eq!(valid.app_type.as_deref(), Some("claude"));
+        assert_eq!(valid.currency, "USD");
+        assert_eq!(valid.thresholds, vec![50, 80, 100]);
+
+        assert!(budget(None, "0").normalize().is_err());
+        assert!(budget(Some("vim"), "10").normalize().is_err());
+        let mut no_thresholds = budget(None, "10");
+        no_thresholds.thresholds.clear();
+        assert!(no_thresholds.normalize().is_err());
+    }
+
+    #[test]
+    fn test_budget_thresholds_fire_once_per_period() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let now = Local::now();
+        let mut global = budget(None, "10");
+        db.save_budget(&mut global)?;
+        let mut codex = budget(Some("codex"), "10");
+        codex.id = "b2".to_string();
+        db.save_budget(&mut codex)?;
+
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO proxy_request_logs (
+                    request_id, provider_id, app_type, model, total_cost_usd,
+                    latency_ms, status_code, created_at
+                ) VALUES ('r1', 'p1', 'claude', 'claude-3', '6', 100, 200, ?1)",
+                params![now.timestamp()],
+            )?;
+        }
+
+        let check = db.check_budget_thresholds(now)?;
+        let alerts = check.alerts;
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].budget_id, "b1");
+        assert_eq!(alerts[0].threshold, 50);
+        assert_eq!(check.over_threshold.len(), 1);
+        let check = db.check_budget_thresholds(now)?;
+        assert!(check.alerts.is_empty());
+        // 已通知过但仍超过阈值，托盘告警保留
+        assert_eq!(check.over_threshold.len(), 1);
+
+        let statuses = db.get_budget_statuses(now)?;
+        let status = statuses.iter().find(|s| s.budget.id == "b1").unwrap();
+        assert!((status.percent - 60.0).abs() < 1e-9);
+        let forecast: f64 = status.forecast.as_deref().unwrap().parse().unwrap();
+        assert!(forecast >= 6.0);
+        let codex_status = statuses.iter().find(|s| s.budget.id == "b2").unwrap();
+        assert_eq!(codex_status.spent, "0.000000");
+
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO proxy_request_logs (
+                    request_id, provider_id, app_type, model, total_cost_usd,
+                    latency_ms, status_code, created_at
+                ) VALUES ('r2', 'p1', 'claude', 'claude-3', '5', 100, 200, ?1)",
+                params![now.timestamp()],
+            )?;
+        }
+        let alerts = db.check_budget_thresholds(now)?.alerts;
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].threshold, 100);
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+//! 多币种成本换算
+//!
+//! 汇率表以“1 美元可兑换的该币种数量”存储，USD 恒为 1，不入表。
+//! 供应商可声明计费币种：exchange 基准按汇率把美元标价换算为计费金额；
+//! fixed 基准按固定比例折算（如中转站“1 元 = 1 美元”），再用汇率反推实际美元成本。
+
+use crate::error::AppError;
+use crate::provider::{BillingCurrencyConfig, BillingRateBasis};
+use crate::proxy::usage::calculator::CostBreakdown;
+use rusqlite::{Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// 基准币种
+pub const BASE_CURRENCY: &str = "USD";
+
+/// 汇率记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeRate {
+    pub currency: String,
+    /// 1 美元可兑换的该币种数量
+    pub rate_per_usd: String,
+    /// 来源（manual 或导入的文件名）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+/// 按供应商计费币种折算后的成本
+#[derive(Debug, Clone)]
+pub struct BilledCost {
+    /// 实际美元成本
+    pub usd: CostBreakdown,
+    pub currency: String,
+    /// 以计费币种计的总成本
+    pub amount: Decimal,
+}
+
+/// 规范化币种代码（3 位字母，转大写）
+pub fn normalize_currency(code: &str) -> Result<String, AppError> {
+    let code = code.trim();
+    if code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(code.to_ascii_uppercase())
+    } else {
+        Err(AppError::localized(
+            "error.invalidCurrency",
+            format!("无效的币种代码: {code}"),
+            format!("Invalid currency code: {code}"),
+        ))
+    }
+}
+
+/// 解析汇率（必须为正数）
+pub fn parse_rate(value: &str) -> Result<Decimal, AppError> {
+    match Decimal::from_str(value.trim()) {
+        Ok(rate) if rate > Decimal::ZERO => Ok(rate),
+        _ => Err(AppError::localized(
+            "error.invalidExchangeRate",
+            format!("无效的汇率: {value}"),
+            format!("Invalid exchange rate: {value}"),
+        )),
+    }
+}
+
+/// 查询币种对美元的汇率（USD 恒为 1，未配置时返回 None）
+pub fn find_exchange_rate(conn: &Connection, currency: &str) -> Result<Option<Decimal>, AppError> {
+    if currency.eq_ignore_ascii_case(BASE_CURRENCY) {
+        return Ok(Some(Decimal::ONE));
+    }
+
+    let raw: Option<String> = conn
+        .query_row(
+            "SELECT rate_per_usd FROM exchange_rates WHERE currency = ?1",
+            [currency.to_ascii_uppercase()],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+    Ok(raw.and_then(|raw| parse_rate(&raw).ok()))
+}
+
+/// 按供应商计费币种折算成本
+///
+/// `rate_per_usd` 为计费币种当前汇率。exchange 基准缺少汇率时无法折算，返回 None；
+/// fixed 基准缺少汇率时仍记录计费金额，美元成本保持按标价计算。
+pub fn apply_billing(
+    cost: &CostBreakdown,
+    config: &BillingCurrencyConfig,
+    rate_per_usd: Option<Decimal>,
+) -> Option<BilledCost> {
+    let currency = normalize_currency(&config.currency).ok()?;
+    match config.rate_basis {
+        BillingRateBasis::Exchange => {
+            let rate = rate_per_usd?;
+            Some(BilledCost {
+                usd: cost.clone(),
+                currency,
+                amount: (cost.total_cost * rate).round_dp(8),
+            })
+        }
+        BillingRateBasis::Fixed => {
+            let fixed = parse_rate(config.fixed_rate.as_deref()?).ok()?;
+            let usd = match rate_per_usd {
+                Some(rate) => cost.scaled(fixed / rate),
+                None => cost.clone(),
+            };
+            Some(BilledCost {
+                usd,
+                currency,
+                amount: (cost.total_cost * fixed).round_dp(8),
+            })
+        }
+    }
+}
+
+fn invalid_rate_file(detail: impl std::fmt::Display) -> AppError {
+    AppError::localized(
+        "error.invalidExchangeRateFile",
+        format!("汇率文件格式无效: {detail}"),
+        format!("Invalid exchange rate file: {detail}"),
+    )
+}
+
+/// 解析汇率文件，返回以美元为基准的 (币种, 汇率) 列表
+///
+/// 支持 JSON（`{"CNY": 7.1}` 或 `{"base": "EUR", "rates": {...}}`）与
+/// CSV（每行 `currency,rate`，可带表头和 `#` 注释）。
+/// base 不是 USD 时要求 rates 中包含 USD，据此换算到美元基准。
+pub fn parse_exchange_rate_file(content: &str) -> Result<Vec<(String, Decimal)>, AppError> {
+    let (base, mut entries) = if content.trim_start().starts_with('{') {
+        parse_json_rates(content)?
+    } else {
+        (BASE_CURRENCY.to_string(), parse_csv_rates(content)?)
+    };
+
+    if base != BASE_CURRENCY {
+        let usd = entries
+            .iter()
+            .find(|(code, _)| code == BASE_CURRENCY)
+            .map(|(_, rate)| *rate)
+            .ok_or_else(|| invalid_rate_file(format!("base 为 {base} 时必须包含 USD 汇率")))?;
+        entries.push((base, Decimal::ONE));
+        for (_, rate) in entries.iter_mut() {
+            *rate /= usd;
+        }
+    }
+
+    entries.retain(|(code, _)| code != BASE_CURRENCY);
+    if entries.is_empty() {
+        return Err(invalid_rate_file("未包含任何汇率"));
+    }
+    Ok(entries)
+}
+
+fn parse_json_rates(content: &str) -> Result<(String, Vec<(String, Decimal)>), AppError> {
+    let value: Value = serde_json::from_str(content).map_err(invalid_rate_file)?;
+    let base = match value.get("base").and_then(Value::as_str) {
+        Some(base) => normalize_currency(base)?,
+        None => BASE_CURRENCY.to_string(),
+    };
+    let rates = value
+        .get("rates")
+        .unwrap_or(&value)
+        .as_object()
+        .ok_or_else(|| invalid_rate_file("rates 必须是对象"))?;
+
+    let mut entries = Vec::new();
+    for (code, rate) in rates {
+        // 顶层格式下跳过常见的元数据字段
+        if matches!(code.as_str(), "base" | "date" | "timestamp" | "rates") {
+            continue;
+        }
+        let raw = match rate {
+            Value::Number(n) => n.to_string(),
+            Value::String(s) => s.clone(),
+            _ => return Err(invalid_rate_file(format!("{code} 的汇率不是数字"))),
+        };
+        entries.push((normalize_currency(code)?, parse_rate(&raw)?));
+    }
+    Ok((base, entries))
+}
+
+fn parse_csv_rates(content: &str) -> Result<Vec<(String, Decimal)>, AppError> {
+    let mut entries = Vec::new();
+    let mut first = true;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let is_first = std::mem::replace(&mut first, false);
+
+        let mut fields = line.split(',').map(str::trim);
+        let (Some(code), Some(rate), None) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(invalid_rate_file(format!("无法解析的行: {line}")));
+        };
+        // 第一行汇率不是数字时视为表头
+        if is_first && Decimal::from_str(rate).is_err() {
+            continue;
+        }
+        entries.push((normalize_currency(code)?, parse_rate(rate)?));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cost(total: &str) -> CostBreakdown {
+        let total = Decimal::from_str(total).unwrap();
+        CostBreakdown {
+            input_cost: total,
+            output_cost: Decimal::ZERO,
+            cache_read_cost: Decimal::ZERO,
+            cache_creation_cost: Decimal::ZERO,
+            total_cost: total,
+        }
+    }
+
+    fn billing(basis: BillingRateBasis, fixed: Option<&str>) -> BillingCurrencyConfig {
+        BillingCurrencyConfig {
+            currency: "cny".to_string(),
+            rate_basis: basis,
+            fixed_rate: fixed.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_apply_billing_exchange_basis() {
+        let rate = Decimal::from_str("7.2").unwrap();
+        let billed = apply_billing(
+            &cost("2"),
+            &billing(BillingRateBasis::Exchange, None),
+            Some(rate),
+        )
+        .unwrap();
+        assert_eq!(billed.currency, "CNY");
+        assert_eq!(billed.amount, Decimal::from_str("14.4").unwrap());
+        assert_eq!(billed.usd.total_cost, Decimal::from(2));
+
+        assert!(
+            apply_billing(&cost("2"), &billing(BillingRateBasis::Exchange, None), None).is_none()
+        );
+    }
+
+    #[test]
+    fn test_apply_billing_fixed_basis() {
+        // “1 元 = 1 美元”：标价 $8 实收 ¥8，按 8 CNY/USD 折合 $1
+        let config = billing(BillingRateBasis::Fixed, Some("1"));
+        let billed = apply_billing(&cost("8"), &config, Some(Decimal::from(8))).unwrap();
+        assert_eq!(billed.amount, Decimal::from(8));
+        assert_eq!(billed.usd.total_cost, Decimal::ONE);
+        assert_eq!(billed.usd.input_cost, Decimal::ONE);
+
+        // 缺少汇率时仍记录计费金额，美元成本按标价
+        let billed = apply_billing(&cost("8"), &config, None).unwrap();
+        assert_eq!(billed.amount, Decimal::from(8));
+        assert_eq!(billed.usd.total_cost, Decimal::from(8));
+
+        assert!(apply_billing(&cost("8"), &billing(BillingRateBasis::Fixed, None), None).is_none());
+    }
+
+    #[test]
+    fn test_parse_exchange_rate_file_formats() {
+        let flat = parse_exchange_rate_file(r#"{"CNY": 7.2, "usd": 1, "JPY": "150"}"#).unwrap();
+        assert_eq!(
+            flat,
+            vec![
+                ("CNY".to_string(), Decimal::from_str("7.2").unwrap()),
+                ("JPY".to_string(), Decimal::from(150)),
+            ]
+        );
+
+        let rebased = parse_exchange_rate_file(
+            r#"{"base": "EUR", "date": "2026-01-01", "rates": {"USD": 1.25, "CNY": 9}}"#,
+        )
+        .unwrap();
+        assert!(rebased.contains(&("CNY".to_string(), Decimal::from_str("7.2").unwrap())));
+        assert!(rebased.contains(&("EUR".to_string(), Decimal::from_str("0.8").unwrap())));
+
+        let csv = parse_exchange_rate_file("# rates\ncurrency,rate\nCNY, 7.2\nhkd,7.8\n").unwrap();
+        assert_eq!(csv.len(), 2);
+        assert_eq!(
+            csv[1],
+            ("HKD".to_string(), Decimal::from_str("7.8").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_exchange_rate_file_rejects_invalid() {
+        assert!(parse_exchange_rate_file("").is_err());
+        assert!(parse_exchange_rate_file(r#"{"CNY": -1}"#).is_err());
+        assert!(parse_exchange_rate_file(r#"{"base": "EUR", "rates": {"CNY": 9}}"#).is_err());
+        assert!(parse_exchange_rate_file("CNY,7.2,extra").is_err());
+        assert!(parse_exchange_rate_file("YUAN,7.2").is_err());
+    }
+}
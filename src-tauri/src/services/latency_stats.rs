@@ -0,0 +1,382 @@
+//! 延迟分位数与首字延迟分析
+//!
+//! 平均延迟会掩盖长尾，这里按供应商 / 模型 / 时间桶计算总延迟与首字延迟（TTFT）的
+//! p50/p90/p99、输出吞吐量（tokens/s）与错误率。分位数采用最近秩法，
+//! 在 SQLite 中用窗口函数一次扫描完成，不把明细行读入内存。
+//!
+//! 只统计经过代理的真实上游请求：排除响应缓存命中、会话记录导入的用量与被取消的对冲请求。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::hedging::HEDGE_CANCELLED_STATUS;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+
+/// 分组维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LatencyGroupBy {
+    Provider,
+    Model,
+    ProviderModel,
+}
+
+/// 延迟与吞吐指标
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyMetrics {
+    pub request_count: u64,
+    pub error_count: u64,
+    /// 错误率（百分比）
+    pub error_rate: f32,
+    /// 成功请求的总延迟分位数（毫秒）
+    pub latency_p50_ms: Option<u64>,
+    pub latency_p90_ms: Option<u64>,
+    pub latency_p99_ms: Option<u64>,
+    /// 成功流式请求的首字延迟分位数（毫秒）
+    pub ttft_p50_ms: Option<u64>,
+    pub ttft_p90_ms: Option<u64>,
+    pub ttft_p99_ms: Option<u64>,
+    /// 输出吞吐量：输出 token 数 / 生成耗时（流式请求扣除首字延迟）
+    pub output_tokens_per_sec: Option<f64>,
+}
+
+/// 按供应商 / 模型分组的延迟统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(flatten)]
+    pub metrics: LatencyMetrics,
+}
+
+/// 延迟时间序列中的一个时间桶
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyTrendPoint {
+    pub date: String,
+    #[serde(flatten)]
+    pub metrics: LatencyMetrics,
+}
+
+/// 延迟统计的过滤条件
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyFilters {
+    pub app_type: Option<String>,
+    pub provider_id: Option<String>,
+    pub model: Option<String>,
+    pub start_date: Option<i64>,
+    pub end_date: Option<i64>,
+}
+
+impl Database {
+    /// 按供应商 / 模型分组统计延迟分位数（按请求数降序）
+    pub fn get_latency_stats(
+        &self,
+        filters: &LatencyFilters,
+        group_by: LatencyGroupBy,
+    ) -> Result<Vec<LatencyStats>, AppError> {
+        let (provider_key, model_key) = match group_by {
+            LatencyGroupBy::Provider => ("l.provider_id", "NULL"),
+            LatencyGroupBy::Model => ("NULL", "l.model"),
+            LatencyGroupBy::ProviderModel => ("l.provider_id", "l.model"),
+        };
+        let (where_clause, params) = latency_where_clause(filters);
+
+        let conn = lock_conn!(self.conn);
+        let sql = latency_sql(
+            &format!("{provider_key} AS provider_id, l.app_type AS app_type, {model_key} AS model"),
+            "provider_id, app_type, model",
+            &where_clause,
+        );
+        let sql = format!(
+            "SELECT m.*, p.name FROM ({sql}) m
+             LEFT JOIN providers p ON m.provider_id = p.id AND m.app_type = p.app_type
+             ORDER BY m.request_count DESC"
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            let provider_id: Option<String> = row.get(0)?;
+            let provider_name: Option<String> = row.get(METRIC_COLUMNS + 3)?;
+            Ok(LatencyStats {
+                provider_name: provider_id
+                    .as_ref()
+                    .map(|id| provider_name.unwrap_or_else(|| id.clone())),
+                provider_id,
+                model: row.get(2)?,
+                metrics: read_metrics(row, 3)?,
+            })
+        })?;
+
+        let mut stats = Vec::new();
+        for row in rows {
+            stats.push(row?);
+        }
+        Ok(stats)
+    }
+
+    /// 延迟时间序列（<=24h 按小时，>24h 按天，与使用量趋势一致）
+    pub fn get_latency_trends(
+        &self,
+        filters: &LatencyFilters,
+    ) -> Result<Vec<LatencyTrendPoint>, AppError> {
+        let end_ts = filters.end_date.unwrap_or_else(|| Local::now().timestamp());
+        let mut start_ts = filters.start_date.unwrap_or(end_ts - 24 * 60 * 60);
+        if start_ts >= end_ts {
+            start_ts = end_ts - 24 * 60 * 60;
+        }
+        let duration = end_ts - start_ts;
+        let (bucket_seconds, bucket_count) = if duration <= 24 * 60 * 60 {
+            (60 * 60, 24)
+        } else {
+            let day = 24 * 60 * 60;
+            (day, (duration + day - 1) / day)
+        };
+
+        let windowed = LatencyFilters {
+            start_date: Some(start_ts),
+            end_date: Some(end_ts),
+            ..filters.clone()
+        };
+        // 时间桶参数固定为 ?1 / ?2，过滤条件的匿名参数依次编号在其后
+        let (where_clause, where_params) = latency_where_clause(&windowed);
+        let mut params: Vec<rusqlite::types::Value> = vec![start_ts.into(), bucket_seconds.into()];
+        params.extend(where_params);
+
+        let conn = lock_conn!(self.conn);
+        let sql = latency_sql(
+            &format!(
+                "MIN(CAST((l.created_at - ?1) / ?2 AS INTEGER), {}) AS bucket_idx",
+                bucket_count - 1
+            ),
+            "bucket_idx",
+            &where_clause,
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            Ok((row.get::<_, i64>(0)?, read_metrics(row, 1)?))
+        })?;
+        let mut buckets = std::collections::HashMap::new();
+        for row in rows {
+            let (idx, metrics) = row?;
+            buckets.insert(idx, metrics);
+        }
+
+        Ok((0..bucket_count)
+            .map(|i| {
+                let date = Local
+                    .timestamp_opt(start_ts + i * bucket_seconds, 0)
+                    .single()
+                    .unwrap_or_else(Local::now)
+                    .to_rfc3339();
+                LatencyTrendPoint {
+                    date,
+                    metrics: buckets.remove(&i).unwrap_or_default(),
+                }
+            })
+            .collect())
+    }
+}
+
+/// `latency_sql` 每组输出的指标列数
+const METRIC_COLUMNS: usize = 10;
+
+/// 构造分组延迟统计 SQL：先按分组键与成功与否分区排序，再以最近秩法取分位数
+///
+/// 输出列为分组键，随后依次为 request_count、error_count、latency p50/p90/p99、
+/// ttft p50/p90/p99、输出 token 总数与生成耗时总数
+fn latency_sql(key_columns: &str, group_columns: &str, where_clause: &str) -> String {
+    let percentile = |column: &str, rank: &str, count: &str, p: &str| {
+        format!("MIN(CASE WHEN ok = 1 AND {column} IS NOT NULL AND {rank} >= {count} * {p} THEN {column} END)")
+    };
+    format!(
+        "WITH base AS (
+            SELECT {key_columns},
+                   l.latency_ms,
+                   l.first_token_ms,
+                   l.output_tokens,
+                   CASE WHEN l.status_code >= 200 AND l.status_code < 300 THEN 1 ELSE 0 END AS ok,
+                   l.latency_ms - COALESCE(l.first_token_ms, 0) AS generation_ms
+            FROM proxy_request_logs l
+            {where_clause}
+        ),
+        ranked AS (
+            SELECT *,
+                   ROW_NUMBER() OVER (PARTITION BY {group_columns}, ok ORDER BY latency_ms)
+                       AS latency_rank,
+                   COUNT(*) OVER (PARTITION BY {group_columns}, ok) AS latency_count,
+                   ROW_NUMBER() OVER (
+                       PARTITION BY {group_columns}, ok
+                       ORDER BY first_token_ms IS NULL, first_token_ms
+                   ) AS ttft_rank,
+                   COUNT(first_token_ms) OVER (PARTITION BY {group_columns}, ok) AS ttft_count
+            FROM base
+        )
+        SELECT {group_columns},
+               COUNT(*) AS request_count,
+               SUM(1 - ok) AS error_count,
+               {p50}, {p90}, {p99},
+               {t50}, {t90}, {t99},
+               SUM(CASE WHEN ok = 1 AND output_tokens > 0 AND generation_ms > 0 THEN output_tokens ELSE 0 END),
+               SUM(CASE WHEN ok = 1 AND output_tokens > 0 AND generation_ms > 0 THEN generation_ms ELSE 0 END)
+        FROM ranked
+        GROUP BY {group_columns}",
+        p50 = percentile("latency_ms", "latency_rank", "latency_count", "0.5"),
+        p90 = percentile("latency_ms", "latency_rank", "latency_count", "0.9"),
+        p99 = percentile("latency_ms", "latency_rank", "latency_count", "0.99"),
+        t50 = percentile("first_token_ms", "ttft_rank", "ttft_count", "0.5"),
+        t90 = percentile("first_token_ms", "ttft_rank", "ttft_count", "0.9"),
+        t99 = percentile("first_token_ms", "ttft_rank", "ttft_count", "0.99"),
+    )
+}
+
+fn latency_where_clause(filters: &LatencyFilters) -> (String, Vec<rusqlite::types::Value>) {
+    // 对冲中落败被取消的请求耗时取决于胜出方，不代表该供应商的延迟
+    let not_cancelled = format!("l.status_code <> {HEDGE_CANCELLED_STATUS}");
+    let mut conditions = vec![
+        "l.from_cache = 0",
+        "l.source = 'proxy'",
+        not_cancelled.as_str(),
+    ];
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+
+    if let Some(ref app_type) = filters.app_type {
+        conditions.push("l.app_type = ?");
+        params.push(app_type.clone().into());
+    }
+    if let Some(ref provider_id) = filters.provider_id {
+        conditions.push("l.provider_id = ?");
+        params.push(provider_id.clone().into());
+    }
+    if let Some(ref model) = filters.model {
+        conditions.push("l.model = ?");
+        params.push(model.clone().into());
+    }
+    if let Some(start) = filters.start_date {
+        conditions.push("l.created_at >= ?");
+        params.push(start.into());
+    }
+    if let Some(end) = filters.end_date {
+        conditions.push("l.created_at <= ?");
+        params.push(end.into());
+    }
+
+    (format!("WHERE {}", conditions.join(" AND ")), params)
+}
+
+fn read_metrics(row: &rusqlite::Row, offset: usize) -> rusqlite::Result<LatencyMetrics> {
+    let request_count = row.get::<_, i64>(offset)? as u64;
+    let error_count = row.get::<_, i64>(offset + 1)? as u64;
+    let ms = |i: usize| -> rusqlite::Result<Option<u64>> {
+        Ok(row
+            .get::<_, Option<i64>>(offset + i)?
+            .map(|v| v.max(0) as u64))
+    };
+    let output_tokens = row.get::<_, i64>(offset + 8)?;
+    let generation_ms = row.get::<_, i64>(offset + 9)?;
+
+    Ok(LatencyMetrics {
+        request_count,
+        error_count,
+        error_rate: if request_count > 0 {
+            (error_count as f32 / request_count as f32) * 100.0
+        } else {
+            0.0
+        },
+        latency_p50_ms: ms(2)?,
+        latency_p90_ms: ms(3)?,
+        latency_p99_ms: ms(4)?,
+        ttft_p50_ms: ms(5)?,
+        ttft_p90_ms: ms(6)?,
+        ttft_p99_ms: ms(7)?,
+        output_tokens_per_sec: (generation_ms > 0)
+            .then(|| output_tokens as f64 * 1000.0 / generation_ms as f64),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+
+    #[test]
+    fn test_latency_percentiles_and_trends() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let now = Local::now().timestamp();
+        {
+            let conn = db.conn.lock().unwrap();
+            // p1/m1：100 个成功请求（延迟 1..=100ms，前 10 个为流式），2 个错误，1 个缓存命中，1 个被取消的对冲请求
+            for i in 1..=100i64 {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                        output_tokens, latency_ms, first_token_ms, status_code, created_at)
+                     VALUES (?1, 'p1', 'claude', 'm1', 10, ?2, ?3, 200, ?4)",
+                    params![format!("ok{i}"), i, (i <= 10).then_some(i / 2), now - 60],
+                )?;
+            }
+            for (id, status, from_cache) in [
+                ("e1", 500, 0),
+                ("e2", 429, 0),
+                ("c1", 200, 1),
+                ("h1", 499, 0),
+            ] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                        latency_ms, status_code, from_cache, created_at)
+                     VALUES (?1, 'p1', 'claude', 'm1', 5000, ?2, ?3, ?4)",
+                    params![id, status, from_cache, now - 60],
+                )?;
+            }
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                    latency_ms, status_code, created_at)
+                 VALUES ('p2', 'p2', 'claude', 'm2', 300, 200, ?1)",
+                params![now - 60],
+            )?;
+        }
+
+        let stats = db.get_latency_stats(&LatencyFilters::default(), LatencyGroupBy::Provider)?;
+        assert_eq!(stats.len(), 2);
+        let p1 = &stats[0].metrics;
+        assert_eq!(stats[0].provider_id.as_deref(), Some("p1"));
+        assert_eq!(stats[0].model, None);
+        assert_eq!((p1.request_count, p1.error_count), (102, 2));
+        assert_eq!(
+            (p1.latency_p50_ms, p1.latency_p90_ms, p1.latency_p99_ms),
+            (Some(50), Some(90), Some(99))
+        );
+        // 流式请求首字延迟为 0,1,1,2,2,3,3,4,4,5
+        assert_eq!(p1.ttft_p50_ms, Some(2));
+        assert_eq!(p1.ttft_p99_ms, Some(5));
+        assert!(p1.output_tokens_per_sec.is_some());
+        assert_eq!(stats[1].metrics.ttft_p50_ms, None);
+
+        let filters = LatencyFilters {
+            model: Some("m2".to_string()),
+            ..Default::default()
+        };
+        let by_model = db.get_latency_stats(&filters, LatencyGroupBy::ProviderModel)?;
+        assert_eq!(by_model.len(), 1);
+        assert_eq!(by_model[0].model.as_deref(), Some("m2"));
+        assert_eq!(by_model[0].metrics.latency_p99_ms, Some(300));
+
+        let trends = db.get_latency_trends(&LatencyFilters {
+            provider_id: Some("p1".to_string()),
+            start_date: Some(now - 3600),
+            end_date: Some(now),
+            ..Default::default()
+        })?;
+        assert_eq!(trends.len(), 24);
+        let total: u64 = trends.iter().map(|p| p.metrics.request_count).sum();
+        assert_eq!(total, 102);
+        Ok(())
+    }
+}
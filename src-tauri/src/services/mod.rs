@@ -1,14 +1,21 @@
+pub mod balance;
+pub mod budget;
 pub mod config;
+pub mod currency;
 pub mod env_checker;
 pub mod env_manager;
+pub mod latency_stats;
 pub mod mcp;
 pub mod omo;
+pub mod pricing_catalog;
 pub mod prompt;
 pub mod provider;
 pub mod proxy;
 pub mod skill;
 pub mod speedtest;
 pub mod stream_check;
+pub mod transcript_usage;
+pub mod usage_export;
 pub mod usage_stats;
 pub mod webdav;
 pub mod webdav_auto_sync;
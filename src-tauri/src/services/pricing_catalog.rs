@@ -0,0 +1,795 @@
+//! 模型定价目录导入
+//!
+//! 支持 LiteLLM 的 `model_prices_and_context_window.json` 与 OpenRouter 的 `/models` 列表，
+//! 来源可以是本地文件或 URL。导入前先生成与现有 model_pricing 的差异预览，确认后再写入。
+//!
+//! 目录中的模型 ID 常带有 `vendor/` 前缀、日期后缀或 `@effort` 后缀，
+//! 这里用 [`canonical_model_id`] 作为别名匹配键，同时用于运行时查价的兜底匹配。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::usage::calculator::{ModelPricing, PricingTier};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// 远程目录下载超时
+const FETCH_TIMEOUT_SECS: u64 = 30;
+
+/// LiteLLM 中由云厂商转售的条目（与原厂模型重名但价格不同），导入时跳过
+const SKIPPED_LITELLM_PROVIDERS: &[&str] = &[
+    "azure",
+    "bedrock",
+    "databricks",
+    "sagemaker",
+    "snowflake",
+    "vertex_ai",
+    "watsonx",
+];
+
+/// 目录中的一条模型定价（价格单位均为 USD / 百万 token）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogPrice {
+    pub model_id: String,
+    pub display_name: String,
+    pub input_cost_per_million: String,
+    pub output_cost_per_million: String,
+    pub cache_read_cost_per_million: String,
+    pub cache_creation_cost_per_million: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiers: Vec<PricingTier>,
+}
+
+/// 解析出的目录条目：(目录中的原始 ID, 定价)
+type CatalogEntries = Vec<(String, CatalogPrice)>;
+
+/// 差异类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CatalogChangeKind {
+    Added,
+    Changed,
+}
+
+/// 一条待应用的定价变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogChange {
+    pub kind: CatalogChangeKind,
+    /// 目录中的原始模型 ID
+    pub source_id: String,
+    /// 将写入的定价（model_id 为匹配到的现有 ID 或规范化后的新 ID）
+    pub incoming: CatalogPrice,
+    /// 当前生效的定价（新增时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<CatalogPrice>,
+}
+
+/// 导入预览
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogPreview {
+    /// 目录格式（litellm / openrouter）
+    pub format: String,
+    pub changes: Vec<CatalogChange>,
+    /// 价格与现有定价一致的条目数
+    pub unchanged: usize,
+    /// 因无价格、非对话模型或重复而跳过的条目数
+    pub skipped: usize,
+}
+
+/// 去掉路由相关的前后缀：`vendor/` 前缀、`:variant` 后缀与 `@effort`（或 Vertex `@date`）后缀
+fn strip_route_affixes(model_id: &str) -> &str {
+    let id = model_id.rsplit_once('/').map_or(model_id, |(_, r)| r);
+    let id = id.split(':').next().unwrap_or(id);
+    id.split('@').next().unwrap_or(id).trim()
+}
+
+/// 去掉 `-20250929` 或 `-2024-08-06` 形式的日期后缀
+fn strip_date_suffix(id: &str) -> &str {
+    let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if let Some((head, tail)) = id.rsplit_once('-') {
+        if tail.len() == 8 && tail.starts_with("20") && is_digits(tail) {
+            return head;
+        }
+    }
+    if id.len() > 11 && id.is_char_boundary(id.len() - 11) {
+        let (head, tail) = id.split_at(id.len() - 11);
+        let parts: Vec<&str> = tail[1..].split('-').collect();
+        if tail.starts_with('-')
+            && parts.len() == 3
+            && parts[0].len() == 4
+            && parts.iter().all(|p| is_digits(p))
+        {
+            return head;
+        }
+    }
+    id
+}
+
+/// 导入时写入的模型 ID：去掉路由前后缀并转小写，保留日期后缀
+pub fn catalog_model_id(raw: &str) -> String {
+    strip_route_affixes(raw).to_ascii_lowercase()
+}
+
+/// 模型 ID 的别名匹配键
+///
+/// 在 [`catalog_model_id`] 基础上再去掉日期后缀，并把版本号中的 `.` 统一为 `-`，
+/// 使 `anthropic/claude-sonnet-4.5` 与 `claude-sonnet-4-5-20250929` 视为同一模型
+pub fn canonical_model_id(model_id: &str) -> String {
+    let id = catalog_model_id(model_id);
+    strip_date_suffix(&id).replace('.', "-")
+}
+
+/// 按别名匹配键在现有定价中查找模型（多个匹配时取 ID 最大者，通常是最新日期版本）
+pub(crate) fn find_alias_model_id(
+    conn: &Connection,
+    model_id: &str,
+) -> Result<Option<String>, AppError> {
+    let key = canonical_model_id(model_id);
+    if key.is_empty() {
+        return Ok(None);
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT model_id FROM model_pricing")
+        .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+
+    Ok(ids
+        .into_iter()
+        .filter(|id| canonical_model_id(id) == key)
+        .max())
+}
+
+fn invalid_catalog(detail: impl std::fmt::Display) -> AppError {
+    AppError::localized(
+        "error.invalidPricingCatalog",
+        format!("定价目录格式无效: {detail}"),
+        format!("Invalid pricing catalog: {detail}"),
+    )
+}
+
+/// 读取目录内容（http/https 开头时下载，否则视为本地文件路径）
+pub async fn load_catalog_source(source: &str) -> Result<String, AppError> {
+    let source = source.trim();
+    if !(source.starts_with("http://") || source.starts_with("https://")) {
+        return std::fs::read_to_string(source).map_err(|e| AppError::io(source, e));
+    }
+
+    let fetch_failed = |detail: String| {
+        AppError::localized(
+            "error.pricingCatalogFetchFailed",
+            format!("下载定价目录失败: {detail}"),
+            format!("Failed to download pricing catalog: {detail}"),
+        )
+    };
+    let response = crate::proxy::http_client::get()
+        .get(source)
+        .timeout(Duration::from_secs(FETCH_TIMEOUT_SECS))
+        .send()
+        .await
+        .map_err(|e| fetch_failed(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(fetch_failed(format!("HTTP {}", response.status().as_u16())));
+    }
+    response
+        .text()
+        .await
+        .map_err(|e| fetch_failed(e.to_string()))
+}
+
+/// 解析目录，自动识别格式，返回 (格式, [(原始 ID, 定价)], 跳过数)
+pub fn parse_catalog(content: &str) -> Result<(&'static str, CatalogEntries, usize), AppError> {
+    let value: Value = serde_json::from_str(content).map_err(invalid_catalog)?;
+    if let Some(models) = value.get("data").and_then(Value::as_array) {
+        let (entries, skipped) = parse_openrouter(models);
+        return Ok(("openrouter", entries, skipped));
+    }
+    let object = value
+        .as_object()
+        .ok_or_else(|| invalid_catalog("顶层必须是对象"))?;
+    let (entries, skipped) = parse_litellm(object);
+    Ok(("litellm", entries, skipped))
+}
+
+/// 解析每 token 单价并换算为每百万 token（支持科学计数法）
+fn per_million(value: Option<&Value>) -> Option<Decimal> {
+    let raw = match value? {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        _ => return None,
+    };
+    let per_token = Decimal::from_str(&raw)
+        .or_else(|_| Decimal::from_scientific(&raw))
+        .ok()?;
+    if per_token < Decimal::ZERO {
+        return None;
+    }
+    Some(
+        (per_token * Decimal::from(1_000_000))
+            .round_dp(6)
+            .normalize(),
+    )
+}
+
+fn price_string(value: Option<Decimal>) -> String {
+    value.unwrap_or(Decimal::ZERO).to_string()
+}
+
+fn parse_litellm(object: &Map<String, Value>) -> (CatalogEntries, usize) {
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+
+    for (key, spec) in object {
+        let provider = spec
+            .get("litellm_provider")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let mode = spec.get("mode").and_then(Value::as_str);
+        let resold = SKIPPED_LITELLM_PROVIDERS
+            .iter()
+            .any(|p| provider.starts_with(p));
+        if key == "sample_spec"
+            || resold
+            || !matches!(mode, None | Some("chat" | "completion" | "responses"))
+        {
+            skipped += 1;
+            continue;
+        }
+
+        let (Some(input), Some(output)) = (
+            per_million(spec.get("input_cost_per_token")),
+            per_million(spec.get("output_cost_per_token")),
+        ) else {
+            skipped += 1;
+            continue;
+        };
+        let cache_read = per_million(spec.get("cache_read_input_token_cost"));
+        let cache_creation = per_million(spec.get("cache_creation_input_token_cost"));
+
+        // 长上下文分档：input_cost_per_token_above_200k_tokens 等
+        let mut tiers = Vec::new();
+        if let Some(fields) = spec.as_object() {
+            for field in fields.keys() {
+                let Some(threshold) = field
+                    .strip_prefix("input_cost_per_token_above_")
+                    .and_then(|rest| rest.strip_suffix("k_tokens"))
+                    .and_then(|k| k.parse::<u64>().ok())
+                else {
+                    continue;
+                };
+                let suffix = format!("above_{threshold}k_tokens");
+                let (Some(tier_input), Some(tier_output)) = (
+                    per_million(spec.get(field)),
+                    per_million(spec.get(format!("output_cost_per_token_{suffix}"))),
+                ) else {
+                    continue;
+                };
+                tiers.push(PricingTier {
+                    above_input_tokens: threshold * 1000,
+                    input_cost_per_million: tier_input.to_string(),
+                    output_cost_per_million: tier_output.to_string(),
+                    cache_read_cost_per_million: price_string(
+                        per_million(spec.get(format!("cache_read_input_token_cost_{suffix}")))
+                            .or(cache_read),
+                    ),
+                    cache_creation_cost_per_million: price_string(
+                        per_million(spec.get(format!("cache_creation_input_token_cost_{suffix}")))
+                            .or(cache_creation),
+                    ),
+                });
+            }
+        }
+        tiers.sort_by_key(|t| t.above_input_tokens);
+
+        let model_id = catalog_model_id(key);
+        entries.push((
+            key.clone(),
+            CatalogPrice {
+                display_name: model_id.clone(),
+                model_id,
+                input_cost_per_million: input.to_string(),
+                output_cost_per_million: output.to_string(),
+                cache_read_cost_per_million: price_string(cache_read),
+                cache_creation_cost_per_million: price_string(cache_creation),
+                tiers,
+            },
+        ));
+    }
+
+    // 同名模型优先采用不带 vendor/ 前缀的原厂条目
+    entries.sort_by_key(|(key, _)| key.contains('/'));
+    (entries, skipped)
+}
+
+fn parse_openrouter(models: &[Value]) -> (CatalogEntries, usize) {
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+
+    for model in models {
+        let Some(id) = model.get("id").and_then(Value::as_str) else {
+            skipped += 1;
+            continue;
+        };
+        let pricing = model.get("pricing");
+        let price = |field: &str| per_million(pricing.and_then(|p| p.get(field)));
+        // :free 等变体与正式版共用规范化 ID，只导入正式版价格
+        let (Some(input), Some(output)) = (price("prompt"), price("completion")) else {
+            skipped += 1;
+            continue;
+        };
+        if id.contains(':') {
+            skipped += 1;
+            continue;
+        }
+
+        let model_id = catalog_model_id(id);
+        // OpenRouter 名称形如 "Anthropic: Claude Sonnet 4.5"
+        let display_name = model
+            .get("name")
+            .and_then(Value::as_str)
+            .map(|name| name.split_once(": ").map_or(name, |(_, n)| n).to_string())
+            .unwrap_or_else(|| model_id.clone());
+        entries.push((
+            id.to_string(),
+            CatalogPrice {
+                model_id,
+                display_name,
+                input_cost_per_million: input.to_string(),
+                output_cost_per_million: output.to_string(),
+                cache_read_cost_per_million: price_string(price("input_cache_read")),
+                cache_creation_cost_per_million: price_string(price("input_cache_write")),
+                tiers: Vec::new(),
+            },
+        ));
+    }
+
+    (entries, skipped)
+}
+
+/// 定价比较键（按数值比较，忽略 "3" 与 "3.00" 之类的格式差异）
+fn price_key(price: &CatalogPrice) -> Vec<Option<Decimal>> {
+    let parse = |s: &str| Decimal::from_str(s).ok();
+    let mut key = vec![
+        parse(&price.input_cost_per_million),
+        parse(&price.output_cost_per_million),
+        parse(&price.cache_read_cost_per_million),
+        parse(&price.cache_creation_cost_per_million),
+    ];
+    for tier in &price.tiers {
+        key.push(Some(Decimal::from(tier.above_input_tokens)));
+        key.push(parse(&tier.input_cost_per_million));
+        key.push(parse(&tier.output_cost_per_million));
+        key.push(parse(&tier.cache_read_cost_per_million));
+        key.push(parse(&tier.cache_creation_cost_per_million));
+    }
+    key
+}
+
+impl Database {
+    /// 生成目录导入预览（与各模型当前生效的定价比较）
+    pub fn preview_pricing_catalog(&self, content: &str) -> Result<CatalogPreview, AppError> {
+        let (format, entries, mut skipped) = parse_catalog(content)?;
+        let current = {
+            let conn = lock_conn!(self.conn);
+            Self::current_model_prices(&conn, chrono::Utc::now().timestamp())?
+        };
+        let mut by_alias: HashMap<String, &str> = HashMap::new();
+        for id in current.keys() {
+            let alias = by_alias.entry(canonical_model_id(id)).or_insert(id);
+            if id.as_str() > *alias {
+                *alias = id;
+            }
+        }
+        // 目录中已有精确匹配的模型族不再做别名匹配，避免别名条目抢占精确条目
+        let exact_families: HashSet<String> = entries
+            .iter()
+            .filter(|(_, entry)| current.contains_key(&entry.model_id))
+            .map(|(_, entry)| canonical_model_id(&entry.model_id))
+            .collect();
+
+        let mut changes = Vec::new();
+        let mut unchanged = 0;
+        let mut seen = HashSet::new();
+        for (source_id, mut incoming) in entries {
+            let target = if current.contains_key(&incoming.model_id) {
+                Some(incoming.model_id.clone())
+            } else {
+                let family = canonical_model_id(&incoming.model_id);
+                by_alias
+                    .get(&family)
+                    .filter(|_| !exact_families.contains(&family))
+                    .map(|id| id.to_string())
+            };
+            if let Some(target) = &target {
+                incoming.model_id = target.clone();
+            }
+            if !seen.insert(incoming.model_id.clone()) {
+                skipped += 1;
+                continue;
+            }
+
+            match target.and_then(|id| current.get(&id)) {
+                Some(existing) => {
+                    incoming.display_name = existing.display_name.clone();
+                    // OpenRouter 列表不含分档信息，保留现有分档
+                    if format == "openrouter" && incoming.tiers.is_empty() {
+                        incoming.tiers = existing.tiers.clone();
+                    }
+                    if price_key(existing) == price_key(&incoming) {
+                        unchanged += 1;
+                    } else {
+                        changes.push(CatalogChange {
+                            kind: CatalogChangeKind::Changed,
+                            source_id,
+                            incoming,
+                            current: Some(existing.clone()),
+                        });
+                    }
+                }
+                None => changes.push(CatalogChange {
+                    kind: CatalogChangeKind::Added,
+                    source_id,
+                    incoming,
+                    current: None,
+                }),
+            }
+        }
+        changes.sort_by(|a, b| a.incoming.model_id.cmp(&b.incoming.model_id));
+
+        Ok(CatalogPreview {
+            format: format.to_string(),
+            changes,
+            unchanged,
+            skipped,
+        })
+    }
+
+    /// 应用预览中选中的定价，写入 `effective_from` 生效的版本（0 表示覆盖基础版本）
+    pub fn apply_pricing_catalog(
+        &self,
+        prices: &[CatalogPrice],
+        effective_from: i64,
+    ) -> Result<usize, AppError> {
+        for price in prices {
+            let valid = !price.model_id.trim().is_empty()
+                && ModelPricing::from_strings(
+                    &price.input_cost_per_million,
+                    &price.output_cost_per_million,
+                    &price.cache_read_cost_per_million,
+                    &price.cache_creation_cost_per_million,
+                )
+                .ok()
+                .is_some_and(|p| p.with_tiers(&price.tiers).is_ok());
+            if !valid {
+                return Err(AppError::localized(
+                    "error.invalidModelPricing",
+                    format!("模型定价无效: {}", price.model_id),
+                    format!("Invalid model pricing: {}", price.model_id),
+                ));
+            }
+        }
+
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        for price in prices {
+            let tiers = if price.tiers.is_empty() {
+                None
+            } else {
+                Some(
+                    serde_json::to_string(&price.tiers)
+                        .map_err(|e| AppError::Database(format!("序列化分档定价失败: {e}")))?,
+                )
+            };
+            tx.execute(
+                "INSERT OR REPLACE INTO model_pricing (
+                    model_id, display_name, input_cost_per_million, output_cost_per_million,
+                    cache_read_cost_per_million, cache_creation_cost_per_million,
+                    effective_from, effective_until, tiers
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, NULL, ?8)",
+                rusqlite::params![
+                    price.model_id,
+                    price.display_name,
+                    price.input_cost_per_million,
+                    price.output_cost_per_million,
+                    price.cache_read_cost_per_million,
+                    price.cache_creation_cost_per_million,
+                    effective_from,
+                    tiers,
+                ],
+            )
+            .map_err(|e| AppError::Database(format!("写入模型定价失败: {e}")))?;
+        }
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+
+        log::info!("已从定价目录导入 {} 个模型", prices.len());
+        Ok(prices.len())
+    }
+
+    /// 各模型在 `at` 时生效的定价（版本选择规则与 select_pricing_version 一致）
+    fn current_model_prices(
+        conn: &Connection,
+        at: i64,
+    ) -> Result<HashMap<String, CatalogPrice>, AppError> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT model_id, display_name, input_cost_per_million, output_cost_per_million,
+                        cache_read_cost_per_million, cache_creation_cost_per_million,
+                        effective_from, effective_until, tiers
+                 FROM model_pricing
+                 ORDER BY model_id, effective_from",
+            )
+            .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    CatalogPrice {
+                        model_id: row.get(0)?,
+                        display_name: row.get(1)?,
+                        input_cost_per_million: row.get(2)?,
+                        output_cost_per_million: row.get(3)?,
+                        cache_read_cost_per_million: row.get(4)?,
+                        cache_creation_cost_per_million: row.get(5)?,
+                        tiers: row
+                            .get::<_, Option<String>>(8)?
+                            .and_then(|json| serde_json::from_str(&json).ok())
+                            .unwrap_or_default(),
+                    },
+                    row.get::<_, i64>(6)?,
+                    row.get::<_, Option<i64>>(7)?,
+                ))
+            })
+            .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+
+        // 优先级：覆盖 at 的版本 > 已生效的最新版本 > 最早版本；同级取生效时间最晚的
+        let rank = |from: i64, until: Option<i64>| {
+            if from <= at && until.is_none_or(|u| at < u) {
+                2
+            } else if from <= at {
+                1
+            } else {
+                0
+            }
+        };
+        let mut current: HashMap<String, (u8, CatalogPrice)> = HashMap::new();
+        for (price, from, until) in rows {
+            let rank = rank(from, until);
+            match current.get(&price.model_id) {
+                Some((best, _)) if *best > rank || (*best == 0 && rank == 0) => {}
+                _ => {
+                    current.insert(price.model_id.clone(), (rank, price));
+                }
+            }
+        }
+        Ok(current.into_iter().map(|(id, (_, p))| (id, p)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::usage_stats::find_model_pricing_row;
+
+    const LITELLM: &str = r#"{
+        "sample_spec": {"input_cost_per_token": 0, "output_cost_per_token": 0, "mode": "chat"},
+        "claude-sonnet-4-5": {
+            "litellm_provider": "anthropic", "mode": "chat",
+            "input_cost_per_token": 3e-06, "output_cost_per_token": 1.5e-05,
+            "cache_read_input_token_cost": 3e-07, "cache_creation_input_token_cost": 3.75e-06,
+            "input_cost_per_token_above_200k_tokens": 6e-06,
+            "output_cost_per_token_above_200k_tokens": 2.25e-05
+        },
+        "bedrock/anthropic.claude-sonnet-4-5": {
+            "litellm_provider": "bedrock", "mode": "chat",
+            "input_cost_per_token": 3.3e-06, "output_cost_per_token": 1.65e-05
+        },
+        "text-embedding-3-small": {
+            "litellm_provider": "openai", "mode": "embedding",
+            "input_cost_per_token": 2e-08, "output_cost_per_token": 0
+        },
+        "deepseek/deepseek-chat": {
+            "litellm_provider": "deepseek", "mode": "chat",
+            "input_cost_per_token": 2.7e-07, "output_cost_per_token": 1.1e-06
+        }
+    }"#;
+
+    const OPENROUTER: &str = r#"{"data": [
+        {"id": "anthropic/claude-sonnet-4.5", "name": "Anthropic: Claude Sonnet 4.5",
+         "pricing": {"prompt": "0.000003", "completion": "0.000015",
+                     "input_cache_read": "0.0000003", "input_cache_write": "0.00000375"}},
+        {"id": "openai/gpt-5.2", "name": "OpenAI: GPT-5.2",
+         "pricing": {"prompt": "0.00000175", "completion": "0.000014"}},
+        {"id": "qwen/qwen3-coder:free", "name": "Qwen: Qwen3 Coder (free)",
+         "pricing": {"prompt": "0", "completion": "0"}},
+        {"id": "openrouter/auto", "name": "Auto Router",
+         "pricing": {"prompt": "-1", "completion": "-1"}}
+    ]}"#;
+
+    #[test]
+    fn test_model_id_normalization() {
+        assert_eq!(
+            catalog_model_id("anthropic/claude-sonnet-4.5"),
+            "claude-sonnet-4.5"
+        );
+        assert_eq!(
+            catalog_model_id("openai/gpt-5.2-codex@high"),
+            "gpt-5.2-codex"
+        );
+        assert_eq!(catalog_model_id("qwen/qwen3-coder:free"), "qwen3-coder");
+
+        assert_eq!(
+            canonical_model_id("anthropic/claude-sonnet-4.5"),
+            "claude-sonnet-4-5"
+        );
+        assert_eq!(
+            canonical_model_id("claude-sonnet-4-5-20250929"),
+            "claude-sonnet-4-5"
+        );
+        assert_eq!(
+            canonical_model_id("claude-sonnet-4-5@20250929"),
+            "claude-sonnet-4-5"
+        );
+        assert_eq!(canonical_model_id("gpt-4o-2024-08-06"), "gpt-4o");
+        // 非日期的数字后缀保留
+        assert_eq!(canonical_model_id("gpt-4-0613"), "gpt-4-0613");
+    }
+
+    #[test]
+    fn test_parse_litellm_catalog() {
+        let (format, entries, skipped) = parse_catalog(LITELLM).unwrap();
+        assert_eq!(format, "litellm");
+        // sample_spec、bedrock 转售与 embedding 被跳过
+        assert_eq!(skipped, 3);
+        assert_eq!(entries.len(), 2);
+
+        let (_, sonnet) = entries
+            .iter()
+            .find(|(key, _)| key == "claude-sonnet-4-5")
+            .unwrap();
+        assert_eq!(sonnet.input_cost_per_million, "3");
+        assert_eq!(sonnet.output_cost_per_million, "15");
+        assert_eq!(sonnet.cache_read_cost_per_million, "0.3");
+        assert_eq!(sonnet.tiers.len(), 1);
+        assert_eq!(sonnet.tiers[0].above_input_tokens, 200_000);
+        assert_eq!(sonnet.tiers[0].input_cost_per_million, "6");
+        // 分档未单独给出缓存价格时沿用基础价格
+        assert_eq!(sonnet.tiers[0].cache_read_cost_per_million, "0.3");
+
+        assert_eq!(entries.last().unwrap().1.model_id, "deepseek-chat");
+    }
+
+    #[test]
+    fn test_parse_openrouter_catalog() {
+        let (format, entries, skipped) = parse_catalog(OPENROUTER).unwrap();
+        assert_eq!(format, "openrouter");
+        assert_eq!(skipped, 2);
+        assert_eq!(entries[0].1.model_id, "claude-sonnet-4.5");
+        assert_eq!(entries[0].1.display_name, "Claude Sonnet 4.5");
+        assert_eq!(entries[0].1.cache_creation_cost_per_million, "3.75");
+        assert_eq!(entries[1].1.input_cost_per_million, "1.75");
+
+        assert!(parse_catalog("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn test_preview_and_apply_catalog() {
+        let db = Database::memory().unwrap();
+        db.ensure_model_pricing_seeded().unwrap();
+
+        let preview = db.preview_pricing_catalog(OPENROUTER).unwrap();
+        // claude-sonnet-4.5 通过别名匹配到已有的带日期 ID，价格一致
+        assert!(preview.unchanged >= 1);
+        assert!(!preview
+            .changes
+            .iter()
+            .any(|c| c.incoming.model_id.starts_with("claude-sonnet-4")));
+
+        let modified = OPENROUTER.replace("0.000015", "0.000016");
+        let preview = db.preview_pricing_catalog(&modified).unwrap();
+        let change = preview
+            .changes
+            .iter()
+            .find(|c| c.kind == CatalogChangeKind::Changed)
+            .expect("changed sonnet");
+        assert_eq!(change.source_id, "anthropic/claude-sonnet-4.5");
+        assert_eq!(change.incoming.model_id, "claude-sonnet-4-5-20250929");
+        assert_eq!(change.incoming.display_name, "Claude Sonnet 4.5");
+        assert_eq!(
+            change.current.as_ref().unwrap().output_cost_per_million,
+            "15"
+        );
+
+        let selected: Vec<CatalogPrice> =
+            preview.changes.iter().map(|c| c.incoming.clone()).collect();
+        assert_eq!(
+            db.apply_pricing_catalog(&selected, 1_000).unwrap(),
+            selected.len()
+        );
+
+        let conn = db.conn.lock().unwrap();
+        let pricing = find_model_pricing_row(&conn, "claude-sonnet-4-5-20250929", 2_000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(pricing.output_cost_per_million, Decimal::from(16));
+        // 生效前仍按旧价格
+        let pricing = find_model_pricing_row(&conn, "claude-sonnet-4-5-20250929", 500)
+            .unwrap()
+            .unwrap();
+        assert_eq!(pricing.output_cost_per_million, Decimal::from(15));
+    }
+
+    #[test]
+    fn test_preview_prefers_exact_ids_over_aliases() {
+        let db = Database::memory().unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO model_pricing (model_id, display_name, input_cost_per_million, output_cost_per_million)
+                 VALUES ('acme-chat-20240101', 'Acme Chat (old)', '1', '2'),
+                        ('acme-chat-20250101', 'Acme Chat', '1', '2')",
+                [],
+            )
+            .unwrap();
+        }
+        let catalog = r#"{
+            "acme-chat": {
+                "litellm_provider": "openai", "mode": "chat",
+                "input_cost_per_token": 5e-06, "output_cost_per_token": 1e-05
+            },
+            "acme-chat-20250101": {
+                "litellm_provider": "openai", "mode": "chat",
+                "input_cost_per_token": 3e-06, "output_cost_per_token": 6e-06
+            }
+        }"#;
+
+        let preview = db.preview_pricing_catalog(catalog).unwrap();
+        assert_eq!(preview.skipped, 0);
+        let find = |id: &str| {
+            preview
+                .changes
+                .iter()
+                .find(|c| c.incoming.model_id == id)
+                .unwrap_or_else(|| panic!("missing change for {id}"))
+        };
+        // 精确条目更新自身，不被别名条目抢占
+        let exact = find("acme-chat-20250101");
+        assert_eq!(exact.kind, CatalogChangeKind::Changed);
+        assert_eq!(exact.source_id, "acme-chat-20250101");
+        assert_eq!(exact.incoming.input_cost_per_million, "3");
+        // 同族已有精确条目时，不带日期的条目作为新模型加入
+        assert_eq!(find("acme-chat").kind, CatalogChangeKind::Added);
+        assert!(!preview
+            .changes
+            .iter()
+            .any(|c| c.incoming.model_id == "acme-chat-20240101"));
+    }
+
+    #[test]
+    fn test_lookup_falls_back_to_alias() {
+        let db = Database::memory().unwrap();
+        db.ensure_model_pricing_seeded().unwrap();
+        let conn = db.conn.lock().unwrap();
+
+        let pricing = find_model_pricing_row(&conn, "anthropic/claude-sonnet-4.5", 0)
+            .unwrap()
+            .expect("alias match");
+        assert_eq!(pricing.input_cost_per_million, Decimal::from(3));
+        assert!(find_model_pricing_row(&conn, "unknown-model-x", 0)
+            .unwrap()
+            .is_none());
+    }
+}
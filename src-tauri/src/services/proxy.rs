@@ -819,7 +819,17 @@ impl ProxyService {
             connect_host
         };
 
-        let proxy_origin = format!("http://{}:{}", connect_host_for_url, config.listen_port);
+        let scheme = if self
+            .db
+            .get_proxy_tls_config()
+            .map(|tls| tls.enabled)
+            .unwrap_or(false)
+        {
+            "https"
+        } else {
+            "http"
+        };
+        let proxy_origin = format!("{scheme}://{}:{}", connect_host_for_url, config.listen_port);
         let proxy_url = proxy_origin.clone();
         let proxy_codex_base_url = format!("{}/v1", proxy_origin.trim_end_matches('/'));
 
@@ -1293,10 +1303,12 @@ impl ProxyService {
 
     fn is_local_proxy_url(url: &str) -> bool {
         let url = url.trim();
-        if !url.starts_with("http://") {
+        let Some(rest) = url
+            .strip_prefix("http://")
+            .or_else(|| url.strip_prefix("https://"))
+        else {
             return false;
-        }
-        let rest = &url["http://".len()..];
+        };
         rest.starts_with("127.0.0.1")
             || rest.starts_with("localhost")
             || rest.starts_with("0.0.0.0")
@@ -1810,7 +1822,7 @@ impl ProxyService {
             .map_err(|e| format!("保存代理配置失败: {e}"))?;
 
         // 检查服务器当前状态
-        let mut server_guard = self.server.write().await;
+        let server_guard = self.server.write().await;
         if server_guard.is_none() {
             return Ok(());
         }
@@ -1820,53 +1832,110 @@ impl ProxyService {
             || new_config.listen_port != previous.listen_port;
 
         if require_restart {
-            if let Some(server) = server_guard.take() {
-                server
-                    .stop()
-                    .await
-                    .map_err(|e| format!("重启前停止代理服务器失败: {e}"))?;
-            }
+            self.restart_server_and_sync_takeover(server_guard, new_config)
+                .await?;
+            log::info!("代理配置已更新，服务器已自动重启应用最新配置");
+            return Ok(());
+        } else if let Some(server) = server_guard.as_ref() {
+            server.apply_runtime_config(&new_config).await;
+            log::info!("代理配置已实时应用，无需重启代理服务器");
+        }
+
+        Ok(())
+    }
+
+    /// 获取本地代理 CA 证书（PEM）
+    ///
+    /// CA 的名称约束按当前监听地址与额外主机生成，未覆盖时重新生成
+    pub async fn ca_cert_pem(&self) -> Result<String, String> {
+        let proxy_config = self
+            .db
+            .get_proxy_config()
+            .await
+            .map_err(|e| format!("获取代理配置失败: {e}"))?;
+        let tls_config = self
+            .db
+            .get_proxy_tls_config()
+            .map_err(|e| format!("获取代理 TLS 配置失败: {e}"))?;
+        let hosts = crate::proxy::tls::server_cert_hosts(&tls_config, &proxy_config.listen_address);
+        crate::proxy::tls::ca_cert_pem(&hosts).map_err(|e| e.to_string())
+    }
+
+    /// 更新代理监听 TLS 配置
+    ///
+    /// 开启前先校验证书（自签模式下按需生成本地 CA），避免重启后服务不可用；
+    /// 服务器运行中时自动重启，并把接管写入的地址同步为新的协议。
+    pub async fn update_tls_config(&self, config: &ProxyTlsConfig) -> Result<(), String> {
+        let proxy_config = self
+            .db
+            .get_proxy_config()
+            .await
+            .map_err(|e| format!("获取代理配置失败: {e}"))?;
+        if config.enabled {
+            crate::proxy::tls::build_acceptor(config, &proxy_config.listen_address)
+                .map_err(|e| e.to_string())?;
+        }
 
-            let app_handle = self.app_handle.read().await.clone();
-            let new_server = ProxyServer::new(new_config, self.db.clone(), app_handle);
-            new_server
-                .start()
+        self.db
+            .set_proxy_tls_config(config)
+            .map_err(|e| format!("保存代理 TLS 配置失败: {e}"))?;
+
+        let server_guard = self.server.write().await;
+        if server_guard.is_none() {
+            return Ok(());
+        }
+        self.restart_server_and_sync_takeover(server_guard, proxy_config)
+            .await?;
+        log::info!("代理 TLS 配置已更新，服务器已自动重启");
+        Ok(())
+    }
+
+    /// 以新配置重启代理服务器，并同步 Live 接管中的代理地址（地址、端口或协议变化后调用）
+    async fn restart_server_and_sync_takeover(
+        &self,
+        mut server_guard: tokio::sync::RwLockWriteGuard<'_, Option<ProxyServer>>,
+        config: ProxyConfig,
+    ) -> Result<(), String> {
+        if let Some(server) = server_guard.take() {
+            server
+                .stop()
                 .await
-                .map_err(|e| format!("重启代理服务器失败: {e}"))?;
+                .map_err(|e| format!("重启前停止代理服务器失败: {e}"))?;
+        }
 
-            *server_guard = Some(new_server);
-            log::info!("代理配置已更新，服务器已自动重启应用最新配置");
+        let app_handle = self.app_handle.read().await.clone();
+        let new_server = ProxyServer::new(config, self.db.clone(), app_handle);
+        new_server
+            .start()
+            .await
+            .map_err(|e| format!("重启代理服务器失败: {e}"))?;
 
-            // 如果当前存在任意 app 的 Live 接管，需要同步更新 Live 中的代理地址（否则客户端仍指向旧端口）
-            drop(server_guard);
-            if let Ok(takeover) = self.get_takeover_status().await {
-                let mut updated_any = false;
+        *server_guard = Some(new_server);
 
-                if takeover.claude {
-                    self.takeover_live_config_best_effort(&AppType::Claude)
-                        .await?;
-                    updated_any = true;
-                }
-                if takeover.codex {
-                    self.takeover_live_config_best_effort(&AppType::Codex)
-                        .await?;
-                    updated_any = true;
-                }
-                if takeover.gemini {
-                    self.takeover_live_config_best_effort(&AppType::Gemini)
-                        .await?;
-                    updated_any = true;
-                }
+        // 如果当前存在任意 app 的 Live 接管，需要同步更新 Live 中的代理地址（否则客户端仍指向旧端口）
+        drop(server_guard);
+        if let Ok(takeover) = self.get_takeover_status().await {
+            let mut updated_any = false;
 
-                if updated_any {
-                    log::info!("已同步更新 Live 配置中的代理地址");
-                }
+            if takeover.claude {
+                self.takeover_live_config_best_effort(&AppType::Claude)
+                    .await?;
+                updated_any = true;
+            }
+            if takeover.codex {
+                self.takeover_live_config_best_effort(&AppType::Codex)
+                    .await?;
+                updated_any = true;
+            }
+            if takeover.gemini {
+                self.takeover_live_config_best_effort(&AppType::Gemini)
+                    .await?;
+                updated_any = true;
             }
 
-            return Ok(());
-        } else if let Some(server) = server_guard.as_ref() {
-            server.apply_runtime_config(&new_config).await;
-            log::info!("代理配置已实时应用，无需重启代理服务器");
+            if updated_any {
+                log::info!("已同步更新 Live 配置中的代理地址");
+            }
         }
 
         Ok(())
@@ -2018,6 +2087,19 @@ model = "gpt-5.1-codex"
         assert_eq!(base_url, new_url);
     }
 
+    #[test]
+    fn is_local_proxy_url_accepts_http_and_https() {
+        assert!(ProxyService::is_local_proxy_url("http://127.0.0.1:15721"));
+        assert!(ProxyService::is_local_proxy_url(
+            "https://127.0.0.1:15721/v1"
+        ));
+        assert!(ProxyService::is_local_proxy_url("https://[::1]:15721"));
+        assert!(!ProxyService::is_local_proxy_url(
+            "https://api.anthropic.com"
+        ));
+        assert!(!ProxyService::is_local_proxy_url("ftp://127.0.0.1"));
+    }
+
     #[tokio::test]
     #[serial]
     async fn sync_claude_token_does_not_add_anthropic_api_key() {
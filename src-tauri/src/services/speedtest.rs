@@ -4,6 +4,7 @@ use serde::Serialize;
 use std::time::Instant;
 
 use crate::error::AppError;
+use crate::provider::ProviderMeta;
 
 const DEFAULT_TIMEOUT_SECS: u64 = 8;
 const MAX_TIMEOUT_SECS: u64 = 30;
@@ -23,9 +24,12 @@ pub struct SpeedtestService;
 
 impl SpeedtestService {
     /// 测试一组端点的响应延迟。
+    ///
+    /// 传入供应商元数据时使用该供应商的代理与 TLS 配置，与实际转发保持一致。
     pub async fn test_endpoints(
         urls: Vec<String>,
         timeout_secs: Option<u64>,
+        provider_meta: Option<&ProviderMeta>,
     ) -> Result<Vec<EndpointLatency>, AppError> {
         if urls.is_empty() {
             return Ok(vec![]);
@@ -65,7 +69,7 @@ impl SpeedtestService {
         }
 
         let timeout = Self::sanitize_timeout(timeout_secs);
-        let (client, request_timeout) = Self::build_client(timeout)?;
+        let (client, request_timeout) = Self::build_client(timeout, provider_meta)?;
 
         let tasks = valid_targets.into_iter().map(|(idx, trimmed, parsed_url)| {
             let client = client.clone();
@@ -116,11 +120,19 @@ impl SpeedtestService {
         Ok(results.into_iter().flatten().collect::<Vec<_>>())
     }
 
-    fn build_client(timeout_secs: u64) -> Result<(Client, std::time::Duration), AppError> {
-        // 使用全局 HTTP 客户端（已包含代理配置）
+    fn build_client(
+        timeout_secs: u64,
+        provider_meta: Option<&ProviderMeta>,
+    ) -> Result<(Client, std::time::Duration), AppError> {
+        // 优先使用供应商单独的代理/TLS 配置，否则使用全局 HTTP 客户端
         // 返回 timeout Duration 供请求级别使用
         let timeout = std::time::Duration::from_secs(timeout_secs);
-        Ok((crate::proxy::http_client::get(), timeout))
+        let client = crate::proxy::http_client::get_for_provider(
+            provider_meta.and_then(|m| m.proxy_config.as_ref()),
+            provider_meta.and_then(|m| m.tls_config.as_ref()),
+        )
+        .map_err(AppError::Message)?;
+        Ok((client, timeout))
     }
 
     fn sanitize_timeout(timeout_secs: Option<u64>) -> u64 {
@@ -155,9 +167,12 @@ mod tests {
 
     #[test]
     fn test_endpoints_handles_empty_list() {
-        let result =
-            tauri::async_runtime::block_on(SpeedtestService::test_endpoints(Vec::new(), Some(5)))
-                .expect("empty list should succeed");
+        let result = tauri::async_runtime::block_on(SpeedtestService::test_endpoints(
+            Vec::new(),
+            Some(5),
+            None,
+        ))
+        .expect("empty list should succeed");
         assert!(result.is_empty());
     }
 
@@ -166,6 +181,7 @@ mod tests {
         let result = tauri::async_runtime::block_on(SpeedtestService::test_endpoints(
             vec!["not a url".into(), "".into()],
             None,
+            None,
         ))
         .expect("invalid inputs should still succeed");
 
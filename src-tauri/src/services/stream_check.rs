@@ -190,9 +190,11 @@ impl StreamCheckService {
             .extract_auth(provider)
             .ok_or_else(|| AppError::Message("API Key not found".to_string()))?;
 
-        // 获取 HTTP 客户端：优先使用供应商单独代理配置，否则使用全局客户端
+        // 获取 HTTP 客户端：优先使用供应商单独代理/TLS 配置，否则使用全局客户端
         let proxy_config = provider.meta.as_ref().and_then(|m| m.proxy_config.as_ref());
-        let client = crate::proxy::http_client::get_for_provider(proxy_config);
+        let tls_config = provider.meta.as_ref().and_then(|m| m.tls_config.as_ref());
+        let client = crate::proxy::http_client::get_for_provider(proxy_config, tls_config)
+            .map_err(AppError::Message)?;
         let request_timeout = std::time::Duration::from_secs(config.timeout_secs);
 
         let model_to_test = Self::resolve_test_model(app_type, provider, config);
@@ -0,0 +1,492 @@
+//! CLI 会话记录用量导入
+//!
+//! 未经过代理的请求（CLI 直连官方 API）不会产生请求日志。这里从会话记录中提取每条助手消息的
+//! token 用量，按消息时间生效的定价计算成本，以 source=transcript 写入 proxy_request_logs，
+//! 统计时可与代理日志合并或按来源分开查看。
+//!
+//! 以消息 ID 去重；按文件 mtime 增量导入，未变化的文件直接跳过。
+//! 经代理转发的消息已有请求日志：同一会话在消息时间附近存在代理日志时跳过，避免重复计费。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::usage::calculator::CostCalculator;
+use crate::session_manager::{self, TranscriptUsage};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// 会话记录导入的用量来源标记
+pub const TRANSCRIPT_SOURCE: &str = "transcript";
+
+/// 会话记录导入日志使用的 provider_id（无对应供应商）
+const TRANSCRIPT_PROVIDER_ID: &str = "transcript";
+
+/// 判定消息已由代理记录的时间窗口（秒）：代理日志在响应结束时写入，与消息时间存在偏差
+const PROXY_MATCH_WINDOW_SECS: i64 = 120;
+
+/// 支持提取用量的 CLI
+pub const TRANSCRIPT_APPS: [&str; 3] = ["claude", "codex", "gemini"];
+
+/// 导入结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptImportResult {
+    pub files_scanned: u32,
+    /// mtime 未变化而跳过的文件数
+    pub files_skipped: u32,
+    /// 新增或用量有更新的记录数
+    pub records_imported: u32,
+    /// 已导入且无变化的记录数
+    pub records_duplicate: u32,
+    /// 已有代理请求日志而跳过的记录数
+    pub records_proxied: u32,
+}
+
+impl Database {
+    /// 从指定 CLI 的会话记录导入用量
+    pub fn import_transcript_usage(
+        &self,
+        app_types: &[String],
+    ) -> Result<TranscriptImportResult, AppError> {
+        let mut result = TranscriptImportResult::default();
+
+        for app_type in app_types {
+            if !TRANSCRIPT_APPS.contains(&app_type.as_str()) {
+                return Err(AppError::localized(
+                    "error.unsupportedTranscriptApp",
+                    format!("不支持从 {app_type} 的会话记录导入用量"),
+                    format!("Importing usage from {app_type} transcripts is not supported"),
+                ));
+            }
+
+            let known = self.get_transcript_import_state(app_type)?;
+            for path in session_manager::usage_files(app_type) {
+                result.files_scanned += 1;
+                let Some(mtime) = file_mtime_ms(&path) else {
+                    continue;
+                };
+                let key = path.to_string_lossy().to_string();
+                if known.get(&key) == Some(&mtime) {
+                    result.files_skipped += 1;
+                    continue;
+                }
+
+                let records = match session_manager::load_usage(app_type, &path) {
+                    Ok(records) => records,
+                    Err(e) => {
+                        log::warn!("[USG-005] 解析会话记录失败 ({}): {e}", path.display());
+                        continue;
+                    }
+                };
+
+                let (imported, duplicate, proxied) =
+                    self.save_transcript_usage(app_type, &key, mtime, &records)?;
+                result.records_imported += imported;
+                result.records_duplicate += duplicate;
+                result.records_proxied += proxied;
+            }
+        }
+
+        // 先于代理日志导入、所在文件之后未再变化的记录
+        result.records_proxied += self.remove_proxied_transcript_usage()?;
+
+        log::info!(
+            "会话记录用量导入完成: 扫描 {} 个文件，跳过 {}，导入 {} 条，重复 {} 条，已由代理记录 {} 条",
+            result.files_scanned,
+            result.files_skipped,
+            result.records_imported,
+            result.records_duplicate,
+            result.records_proxied
+        );
+        Ok(result)
+    }
+
+    /// 已导入文件的 mtime（毫秒）
+    fn get_transcript_import_state(
+        &self,
+        app_type: &str,
+    ) -> Result<HashMap<String, i64>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare("SELECT path, mtime FROM transcript_import_state WHERE app_type = ?1")
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        let rows = stmt
+            .query_map([app_type], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(rows)
+    }
+
+    /// 写入单个文件的用量记录并更新导入进度，返回（新增或更新数，重复数，已由代理记录数）
+    ///
+    /// 会话进行中写入的消息用量可能在之后更新，已存在的记录仅在 token 数变化时重新计费。
+    /// 已由代理记录的消息不写入；先于代理日志导入的记录在此时删除
+    fn save_transcript_usage(
+        &self,
+        app_type: &str,
+        path: &str,
+        mtime: i64,
+        records: &[TranscriptUsage],
+    ) -> Result<(u32, u32, u32), AppError> {
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let mut pricing_cache = HashMap::new();
+        let (mut imported, mut duplicate, mut proxied) = (0, 0, 0);
+        for record in records {
+            let created_at = record.ts.unwrap_or(mtime) / 1000;
+            let request_id = format!("{TRANSCRIPT_SOURCE}:{app_type}:{}", record.message_id);
+            if Self::is_proxied(&tx, app_type, record.session_id.as_deref(), created_at)? {
+                Self::retract_usage_rollup(&tx, &request_id)?;
+                tx.execute(
+                    "DELETE FROM proxy_request_logs WHERE request_id = ?1 AND source = ?2",
+                    rusqlite::params![request_id, TRANSCRIPT_SOURCE],
+                )
+                .map_err(|e| AppError::Database(format!("写入会话记录用量失败: {e}")))?;
+                proxied += 1;
+                continue;
+            }
+            let pricing =
+                Self::get_model_pricing_cached(&tx, &mut pricing_cache, &record.model, created_at)?;
+            let cost = pricing.map(|pricing| {
+                let tier = pricing.for_prompt_tokens(record.usage.prompt_tokens(app_type));
+                CostCalculator::calculate(&record.usage, tier, Decimal::ONE)
+            });
+            let costs = match cost {
+                Some(cost) => [
+                    cost.input_cost,
+                    cost.output_cost,
+                    cost.cache_read_cost,
+                    cost.cache_creation_cost,
+                    cost.total_cost,
+                ]
+                .map(|value| value.to_string()),
+                None => ["0", "0", "0", "0", "0"].map(str::to_string),
+            };
+
+            let changed = tx
+                .execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model, request_model,
+                        input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
+                        input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd,
+                        total_cost_usd, latency_ms, status_code, session_id, cost_multiplier,
+                        cache_hit_rate, source, created_at
+                    ) VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, 0, 200, ?14, '1', ?15, ?16, ?17)
+                    ON CONFLICT(request_id) DO UPDATE SET
+                        input_tokens = excluded.input_tokens,
+                        output_tokens = excluded.output_tokens,
+                        cache_read_tokens = excluded.cache_read_tokens,
+                        cache_creation_tokens = excluded.cache_creation_tokens,
+                        input_cost_usd = excluded.input_cost_usd,
+                        output_cost_usd = excluded.output_cost_usd,
+                        cache_read_cost_usd = excluded.cache_read_cost_usd,
+                        cache_creation_cost_usd = excluded.cache_creation_cost_usd,
+                        total_cost_usd = excluded.total_cost_usd,
+                        cache_hit_rate = excluded.cache_hit_rate
+                    WHERE source = excluded.source AND (
+                        input_tokens != excluded.input_tokens
+                        OR output_tokens != excluded.output_tokens
+                        OR cache_read_tokens != excluded.cache_read_tokens
+                        OR cache_creation_tokens != excluded.cache_creation_tokens
+                    )",
+                    rusqlite::params![
+                        request_id,
+                        TRANSCRIPT_PROVIDER_ID,
+                        app_type,
+                        record.model,
+                        record.usage.input_tokens,
+                        record.usage.output_tokens,
+                        record.usage.cache_read_tokens,
+                        record.usage.cache_creation_tokens,
+                        costs[0],
+                        costs[1],
+                        costs[2],
+                        costs[3],
+                        costs[4],
+                        record.session_id,
+                        record.usage.cache_hit_rate(app_type),
+                        TRANSCRIPT_SOURCE,
+                        created_at,
+                    ],
+                )
+                .map_err(|e| AppError::Database(format!("写入会话记录用量失败: {e}")))?;
+
+            if changed > 0 {
+                imported += 1;
+            } else {
+                duplicate += 1;
+            }
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO transcript_import_state (path, app_type, mtime, imported_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![path, app_type, mtime, chrono::Utc::now().timestamp()],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok((imported, duplicate, proxied))
+    }
+
+    /// 删除已有代理请求日志覆盖的会话记录用量（同时扣除小时汇总），返回删除数
+    fn remove_proxied_transcript_usage(&self) -> Result<u32, AppError> {
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let request_ids: Vec<String> = {
+            let mut stmt = tx
+                .prepare(
+                    "SELECT t.request_id FROM proxy_request_logs t
+                     WHERE t.source = ?1 AND t.session_id IS NOT NULL AND t.session_id != ''
+                       AND EXISTS (
+                           SELECT 1 FROM proxy_request_logs p
+                           WHERE p.session_id = t.session_id AND p.app_type = t.app_type
+                             AND p.source != ?1
+                             AND p.created_at BETWEEN t.created_at - ?2 AND t.created_at + ?2
+                       )",
+                )
+                .map_err(|e| AppError::Database(e.to_string()))?;
+            let rows = stmt
+                .query_map(
+                    rusqlite::params![TRANSCRIPT_SOURCE, PROXY_MATCH_WINDOW_SECS],
+                    |row| row.get(0),
+                )
+                .map_err(|e| AppError::Database(e.to_string()))?
+                .collect::<Result<_, _>>()
+                .map_err(|e| AppError::Database(e.to_string()))?;
+            rows
+        };
+
+        for request_id in &request_ids {
+            Self::retract_usage_rollup(&tx, request_id)?;
+            tx.execute(
+                "DELETE FROM proxy_request_logs WHERE request_id = ?1",
+                [request_id],
+            )
+            .map_err(|e| AppError::Database(format!("删除重复的会话记录用量失败: {e}")))?;
+        }
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(request_ids.len() as u32)
+    }
+
+    /// 同一会话在消息时间附近是否已有代理（含 Mock）请求日志
+    fn is_proxied(
+        conn: &rusqlite::Connection,
+        app_type: &str,
+        session_id: Option<&str>,
+        created_at: i64,
+    ) -> Result<bool, AppError> {
+        let Some(session_id) = session_id.filter(|id| !id.is_empty()) else {
+            return Ok(false);
+        };
+        conn.query_row(
+            "SELECT EXISTS(
+                SELECT 1 FROM proxy_request_logs
+                WHERE session_id = ?1 AND app_type = ?2 AND source != ?3
+                  AND created_at BETWEEN ?4 AND ?5
+            )",
+            rusqlite::params![
+                session_id,
+                app_type,
+                TRANSCRIPT_SOURCE,
+                created_at - PROXY_MATCH_WINDOW_SECS,
+                created_at + PROXY_MATCH_WINDOW_SECS,
+            ],
+            |row| row.get(0),
+        )
+        .map_err(|e| AppError::Database(format!("查询代理请求日志失败: {e}")))
+    }
+}
+
+fn file_mtime_ms(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_temp(name: &str, content: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(content.as_bytes())
+            .unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_load_claude_usage_keeps_last_block() {
+        let content = [
+            r#"{"type":"user","sessionId":"s1","message":{"role":"user","content":"hi"}}"#,
+            r#"{"type":"assistant","sessionId":"s1","timestamp":"2026-01-01T00:00:00Z","message":{"id":"msg_1","model":"claude-sonnet-4-5","usage":{"input_tokens":10,"output_tokens":1,"cache_read_input_tokens":100}}}"#,
+            r#"{"type":"assistant","sessionId":"s1","timestamp":"2026-01-01T00:00:01Z","message":{"id":"msg_1","model":"claude-sonnet-4-5","usage":{"input_tokens":10,"output_tokens":50,"cache_read_input_tokens":100}}}"#,
+            r#"{"type":"assistant","sessionId":"s1","message":{"id":"msg_2","model":"<synthetic>","usage":{"input_tokens":0,"output_tokens":0}}}"#,
+        ]
+        .join("\n");
+        let (_dir, path) = write_temp("s1.jsonl", &content);
+
+        let records = session_manager::load_usage("claude", &path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].message_id, "msg_1");
+        assert_eq!(records[0].usage.output_tokens, 50);
+        assert_eq!(records[0].usage.cache_read_tokens, 100);
+        assert_eq!(records[0].session_id.as_deref(), Some("s1"));
+    }
+
+    #[test]
+    fn test_load_codex_and_gemini_usage() {
+        let content = [
+            r#"{"type":"session_meta","payload":{"id":"c1"}}"#,
+            r#"{"type":"turn_context","payload":{"model":"gpt-5-codex"}}"#,
+            r#"{"type":"event_msg","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":200,"cached_input_tokens":150,"output_tokens":20},"total_token_usage":{"total_tokens":220}}}}"#,
+            r#"{"type":"event_msg","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":200,"cached_input_tokens":150,"output_tokens":20},"total_token_usage":{"total_tokens":220}}}}"#,
+            r#"{"type":"event_msg","payload":{"type":"token_count","info":null}}"#,
+        ]
+        .join("\n");
+        let (_dir, path) = write_temp("rollout.jsonl", &content);
+        let records = session_manager::load_usage("codex", &path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].message_id, "c1:220");
+        assert_eq!(records[0].model, "gpt-5-codex");
+        assert_eq!(records[0].usage.cache_read_tokens, 150);
+
+        let content = r#"{"sessionId":"g1","messages":[
+            {"id":"u1","type":"user","content":"hi"},
+            {"id":"m1","type":"gemini","model":"gemini-2.5-pro","timestamp":"2026-01-01T00:00:00Z",
+             "tokens":{"input":1000,"output":40,"cached":600,"thoughts":10,"total":1050}}
+        ]}"#;
+        let (_dir, path) = write_temp("session.json", content);
+        let records = session_manager::load_usage("gemini", &path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].usage.output_tokens, 50);
+        assert_eq!(records[0].usage.cache_read_tokens, 600);
+    }
+
+    #[test]
+    fn test_save_transcript_usage_dedupes_and_updates() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let mut record = TranscriptUsage {
+            message_id: "msg_1".to_string(),
+            session_id: Some("s1".to_string()),
+            model: "claude-sonnet-4-5-20250929".to_string(),
+            usage: Default::default(),
+            ts: Some(1_767_225_600_000),
+        };
+        record.usage.input_tokens = 1_000_000;
+
+        assert_eq!(
+            db.save_transcript_usage("claude", "/a", 1, &[record.clone()])?,
+            (1, 0, 0)
+        );
+        assert_eq!(
+            db.save_transcript_usage("claude", "/a", 2, &[record.clone()])?,
+            (0, 1, 0)
+        );
+        record.usage.output_tokens = 10;
+        assert_eq!(
+            db.save_transcript_usage("claude", "/a", 3, &[record])?,
+            (1, 0, 0)
+        );
+
+        let state = db.get_transcript_import_state("claude")?;
+        assert_eq!(state.get("/a"), Some(&3));
+
+        let summary = db.get_usage_summary(None, None, None, Some(TRANSCRIPT_SOURCE))?;
+        assert_eq!(summary.total_requests, 1);
+        assert_eq!(summary.total_output_tokens, 10);
+        assert!(summary.total_cost.parse::<f64>().unwrap() > 0.0);
+        assert_eq!(
+            db.get_usage_summary(None, None, None, Some("proxy"))?
+                .total_requests,
+            0
+        );
+        assert!(db
+            .get_usage_summary(None, None, None, Some("other"))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_transcript_usage_skips_proxied_messages() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let ts = 1_767_225_600_000;
+        let record = |message_id: &str, session_id: &str, ts: i64| TranscriptUsage {
+            message_id: message_id.to_string(),
+            session_id: Some(session_id.to_string()),
+            model: "claude-sonnet-4-5-20250929".to_string(),
+            usage: Default::default(),
+            ts: Some(ts),
+        };
+
+        // 代理日志出现前导入的记录，在之后的导入中被移除
+        assert_eq!(
+            db.save_transcript_usage("claude", "/a", 1, &[record("msg_1", "s1", ts)])?,
+            (1, 0, 0)
+        );
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                    status_code, latency_ms, session_id, created_at)
+                 VALUES ('p1', 'p1', 'claude', 'claude-sonnet-4-5-20250929', 200, 100, 's1', ?1)",
+                [ts / 1000 + 30],
+            )
+            .unwrap();
+        }
+
+        let records = [
+            record("msg_1", "s1", ts),
+            // 其他会话、或超出时间窗口的消息照常导入
+            record("msg_2", "s2", ts),
+            record("msg_3", "s1", ts + (PROXY_MATCH_WINDOW_SECS + 60) * 1000),
+        ];
+        assert_eq!(
+            db.save_transcript_usage("claude", "/a", 2, &records)?,
+            (2, 0, 1)
+        );
+
+        let summary = db.get_usage_summary(None, None, None, Some(TRANSCRIPT_SOURCE))?;
+        assert_eq!(summary.total_requests, 2);
+
+        // 导入后才出现的代理日志：文件未变化时由清理步骤移除
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                    status_code, latency_ms, session_id, created_at)
+                 VALUES ('p2', 'p1', 'claude', 'claude-sonnet-4-5-20250929', 200, 100, 's2', ?1)",
+                [ts / 1000 - 10],
+            )
+            .unwrap();
+        }
+        assert_eq!(db.remove_proxied_transcript_usage()?, 1);
+        let summary = db.get_usage_summary(None, None, None, Some(TRANSCRIPT_SOURCE))?;
+        assert_eq!(summary.total_requests, 1);
+
+        // 被移除的记录同时从小时汇总中扣除
+        let conn = lock_conn!(db.conn);
+        let rollup_requests: i64 = conn
+            .query_row(
+                "SELECT SUM(request_count) FROM usage_hourly_rollups WHERE source = ?1",
+                [TRANSCRIPT_SOURCE],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(rollup_requests, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,605 @@
+//! 用量导出与定期花费报告
+//!
+//! 请求日志按 `LogFilters` 筛选后导出为 CSV/JSON；花费报告包含总计，以及按供应商、
+//! 模型和客户端令牌（即使用者）的拆分。定期报告在应用运行期间每小时检查一次，
+//! 为上一个完整的自然周/月生成报告文件，目标文件已存在时不重复生成。
+
+use crate::config::{get_app_config_dir, write_json_file, write_text_file};
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::services::usage_stats::{
+    usage_rows_sql, CurrencyCost, LogFilters, RequestLogDetail, UsageSummary,
+};
+use crate::time_util::local_midnight;
+use chrono::{DateTime, Datelike, Duration, Local, Months, NaiveDate, TimeZone};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// 导出分页大小
+const EXPORT_PAGE_SIZE: u32 = 1000;
+
+/// 导出文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl UsageExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// 定期报告周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageReportPeriod {
+    Weekly,
+    Monthly,
+}
+
+impl UsageReportPeriod {
+    /// `today` 之前最近一个完整周期：(起始日, 下一周期起始日, 文件名标签)
+    fn last_complete(self, today: NaiveDate) -> (NaiveDate, NaiveDate, String) {
+        match self {
+            Self::Weekly => {
+                let this_week =
+                    today - Duration::days(i64::from(today.weekday().num_days_from_monday()));
+                let start = this_week - Duration::days(7);
+                let week = start.iso_week();
+                (
+                    start,
+                    this_week,
+                    format!("weekly-{}-W{:02}", week.year(), week.week()),
+                )
+            }
+            Self::Monthly => {
+                let this_month = today.with_day(1).unwrap_or(today);
+                let start = this_month
+                    .checked_sub_months(Months::new(1))
+                    .unwrap_or(this_month);
+                (
+                    start,
+                    this_month,
+                    format!("monthly-{}", start.format("%Y-%m")),
+                )
+            }
+        }
+    }
+}
+
+/// 定期报告设置（设备级，保存在 settings.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReportSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_report_periods")]
+    pub periods: Vec<UsageReportPeriod>,
+    #[serde(default)]
+    pub format: UsageExportFormat,
+    /// 报告输出目录，未设置时为 `~/.cc-switch/reports`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// 报告币种，未设置时按美元
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+fn default_report_periods() -> Vec<UsageReportPeriod> {
+    vec![UsageReportPeriod::Monthly]
+}
+
+impl UsageReportSettings {
+    fn output_dir(&self) -> PathBuf {
+        self.directory
+            .as_deref()
+            .map(str::trim)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| get_app_config_dir().join("reports"))
+    }
+}
+
+/// 花费拆分中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendBreakdown {
+    pub key: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_type: Option<String>,
+    pub request_count: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_cost: String,
+}
+
+/// 花费报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    pub period_start: i64,
+    pub period_end: i64,
+    pub generated_at: i64,
+    pub currency: String,
+    pub summary: UsageSummary,
+    pub by_provider: Vec<SpendBreakdown>,
+    pub by_model: Vec<SpendBreakdown>,
+    /// 按客户端令牌拆分，未使用令牌的请求归入空键
+    pub by_client: Vec<SpendBreakdown>,
+}
+
+/// CSV 字段转义：包含分隔符、引号或换行时加引号
+///
+/// 以 `=`、`+`、`-`、`@` 开头的非数字文本（模型名、错误信息、令牌名等用户可控内容）
+/// 前加 `'`，防止在电子表格中被当作公式执行
+fn csv_field(value: &str) -> String {
+    let value =
+        if value.starts_with(['=', '+', '-', '@', '\t', '\r']) && value.parse::<f64>().is_err() {
+            format!("'{value}")
+        } else {
+            value.to_string()
+        };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn csv_line(fields: &[String]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+fn format_local_time(ts: i64) -> String {
+    Local
+        .timestamp_opt(ts, 0)
+        .single()
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default()
+}
+
+const LOG_CSV_HEADER: &[&str] = &[
+    "request_id",
+    "time",
+    "app_type",
+    "provider_id",
+    "provider_name",
+    "model",
+    "request_model",
+    "source",
+    "client_token_id",
+    "status_code",
+    "input_tokens",
+    "output_tokens",
+    "cache_read_tokens",
+    "cache_creation_tokens",
+    "total_cost_usd",
+    "billing_currency",
+    "billing_cost",
+    "latency_ms",
+    "is_streaming",
+    "error_message",
+];
+
+fn log_csv_line(log: &RequestLogDetail) -> String {
+    csv_line(&[
+        log.request_id.clone(),
+        format_local_time(log.created_at),
+        log.app_type.clone(),
+        log.provider_id.clone(),
+        log.provider_name.clone().unwrap_or_default(),
+        log.model.clone(),
+        log.request_model.clone().unwrap_or_default(),
+        log.source.clone(),
+        log.client_token_id.clone().unwrap_or_default(),
+        log.status_code.to_string(),
+        log.input_tokens.to_string(),
+        log.output_tokens.to_string(),
+        log.cache_read_tokens.to_string(),
+        log.cache_creation_tokens.to_string(),
+        log.total_cost_usd.clone(),
+        log.billing_currency.clone().unwrap_or_default(),
+        log.billing_cost.clone().unwrap_or_default(),
+        log.latency_ms.to_string(),
+        log.is_streaming.to_string(),
+        log.error_message.clone().unwrap_or_default(),
+    ])
+}
+
+fn report_to_csv(report: &UsageReport) -> String {
+    let header: Vec<String> = [
+        "section",
+        "key",
+        "name",
+        "app_type",
+        "request_count",
+        "input_tokens",
+        "output_tokens",
+        "total_cost",
+        "currency",
+    ]
+    .iter()
+    .map(|h| h.to_string())
+    .collect();
+    let mut out = csv_line(&header);
+    out.push_str(&csv_line(&[
+        "total".to_string(),
+        String::new(),
+        format!(
+            "{} ~ {}",
+            format_local_time(report.period_start),
+            format_local_time(report.period_end)
+        ),
+        String::new(),
+        report.summary.total_requests.to_string(),
+        report.summary.total_input_tokens.to_string(),
+        report.summary.total_output_tokens.to_string(),
+        report.summary.total_cost.clone(),
+        report.currency.clone(),
+    ]));
+    for (section, rows) in [
+        ("provider", &report.by_provider),
+        ("model", &report.by_model),
+        ("client", &report.by_client),
+    ] {
+        for row in rows {
+            out.push_str(&csv_line(&[
+                section.to_string(),
+                row.key.clone(),
+                row.name.clone(),
+                row.app_type.clone().unwrap_or_default(),
+                row.request_count.to_string(),
+                row.input_tokens.to_string(),
+                row.output_tokens.to_string(),
+                row.total_cost.clone(),
+                report.currency.clone(),
+            ]));
+        }
+    }
+    out
+}
+
+/// 执行返回 (key, name, app_type, 请求数, 输入, 输出, 成本) 的拆分查询
+fn query_breakdown(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<SpendBreakdown>, AppError> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params, |row| {
+        Ok(SpendBreakdown {
+            key: row.get(0)?,
+            name: row.get(1)?,
+            app_type: row.get(2)?,
+            request_count: row.get::<_, i64>(3)? as u64,
+            input_tokens: row.get::<_, i64>(4)? as u64,
+            output_tokens: row.get::<_, i64>(5)? as u64,
+            total_cost: format!("{:.6}", row.get::<_, f64>(6)?),
+        })
+    })?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+}
+
+impl Database {
+    /// 按筛选条件导出全部请求日志，返回导出条数
+    ///
+    /// 逐页写入临时文件，完成后替换目标文件，内存中最多只保留一页日志
+    pub fn export_request_logs(
+        &self,
+        filters: &LogFilters,
+        format: UsageExportFormat,
+        path: &Path,
+    ) -> Result<u64, AppError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| AppError::io(parent, e))?;
+        }
+        let tmp = path.with_extension(format!("{}.tmp", format.extension()));
+        let count = match self.write_request_logs(filters, format, &tmp) {
+            Ok(count) => count,
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                return Err(e);
+            }
+        };
+        fs::rename(&tmp, path).map_err(|e| AppError::io(path, e))?;
+        log::info!("已导出 {count} 条请求日志到 {}", path.display());
+        Ok(count)
+    }
+
+    fn write_request_logs(
+        &self,
+        filters: &LogFilters,
+        format: UsageExportFormat,
+        path: &Path,
+    ) -> Result<u64, AppError> {
+        let file = fs::File::create(path).map_err(|e| AppError::io(path, e))?;
+        let mut out = BufWriter::new(file);
+        let io_err = |e| AppError::io(path, e);
+
+        // 固定截止时间，避免导出期间新写入的日志导致分页错位
+        let mut filters = filters.clone();
+        let now = chrono::Utc::now().timestamp();
+        filters.end_date = Some(filters.end_date.map_or(now, |end| end.min(now)));
+
+        match format {
+            UsageExportFormat::Csv => {
+                let header: Vec<String> = LOG_CSV_HEADER.iter().map(|h| h.to_string()).collect();
+                out.write_all(csv_line(&header).as_bytes())
+                    .map_err(io_err)?;
+            }
+            UsageExportFormat::Json => out.write_all(b"[").map_err(io_err)?,
+        }
+
+        let mut count = 0u64;
+        let mut page = 0;
+        loop {
+            let batch = self.get_request_logs(&filters, page, EXPORT_PAGE_SIZE)?;
+            for log in &batch.data {
+                match format {
+                    UsageExportFormat::Csv => {
+                        out.write_all(log_csv_line(log).as_bytes())
+                            .map_err(io_err)?;
+                    }
+                    UsageExportFormat::Json => {
+                        let separator: &[u8] = if count == 0 { b"\n  " } else { b",\n  " };
+                        out.write_all(separator).map_err(io_err)?;
+                        serde_json::to_writer(&mut out, log)
+                            .map_err(|e| AppError::JsonSerialize { source: e })?;
+                    }
+                }
+                count += 1;
+            }
+            if batch.data.len() < EXPORT_PAGE_SIZE as usize {
+                break;
+            }
+            page += 1;
+        }
+
+        if format == UsageExportFormat::Json {
+            let end: &[u8] = if count == 0 { b"]\n" } else { b"\n]\n" };
+            out.write_all(end).map_err(io_err)?;
+        }
+        out.flush().map_err(io_err)?;
+        Ok(count)
+    }
+
+    /// 生成 `[start, end]` 的花费报告
+    ///
+    /// 总计与供应商、模型拆分读取小时汇总，日志清理后仍可生成；
+    /// 客户端令牌拆分依赖原始日志，只覆盖仍保留的日志。
+    pub fn build_usage_report(
+        &self,
+        start: i64,
+        end: i64,
+        currency: Option<&str>,
+    ) -> Result<UsageReport, AppError> {
+        let summary = self.get_usage_summary(Some(start), Some(end), currency, None)?;
+
+        let conn = lock_conn!(self.conn);
+        let cost = CurrencyCost::resolve(&conn, currency, "u.")?;
+        let rows = usage_rows_sql(Some(start), Some(end), true);
+
+        let by_provider = query_breakdown(
+            &conn,
+            &format!(
+                "SELECT u.provider_id, COALESCE(p.name, u.provider_id), u.app_type,
+                        SUM(u.request_count), COALESCE(SUM(u.input_tokens), 0),
+                        COALESCE(SUM(u.output_tokens), 0), COALESCE(SUM({cost_expr}), 0) as total_cost
+                 FROM ({rows}) u
+                 LEFT JOIN providers p ON u.provider_id = p.id AND u.app_type = p.app_type
+                 GROUP BY u.provider_id, u.app_type
+                 HAVING SUM(u.request_count) > 0
+                 ORDER BY total_cost DESC",
+                cost_expr = cost.expr
+            ),
+            [],
+        )?;
+
+        let by_model = query_breakdown(
+            &conn,
+            &format!(
+                "SELECT u.model, u.model, NULL,
+                        SUM(u.request_count), COALESCE(SUM(u.input_tokens), 0),
+                        COALESCE(SUM(u.output_tokens), 0), COALESCE(SUM({cost_expr}), 0) as total_cost
+                 FROM ({rows}) u
+                 GROUP BY u.model
+                 HAVING SUM(u.request_count) > 0
+                 ORDER BY total_cost DESC",
+                cost_expr = cost.expr
+            ),
+            [],
+        )?;
+
+        let raw_cost = CurrencyCost::resolve(&conn, currency, "l.")?;
+        let by_client = query_breakdown(
+            &conn,
+            &format!(
+                "SELECT COALESCE(l.client_token_id, ''), COALESCE(t.name, l.client_token_id, ''), NULL,
+                        COUNT(*), COALESCE(SUM(l.input_tokens), 0),
+                        COALESCE(SUM(l.output_tokens), 0), COALESCE(SUM({cost_expr}), 0) as total_cost
+                 FROM proxy_request_logs l
+                 LEFT JOIN proxy_client_tokens t ON t.id = l.client_token_id
+                 WHERE l.created_at >= ?1 AND l.created_at <= ?2
+                 GROUP BY l.client_token_id
+                 ORDER BY total_cost DESC",
+                cost_expr = raw_cost.expr
+            ),
+            params![start, end],
+        )?;
+
+        Ok(UsageReport {
+            period_start: start,
+            period_end: end,
+            generated_at: Local::now().timestamp(),
+            currency: cost.currency,
+            summary,
+            by_provider,
+            by_model,
+            by_client,
+        })
+    }
+
+    /// 生成花费报告并写入文件
+    pub fn export_usage_report(
+        &self,
+        start: i64,
+        end: i64,
+        currency: Option<&str>,
+        format: UsageExportFormat,
+        path: &Path,
+    ) -> Result<UsageReport, AppError> {
+        let report = self.build_usage_report(start, end, currency)?;
+        match format {
+            UsageExportFormat::Csv => write_text_file(path, &report_to_csv(&report))?,
+            UsageExportFormat::Json => write_json_file(path, &report)?,
+        }
+        log::info!("已导出用量报告到 {}", path.display());
+        Ok(report)
+    }
+
+    /// 为已启用的周期生成上一个完整周期的报告，返回新生成的文件
+    pub fn generate_scheduled_usage_reports(
+        &self,
+        now: DateTime<Local>,
+    ) -> Result<Vec<PathBuf>, AppError> {
+        let Some(settings) = crate::settings::get_usage_report_settings() else {
+            return Ok(Vec::new());
+        };
+        if !settings.enabled {
+            return Ok(Vec::new());
+        }
+
+        let dir = settings.output_dir();
+        let mut generated = Vec::new();
+        for period in &settings.periods {
+            let (start, next, label) = period.last_complete(now.date_naive());
+            let path = dir.join(format!(
+                "usage-report-{label}.{}",
+                settings.format.extension()
+            ));
+            if path.exists() {
+                continue;
+            }
+            self.export_usage_report(
+                local_midnight(start).unwrap_or_default(),
+                local_midnight(next).unwrap_or_default() - 1,
+                settings.currency.as_deref(),
+                settings.format,
+                &path,
+            )?;
+            generated.push(path);
+        }
+        Ok(generated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_complete_period() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 4).unwrap(); // 周三
+        let (start, next, label) = UsageReportPeriod::Weekly.last_complete(today);
+        assert_eq!(start, NaiveDate::from_ymd_opt(2026, 2, 23).unwrap());
+        assert_eq!(next, NaiveDate::from_ymd_opt(2026, 3, 2).unwrap());
+        assert_eq!(label, "weekly-2026-W09");
+
+        let (start, next, label) = UsageReportPeriod::Monthly.last_complete(today);
+        assert_eq!(start, NaiveDate::from_ymd_opt(2026, 2, 1).unwrap());
+        assert_eq!(next, NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+        assert_eq!(label, "monthly-2026-02");
+
+        let january = NaiveDate::from_ymd_opt(2026, 1, 15).unwrap();
+        let (start, _, label) = UsageReportPeriod::Monthly.last_complete(january);
+        assert_eq!(start, NaiveDate::from_ymd_opt(2025, 12, 1).unwrap());
+        assert_eq!(label, "monthly-2025-12");
+    }
+
+    #[test]
+    fn test_csv_field_guards_formula_injection() {
+        assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_field("+cmd"), "'+cmd");
+        assert_eq!(csv_field("-2+3"), "'-2+3");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        // 数字保持原样
+        assert_eq!(csv_field("-0.5"), "-0.5");
+        assert_eq!(csv_field("claude-3"), "claude-3");
+    }
+
+    #[test]
+    fn test_export_logs_and_report() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO proxy_client_tokens (id, name, token_hash, token_prefix, created_at)
+                 VALUES ('t1', 'alice', 'hash', 'ccs_', 0)",
+                [],
+            )?;
+            for (id, token, model, cost) in [
+                ("r1", Some("t1"), "claude-3", "0.5"),
+                ("r2", Some("t1"), "claude-3", "0.25"),
+                ("r3", None, "gpt-4, \"turbo\"", "1"),
+            ] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model, input_tokens, output_tokens,
+                        total_cost_usd, latency_ms, status_code, client_token_id, created_at
+                    ) VALUES (?1, 'p1', 'claude', ?2, 100, 50, ?3, 100, 200, ?4, 1000)",
+                    params![id, model, cost, token],
+                )?;
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let csv_path = dir.path().join("logs.csv");
+        let count =
+            db.export_request_logs(&LogFilters::default(), UsageExportFormat::Csv, &csv_path)?;
+        assert_eq!(count, 3);
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.contains("\"gpt-4, \"\"turbo\"\"\""));
+
+        let json_path = dir.path().join("logs.json");
+        let count =
+            db.export_request_logs(&LogFilters::default(), UsageExportFormat::Json, &json_path)?;
+        assert_eq!(count, 3);
+        let logs: Vec<serde_json::Value> =
+            serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(logs.len(), 3);
+        assert!(!dir.path().join("logs.json.tmp").exists());
+
+        let report = db.build_usage_report(0, 3599, None)?;
+        assert_eq!(report.summary.total_requests, 3);
+        assert_eq!(report.by_provider.len(), 1);
+        assert_eq!(report.by_model[0].key, "gpt-4, \"turbo\"");
+        assert_eq!(report.by_client.len(), 2);
+        let alice = report.by_client.iter().find(|c| c.key == "t1").unwrap();
+        assert_eq!(alice.name, "alice");
+        assert_eq!(alice.request_count, 2);
+        assert_eq!(alice.total_cost, "0.750000");
+
+        let report_csv = report_to_csv(&report);
+        assert!(report_csv.starts_with("section,key,name"));
+        assert_eq!(report_csv.lines().count(), 1 + 1 + 1 + 2 + 2);
+        Ok(())
+    }
+}
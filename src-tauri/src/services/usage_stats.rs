@@ -4,8 +4,14 @@
 
 use crate::database::{lock_conn, Database};
 use crate::error::AppError;
+use crate::proxy::usage::calculator::{CostCalculator, ModelPricing, PricingTier};
+use crate::proxy::usage::parser::TokenUsage;
+use crate::services::currency::{find_exchange_rate, normalize_currency, BASE_CURRENCY};
+use crate::services::pricing_catalog::find_alias_model_id;
+use crate::session_manager::SessionMeta;
 use chrono::{Local, TimeZone};
 use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::prelude::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -22,6 +28,8 @@ pub struct UsageSummary {
     pub total_cache_creation_tokens: u64,
     pub total_cache_read_tokens: u64,
     pub success_rate: f32,
+    /// 成本所用币种
+    pub currency: String,
 }
 
 /// 每日统计
@@ -62,6 +70,71 @@ pub struct ModelStats {
     pub avg_cost_per_request: String,
 }
 
+/// 会话或项目内单个模型的用量占比
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelUsageShare {
+    pub model: String,
+    pub request_count: u64,
+    pub total_tokens: u64,
+    pub total_cost: String,
+}
+
+/// 按会话汇总的用量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionUsageStats {
+    /// 会话 ID（已去除代理为 Codex 会话添加的 `codex_` 前缀，与会话管理器一致）
+    pub session_id: String,
+    pub app_type: String,
+    pub request_count: u64,
+    pub total_input_tokens: u64,
+    pub total_output_tokens: u64,
+    pub total_cache_read_tokens: u64,
+    pub total_cache_creation_tokens: u64,
+    pub total_cost: String,
+    pub currency: String,
+    pub first_request_at: i64,
+    pub last_request_at: i64,
+    /// 按成本降序的模型构成
+    pub models: Vec<ModelUsageShare>,
+}
+
+/// 按项目目录汇总的用量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectUsageStats {
+    /// 项目目录（会话管理器中找不到对应会话时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_dir: Option<String>,
+    pub session_count: u64,
+    pub request_count: u64,
+    pub total_tokens: u64,
+    pub total_cost: String,
+    pub currency: String,
+    pub last_request_at: i64,
+    pub models: Vec<ModelUsageShare>,
+}
+
+/// 会话缓存统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionCacheStats {
+    pub session_id: String,
+    pub request_count: u64,
+    /// 会话内使用过的供应商数量
+    pub provider_count: u64,
+    /// 相邻请求之间切换供应商的次数（切换会导致上游缓存失效）
+    pub provider_switches: u64,
+    pub total_prompt_tokens: u64,
+    pub total_cache_read_tokens: u64,
+    pub total_cache_creation_tokens: u64,
+    /// 按 token 加权的缓存命中率（0-1）
+    pub cache_hit_rate: f64,
+    /// 缓存命中相比按输入价格计费节省的金额（已乘倍率）
+    pub estimated_savings_usd: String,
+}
+
 /// 请求日志过滤器
 #[derive(Debug, Clone, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -75,6 +148,8 @@ pub struct LogFilters {
     pub status_filter: Option<String>,
     pub start_date: Option<i64>,
     pub end_date: Option<i64>,
+    /// 用量来源：proxy（代理请求）或 transcript（CLI 会话记录）
+    pub source: Option<String>,
 }
 
 /// 分页请求日志响应
@@ -134,6 +209,28 @@ pub struct RequestLogDetail {
     pub request_body: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub response_body: Option<String>,
+    /// prompt cache 命中率（0-1），无 prompt token 时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_hit_rate: Option<f64>,
+    /// 命中的路由规则名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub routing_rule: Option<String>,
+    /// 是否由响应缓存直接返回
+    pub from_cache: bool,
+    /// 发起请求的客户端令牌 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_token_id: Option<String>,
+    /// 触发的整流事件
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rectifier: Option<String>,
+    /// 供应商计费币种（未声明时为空，即按美元计费）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing_currency: Option<String>,
+    /// 以计费币种计的总成本
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing_cost: Option<String>,
+    /// 用量来源：proxy 或 transcript
+    pub source: String,
     pub created_at: i64,
 }
 
@@ -143,41 +240,31 @@ impl Database {
         &self,
         start_date: Option<i64>,
         end_date: Option<i64>,
+        currency: Option<&str>,
+        source: Option<&str>,
     ) -> Result<UsageSummary, AppError> {
+        let where_clause = usage_source_condition(source, "u.")?
+            .map(|condition| format!("WHERE {condition}"))
+            .unwrap_or_default();
         let conn = lock_conn!(self.conn);
-
-        let (where_clause, params_vec) = if start_date.is_some() || end_date.is_some() {
-            let mut conditions = Vec::new();
-            let mut params = Vec::new();
-
-            if let Some(start) = start_date {
-                conditions.push("created_at >= ?");
-                params.push(start);
-            }
-            if let Some(end) = end_date {
-                conditions.push("created_at <= ?");
-                params.push(end);
-            }
-
-            (format!("WHERE {}", conditions.join(" AND ")), params)
-        } else {
-            (String::new(), Vec::new())
-        };
+        let cost = CurrencyCost::resolve(&conn, currency, "u.")?;
 
         let sql = format!(
             "SELECT
-                COUNT(*) as total_requests,
-                COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost,
-                COALESCE(SUM(input_tokens), 0) as total_input_tokens,
-                COALESCE(SUM(output_tokens), 0) as total_output_tokens,
-                COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
-                COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens,
-                COALESCE(SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END), 0) as success_count
-             FROM proxy_request_logs
-             {where_clause}"
+                COALESCE(SUM(u.request_count), 0) as total_requests,
+                COALESCE(SUM({cost_expr}), 0) as total_cost,
+                COALESCE(SUM(u.input_tokens), 0) as total_input_tokens,
+                COALESCE(SUM(u.output_tokens), 0) as total_output_tokens,
+                COALESCE(SUM(u.cache_creation_tokens), 0) as total_cache_creation_tokens,
+                COALESCE(SUM(u.cache_read_tokens), 0) as total_cache_read_tokens,
+                COALESCE(SUM(CASE WHEN u.status_class = 2 THEN u.request_count ELSE 0 END), 0) as success_count
+             FROM ({rows}) u
+             {where_clause}",
+            cost_expr = cost.expr,
+            rows = usage_rows_sql(start_date, end_date, true)
         );
 
-        let result = conn.query_row(&sql, rusqlite::params_from_iter(params_vec), |row| {
+        let result = conn.query_row(&sql, [], |row| {
             let total_requests: i64 = row.get(0)?;
             let total_cost: f64 = row.get(1)?;
             let total_input_tokens: i64 = row.get(2)?;
@@ -200,6 +287,7 @@ impl Database {
                 total_cache_creation_tokens: total_cache_creation_tokens as u64,
                 total_cache_read_tokens: total_cache_read_tokens as u64,
                 success_rate,
+                currency: cost.currency.clone(),
             })
         })?;
 
@@ -211,8 +299,26 @@ impl Database {
         &self,
         start_date: Option<i64>,
         end_date: Option<i64>,
+        currency: Option<&str>,
+        source: Option<&str>,
+    ) -> Result<Vec<DailyStats>, AppError> {
+        self.get_app_daily_trends(start_date, end_date, currency, source, None)
+    }
+
+    /// 获取单个应用（`app_type` 为 None 时为全部应用）的趋势
+    pub(crate) fn get_app_daily_trends(
+        &self,
+        start_date: Option<i64>,
+        end_date: Option<i64>,
+        currency: Option<&str>,
+        source: Option<&str>,
+        app_type: Option<&str>,
     ) -> Result<Vec<DailyStats>, AppError> {
+        let source_clause = usage_source_condition(source, "u.")?
+            .map(|condition| format!(" AND {condition}"))
+            .unwrap_or_default();
         let conn = lock_conn!(self.conn);
+        let cost = CurrencyCost::resolve(&conn, currency, "u.")?;
 
         let end_ts = end_date.unwrap_or_else(|| Local::now().timestamp());
         let mut start_ts = start_date.unwrap_or_else(|| end_ts - 24 * 60 * 60);
@@ -242,23 +348,28 @@ impl Database {
             bucket_count = 1;
         }
 
-        let sql = "
-            SELECT
-                CAST((created_at - ?1) / ?3 AS INTEGER) as bucket_idx,
-                COUNT(*) as request_count,
-                COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost,
-                COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
-                COALESCE(SUM(input_tokens), 0) as total_input_tokens,
-                COALESCE(SUM(output_tokens), 0) as total_output_tokens,
-                COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
-                COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens
-            FROM proxy_request_logs
-            WHERE created_at >= ?1 AND created_at <= ?2
+        // 小时桶与整点对齐时才能直接使用小时汇总；按天分桶时跨桶边界的小时整体计入其起始所在桶
+        let use_rollups = bucket_seconds > 60 * 60 || start_ts % (60 * 60) == 0;
+        let sql = format!(
+            "SELECT
+                CAST((u.ts - ?1) / ?2 AS INTEGER) as bucket_idx,
+                COALESCE(SUM(u.request_count), 0) as request_count,
+                COALESCE(SUM({cost_expr}), 0) as total_cost,
+                COALESCE(SUM(u.input_tokens + u.output_tokens), 0) as total_tokens,
+                COALESCE(SUM(u.input_tokens), 0) as total_input_tokens,
+                COALESCE(SUM(u.output_tokens), 0) as total_output_tokens,
+                COALESCE(SUM(u.cache_creation_tokens), 0) as total_cache_creation_tokens,
+                COALESCE(SUM(u.cache_read_tokens), 0) as total_cache_read_tokens
+            FROM ({rows}) u
+            WHERE (?3 IS NULL OR u.app_type = ?3){source_clause}
             GROUP BY bucket_idx
-            ORDER BY bucket_idx ASC";
+            ORDER BY bucket_idx ASC",
+            cost_expr = cost.expr,
+            rows = usage_rows_sql(Some(start_ts), Some(end_ts), use_rollups)
+        );
 
-        let mut stmt = conn.prepare(sql)?;
-        let rows = stmt.query_map(params![start_ts, end_ts, bucket_seconds], |row| {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![start_ts, bucket_seconds, app_type], |row| {
             Ok((
                 row.get::<_, i64>(0)?,
                 DailyStats {
@@ -283,7 +394,24 @@ impl Database {
             if bucket_idx >= bucket_count {
                 bucket_idx = bucket_count - 1;
             }
-            map.insert(bucket_idx, stat);
+            // 恰好落在窗口终点的记录并入最后一个桶，不能覆盖已有数据
+            match map.entry(bucket_idx) {
+                std::collections::hash_map::Entry::Occupied(mut entry) => {
+                    let merged = entry.get_mut();
+                    let cost = merged.total_cost.parse::<f64>().unwrap_or(0.0)
+                        + stat.total_cost.parse::<f64>().unwrap_or(0.0);
+                    merged.total_cost = format!("{cost:.6}");
+                    merged.request_count += stat.request_count;
+                    merged.total_tokens += stat.total_tokens;
+                    merged.total_input_tokens += stat.total_input_tokens;
+                    merged.total_output_tokens += stat.total_output_tokens;
+                    merged.total_cache_creation_tokens += stat.total_cache_creation_tokens;
+                    merged.total_cache_read_tokens += stat.total_cache_read_tokens;
+                }
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(stat);
+                }
+            }
         }
 
         let mut stats = Vec::with_capacity(bucket_count as usize);
@@ -317,23 +445,37 @@ impl Database {
     }
 
     /// 获取 Provider 统计
-    pub fn get_provider_stats(&self) -> Result<Vec<ProviderStats>, AppError> {
+    pub fn get_provider_stats(
+        &self,
+        currency: Option<&str>,
+        source: Option<&str>,
+    ) -> Result<Vec<ProviderStats>, AppError> {
+        let where_clause = usage_source_condition(source, "u.")?
+            .map(|condition| format!("WHERE {condition}"))
+            .unwrap_or_default();
         let conn = lock_conn!(self.conn);
+        let cost = CurrencyCost::resolve(&conn, currency, "u.")?;
 
-        let sql = "SELECT
-                l.provider_id,
+        let sql = format!(
+            "SELECT
+                u.provider_id,
                 p.name as provider_name,
-                COUNT(*) as request_count,
-                COALESCE(SUM(l.input_tokens + l.output_tokens), 0) as total_tokens,
-                COALESCE(SUM(CAST(l.total_cost_usd AS REAL)), 0) as total_cost,
-                COALESCE(SUM(CASE WHEN l.status_code >= 200 AND l.status_code < 300 THEN 1 ELSE 0 END), 0) as success_count,
-                COALESCE(AVG(l.latency_ms), 0) as avg_latency
-             FROM proxy_request_logs l
-             LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
-             GROUP BY l.provider_id, l.app_type
-             ORDER BY total_cost DESC";
+                SUM(u.request_count) as request_count,
+                COALESCE(SUM(u.input_tokens + u.output_tokens), 0) as total_tokens,
+                COALESCE(SUM({cost_expr}), 0) as total_cost,
+                COALESCE(SUM(CASE WHEN u.status_class = 2 THEN u.request_count ELSE 0 END), 0) as success_count,
+                COALESCE(CAST(SUM(u.latency_ms) AS REAL) / NULLIF(SUM(u.request_count), 0), 0) as avg_latency
+             FROM ({rows}) u
+             LEFT JOIN providers p ON u.provider_id = p.id AND u.app_type = p.app_type
+             {where_clause}
+             GROUP BY u.provider_id, u.app_type
+             HAVING SUM(u.request_count) > 0
+             ORDER BY total_cost DESC",
+            cost_expr = cost.expr,
+            rows = usage_rows_sql(None, None, true)
+        );
 
-        let mut stmt = conn.prepare(sql)?;
+        let mut stmt = conn.prepare(&sql)?;
         let rows = stmt.query_map([], |row| {
             let request_count: i64 = row.get(2)?;
             let success_count: i64 = row.get(5)?;
@@ -364,20 +506,389 @@ impl Database {
         Ok(stats)
     }
 
-    /// 获取模型统计
-    pub fn get_model_stats(&self) -> Result<Vec<ModelStats>, AppError> {
+    /// 批量获取多个 Provider 近期成功流式请求的平均首字延迟（毫秒）
+    ///
+    /// 只统计首字延迟：非流式请求的总延迟包含完整生成耗时，与首字延迟不可比。
+    /// 每个 Provider 取最近 `sample_size` 条样本，单条查询完成；无样本的 Provider 不出现在结果中
+    pub fn get_recent_provider_latencies(
+        &self,
+        provider_ids: &[&str],
+        app_type: &str,
+        sample_size: u32,
+    ) -> Result<HashMap<String, f64>, AppError> {
+        if provider_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let conn = lock_conn!(self.conn);
+
+        let placeholders = (0..provider_ids.len())
+            .map(|i| format!("?{}", i + 3))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "SELECT provider_id, AVG(first_token_ms) FROM (
+                SELECT provider_id, first_token_ms,
+                       ROW_NUMBER() OVER (PARTITION BY provider_id ORDER BY created_at DESC) AS rn
+                FROM proxy_request_logs
+                WHERE app_type = ?1 AND provider_id IN ({placeholders})
+                  AND from_cache = 0 AND first_token_ms IS NOT NULL
+                  AND status_code >= 200 AND status_code < 300
+             )
+             WHERE rn <= ?2
+             GROUP BY provider_id"
+        );
+
+        let mut values: Vec<&dyn rusqlite::ToSql> = vec![&app_type, &sample_size];
+        values.extend(provider_ids.iter().map(|id| id as &dyn rusqlite::ToSql));
+
+        let mut stmt = conn.prepare(&sql)?;
+        let latencies = stmt
+            .query_map(values.as_slice(), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(latencies)
+    }
+
+    /// 获取 Provider 近期成功非流式请求的总延迟样本（毫秒，最新在前）
+    pub fn get_recent_latency_samples(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+        sample_size: u32,
+    ) -> Result<Vec<u64>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn.prepare(
+            "SELECT latency_ms
+             FROM proxy_request_logs
+             WHERE provider_id = ?1 AND app_type = ?2 AND is_streaming = 0 AND from_cache = 0
+               AND status_code >= 200 AND status_code < 300
+             ORDER BY created_at DESC
+             LIMIT ?3",
+        )?;
+        let samples = stmt
+            .query_map(params![provider_id, app_type, sample_size], |row| {
+                row.get::<_, i64>(0)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(samples.into_iter().map(|v| v.max(0) as u64).collect())
+    }
+
+    /// 获取会话的缓存命中统计（仅统计成功请求）
+    ///
+    /// 会话不存在时返回 None
+    pub fn get_session_cache_stats(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<SessionCacheStats>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn.prepare(
+            "SELECT app_type, provider_id, model, input_tokens, cache_read_tokens,
+                    cache_creation_tokens, cost_multiplier, created_at
+             FROM proxy_request_logs
+             WHERE session_id = ?1 AND from_cache = 0
+               AND status_code >= 200 AND status_code < 300
+             ORDER BY created_at ASC, rowid ASC",
+        )?;
+        let rows = stmt.query_map([session_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                TokenUsage {
+                    input_tokens: row.get::<_, i64>(3)? as u32,
+                    cache_read_tokens: row.get::<_, i64>(4)? as u32,
+                    cache_creation_tokens: row.get::<_, i64>(5)? as u32,
+                    ..Default::default()
+                },
+                row.get::<_, Option<String>>(6)?,
+                row.get::<_, i64>(7)?,
+            ))
+        })?;
+
+        let million = rust_decimal::Decimal::from(1_000_000u64);
+        let mut pricing_cache = HashMap::new();
+        let mut providers = std::collections::HashSet::new();
+        let mut last_provider: Option<String> = None;
+        let mut stats = SessionCacheStats {
+            session_id: session_id.to_string(),
+            request_count: 0,
+            provider_count: 0,
+            provider_switches: 0,
+            total_prompt_tokens: 0,
+            total_cache_read_tokens: 0,
+            total_cache_creation_tokens: 0,
+            cache_hit_rate: 0.0,
+            estimated_savings_usd: String::new(),
+        };
+        let mut savings = rust_decimal::Decimal::ZERO;
+
+        for row in rows {
+            let (app_type, provider_id, model, usage, multiplier, created_at) = row?;
+
+            stats.request_count += 1;
+            stats.total_prompt_tokens += usage.prompt_tokens(&app_type);
+            stats.total_cache_read_tokens += usage.cache_read_tokens as u64;
+            stats.total_cache_creation_tokens += usage.cache_creation_tokens as u64;
+            if last_provider.as_deref().is_some_and(|p| p != provider_id) {
+                stats.provider_switches += 1;
+            }
+            providers.insert(provider_id.clone());
+            last_provider = Some(provider_id);
+
+            if usage.cache_read_tokens > 0 {
+                if let Some(pricing) =
+                    Self::get_model_pricing_cached(&conn, &mut pricing_cache, &model, created_at)?
+                {
+                    let pricing = pricing.for_prompt_tokens(usage.prompt_tokens(&app_type));
+                    let multiplier = multiplier
+                        .and_then(|m| rust_decimal::Decimal::from_str(&m).ok())
+                        .unwrap_or(rust_decimal::Decimal::ONE);
+                    savings += rust_decimal::Decimal::from(usage.cache_read_tokens as u64)
+                        * (pricing.input_cost_per_million - pricing.cache_read_cost_per_million)
+                        / million
+                        * multiplier;
+                }
+            }
+        }
+
+        if stats.request_count == 0 {
+            return Ok(None);
+        }
+
+        stats.provider_count = providers.len() as u64;
+        if stats.total_prompt_tokens > 0 {
+            stats.cache_hit_rate =
+                stats.total_cache_read_tokens as f64 / stats.total_prompt_tokens as f64;
+        }
+        stats.estimated_savings_usd = format!("{savings:.6}");
+
+        Ok(Some(stats))
+    }
+
+    /// 按会话汇总用量（按成本降序）
+    pub fn get_session_usage_stats(
+        &self,
+        start_date: Option<i64>,
+        end_date: Option<i64>,
+        currency: Option<&str>,
+        source: Option<&str>,
+    ) -> Result<Vec<SessionUsageStats>, AppError> {
+        let source_condition = usage_source_condition(source, "")?;
         let conn = lock_conn!(self.conn);
+        let cost = CurrencyCost::resolve(&conn, currency, "")?;
 
-        let sql = "SELECT
+        let mut conditions = vec!["session_id IS NOT NULL AND session_id != ''".to_string()];
+        let mut params = Vec::new();
+        if let Some(start) = start_date {
+            conditions.push("created_at >= ?".to_string());
+            params.push(start);
+        }
+        if let Some(end) = end_date {
+            conditions.push("created_at <= ?".to_string());
+            params.push(end);
+        }
+        conditions.extend(source_condition);
+
+        let sql = format!(
+            "SELECT
+                CASE WHEN substr(session_id, 1, 6) = 'codex_' THEN substr(session_id, 7)
+                     ELSE session_id END as sid,
+                app_type,
                 model,
-                COUNT(*) as request_count,
-                COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
-                COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost
+                COUNT(*),
+                COALESCE(SUM(input_tokens), 0),
+                COALESCE(SUM(output_tokens), 0),
+                COALESCE(SUM(cache_read_tokens), 0),
+                COALESCE(SUM(cache_creation_tokens), 0),
+                COALESCE(SUM({cost_expr}), 0),
+                MIN(created_at),
+                MAX(created_at)
              FROM proxy_request_logs
-             GROUP BY model
-             ORDER BY total_cost DESC";
+             WHERE {}
+             GROUP BY sid, app_type, model",
+            conditions.join(" AND "),
+            cost_expr = cost.expr
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)? as u64,
+                [
+                    row.get::<_, i64>(4)? as u64,
+                    row.get::<_, i64>(5)? as u64,
+                    row.get::<_, i64>(6)? as u64,
+                    row.get::<_, i64>(7)? as u64,
+                ],
+                row.get::<_, f64>(8)?,
+                row.get::<_, i64>(9)?,
+                row.get::<_, i64>(10)?,
+            ))
+        })?;
+
+        let mut sessions: HashMap<(String, String), (SessionUsageStats, f64)> = HashMap::new();
+        for row in rows {
+            let (session_id, app_type, model, count, tokens, total_cost, first, last) = row?;
+            let (stats, session_cost) = sessions
+                .entry((session_id.clone(), app_type.clone()))
+                .or_insert_with(|| {
+                    (
+                        SessionUsageStats {
+                            session_id,
+                            app_type,
+                            request_count: 0,
+                            total_input_tokens: 0,
+                            total_output_tokens: 0,
+                            total_cache_read_tokens: 0,
+                            total_cache_creation_tokens: 0,
+                            total_cost: String::new(),
+                            currency: cost.currency.clone(),
+                            first_request_at: first,
+                            last_request_at: last,
+                            models: Vec::new(),
+                        },
+                        0.0,
+                    )
+                });
+
+            stats.request_count += count;
+            stats.total_input_tokens += tokens[0];
+            stats.total_output_tokens += tokens[1];
+            stats.total_cache_read_tokens += tokens[2];
+            stats.total_cache_creation_tokens += tokens[3];
+            stats.first_request_at = stats.first_request_at.min(first);
+            stats.last_request_at = stats.last_request_at.max(last);
+            stats.models.push(ModelUsageShare {
+                model,
+                request_count: count,
+                total_tokens: tokens[0] + tokens[1],
+                total_cost: format!("{total_cost:.6}"),
+            });
+            *session_cost += total_cost;
+        }
 
-        let mut stmt = conn.prepare(sql)?;
+        let mut sessions: Vec<_> = sessions.into_values().collect();
+        sessions.sort_by(|(a, a_cost), (b, b_cost)| {
+            b_cost
+                .total_cmp(a_cost)
+                .then_with(|| b.last_request_at.cmp(&a.last_request_at))
+        });
+
+        Ok(sessions
+            .into_iter()
+            .map(|(mut stats, total_cost)| {
+                stats.total_cost = format!("{total_cost:.6}");
+                sort_model_shares(&mut stats.models);
+                stats
+            })
+            .collect())
+    }
+
+    /// 按项目目录汇总用量（会话与项目的对应关系来自会话管理器扫描结果）
+    pub fn get_project_usage_stats(
+        &self,
+        sessions: &[SessionMeta],
+        start_date: Option<i64>,
+        end_date: Option<i64>,
+        currency: Option<&str>,
+        source: Option<&str>,
+    ) -> Result<Vec<ProjectUsageStats>, AppError> {
+        let project_dirs: HashMap<&str, &str> = sessions
+            .iter()
+            .filter_map(|s| Some((s.session_id.as_str(), s.project_dir.as_deref()?)))
+            .collect();
+
+        let mut projects: HashMap<Option<String>, (ProjectUsageStats, f64)> = HashMap::new();
+        for session in self.get_session_usage_stats(start_date, end_date, currency, source)? {
+            let project_dir = project_dirs
+                .get(session.session_id.as_str())
+                .map(|dir| dir.to_string());
+            let (project, project_cost) =
+                projects.entry(project_dir.clone()).or_insert_with(|| {
+                    (
+                        ProjectUsageStats {
+                            project_dir,
+                            session_count: 0,
+                            request_count: 0,
+                            total_tokens: 0,
+                            total_cost: String::new(),
+                            currency: session.currency.clone(),
+                            last_request_at: 0,
+                            models: Vec::new(),
+                        },
+                        0.0,
+                    )
+                });
+
+            project.session_count += 1;
+            project.request_count += session.request_count;
+            project.total_tokens += session.total_input_tokens + session.total_output_tokens;
+            project.last_request_at = project.last_request_at.max(session.last_request_at);
+            *project_cost += session.total_cost.parse::<f64>().unwrap_or(0.0);
+
+            for share in session.models {
+                match project.models.iter_mut().find(|m| m.model == share.model) {
+                    Some(existing) => {
+                        existing.request_count += share.request_count;
+                        existing.total_tokens += share.total_tokens;
+                        let sum = existing.total_cost.parse::<f64>().unwrap_or(0.0)
+                            + share.total_cost.parse::<f64>().unwrap_or(0.0);
+                        existing.total_cost = format!("{sum:.6}");
+                    }
+                    None => project.models.push(share),
+                }
+            }
+        }
+
+        let mut projects: Vec<_> = projects.into_values().collect();
+        projects.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        Ok(projects
+            .into_iter()
+            .map(|(mut project, total_cost)| {
+                project.total_cost = format!("{total_cost:.6}");
+                sort_model_shares(&mut project.models);
+                project
+            })
+            .collect())
+    }
+
+    /// 获取模型统计
+    pub fn get_model_stats(
+        &self,
+        currency: Option<&str>,
+        source: Option<&str>,
+    ) -> Result<Vec<ModelStats>, AppError> {
+        let where_clause = usage_source_condition(source, "u.")?
+            .map(|condition| format!("WHERE {condition}"))
+            .unwrap_or_default();
+        let conn = lock_conn!(self.conn);
+        let cost = CurrencyCost::resolve(&conn, currency, "u.")?;
+
+        let sql = format!(
+            "SELECT
+                u.model,
+                SUM(u.request_count) as request_count,
+                COALESCE(SUM(u.input_tokens + u.output_tokens), 0) as total_tokens,
+                COALESCE(SUM({cost_expr}), 0) as total_cost
+             FROM ({rows}) u
+             {where_clause}
+             GROUP BY u.model
+             HAVING SUM(u.request_count) > 0
+             ORDER BY total_cost DESC",
+            cost_expr = cost.expr,
+            rows = usage_rows_sql(None, None, true)
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
         let rows = stmt.query_map([], |row| {
             let request_count: i64 = row.get(1)?;
             let total_cost: f64 = row.get(3)?;
@@ -411,6 +922,7 @@ impl Database {
         page: u32,
         page_size: u32,
     ) -> Result<PaginatedLogs, AppError> {
+        let source_condition = usage_source_condition(filters.source.as_deref(), "l.")?;
         let conn = lock_conn!(self.conn);
 
         let mut conditions = Vec::new();
@@ -461,6 +973,9 @@ impl Database {
             conditions.push("l.created_at <= ?");
             params.push(Box::new(end));
         }
+        if let Some(ref condition) = source_condition {
+            conditions.push(condition);
+        }
 
         let where_clause = if conditions.is_empty() {
             String::new()
@@ -490,7 +1005,9 @@ impl Database {
                     l.input_tokens, l.output_tokens, l.cache_read_tokens, l.cache_creation_tokens,
                     l.input_cost_usd, l.output_cost_usd, l.cache_read_cost_usd, l.cache_creation_cost_usd, l.total_cost_usd,
                     l.is_streaming, l.latency_ms, l.first_token_ms,
-                    l.status_code, l.error_message, l.created_at
+                    l.status_code, l.error_message, l.created_at, l.cache_hit_rate,
+                    l.routing_rule, l.from_cache, l.client_token_id, l.rectifier,
+                    l.billing_currency, l.billing_cost, l.source
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
              {where_clause}
@@ -529,6 +1046,14 @@ impl Database {
                 error_message: row.get(20)?,
                 request_body: None,
                 response_body: None,
+                cache_hit_rate: row.get(22)?,
+                routing_rule: row.get(23)?,
+                from_cache: row.get::<_, i64>(24)? != 0,
+                client_token_id: row.get(25)?,
+                rectifier: row.get(26)?,
+                billing_currency: row.get(27)?,
+                billing_cost: row.get(28)?,
+                source: row.get(29)?,
                 created_at: row.get(21)?,
             })
         })?;
@@ -569,7 +1094,9 @@ impl Database {
                     l.input_tokens, l.output_tokens, l.cache_read_tokens, l.cache_creation_tokens,
                     l.input_cost_usd, l.output_cost_usd, l.cache_read_cost_usd, l.cache_creation_cost_usd, l.total_cost_usd,
                     l.is_streaming, l.latency_ms, l.first_token_ms,
-                    l.status_code, l.error_message, l.request_body, l.response_body, l.created_at
+                    l.status_code, l.error_message, l.request_body, l.response_body, l.created_at,
+                    l.cache_hit_rate, l.routing_rule, l.from_cache, l.client_token_id, l.rectifier,
+                    l.billing_currency, l.billing_cost, l.source
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
              WHERE l.request_id = ?",
@@ -599,6 +1126,14 @@ impl Database {
                     error_message: row.get(20)?,
                     request_body: row.get(21)?,
                     response_body: row.get(22)?,
+                    cache_hit_rate: row.get(24)?,
+                    routing_rule: row.get(25)?,
+                    from_cache: row.get::<_, i64>(26)? != 0,
+                    client_token_id: row.get(27)?,
+                    rectifier: row.get(28)?,
+                    billing_currency: row.get(29)?,
+                    billing_cost: row.get(30)?,
+                    source: row.get(31)?,
                     created_at: row.get(23)?,
                 })
             },
@@ -715,8 +1250,10 @@ impl Database {
         &self,
         provider_id: &str,
         app_type: &str,
+        currency: Option<&str>,
     ) -> Result<ProviderLimitStatus, AppError> {
         let conn = lock_conn!(self.conn);
+        let cost = CurrencyCost::resolve(&conn, currency, "")?;
 
         // 获取 provider 的限额设置
         let (limit_daily, limit_monthly) = conn
@@ -739,17 +1276,21 @@ impl Database {
                     .get("limitMonthlyUsd")
                     .and_then(|v| v.as_str())
                     .and_then(|s| s.parse::<f64>().ok());
-                (daily, monthly)
+                // 限额以美元配置，换算到报告币种后比较
+                (daily.map(|l| l * cost.rate), monthly.map(|l| l * cost.rate))
             })
             .unwrap_or((None, None));
 
         // 计算今日使用量
         let daily_usage: f64 = conn
             .query_row(
-                "SELECT COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
+                &format!(
+                    "SELECT COALESCE(SUM({}), 0)
              FROM proxy_request_logs
              WHERE provider_id = ? AND app_type = ?
                AND date(datetime(created_at, 'unixepoch', 'localtime')) = date('now', 'localtime')",
+                    cost.expr
+                ),
                 params![provider_id, app_type],
                 |row| row.get(0),
             )
@@ -758,10 +1299,10 @@ impl Database {
         // 计算本月使用量
         let monthly_usage: f64 = conn
             .query_row(
-                "SELECT COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
+                &format!("SELECT COALESCE(SUM({}), 0)
              FROM proxy_request_logs
              WHERE provider_id = ? AND app_type = ?
-               AND strftime('%Y-%m', datetime(created_at, 'unixepoch', 'localtime')) = strftime('%Y-%m', 'now', 'localtime')",
+               AND strftime('%Y-%m', datetime(created_at, 'unixepoch', 'localtime')) = strftime('%Y-%m', 'now', 'localtime')", cost.expr),
                 params![provider_id, app_type],
                 |row| row.get(0),
             )
@@ -782,6 +1323,7 @@ impl Database {
             monthly_usage: format!("{monthly_usage:.6}"),
             monthly_limit: limit_monthly.map(|l| format!("{l:.2}")),
             monthly_exceeded,
+            currency: cost.currency,
         })
     }
 
@@ -840,14 +1382,141 @@ pub struct ProviderLimitStatus {
     pub monthly_usage: String,
     pub monthly_limit: Option<String>,
     pub monthly_exceeded: bool,
+    /// 用量与限额所用币种
+    pub currency: String,
+}
+
+/// 统计查询的数据源（派生表，列名与请求日志一致，另有 ts、status_class、request_count）
+///
+/// 完整落在 `[start, end]` 内的整点小时读取小时汇总表，首尾不足一小时的部分读取原始日志；
+/// 未指定结束时间时当前小时读取原始日志。请求日志被清理后汇总仍保留，长期统计不受影响。
+/// 时间均为内部计算的整数，直接内联到 SQL。
+pub(crate) fn usage_rows_sql(start: Option<i64>, end: Option<i64>, use_rollups: bool) -> String {
+    const HOUR: i64 = 60 * 60;
+    let rollup_from = start.map(|s| (s + HOUR - 1).div_euclid(HOUR) * HOUR);
+    let rollup_to = match end {
+        Some(e) => (e + 1).div_euclid(HOUR) * HOUR,
+        None => Local::now().timestamp().div_euclid(HOUR) * HOUR,
+    };
+
+    let raw_columns =
+        "created_at AS ts, app_type, provider_id, model, status_code / 100 AS status_class,
+        source, billing_currency, 1 AS request_count, input_tokens, output_tokens,
+        cache_read_tokens, cache_creation_tokens, total_cost_usd, billing_cost, latency_ms";
+    let range = |column: &str, from: Option<i64>, to: Option<i64>, to_inclusive: bool| {
+        let mut conditions = Vec::new();
+        if let Some(from) = from {
+            conditions.push(format!("{column} >= {from}"));
+        }
+        if let Some(to) = to {
+            let op = if to_inclusive { "<=" } else { "<" };
+            conditions.push(format!("{column} {op} {to}"));
+        }
+        if conditions.is_empty() {
+            "1 = 1".to_string()
+        } else {
+            conditions.join(" AND ")
+        }
+    };
+
+    if !use_rollups || rollup_from.is_some_and(|from| from >= rollup_to) {
+        return format!(
+            "SELECT {raw_columns} FROM proxy_request_logs WHERE {}",
+            range("created_at", start, end, true)
+        );
+    }
+
+    let head = match (start, rollup_from) {
+        (Some(s), Some(from)) if s < from => {
+            format!("({})", range("created_at", Some(s), Some(from), false))
+        }
+        _ => "0".to_string(),
+    };
+    let tail = range("created_at", Some(rollup_to), end, true);
+    format!(
+        "SELECT {raw_columns} FROM proxy_request_logs WHERE {head} OR ({tail})
+         UNION ALL
+         SELECT bucket_start AS ts, app_type, provider_id, model, status_class, source,
+                NULLIF(billing_currency, ''), request_count, input_tokens, output_tokens,
+                cache_read_tokens, cache_creation_tokens, total_cost_usd, billing_cost, latency_ms
+         FROM usage_hourly_rollups WHERE {}",
+        range("bucket_start", rollup_from, Some(rollup_to), false)
+    )
+}
+
+/// 模型构成按成本降序排列
+fn sort_model_shares(models: &mut [ModelUsageShare]) {
+    let cost = |m: &ModelUsageShare| m.total_cost.parse::<f64>().unwrap_or(0.0);
+    models.sort_by(|a, b| cost(b).total_cmp(&cost(a)));
+}
+
+/// 用量来源过滤条件（未指定时合并代理与会话记录）
+fn usage_source_condition(
+    source: Option<&str>,
+    column_prefix: &str,
+) -> Result<Option<String>, AppError> {
+    match source {
+        None => Ok(None),
+        Some(source @ ("proxy" | "transcript" | "mock")) => {
+            Ok(Some(format!("{column_prefix}source = '{source}'")))
+        }
+        Some(other) => Err(AppError::localized(
+            "error.invalidUsageSource",
+            format!("无效的用量来源: {other}"),
+            format!("Invalid usage source: {other}"),
+        )),
+    }
+}
+
+/// 按报告币种汇总成本所需的 SQL 表达式与汇率
+pub(crate) struct CurrencyCost {
+    pub(crate) currency: String,
+    /// 1 美元可兑换的报告币种数量
+    pub(crate) rate: f64,
+    /// 单条日志以报告币种计的成本
+    pub(crate) expr: String,
 }
 
-#[derive(Clone)]
-struct PricingInfo {
-    input: rust_decimal::Decimal,
-    output: rust_decimal::Decimal,
-    cache_read: rust_decimal::Decimal,
-    cache_creation: rust_decimal::Decimal,
+impl CurrencyCost {
+    /// 计费币种与报告币种一致的日志直接取实际计费金额，其余把美元成本按汇率换算；
+    /// 未指定币种时按美元报告
+    pub(crate) fn resolve(
+        conn: &Connection,
+        currency: Option<&str>,
+        column_prefix: &str,
+    ) -> Result<Self, AppError> {
+        let usd_cost = format!("CAST({column_prefix}total_cost_usd AS REAL)");
+        let currency = match currency {
+            Some(code) => normalize_currency(code)?,
+            None => BASE_CURRENCY.to_string(),
+        };
+        if currency == BASE_CURRENCY {
+            return Ok(Self {
+                currency,
+                rate: 1.0,
+                expr: usd_cost,
+            });
+        }
+
+        let rate = find_exchange_rate(conn, &currency)?.ok_or_else(|| {
+            AppError::localized(
+                "error.exchangeRateMissing",
+                format!("汇率表中没有 {currency} 的汇率"),
+                format!("No exchange rate configured for {currency}"),
+            )
+        })?;
+        // 币种代码已校验为 3 位字母、汇率为 Decimal，可直接内联到 SQL
+        let expr = format!(
+            "(CASE WHEN {column_prefix}billing_currency = '{currency}' AND {column_prefix}billing_cost IS NOT NULL
+                THEN CAST({column_prefix}billing_cost AS REAL)
+                ELSE {usd_cost} * {rate} END)"
+        );
+        Ok(Self {
+            currency,
+            rate: rate.to_f64().unwrap_or(1.0),
+            expr,
+        })
+    }
 }
 
 impl Database {
@@ -855,7 +1524,7 @@ impl Database {
         conn: &Connection,
         log: &mut RequestLogDetail,
         provider_cache: &mut HashMap<(String, String), rust_decimal::Decimal>,
-        pricing_cache: &mut HashMap<String, PricingInfo>,
+        pricing_cache: &mut HashMap<String, Vec<PricingVersion>>,
     ) -> Result<(), AppError> {
         let total_cost = rust_decimal::Decimal::from_str(&log.total_cost_usd)
             .unwrap_or(rust_decimal::Decimal::ZERO);
@@ -869,8 +1538,14 @@ impl Database {
             return Ok(());
         }
 
-        let pricing = match Self::get_model_pricing_cached(conn, pricing_cache, &log.model)? {
-            Some(info) => info,
+        // 按日志写入时生效的价格回填，避免用当前价格重算历史请求
+        let pricing = match Self::get_model_pricing_cached(
+            conn,
+            pricing_cache,
+            &log.model,
+            log.created_at,
+        )? {
+            Some(pricing) => pricing,
             None => return Ok(()),
         };
         let multiplier = Self::get_cost_multiplier_cached(
@@ -880,33 +1555,21 @@ impl Database {
             &log.app_type,
         )?;
 
-        let million = rust_decimal::Decimal::from(1_000_000u64);
+        let usage = TokenUsage {
+            input_tokens: log.input_tokens,
+            output_tokens: log.output_tokens,
+            cache_read_tokens: log.cache_read_tokens,
+            cache_creation_tokens: log.cache_creation_tokens,
+            model: None,
+        };
+        let pricing = pricing.for_prompt_tokens(usage.prompt_tokens(&log.app_type));
+        let cost = CostCalculator::calculate(&usage, pricing, multiplier);
 
-        // 与 CostCalculator::calculate 保持一致的计算逻辑：
-        // 1. input_cost 需要扣除 cache_read_tokens（避免缓存部分被重复计费）
-        // 2. 各项成本是基础成本（不含倍率）
-        // 3. 倍率只作用于最终总价
-        let billable_input_tokens =
-            (log.input_tokens as u64).saturating_sub(log.cache_read_tokens as u64);
-        let input_cost =
-            rust_decimal::Decimal::from(billable_input_tokens) * pricing.input / million;
-        let output_cost =
-            rust_decimal::Decimal::from(log.output_tokens as u64) * pricing.output / million;
-        let cache_read_cost = rust_decimal::Decimal::from(log.cache_read_tokens as u64)
-            * pricing.cache_read
-            / million;
-        let cache_creation_cost = rust_decimal::Decimal::from(log.cache_creation_tokens as u64)
-            * pricing.cache_creation
-            / million;
-        // 总成本 = 基础成本之和 × 倍率
-        let base_total = input_cost + output_cost + cache_read_cost + cache_creation_cost;
-        let total_cost = base_total * multiplier;
-
-        log.input_cost_usd = format!("{input_cost:.6}");
-        log.output_cost_usd = format!("{output_cost:.6}");
-        log.cache_read_cost_usd = format!("{cache_read_cost:.6}");
-        log.cache_creation_cost_usd = format!("{cache_creation_cost:.6}");
-        log.total_cost_usd = format!("{total_cost:.6}");
+        log.input_cost_usd = format!("{:.6}", cost.input_cost);
+        log.output_cost_usd = format!("{:.6}", cost.output_cost);
+        log.cache_read_cost_usd = format!("{:.6}", cost.cache_read_cost);
+        log.cache_creation_cost_usd = format!("{:.6}", cost.cache_creation_cost);
+        log.total_cost_usd = format!("{:.6}", cost.total_cost);
 
         conn.execute(
             "UPDATE proxy_request_logs
@@ -963,40 +1626,69 @@ impl Database {
         Ok(multiplier)
     }
 
-    fn get_model_pricing_cached(
+    /// 查询模型在 `at`（Unix 秒）时生效的定价，按模型缓存全部生效版本
+    pub(crate) fn get_model_pricing_cached(
         conn: &Connection,
-        cache: &mut HashMap<String, PricingInfo>,
+        cache: &mut HashMap<String, Vec<PricingVersion>>,
         model: &str,
-    ) -> Result<Option<PricingInfo>, AppError> {
-        if let Some(info) = cache.get(model) {
-            return Ok(Some(info.clone()));
+        at: i64,
+    ) -> Result<Option<ModelPricing>, AppError> {
+        if !cache.contains_key(model) {
+            let versions = find_model_pricing_versions(conn, model)?;
+            cache.insert(model.to_string(), versions);
         }
+        Ok(cache
+            .get(model)
+            .and_then(|versions| select_pricing_version(versions, at))
+            .cloned())
+    }
+}
 
-        let row = find_model_pricing_row(conn, model)?;
-        let Some((input, output, cache_read, cache_creation)) = row else {
-            return Ok(None);
-        };
-
-        let pricing = PricingInfo {
-            input: rust_decimal::Decimal::from_str(&input)
-                .map_err(|e| AppError::Database(format!("解析输入价格失败: {e}")))?,
-            output: rust_decimal::Decimal::from_str(&output)
-                .map_err(|e| AppError::Database(format!("解析输出价格失败: {e}")))?,
-            cache_read: rust_decimal::Decimal::from_str(&cache_read)
-                .map_err(|e| AppError::Database(format!("解析缓存读取价格失败: {e}")))?,
-            cache_creation: rust_decimal::Decimal::from_str(&cache_creation)
-                .map_err(|e| AppError::Database(format!("解析缓存写入价格失败: {e}")))?,
-        };
+/// 模型定价的一个生效版本（生效区间为 `[effective_from, effective_until)`，Unix 秒）
+#[derive(Debug, Clone)]
+pub(crate) struct PricingVersion {
+    pub effective_from: i64,
+    pub effective_until: Option<i64>,
+    pub pricing: ModelPricing,
+}
 
-        cache.insert(model.to_string(), pricing.clone());
-        Ok(Some(pricing))
+impl PricingVersion {
+    fn covers(&self, at: i64) -> bool {
+        self.effective_from <= at && self.effective_until.is_none_or(|until| at < until)
     }
 }
 
+/// 从按 effective_from 升序排列的版本中选出 `at` 时生效的定价
+///
+/// 多个版本重叠时取生效时间最晚的；没有版本覆盖时取时间上最接近的版本
+pub(crate) fn select_pricing_version(
+    versions: &[PricingVersion],
+    at: i64,
+) -> Option<&ModelPricing> {
+    versions
+        .iter()
+        .rev()
+        .find(|v| v.covers(at))
+        .or_else(|| versions.iter().rev().find(|v| v.effective_from <= at))
+        .or_else(|| versions.first())
+        .map(|v| &v.pricing)
+}
+
+/// 查询模型在 `at`（Unix 秒）时生效的定价
 pub(crate) fn find_model_pricing_row(
     conn: &Connection,
     model_id: &str,
-) -> Result<Option<(String, String, String, String)>, AppError> {
+    at: i64,
+) -> Result<Option<ModelPricing>, AppError> {
+    let versions = find_model_pricing_versions(conn, model_id)?;
+    Ok(select_pricing_version(&versions, at).cloned())
+}
+
+/// 查询模型的全部定价版本（按 effective_from 升序）
+pub(crate) fn find_model_pricing_versions(
+    conn: &Connection,
+    model_id: &str,
+) -> Result<Vec<PricingVersion>, AppError> {
     // 清洗模型名称：去前缀(/)、去后缀(:)、@ 替换为 -
     // 例如 moonshotai/gpt-5.2-codex@low:v2 → gpt-5.2-codex-low
     let cleaned = model_id
@@ -1008,31 +1700,90 @@ pub(crate) fn find_model_pricing_row(
         .trim()
         .replace('@', "-");
 
-    // 精确匹配清洗后的名称
-    let exact = conn
-        .query_row(
-            "SELECT input_cost_per_million, output_cost_per_million,
-                    cache_read_cost_per_million, cache_creation_cost_per_million
-             FROM model_pricing
-             WHERE model_id = ?1",
-            [&cleaned],
-            |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, String>(2)?,
-                    row.get::<_, String>(3)?,
-                ))
-            },
-        )
-        .optional()
-        .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+    // 精确匹配清洗后的名称，未命中时按别名（去日期后缀、`.` 与 `-` 等价）兜底
+    let mut rows = query_model_pricing_rows(conn, &cleaned)?;
+    if rows.is_empty() {
+        if let Some(alias) = find_alias_model_id(conn, model_id)? {
+            log::debug!("模型 {model_id} 按别名匹配到定价 {alias}");
+            rows = query_model_pricing_rows(conn, &alias)?;
+        }
+    }
 
-    if exact.is_none() {
+    if rows.is_empty() {
         log::warn!("模型 {model_id}（清洗后: {cleaned}）未找到定价信息，成本将记录为 0");
     }
 
-    Ok(exact)
+    rows.into_iter()
+        .map(
+            |(
+                input,
+                output,
+                cache_read,
+                cache_creation,
+                effective_from,
+                effective_until,
+                tiers,
+            )| {
+                let tiers: Vec<PricingTier> = match tiers.as_deref().filter(|t| !t.is_empty()) {
+                    Some(json) => serde_json::from_str(json)
+                        .map_err(|e| AppError::Database(format!("解析分档定价失败: {e}")))?,
+                    None => Vec::new(),
+                };
+                let pricing =
+                    ModelPricing::from_strings(&input, &output, &cache_read, &cache_creation)
+                        .map_err(|e| AppError::Database(format!("解析定价数据失败: {e}")))?
+                        .with_tiers(&tiers)
+                        .map_err(|e| AppError::Database(format!("解析分档定价失败: {e}")))?;
+                Ok(PricingVersion {
+                    effective_from,
+                    effective_until,
+                    pricing,
+                })
+            },
+        )
+        .collect()
+}
+
+type PricingRow = (
+    String,
+    String,
+    String,
+    String,
+    i64,
+    Option<i64>,
+    Option<String>,
+);
+
+fn query_model_pricing_rows(
+    conn: &Connection,
+    model_id: &str,
+) -> Result<Vec<PricingRow>, AppError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT input_cost_per_million, output_cost_per_million,
+                    cache_read_cost_per_million, cache_creation_cost_per_million,
+                    effective_from, effective_until, tiers
+             FROM model_pricing
+             WHERE model_id = ?1
+             ORDER BY effective_from ASC",
+        )
+        .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+    let rows = stmt
+        .query_map([model_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+                row.get::<_, Option<String>>(6)?,
+            ))
+        })
+        .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+    Ok(rows)
 }
 
 #[cfg(test)]
@@ -1064,13 +1815,190 @@ mod tests {
             )?;
         }
 
-        let summary = db.get_usage_summary(None, None)?;
+        let summary = db.get_usage_summary(None, None, None, None)?;
         assert_eq!(summary.total_requests, 2);
         assert_eq!(summary.success_rate, 100.0);
 
         Ok(())
     }
 
+    #[test]
+    fn test_usage_stats_served_from_hourly_rollups() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let hour = 60 * 60;
+
+        {
+            let conn = lock_conn!(db.conn);
+            for (id, status, created_at) in [
+                ("edge-before", 200, 10 * hour + 100),
+                ("head", 200, 10 * hour + 1800),
+                ("rollup-ok", 200, 11 * hour + 5),
+                ("rollup-err", 500, 11 * hour + 2000),
+                ("tail", 200, 12 * hour + 10),
+                ("edge-after", 200, 12 * hour + 500),
+            ] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model,
+                        input_tokens, output_tokens, total_cost_usd,
+                        latency_ms, status_code, created_at
+                    ) VALUES (?, 'p1', 'claude', 'claude-3', 100, 50, '0.01', 100, ?, ?)",
+                    params![id, status, created_at],
+                )?;
+            }
+        }
+
+        // 首尾不足一小时的部分按原始日志精确计数
+        let range = (Some(10 * hour + 1000), Some(12 * hour + 100));
+        let summary = db.get_usage_summary(range.0, range.1, None, None)?;
+        assert_eq!(summary.total_requests, 4);
+        assert_eq!(summary.success_rate, 75.0);
+
+        // 清理日志后汇总仍保留
+        assert_eq!(db.delete_request_logs_by_date(11 * hour, 12 * hour - 1)?, 2);
+        let summary = db.get_usage_summary(range.0, range.1, None, None)?;
+        assert_eq!(summary.total_requests, 4);
+        assert_eq!(summary.total_input_tokens, 400);
+
+        let trends = db.get_daily_trends(Some(0), Some(24 * hour - 1), None, None)?;
+        let total: u64 = trends.iter().map(|t| t.request_count).sum();
+        assert_eq!(total, 6);
+
+        let providers = db.get_provider_stats(None, None)?;
+        assert_eq!(providers[0].request_count, 6);
+        assert!((providers[0].success_rate - 500.0 / 6.0).abs() < 0.01);
+        assert_eq!(providers[0].avg_latency_ms, 100);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_session_cache_stats() -> Result<(), AppError> {
+        let db = Database::memory()?;
+
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT OR REPLACE INTO model_pricing (model_id, display_name, input_cost_per_million,
+                    output_cost_per_million, cache_read_cost_per_million)
+                 VALUES ('session-model', 'Session Model', '3', '15', '0.3')",
+                [],
+            )?;
+            for (id, provider, input, cache_read, status, created_at) in [
+                ("s1", "p1", 1000, 0, 200, 1000),
+                ("s2", "p1", 100, 900, 200, 1001),
+                ("s3", "p2", 1000, 0, 200, 1002),
+                ("s4", "p2", 0, 0, 500, 1003),
+            ] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model, input_tokens, cache_read_tokens,
+                        latency_ms, status_code, session_id, cost_multiplier, created_at
+                    ) VALUES (?1, ?2, 'claude', 'session-model', ?3, ?4, 100, ?5, 'sess-1', '2', ?6)",
+                    params![id, provider, input, cache_read, status, created_at],
+                )?;
+            }
+        }
+
+        let stats = db
+            .get_session_cache_stats("sess-1")?
+            .expect("session stats");
+        assert_eq!(stats.request_count, 3);
+        assert_eq!(stats.provider_count, 2);
+        assert_eq!(stats.provider_switches, 1);
+        assert_eq!(stats.total_prompt_tokens, 3000);
+        assert_eq!(stats.total_cache_read_tokens, 900);
+        assert!((stats.cache_hit_rate - 0.3).abs() < 1e-9);
+        // 900 × (3 - 0.3) / 1M × 2
+        assert_eq!(stats.estimated_savings_usd, "0.004860");
+
+        assert!(db.get_session_cache_stats("missing")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_and_project_usage_stats() -> Result<(), AppError> {
+        let db = Database::memory()?;
+
+        {
+            let conn = lock_conn!(db.conn);
+            for (id, app, model, session, cost, source, created_at) in [
+                ("r1", "codex", "gpt-5", "codex_abc", "1.5", "proxy", 1000),
+                (
+                    "r2",
+                    "codex",
+                    "gpt-5-mini",
+                    "abc",
+                    "0.5",
+                    "transcript",
+                    1001,
+                ),
+                ("r3", "claude", "claude-sonnet", "def", "3", "proxy", 1002),
+                (
+                    "r4",
+                    "claude",
+                    "claude-sonnet",
+                    "orphan",
+                    "0.25",
+                    "proxy",
+                    1003,
+                ),
+            ] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model, input_tokens, output_tokens,
+                        total_cost_usd, latency_ms, status_code, session_id, source, created_at
+                    ) VALUES (?1, 'p1', ?2, ?3, 100, 10, ?4, 100, 200, ?5, ?6, ?7)",
+                    params![id, app, model, cost, session, source, created_at],
+                )?;
+            }
+        }
+
+        let sessions = db.get_session_usage_stats(None, None, None, None)?;
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(sessions[0].session_id, "def");
+        let codex = &sessions[1];
+        assert_eq!(codex.session_id, "abc");
+        assert_eq!(codex.request_count, 2);
+        assert_eq!(codex.total_cost, "2.000000");
+        assert_eq!(codex.models[0].model, "gpt-5");
+        assert_eq!(
+            (codex.first_request_at, codex.last_request_at),
+            (1000, 1001)
+        );
+
+        let proxy_only = db.get_session_usage_stats(None, None, None, Some("proxy"))?;
+        assert_eq!(proxy_only[1].total_cost, "1.500000");
+
+        let meta = |session_id: &str, project_dir: &str| SessionMeta {
+            provider_id: "claude".to_string(),
+            session_id: session_id.to_string(),
+            title: None,
+            summary: None,
+            project_dir: Some(project_dir.to_string()),
+            created_at: None,
+            last_active_at: None,
+            source_path: None,
+            resume_command: None,
+            usage: None,
+        };
+        let projects = db.get_project_usage_stats(
+            &[meta("abc", "/repo/a"), meta("def", "/repo/a")],
+            None,
+            None,
+            None,
+            None,
+        )?;
+        assert_eq!(projects.len(), 2);
+        assert_eq!(projects[0].project_dir.as_deref(), Some("/repo/a"));
+        assert_eq!(projects[0].session_count, 2);
+        assert_eq!(projects[0].total_cost, "5.000000");
+        assert_eq!(projects[0].models[0].model, "claude-sonnet");
+        assert_eq!(projects[0].models.len(), 3);
+        assert!(projects[1].project_dir.is_none());
+        Ok(())
+    }
+
     #[test]
     fn test_get_model_stats() -> Result<(), AppError> {
         let db = Database::memory()?;
@@ -1099,7 +2027,7 @@ mod tests {
             )?;
         }
 
-        let stats = db.get_model_stats()?;
+        let stats = db.get_model_stats(None, None)?;
         assert_eq!(stats.len(), 1);
         assert_eq!(stats[0].model, "claude-3-sonnet");
         assert_eq!(stats[0].request_count, 1);
@@ -1129,35 +2057,80 @@ mod tests {
         )?;
 
         // 测试精确匹配（seed_model_pricing 已预置 claude-sonnet-4-5-20250929）
-        let result = find_model_pricing_row(&conn, "claude-sonnet-4-5-20250929")?;
+        let result = find_model_pricing_row(&conn, "claude-sonnet-4-5-20250929", 0)?;
         assert!(
             result.is_some(),
             "应该能精确匹配 claude-sonnet-4-5-20250929"
         );
 
         // 清洗：去除前缀和冒号后缀
-        let result = find_model_pricing_row(&conn, "anthropic/claude-haiku-4.5")?;
+        let result = find_model_pricing_row(&conn, "anthropic/claude-haiku-4.5", 0)?;
         assert!(
             result.is_some(),
             "带前缀的模型 anthropic/claude-haiku-4.5 应能匹配到 claude-haiku-4.5"
         );
-        let result = find_model_pricing_row(&conn, "moonshotai/kimi-k2-0905:exa")?;
+        let result = find_model_pricing_row(&conn, "moonshotai/kimi-k2-0905:exa", 0)?;
         assert!(
             result.is_some(),
             "带前缀+冒号后缀的模型应清洗后匹配到 kimi-k2-0905"
         );
 
         // 清洗：@ 替换为 -（seed_model_pricing 已预置 gpt-5.2-codex-low）
-        let result = find_model_pricing_row(&conn, "gpt-5.2-codex@low")?;
+        let result = find_model_pricing_row(&conn, "gpt-5.2-codex@low", 0)?;
         assert!(
             result.is_some(),
             "带 @ 分隔符的模型 gpt-5.2-codex@low 应能匹配到 gpt-5.2-codex-low"
         );
 
         // 测试不存在的模型
-        let result = find_model_pricing_row(&conn, "unknown-model-123")?;
+        let result = find_model_pricing_row(&conn, "unknown-model-123", 0)?;
         assert!(result.is_none(), "不应该匹配不存在的模型");
 
         Ok(())
     }
+
+    #[test]
+    fn test_model_pricing_effective_dates() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let conn = lock_conn!(db.conn);
+
+        // 旧价格 [0, 1000)，新价格 [1000, ∞)，以及一段 [5000, 6000) 的临时折扣
+        for (from, until, input) in [
+            (0, Some(1000), "2"),
+            (1000, None, "4"),
+            (5000, Some(6000), "1"),
+        ] {
+            conn.execute(
+                "INSERT INTO model_pricing (model_id, display_name, input_cost_per_million,
+                    output_cost_per_million, effective_from, effective_until)
+                 VALUES ('dated-model', 'Dated', ?1, '8', ?2, ?3)",
+                params![input, from, until],
+            )?;
+        }
+
+        let input_at = |at: i64| -> Result<rust_decimal::Decimal, AppError> {
+            Ok(find_model_pricing_row(&conn, "dated-model", at)?
+                .expect("pricing should exist")
+                .input_cost_per_million)
+        };
+        assert_eq!(input_at(999)?, rust_decimal::Decimal::from(2));
+        assert_eq!(input_at(1000)?, rust_decimal::Decimal::from(4));
+        assert_eq!(input_at(5500)?, rust_decimal::Decimal::from(1));
+        assert_eq!(input_at(6000)?, rust_decimal::Decimal::from(4));
+
+        // 只有未来版本时，回退到最早的版本
+        conn.execute(
+            "INSERT INTO model_pricing (model_id, display_name, input_cost_per_million,
+                output_cost_per_million, effective_from)
+             VALUES ('future-model', 'Future', '7', '8', 1000)",
+            [],
+        )?;
+        let pricing = find_model_pricing_row(&conn, "future-model", 10)?;
+        assert_eq!(
+            pricing.map(|p| p.input_cost_per_million),
+            Some(rust_decimal::Decimal::from(7))
+        );
+
+        Ok(())
+    }
 }
@@ -2,7 +2,11 @@ pub mod providers;
 pub mod terminal;
 
 use serde::Serialize;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::proxy::usage::parser::TokenUsage;
+use crate::services::usage_stats::SessionUsageStats;
 
 use providers::{claude, codex, gemini, openclaw, opencode};
 
@@ -25,6 +29,9 @@ pub struct SessionMeta {
     pub source_path: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resume_command: Option<String>,
+    /// 请求日志中该会话的累计成本与模型构成（由 list_sessions 填充）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<SessionUsageStats>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -36,6 +43,17 @@ pub struct SessionMessage {
     pub ts: Option<i64>,
 }
 
+/// 会话记录中单条助手消息的 token 用量
+#[derive(Debug, Clone)]
+pub struct TranscriptUsage {
+    /// 去重键（消息 ID 或等效标识，同一文件重复写入的消息只保留最后一次）
+    pub message_id: String,
+    pub session_id: Option<String>,
+    pub model: String,
+    pub usage: TokenUsage,
+    pub ts: Option<i64>,
+}
+
 pub fn scan_sessions() -> Vec<SessionMeta> {
     let mut sessions = Vec::new();
     sessions.extend(codex::scan_sessions());
@@ -64,3 +82,34 @@ pub fn load_messages(provider_id: &str, source_path: &str) -> Result<Vec<Session
         _ => Err(format!("Unsupported provider: {provider_id}")),
     }
 }
+
+/// 为会话附加请求日志中的用量汇总（按 app 与会话 ID 匹配）
+pub fn attach_usage(sessions: &mut [SessionMeta], stats: Vec<SessionUsageStats>) {
+    let mut by_session: HashMap<(String, String), SessionUsageStats> = stats
+        .into_iter()
+        .map(|s| ((s.app_type.clone(), s.session_id.clone()), s))
+        .collect();
+    for session in sessions.iter_mut() {
+        session.usage =
+            by_session.remove(&(session.provider_id.clone(), session.session_id.clone()));
+    }
+}
+
+/// 可提取用量的会话文件（Claude 包含子代理会话）
+pub fn usage_files(app_type: &str) -> Vec<PathBuf> {
+    match app_type {
+        "claude" => claude::usage_files(),
+        "codex" => codex::usage_files(),
+        "gemini" => gemini::usage_files(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn load_usage(app_type: &str, path: &Path) -> Result<Vec<TranscriptUsage>, String> {
+    match app_type {
+        "claude" => claude::load_usage(path),
+        "codex" => codex::load_usage(path),
+        "gemini" => gemini::load_usage(path),
+        _ => Err(format!("Unsupported provider: {app_type}")),
+    }
+}
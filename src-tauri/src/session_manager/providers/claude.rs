@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
@@ -5,7 +6,8 @@ use std::path::{Path, PathBuf};
 use serde_json::Value;
 
 use crate::config::get_claude_config_dir;
-use crate::session_manager::{SessionMessage, SessionMeta};
+use crate::proxy::usage::parser::TokenUsage;
+use crate::session_manager::{SessionMessage, SessionMeta, TranscriptUsage};
 
 use super::utils::{extract_text, parse_timestamp_to_ms, path_basename, truncate_summary};
 
@@ -68,6 +70,84 @@ pub fn load_messages(path: &Path) -> Result<Vec<SessionMessage>, String> {
     Ok(messages)
 }
 
+pub fn usage_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_jsonl_files(&get_claude_config_dir().join("projects"), &mut files);
+    files
+}
+
+pub fn load_usage(path: &Path) -> Result<Vec<TranscriptUsage>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open session file: {e}"))?;
+    let reader = BufReader::new(file);
+    let mut records: Vec<TranscriptUsage> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for line in reader.lines() {
+        let line = match line {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let value: Value = match serde_json::from_str(&line) {
+            Ok(parsed) => parsed,
+            Err(_) => continue,
+        };
+
+        if value.get("type").and_then(Value::as_str) != Some("assistant") {
+            continue;
+        }
+        let Some(message) = value.get("message") else {
+            continue;
+        };
+        let Some(usage) = message.get("usage") else {
+            continue;
+        };
+        let model = message
+            .get("model")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        // 本地生成的错误/中断消息不计费
+        if model.is_empty() || model == "<synthetic>" {
+            continue;
+        }
+        let Some(message_id) = message
+            .get("id")
+            .or_else(|| value.get("uuid"))
+            .and_then(Value::as_str)
+        else {
+            continue;
+        };
+
+        let tokens = |field: &str| usage.get(field).and_then(Value::as_u64).unwrap_or(0) as u32;
+        let record = TranscriptUsage {
+            message_id: message_id.to_string(),
+            session_id: value
+                .get("sessionId")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            model: model.to_string(),
+            usage: TokenUsage {
+                input_tokens: tokens("input_tokens"),
+                output_tokens: tokens("output_tokens"),
+                cache_read_tokens: tokens("cache_read_input_tokens"),
+                cache_creation_tokens: tokens("cache_creation_input_tokens"),
+                model: Some(model.to_string()),
+            },
+            ts: value.get("timestamp").and_then(parse_timestamp_to_ms),
+        };
+
+        // 同一消息的多个内容块分行写入，以最后一行的用量为准
+        match index.get(message_id) {
+            Some(&i) => records[i] = record,
+            None => {
+                index.insert(message_id.to_string(), records.len());
+                records.push(record);
+            }
+        }
+    }
+
+    Ok(records)
+}
+
 fn parse_session(path: &Path) -> Option<SessionMeta> {
     if is_agent_session(path) {
         return None;
@@ -149,6 +229,7 @@ fn parse_session(path: &Path) -> Option<SessionMeta> {
         last_active_at,
         source_path: Some(path.to_string_lossy().to_string()),
         resume_command: Some(format!("claude --resume {session_id}")),
+        usage: None,
     })
 }
 
@@ -6,7 +6,8 @@ use regex::Regex;
 use serde_json::Value;
 
 use crate::codex_config::get_codex_config_dir;
-use crate::session_manager::{SessionMessage, SessionMeta};
+use crate::proxy::usage::parser::TokenUsage;
+use crate::session_manager::{SessionMessage, SessionMeta, TranscriptUsage};
 
 use super::utils::{extract_text, parse_timestamp_to_ms, path_basename, truncate_summary};
 
@@ -73,6 +74,90 @@ pub fn load_messages(path: &Path) -> Result<Vec<SessionMessage>, String> {
     Ok(messages)
 }
 
+pub fn usage_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_jsonl_files(&get_codex_config_dir().join("sessions"), &mut files);
+    files
+}
+
+/// 从 token_count 事件提取每轮用量
+///
+/// Codex 没有消息 ID，以会话 ID + 累计 token 总数作为去重键（重复的 token_count 事件累计值不变）
+pub fn load_usage(path: &Path) -> Result<Vec<TranscriptUsage>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open session file: {e}"))?;
+    let reader = BufReader::new(file);
+    let mut records = Vec::new();
+    let mut session_id = infer_session_id_from_filename(path);
+    let mut model: Option<String> = None;
+    let mut last_total: Option<u64> = None;
+
+    for line in reader.lines() {
+        let line = match line {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let value: Value = match serde_json::from_str(&line) {
+            Ok(parsed) => parsed,
+            Err(_) => continue,
+        };
+        let Some(payload) = value.get("payload") else {
+            continue;
+        };
+
+        match value.get("type").and_then(Value::as_str) {
+            Some("session_meta") => {
+                if let Some(id) = payload.get("id").and_then(Value::as_str) {
+                    session_id = Some(id.to_string());
+                }
+            }
+            Some("turn_context") => {
+                if let Some(m) = payload.get("model").and_then(Value::as_str) {
+                    model = Some(m.to_string());
+                }
+            }
+            Some("event_msg")
+                if payload.get("type").and_then(Value::as_str) == Some("token_count") =>
+            {
+                let Some(info) = payload.get("info").filter(|info| !info.is_null()) else {
+                    continue;
+                };
+                let (Some(last), Some(total)) = (
+                    info.get("last_token_usage"),
+                    info.get("total_token_usage")
+                        .and_then(|t| t.get("total_tokens"))
+                        .and_then(Value::as_u64),
+                ) else {
+                    continue;
+                };
+                if last_total == Some(total) {
+                    continue;
+                }
+                last_total = Some(total);
+
+                let tokens =
+                    |field: &str| last.get(field).and_then(Value::as_u64).unwrap_or(0) as u32;
+                let model = model.clone().unwrap_or_else(|| "unknown".to_string());
+                records.push(TranscriptUsage {
+                    message_id: format!("{}:{total}", session_id.as_deref().unwrap_or_default()),
+                    session_id: session_id.clone(),
+                    model: model.clone(),
+                    usage: TokenUsage {
+                        input_tokens: tokens("input_tokens"),
+                        output_tokens: tokens("output_tokens"),
+                        cache_read_tokens: tokens("cached_input_tokens"),
+                        cache_creation_tokens: 0,
+                        model: Some(model),
+                    },
+                    ts: value.get("timestamp").and_then(parse_timestamp_to_ms),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(records)
+}
+
 fn parse_session(path: &Path) -> Option<SessionMeta> {
     let file = File::open(path).ok()?;
     let reader = BufReader::new(file);
@@ -161,6 +246,7 @@ fn parse_session(path: &Path) -> Option<SessionMeta> {
         last_active_at,
         source_path: Some(path.to_string_lossy().to_string()),
         resume_command: Some(format!("codex resume {session_id}")),
+        usage: None,
     })
 }
 
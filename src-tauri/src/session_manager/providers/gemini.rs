@@ -1,23 +1,31 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use serde_json::Value;
 
-use crate::session_manager::{SessionMessage, SessionMeta};
+use crate::proxy::usage::parser::TokenUsage;
+use crate::session_manager::{SessionMessage, SessionMeta, TranscriptUsage};
 
 use super::utils::{parse_timestamp_to_ms, truncate_summary};
 
 const PROVIDER_ID: &str = "gemini";
 
 pub fn scan_sessions() -> Vec<SessionMeta> {
+    usage_files()
+        .iter()
+        .filter_map(|path| parse_session(path))
+        .collect()
+}
+
+/// 会话文件：tmp/<project_hash>/chats/session-*.json
+pub fn usage_files() -> Vec<PathBuf> {
     let gemini_dir = crate::gemini_config::get_gemini_dir();
     let tmp_dir = gemini_dir.join("tmp");
     if !tmp_dir.exists() {
         return Vec::new();
     }
 
-    let mut sessions = Vec::new();
+    let mut files = Vec::new();
 
-    // Iterate over project hash directories: tmp/<project_hash>/chats/session-*.json
     let project_dirs = match std::fs::read_dir(&tmp_dir) {
         Ok(entries) => entries,
         Err(_) => return Vec::new(),
@@ -36,16 +44,67 @@ pub fn scan_sessions() -> Vec<SessionMeta> {
 
         for file_entry in chat_files.flatten() {
             let path = file_entry.path();
-            if path.extension().and_then(|e| e.to_str()) != Some("json") {
-                continue;
-            }
-            if let Some(meta) = parse_session(&path) {
-                sessions.push(meta);
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                files.push(path);
             }
         }
     }
 
-    sessions
+    files
+}
+
+pub fn load_usage(path: &Path) -> Result<Vec<TranscriptUsage>, String> {
+    let data = std::fs::read_to_string(path).map_err(|e| format!("Failed to read session: {e}"))?;
+    let value: Value =
+        serde_json::from_str(&data).map_err(|e| format!("Failed to parse session JSON: {e}"))?;
+
+    let session_id = value
+        .get("sessionId")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let messages = value
+        .get("messages")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "No messages array found".to_string())?;
+
+    let mut records = Vec::new();
+    for msg in messages {
+        if msg.get("type").and_then(Value::as_str) != Some("gemini") {
+            continue;
+        }
+        let (Some(id), Some(tokens)) = (msg.get("id").and_then(Value::as_str), msg.get("tokens"))
+        else {
+            continue;
+        };
+        let count = |field: &str| tokens.get(field).and_then(Value::as_u64).unwrap_or(0);
+        let input = count("input");
+        // 与代理解析一致：输出 = 总量 - 输入（含 thoughts / tool）
+        let output = match tokens.get("total").and_then(Value::as_u64) {
+            Some(total) => total.saturating_sub(input),
+            None => count("output") + count("thoughts"),
+        };
+        let model = msg
+            .get("model")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+            .to_string();
+
+        records.push(TranscriptUsage {
+            message_id: id.to_string(),
+            session_id: session_id.clone(),
+            model: model.clone(),
+            usage: TokenUsage {
+                input_tokens: input as u32,
+                output_tokens: output as u32,
+                cache_read_tokens: count("cached") as u32,
+                cache_creation_tokens: 0,
+                model: Some(model),
+            },
+            ts: msg.get("timestamp").and_then(parse_timestamp_to_ms),
+        });
+    }
+
+    Ok(records)
 }
 
 pub fn load_messages(path: &Path) -> Result<Vec<SessionMessage>, String> {
@@ -113,5 +172,6 @@ fn parse_session(path: &Path) -> Option<SessionMeta> {
         last_active_at: last_active_at.or(created_at),
         source_path: Some(source_path),
         resume_command: Some(format!("gemini --resume {session_id}")),
+        usage: None,
     })
 }
@@ -7,6 +7,7 @@ use std::sync::{OnceLock, RwLock};
 use crate::app_config::AppType;
 use crate::error::AppError;
 use crate::services::skill::SyncMethod;
+use crate::services::usage_export::UsageReportSettings;
 
 /// 自定义端点配置（历史兼容，实际存储在 provider.meta.custom_endpoints）
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -253,6 +254,11 @@ pub struct AppSettings {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub backup_retain_count: Option<u32>,
 
+    // ===== 用量报告设置 =====
+    /// 定期花费报告（周报/月报）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_report: Option<UsageReportSettings>,
+
     // ===== 终端设置 =====
     /// 首选终端应用（可选，默认使用系统默认终端）
     /// - macOS: "terminal" | "iterm2" | "warp" | "alacritty" | "kitty" | "ghostty"
@@ -299,6 +305,7 @@ impl Default for AppSettings {
             webdav_backup: None,
             backup_interval_hours: None,
             backup_retain_count: None,
+            usage_report: None,
             preferred_terminal: None,
         }
     }
@@ -660,6 +667,20 @@ pub fn effective_backup_retain_count() -> usize {
         .unwrap_or(10)
 }
 
+// ===== 用量报告设置管理函数 =====
+
+/// 获取定期用量报告设置
+pub fn get_usage_report_settings() -> Option<UsageReportSettings> {
+    settings_store()
+        .read()
+        .unwrap_or_else(|e| {
+            log::warn!("设置锁已毒化，使用恢复值: {e}");
+            e.into_inner()
+        })
+        .usage_report
+        .clone()
+}
+
 // ===== 终端设置管理函数 =====
 
 /// 获取首选终端应用
@@ -0,0 +1,55 @@
+//! 本地时间辅助函数
+//!
+//! 客户端令牌配额、花费预算与定期报告共用的自然日/自然月边界计算。
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone};
+
+/// 周期：按自然日
+pub const PERIOD_DAILY: &str = "daily";
+/// 周期：按自然月
+pub const PERIOD_MONTHLY: &str = "monthly";
+
+/// 本地时间某日零点（Unix 秒）；夏令时跳过零点时取最早的合法时刻
+pub fn local_midnight(date: NaiveDate) -> Option<i64> {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|dt| Local.from_local_datetime(&dt).earliest())
+        .map(|dt| dt.timestamp())
+}
+
+/// 当前周期的起点（本地时间的当日/当月零点，Unix 秒）
+///
+/// `period` 为 [`PERIOD_MONTHLY`] 时取当月 1 日，其余按自然日处理
+pub fn period_start(period: &str, now: DateTime<Local>) -> i64 {
+    let date = if period == PERIOD_MONTHLY {
+        now.date_naive().with_day(1).unwrap_or(now.date_naive())
+    } else {
+        now.date_naive()
+    };
+    local_midnight(date).unwrap_or_else(|| now.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_period_start() {
+        let now = Local.with_ymd_and_hms(2026, 3, 15, 13, 45, 0).unwrap();
+        let daily = period_start(PERIOD_DAILY, now);
+        let monthly = period_start(PERIOD_MONTHLY, now);
+        assert_eq!(
+            daily,
+            Local
+                .with_ymd_and_hms(2026, 3, 15, 0, 0, 0)
+                .unwrap()
+                .timestamp()
+        );
+        assert_eq!(
+            monthly,
+            Local
+                .with_ymd_and_hms(2026, 3, 1, 0, 0, 0)
+                .unwrap()
+                .timestamp()
+        );
+    }
+}
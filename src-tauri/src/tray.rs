@@ -2,6 +2,9 @@
 //!
 //! 负责系统托盘图标和菜单的创建、更新和事件处理。
 
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
 use tauri::menu::{CheckMenuItem, Menu, MenuBuilder, MenuItem};
 use tauri::{Emitter, Manager};
 
@@ -444,3 +447,102 @@ pub fn handle_tray_menu_event(app: &tauri::AppHandle, event_id: &str) {
         }
     }
 }
+
+/// 托盘提示中的告警：键区分来源（如 `budget:<id>`、`balance:<app>:<provider>`），
+/// 各功能只增删自己的条目，互不覆盖
+static TRAY_ALERTS: Lazy<Mutex<BTreeMap<String, String>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// 由当前全部告警拼出托盘提示；没有告警时为 None（移除提示）
+fn compose_tray_tooltip(alerts: &BTreeMap<String, String>) -> Option<String> {
+    match alerts.len() {
+        0 => None,
+        1 => alerts
+            .values()
+            .next()
+            .map(|message| format!("CC Switch - {message}")),
+        _ => Some(
+            std::iter::once("CC Switch")
+                .chain(alerts.values().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+    }
+}
+
+/// 用告警集合中的变更刷新托盘提示；`update` 返回是否有变化
+fn update_tray_alerts(
+    app: &tauri::AppHandle,
+    update: impl FnOnce(&mut BTreeMap<String, String>) -> bool,
+) {
+    let tooltip = {
+        let Ok(mut alerts) = TRAY_ALERTS.lock() else {
+            return;
+        };
+        if !update(&mut alerts) {
+            return;
+        }
+        compose_tray_tooltip(&alerts)
+    };
+    if let Some(tray) = app.tray_by_id("main") {
+        if let Err(e) = tray.set_tooltip(tooltip) {
+            log::debug!("更新托盘提示失败: {e}");
+        }
+    }
+}
+
+/// 设置（`Some`）或清除（`None`）一条托盘告警
+pub fn set_tray_alert(app: &tauri::AppHandle, key: &str, message: Option<String>) {
+    update_tray_alerts(app, |alerts| match message {
+        Some(message) => alerts.insert(key.to_string(), message.clone()) != Some(message),
+        None => alerts.remove(key).is_some(),
+    });
+}
+
+/// 用 `current` 替换键以 `prefix` 开头的全部托盘告警（告警条件消失的条目随之清除）
+pub fn replace_tray_alerts(
+    app: &tauri::AppHandle,
+    prefix: &str,
+    current: impl IntoIterator<Item = (String, String)>,
+) {
+    let current: BTreeMap<String, String> = current.into_iter().collect();
+    update_tray_alerts(app, |alerts| {
+        let previous: BTreeMap<String, String> = alerts
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, message)| (key.clone(), message.clone()))
+            .collect();
+        if previous == current {
+            return false;
+        }
+        alerts.retain(|key, _| !key.starts_with(prefix));
+        alerts.extend(current);
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_tray_tooltip() {
+        let mut alerts = BTreeMap::new();
+        assert_eq!(compose_tray_tooltip(&alerts), None);
+
+        alerts.insert("budget:b1".to_string(), "预算「team」已用 80%".to_string());
+        assert_eq!(
+            compose_tray_tooltip(&alerts).as_deref(),
+            Some("CC Switch - 预算「team」已用 80%")
+        );
+
+        alerts.insert(
+            "balance:claude:p1".to_string(),
+            "Relay 余额不足：1.00".to_string(),
+        );
+        assert_eq!(
+            compose_tray_tooltip(&alerts).as_deref(),
+            Some("CC Switch\nRelay 余额不足：1.00\n预算「team」已用 80%")
+        );
+    }
+}
@@ -781,6 +781,25 @@ fn import_sql_accepts_cc_switch_exported_backup() {
     }
 
     let state = create_test_state_with_config(&config).expect("create test state");
+
+    // 写入请求日志（小时汇总由触发器同步），确保导出的汇总数据能原样导回
+    let transcript_dir = home.join(".claude").join("projects").join("demo");
+    fs::create_dir_all(&transcript_dir).expect("create transcript dir");
+    fs::write(
+        transcript_dir.join("s1.jsonl"),
+        [
+            r#"{"type":"assistant","sessionId":"s1","timestamp":"2026-01-01T00:00:00Z","message":{"id":"msg_1","model":"claude-sonnet-4-5-20250929","usage":{"input_tokens":10,"output_tokens":5}}}"#,
+            r#"{"type":"assistant","sessionId":"s1","timestamp":"2026-01-01T00:10:00Z","message":{"id":"msg_2","model":"claude-sonnet-4-5-20250929","usage":{"input_tokens":20,"output_tokens":5}}}"#,
+        ]
+        .join("\n"),
+    )
+    .expect("write transcript");
+    let imported = state
+        .db
+        .import_transcript_usage(&["claude".to_string()])
+        .expect("import transcript usage");
+    assert_eq!(imported.records_imported, 2);
+
     let export_path = home.join("cc-switch-export.sql");
     state
         .db
@@ -803,4 +822,12 @@ fn import_sql_accepts_cc_switch_exported_backup() {
         providers.contains_key("test-provider"),
         "imported providers should contain test-provider"
     );
+
+    // 汇总数据与请求日志均已导入，且未被触发器重复累加
+    let summary = state
+        .db
+        .get_usage_summary(None, None, None, None)
+        .expect("usage summary");
+    assert_eq!(summary.total_requests, 2);
+    assert_eq!(summary.total_input_tokens, 30);
 }
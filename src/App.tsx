@@ -24,7 +24,9 @@ import {
 } from "lucide-react";
 import type { Provider, VisibleApps } from "@/types";
 import type { EnvConflict } from "@/types/env";
+import type { BalanceAlert, BudgetAlert } from "@/types/usage";
 import { useProvidersQuery, useSettingsQuery } from "@/lib/query";
+import { usageKeys } from "@/lib/query/usage";
 import {
   providersApi,
   settingsApi,
@@ -378,6 +380,101 @@ function App() {
     };
   }, [queryClient, t]);
 
+  useEffect(() => {
+    let unsubscribe: (() => void) | undefined;
+    let active = true;
+
+    const setupListener = async () => {
+      try {
+        const off = await listen<BudgetAlert>(
+          "budget-threshold-crossed",
+          async (event) => {
+            const alert = event.payload;
+            await queryClient.invalidateQueries({ queryKey: usageKeys.all });
+            toast.warning(
+              t("usage.budgetThresholdToast", {
+                name: alert.name,
+                percent: Math.round(alert.percent),
+                threshold: alert.threshold,
+              }),
+            );
+          },
+        );
+        if (!active) {
+          off();
+          return;
+        }
+        unsubscribe = off;
+      } catch (error) {
+        console.error(
+          "[App] Failed to subscribe budget-threshold-crossed event",
+          error,
+        );
+      }
+    };
+
+    void setupListener();
+    return () => {
+      active = false;
+      unsubscribe?.();
+    };
+  }, [queryClient, t]);
+
+  useEffect(() => {
+    let unsubscribe: (() => void) | undefined;
+    let active = true;
+
+    const setupListener = async () => {
+      try {
+        const off = await listen<BalanceAlert>(
+          "provider-balance-low",
+          (event) => {
+            const alert = event.payload;
+            const remaining = `${alert.remaining.toFixed(2)}${
+              alert.unit ? ` ${alert.unit}` : ""
+            }`;
+            if (alert.exhausted) {
+              toast.error(
+                t("usage.balanceExhaustedToast", { name: alert.providerName }),
+              );
+            } else if (alert.runsOutInDays !== undefined) {
+              toast.warning(
+                t("usage.balanceLowForecastToast", {
+                  name: alert.providerName,
+                  remaining,
+                  days: Math.max(1, Math.round(alert.runsOutInDays)),
+                }),
+              );
+            } else {
+              toast.warning(
+                t("usage.balanceLowToast", {
+                  name: alert.providerName,
+                  remaining,
+                }),
+              );
+            }
+          },
+        );
+        if (!active) {
+          off();
+          return;
+        }
+        unsubscribe = off;
+      } catch (error) {
+        console.error(
+          "[App] Failed to subscribe provider-balance-low event",
+          error,
+        );
+      }
+    };
+
+    void setupListener();
+    return () => {
+      active = false;
+      unsubscribe?.();
+    };
+  }, [t]);
+
   useEffect(() => {
     const checkEnvOnStartup = async () => {
       try {
@@ -175,10 +175,14 @@ export function ProxyPanel() {
   };
 
   // 格式化地址用于 URL（IPv6 需要方括号）
-  const formatAddressForUrl = (address: string, port: number): string => {
+  const formatAddressForUrl = (
+    address: string,
+    port: number,
+    tls?: boolean,
+  ): string => {
     const isIPv6 = address.includes(":");
     const host = isIPv6 ? `[${address}]` : address;
-    return `http://${host}:${port}`;
+    return `${tls ? "https" : "http"}://${host}:${port}`;
   };
 
   return (
@@ -195,14 +199,22 @@ export function ProxyPanel() {
                 </p>
                 <div className="flex flex-col gap-2 sm:flex-row sm:items-center">
                   <code className="flex-1 text-sm bg-background px-3 py-2 rounded border border-border/60">
-                    {formatAddressForUrl(status.address, status.port)}
+                    {formatAddressForUrl(
+                      status.address,
+                      status.port,
+                      status.tls,
+                    )}
                   </code>
                   <Button
                     size="sm"
                     variant="outline"
                     onClick={() => {
                       navigator.clipboard.writeText(
-                        formatAddressForUrl(status.address, status.port),
+                        formatAddressForUrl(
+                          status.address,
+                          status.port,
+                          status.tls,
+                        ),
                       );
                       toast.success(
                         t("proxy.panel.addressCopied", {
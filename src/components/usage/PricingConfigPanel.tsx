@@ -50,7 +50,9 @@ export function PricingConfigPanel() {
   const deleteMutation = useDeleteModelPricing();
   const [editingModel, setEditingModel] = useState<ModelPricing | null>(null);
   const [isAddingNew, setIsAddingNew] = useState(false);
-  const [deleteConfirm, setDeleteConfirm] = useState<string | null>(null);
+  const [deleteConfirm, setDeleteConfirm] = useState<ModelPricing | null>(
+    null,
+  );
 
   // 三个应用的配置状态
   const [appConfigs, setAppConfigs] = useState<AppConfigState>({
@@ -179,10 +181,13 @@ export function PricingConfigPanel() {
     }
   };
 
-  const handleDelete = (modelId: string) => {
-    deleteMutation.mutate(modelId, {
-      onSuccess: () => setDeleteConfirm(null),
-    });
+  const handleDelete = (model: ModelPricing) => {
+    deleteMutation.mutate(
+      { modelId: model.modelId, effectiveFrom: model.effectiveFrom },
+      {
+        onSuccess: () => setDeleteConfirm(null),
+      },
+    );
   };
 
   const handleAddNew = () => {
@@ -194,6 +199,7 @@ export function PricingConfigPanel() {
       outputCostPerMillion: "0",
       cacheReadCostPerMillion: "0",
       cacheCreationCostPerMillion: "0",
+      effectiveFrom: 0,
     });
   };
 
@@ -385,7 +391,7 @@ export function PricingConfigPanel() {
                 </TableHeader>
                 <TableBody>
                   {pricing.map((model) => (
-                    <TableRow key={model.modelId}>
+                    <TableRow key={`${model.modelId}:${model.effectiveFrom}`}>
                       <TableCell className="font-mono text-sm">
                         {model.modelId}
                       </TableCell>
@@ -418,7 +424,7 @@ export function PricingConfigPanel() {
                           <Button
                             variant="ghost"
                             size="icon"
-                            onClick={() => setDeleteConfirm(model.modelId)}
+                            onClick={() => setDeleteConfirm(model)}
                             title={t("common.delete")}
                             className="text-destructive hover:text-destructive"
                           >
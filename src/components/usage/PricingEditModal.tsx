@@ -67,6 +67,10 @@ export function PricingEditModal({
         outputCost: formData.outputCost,
         cacheReadCost: formData.cacheReadCost,
         cacheCreationCost: formData.cacheCreationCost,
+        // 编辑时保留该版本的生效时间段与分档价格
+        effectiveFrom: isNew ? undefined : model.effectiveFrom,
+        effectiveUntil: isNew ? undefined : model.effectiveUntil,
+        tiers: isNew ? undefined : model.tiers,
       });
 
       toast.success(
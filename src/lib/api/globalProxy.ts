@@ -5,6 +5,7 @@
  */
 
 import { invoke } from "@tauri-apps/api/core";
+import type { UpstreamTlsConfig } from "@/types";
 
 /**
  * 代理测试结果
@@ -56,14 +57,114 @@ export async function setGlobalProxyUrl(url: string): Promise<void> {
   }
 }
 
+/**
+ * 获取全局上游 TLS 配置
+ */
+export async function getUpstreamTlsConfig(): Promise<UpstreamTlsConfig> {
+  return invoke<UpstreamTlsConfig>("get_upstream_tls_config");
+}
+
+/**
+ * 设置全局上游 TLS 配置
+ *
+ * 证书文件无法加载时抛出错误且不会保存
+ */
+export async function setUpstreamTlsConfig(
+  config: UpstreamTlsConfig,
+): Promise<void> {
+  try {
+    return await invoke("set_upstream_tls_config", { config });
+  } catch (error) {
+    throw new Error(typeof error === "string" ? error : String(error));
+  }
+}
+
 /**
  * 测试代理连接
  *
  * @param url - 要测试的代理 URL
+ * @param targetUrl - 可选，优先测试的目标地址
  * @returns 测试结果，包含是否成功、延迟和错误信息
  */
-export async function testProxyUrl(url: string): Promise<ProxyTestResult> {
-  return invoke<ProxyTestResult>("test_proxy_url", { url });
+export async function testProxyUrl(
+  url: string,
+  targetUrl?: string,
+): Promise<ProxyTestResult> {
+  return invoke<ProxyTestResult>("test_proxy_url", { url, targetUrl });
+}
+
+/**
+ * 上游代理规则匹配方式
+ */
+export type UpstreamProxyRuleMatch = "domain" | "domainSuffix" | "cidr";
+
+/**
+ * 单条上游代理规则
+ */
+export interface UpstreamProxyRule {
+  matchType: UpstreamProxyRuleMatch;
+  // 域名、域名后缀或 CIDR（CIDR 对域名目标先解析再匹配）
+  pattern: string;
+  // 代理 URL 或 "direct"
+  proxy: string;
+}
+
+/**
+ * 上游代理规则（有序规则列表或 PAC 脚本）
+ */
+export interface UpstreamProxyRules {
+  enabled: boolean;
+  mode: "rules" | "pac";
+  rules: UpstreamProxyRule[];
+  pacScript?: string;
+}
+
+/**
+ * 上游代理规则测试结果
+ */
+export interface ProxyRulesTestResult {
+  decisions: { url: string; route: string; error: string | null }[];
+  proxies: {
+    proxyUrl: string;
+    reachable: boolean;
+    result: ProxyTestResult | null;
+  }[];
+}
+
+/**
+ * 获取上游代理规则
+ */
+export async function getUpstreamProxyRules(): Promise<UpstreamProxyRules> {
+  return invoke<UpstreamProxyRules>("get_upstream_proxy_rules");
+}
+
+/**
+ * 设置上游代理规则（规则无效时抛出错误且不会保存）
+ */
+export async function setUpstreamProxyRules(
+  rules: UpstreamProxyRules,
+): Promise<void> {
+  try {
+    return await invoke("set_upstream_proxy_rules", { rules });
+  } catch (error) {
+    throw new Error(typeof error === "string" ? error : String(error));
+  }
+}
+
+/**
+ * 测试上游代理规则（不保存）
+ *
+ * @param rules - 待测试的规则
+ * @param urls - 示例目标地址，返回每个地址命中的路由
+ */
+export async function testUpstreamProxyRules(
+  rules: UpstreamProxyRules,
+  urls: string[],
+): Promise<ProxyRulesTestResult> {
+  return invoke<ProxyRulesTestResult>("test_upstream_proxy_rules", {
+    rules,
+    urls,
+  });
 }
 
 /**
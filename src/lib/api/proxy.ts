@@ -6,6 +6,16 @@ import type {
   ProxyTakeoverStatus,
   GlobalProxyConfig,
   AppProxyConfig,
+  LoadBalanceStrategy,
+  RoutingRule,
+  ProxyHook,
+  MockFixture,
+  ClientAuthConfig,
+  ClientToken,
+  ClientTokenQuotaPeriod,
+  ClientTokenUsage,
+  IssuedClientToken,
+  ProxyTlsConfig,
 } from "@/types/proxy";
 
 export const proxyApi = {
@@ -117,4 +127,145 @@ export const proxyApi = {
   async setPricingModelSource(appType: string, value: string): Promise<void> {
     return invoke("set_pricing_model_source", { appType, value });
   },
+
+  // 获取负载均衡策略
+  async getLoadBalanceStrategy(appType: string): Promise<LoadBalanceStrategy> {
+    return invoke("get_load_balance_strategy", { appType });
+  },
+
+  // 设置负载均衡策略
+  async setLoadBalanceStrategy(
+    appType: string,
+    value: LoadBalanceStrategy,
+  ): Promise<void> {
+    return invoke("set_load_balance_strategy", { appType, value });
+  },
+
+  // ========== 模型路由规则 API ==========
+
+  // 获取路由规则
+  async getRoutingRules(appType: string): Promise<RoutingRule[]> {
+    return invoke("get_routing_rules", { appType });
+  },
+
+  // 新增或更新路由规则（id 为空时由后端生成）
+  async saveRoutingRule(rule: RoutingRule): Promise<RoutingRule> {
+    return invoke("save_routing_rule", { rule });
+  },
+
+  // 删除路由规则
+  async deleteRoutingRule(id: string): Promise<void> {
+    return invoke("delete_routing_rule", { id });
+  },
+
+  // ========== 代理钩子 API ==========
+
+  // 获取钩子列表
+  async getProxyHooks(appType: string): Promise<ProxyHook[]> {
+    return invoke("get_proxy_hooks", { appType });
+  },
+
+  // 新增或更新钩子（保存前后端会编译脚本校验）
+  async saveProxyHook(hook: ProxyHook): Promise<ProxyHook> {
+    return invoke("save_proxy_hook", { hook });
+  },
+
+  // 删除钩子
+  async deleteProxyHook(id: string): Promise<void> {
+    return invoke("delete_proxy_hook", { id });
+  },
+
+  // ========== Mock 上游 API ==========
+
+  // 获取 Mock fixture 列表（按 sortIndex 排序）
+  async getMockFixtures(appType: string): Promise<MockFixture[]> {
+    return invoke("get_mock_fixtures", { appType });
+  },
+
+  // 新增或更新 Mock fixture
+  async saveMockFixture(fixture: MockFixture): Promise<MockFixture> {
+    return invoke("save_mock_fixture", { fixture });
+  },
+
+  // 删除 Mock fixture
+  async deleteMockFixture(id: string): Promise<void> {
+    return invoke("delete_mock_fixture", { id });
+  },
+
+  // 从请求日志录制 Mock fixture
+  async captureMockFixture(
+    requestId: string,
+    name?: string,
+  ): Promise<MockFixture> {
+    return invoke("capture_mock_fixture", { requestId, name });
+  },
+
+  // ========== TLS 监听 API ==========
+
+  // 获取代理监听 TLS 配置
+  async getProxyTlsConfig(): Promise<ProxyTlsConfig> {
+    return invoke("get_proxy_tls_config");
+  },
+
+  // 更新代理监听 TLS 配置（运行中会自动重启代理）
+  async updateProxyTlsConfig(config: ProxyTlsConfig): Promise<void> {
+    return invoke("update_proxy_tls_config", { config });
+  },
+
+  // 获取本地代理 CA 证书（PEM）
+  async getProxyCaCert(): Promise<string> {
+    return invoke("get_proxy_ca_cert");
+  },
+
+  // 导出本地代理 CA 证书到文件
+  async exportProxyCaCert(filePath: string): Promise<void> {
+    return invoke("export_proxy_ca_cert", { filePath });
+  },
+
+  // ========== 客户端认证 API ==========
+
+  // 获取客户端认证配置
+  async getClientAuthConfig(): Promise<ClientAuthConfig> {
+    return invoke("get_client_auth_config");
+  },
+
+  // 设置客户端认证配置
+  async setClientAuthConfig(config: ClientAuthConfig): Promise<boolean> {
+    return invoke("set_client_auth_config", { config });
+  },
+
+  // 获取全部客户端令牌
+  async listClientTokens(): Promise<ClientToken[]> {
+    return invoke("list_client_tokens");
+  },
+
+  // 签发客户端令牌（明文仅在返回值中出现一次）
+  async createClientToken(
+    name: string,
+    requestLimit?: number,
+    costLimitUsd?: string,
+    quotaPeriod?: ClientTokenQuotaPeriod,
+  ): Promise<IssuedClientToken> {
+    return invoke("create_client_token", {
+      name,
+      requestLimit,
+      costLimitUsd,
+      quotaPeriod,
+    });
+  },
+
+  // 更新令牌名称与配额
+  async updateClientToken(token: ClientToken): Promise<ClientToken> {
+    return invoke("update_client_token", { token });
+  },
+
+  // 吊销客户端令牌
+  async revokeClientToken(id: string): Promise<void> {
+    return invoke("revoke_client_token", { id });
+  },
+
+  // 获取令牌当前周期用量
+  async getClientTokenUsage(id: string): Promise<ClientTokenUsage> {
+    return invoke("get_client_token_usage", { id });
+  },
 };
@@ -196,6 +196,50 @@ export const settingsApi = {
     return await invoke("set_rectifier_config", { config });
   },
 
+  async getSessionAffinityConfig(): Promise<SessionAffinityConfig> {
+    return await invoke("get_session_affinity_config");
+  },
+
+  async setSessionAffinityConfig(
+    config: SessionAffinityConfig,
+  ): Promise<boolean> {
+    return await invoke("set_session_affinity_config", { config });
+  },
+
+  async getContextOverflowConfig(): Promise<ContextOverflowConfig> {
+    return await invoke("get_context_overflow_config");
+  },
+
+  async setContextOverflowConfig(
+    config: ContextOverflowConfig,
+  ): Promise<boolean> {
+    return await invoke("set_context_overflow_config", { config });
+  },
+
+  async getHedgingConfig(): Promise<HedgingConfig> {
+    return await invoke("get_hedging_config");
+  },
+
+  async setHedgingConfig(config: HedgingConfig): Promise<boolean> {
+    return await invoke("set_hedging_config", { config });
+  },
+
+  async getResponseCacheConfig(): Promise<ResponseCacheConfig> {
+    return await invoke("get_response_cache_config");
+  },
+
+  async setResponseCacheConfig(config: ResponseCacheConfig): Promise<boolean> {
+    return await invoke("set_response_cache_config", { config });
+  },
+
+  async getSecretScanConfig(): Promise<SecretScanConfig> {
+    return await invoke("get_secret_scan_config");
+  },
+
+  async setSecretScanConfig(config: SecretScanConfig): Promise<boolean> {
+    return await invoke("set_secret_scan_config", { config });
+  },
+
   async getLogConfig(): Promise<LogConfig> {
     return await invoke("get_log_config");
   },
@@ -211,9 +255,58 @@ export interface RectifierConfig {
   requestThinkingBudget: boolean;
 }
 
+export interface SessionAffinityConfig {
+  enabled: boolean;
+  ttlSeconds: number;
+}
+
+export interface ContextFallbackTarget {
+  providerId?: string;
+  model?: string;
+}
+
+export interface ContextOverflowConfig {
+  enabled: boolean;
+  // 键为 claude / codex / gemini
+  targets: Record<string, ContextFallbackTarget>;
+}
+
+export interface HedgingConfig {
+  enabled: boolean;
+  latencyPercentile: number;
+  minDelayMs: number;
+  maxRequestBytes: number;
+}
+
+export interface ResponseCacheConfig {
+  enabled: boolean;
+  ttlSeconds: number;
+  maxEntries: number;
+  maxEntryBytes: number;
+}
+
+export type SecretScanAction = "warn" | "redact" | "block";
+
+export interface SecretScanConfig {
+  enabled: boolean;
+  action: SecretScanAction;
+  // 内置检测器：aws_access_key、aws_secret_key、github_token、private_key、jwt、high_entropy
+  disabledDetectors: string[];
+  customPatterns: { name: string; pattern: string }[];
+  entropyThreshold: number;
+}
+
+export interface OtlpConfig {
+  enabled: boolean;
+  endpoint: string;
+  sampleRatio: number;
+  serviceName: string;
+}
+
 export interface LogConfig {
   enabled: boolean;
   level: "error" | "warn" | "info" | "debug" | "trace";
+  otlp?: OtlpConfig;
 }
 
 export interface BackupEntry {
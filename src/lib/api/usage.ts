@@ -7,8 +7,27 @@ import type {
   RequestLog,
   LogFilters,
   ModelPricing,
+  PricingTier,
   ProviderLimitStatus,
   PaginatedLogs,
+  SessionCacheStats,
+  SessionUsageStats,
+  ProjectUsageStats,
+  LatencyFilters,
+  LatencyGroupBy,
+  LatencyStats,
+  LatencyTrendPoint,
+  ExchangeRate,
+  CatalogPreview,
+  CatalogPrice,
+  UsageSource,
+  TranscriptImportResult,
+  UsageExportFormat,
+  UsageReport,
+  UsageBudget,
+  BudgetStatus,
+  BalanceSnapshot,
+  BalanceForecast,
 } from "@/types/usage";
 import type { UsageResult } from "@/types";
 import type { AppId } from "./types";
@@ -44,26 +63,48 @@ export const usageApi = {
   },
 
   // Proxy usage statistics methods
+  // currency 为空时按美元报告，其余币种需在汇率表中配置；
+  // source 为空时合并代理与会话记录用量
   getUsageSummary: async (
     startDate?: number,
     endDate?: number,
+    currency?: string,
+    source?: UsageSource,
   ): Promise<UsageSummary> => {
-    return invoke("get_usage_summary", { startDate, endDate });
+    return invoke("get_usage_summary", {
+      startDate,
+      endDate,
+      currency,
+      source,
+    });
   },
 
   getUsageTrends: async (
     startDate?: number,
     endDate?: number,
+    currency?: string,
+    source?: UsageSource,
   ): Promise<DailyStats[]> => {
-    return invoke("get_usage_trends", { startDate, endDate });
+    return invoke("get_usage_trends", {
+      startDate,
+      endDate,
+      currency,
+      source,
+    });
   },
 
-  getProviderStats: async (): Promise<ProviderStats[]> => {
-    return invoke("get_provider_stats");
+  getProviderStats: async (
+    currency?: string,
+    source?: UsageSource,
+  ): Promise<ProviderStats[]> => {
+    return invoke("get_provider_stats", { currency, source });
   },
 
-  getModelStats: async (): Promise<ModelStats[]> => {
-    return invoke("get_model_stats");
+  getModelStats: async (
+    currency?: string,
+    source?: UsageSource,
+  ): Promise<ModelStats[]> => {
+    return invoke("get_model_stats", { currency, source });
   },
 
   getRequestLogs: async (
@@ -82,6 +123,53 @@ export const usageApi = {
     return invoke("get_request_detail", { requestId });
   },
 
+  getSessionCacheStats: async (
+    sessionId: string,
+  ): Promise<SessionCacheStats | null> => {
+    return invoke("get_session_cache_stats", { sessionId });
+  },
+
+  getSessionUsageStats: async (
+    startDate?: number,
+    endDate?: number,
+    currency?: string,
+    source?: UsageSource,
+  ): Promise<SessionUsageStats[]> => {
+    return invoke("get_session_usage_stats", {
+      startDate,
+      endDate,
+      currency,
+      source,
+    });
+  },
+
+  getProjectUsageStats: async (
+    startDate?: number,
+    endDate?: number,
+    currency?: string,
+    source?: UsageSource,
+  ): Promise<ProjectUsageStats[]> => {
+    return invoke("get_project_usage_stats", {
+      startDate,
+      endDate,
+      currency,
+      source,
+    });
+  },
+
+  getLatencyStats: async (
+    filters: LatencyFilters,
+    groupBy: LatencyGroupBy,
+  ): Promise<LatencyStats[]> => {
+    return invoke("get_latency_stats", { filters, groupBy });
+  },
+
+  getLatencyTrends: async (
+    filters: LatencyFilters,
+  ): Promise<LatencyTrendPoint[]> => {
+    return invoke("get_latency_trends", { filters });
+  },
+
   getAvailableFilters: async (
     startDate?: number,
     endDate?: number,
@@ -100,6 +188,9 @@ export const usageApi = {
     outputCost: string,
     cacheReadCost: string,
     cacheCreationCost: string,
+    effectiveFrom?: number,
+    effectiveUntil?: number,
+    tiers?: PricingTier[],
   ): Promise<void> => {
     return invoke("update_model_pricing", {
       modelId,
@@ -108,18 +199,66 @@ export const usageApi = {
       outputCost,
       cacheReadCost,
       cacheCreationCost,
+      effectiveFrom,
+      effectiveUntil,
+      tiers,
     });
   },
 
-  deleteModelPricing: async (modelId: string): Promise<void> => {
-    return invoke("delete_model_pricing", { modelId });
+  // 指定 effectiveFrom 时只删除该版本，否则删除该模型的全部定价版本
+  deleteModelPricing: async (
+    modelId: string,
+    effectiveFrom?: number,
+  ): Promise<void> => {
+    return invoke("delete_model_pricing", { modelId, effectiveFrom });
+  },
+
+  // source 为本地文件路径或 http(s) URL
+  previewPricingCatalog: async (source: string): Promise<CatalogPreview> => {
+    return invoke("preview_pricing_catalog", { source });
+  },
+
+  // effectiveFrom 为空时覆盖基础版本，否则新增从该时间生效的版本
+  applyPricingCatalog: async (
+    prices: CatalogPrice[],
+    effectiveFrom?: number,
+  ): Promise<number> => {
+    return invoke("apply_pricing_catalog", { prices, effectiveFrom });
   },
 
   checkProviderLimits: async (
     providerId: string,
     appType: string,
+    currency?: string,
   ): Promise<ProviderLimitStatus> => {
-    return invoke("check_provider_limits", { providerId, appType });
+    return invoke("check_provider_limits", { providerId, appType, currency });
+  },
+
+  getExchangeRates: async (): Promise<ExchangeRate[]> => {
+    return invoke("get_exchange_rates");
+  },
+
+  saveExchangeRate: async (
+    currency: string,
+    ratePerUsd: string,
+  ): Promise<ExchangeRate> => {
+    return invoke("save_exchange_rate", { currency, ratePerUsd });
+  },
+
+  deleteExchangeRate: async (currency: string): Promise<void> => {
+    return invoke("delete_exchange_rate", { currency });
+  },
+
+  // 从 JSON（{"CNY": 7.1} 或 {"base", "rates"}）或 CSV（currency,rate）文件导入
+  importExchangeRates: async (filePath: string): Promise<ExchangeRate[]> => {
+    return invoke("import_exchange_rates", { filePath });
+  },
+
+  // 从 CLI 会话记录导入用量，appTypes 为空时导入 claude / codex / gemini
+  importTranscriptUsage: async (
+    appTypes?: string[],
+  ): Promise<TranscriptImportResult> => {
+    return invoke("import_transcript_usage", { appTypes });
   },
 
   deleteRequestLogsByDate: async (
@@ -135,4 +274,69 @@ export const usageApi = {
   ): Promise<number> => {
     return invoke("count_request_logs_by_date", { startDate, endDate });
   },
+
+  exportRequestLogs: async (
+    filters: LogFilters,
+    format: UsageExportFormat,
+    filePath: string,
+  ): Promise<number> => {
+    return invoke("export_request_logs", { filters, format, filePath });
+  },
+
+  exportUsageReport: async (
+    startDate: number,
+    endDate: number,
+    format: UsageExportFormat,
+    filePath: string,
+    currency?: string,
+  ): Promise<UsageReport> => {
+    return invoke("export_usage_report", {
+      startDate,
+      endDate,
+      currency,
+      format,
+      filePath,
+    });
+  },
+
+  generateScheduledUsageReports: async (): Promise<string[]> => {
+    return invoke("generate_scheduled_usage_reports");
+  },
+
+  getBudgets: async (): Promise<UsageBudget[]> => {
+    return invoke("get_budgets");
+  },
+
+  saveBudget: async (budget: Partial<UsageBudget>): Promise<UsageBudget> => {
+    return invoke("save_budget", { budget });
+  },
+
+  deleteBudget: async (id: string): Promise<boolean> => {
+    return invoke("delete_budget", { id });
+  },
+
+  getBudgetStatuses: async (): Promise<BudgetStatus[]> => {
+    return invoke("get_budget_statuses");
+  },
+
+  getBalanceHistory: async (
+    providerId: string,
+    appId: AppId,
+    start?: number,
+    end?: number,
+  ): Promise<BalanceSnapshot[]> => {
+    return invoke("get_balance_history", {
+      providerId,
+      app: appId,
+      start,
+      end,
+    });
+  },
+
+  getBalanceForecast: async (
+    providerId: string,
+    appId: AppId,
+  ): Promise<BalanceForecast | null> => {
+    return invoke("get_balance_forecast", { providerId, app: appId });
+  },
 };
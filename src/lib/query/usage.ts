@@ -1,7 +1,7 @@
 import { useQuery, useMutation, useQueryClient } from "@tanstack/react-query";
 import { useMemo } from "react";
 import { usageApi } from "@/lib/api/usage";
-import type { LogFilters } from "@/types/usage";
+import type { LogFilters, PricingTier } from "@/types/usage";
 
 const DEFAULT_REFETCH_INTERVAL_MS = 30000;
 
@@ -104,7 +104,7 @@ export function useUsageTrends(days: number, options?: UsageQueryOptions) {
 export function useProviderStats(options?: UsageQueryOptions) {
   return useQuery({
     queryKey: usageKeys.providerStats(),
-    queryFn: usageApi.getProviderStats,
+    queryFn: () => usageApi.getProviderStats(),
     refetchInterval: options?.refetchInterval ?? DEFAULT_REFETCH_INTERVAL_MS, // 每30秒自动刷新
     refetchIntervalInBackground: options?.refetchIntervalInBackground ?? false,
   });
@@ -113,7 +113,7 @@ export function useProviderStats(options?: UsageQueryOptions) {
 export function useModelStats(options?: UsageQueryOptions) {
   return useQuery({
     queryKey: usageKeys.modelStats(),
-    queryFn: usageApi.getModelStats,
+    queryFn: () => usageApi.getModelStats(),
     refetchInterval: options?.refetchInterval ?? DEFAULT_REFETCH_INTERVAL_MS,
     refetchIntervalInBackground: options?.refetchIntervalInBackground ?? false,
   });
@@ -226,6 +226,9 @@ export function useUpdateModelPricing() {
       outputCost: string;
       cacheReadCost: string;
       cacheCreationCost: string;
+      effectiveFrom?: number;
+      effectiveUntil?: number;
+      tiers?: PricingTier[];
     }) =>
       usageApi.updateModelPricing(
         params.modelId,
@@ -234,6 +237,9 @@ export function useUpdateModelPricing() {
         params.outputCost,
         params.cacheReadCost,
         params.cacheCreationCost,
+        params.effectiveFrom,
+        params.effectiveUntil,
+        params.tiers,
       ),
     onSuccess: () => {
       queryClient.invalidateQueries({ queryKey: usageKeys.pricing() });
@@ -245,7 +251,8 @@ export function useDeleteModelPricing() {
   const queryClient = useQueryClient();
 
   return useMutation({
-    mutationFn: (modelId: string) => usageApi.deleteModelPricing(modelId),
+    mutationFn: (params: { modelId: string; effectiveFrom?: number }) =>
+      usageApi.deleteModelPricing(params.modelId, params.effectiveFrom),
     onSuccess: () => {
       queryClient.invalidateQueries({ queryKey: usageKeys.pricing() });
     },
@@ -1,3 +1,5 @@
+import type { SessionUsageStats, UsageReportSettings } from "@/types/usage";
+
 export type ProviderCategory =
   | "official" // 官方
   | "cn_official" // 开源官方（原"国产官方"）
@@ -121,6 +123,56 @@ export interface ProviderProxyConfig {
   proxyPassword?: string;
 }
 
+// 上游 TLS 配置（全局或供应商单独配置）
+export interface UpstreamTlsConfig {
+  // 是否启用（供应商单独配置启用时覆盖全局配置）
+  enabled: boolean;
+  // 额外信任的 CA 证书文件（PEM）
+  caCertPaths?: string[];
+  // 禁用内置根证书，仅信任 caCertPaths
+  disableSystemRoots?: boolean;
+  // mTLS 客户端证书文件（PEM）
+  clientCertPath?: string;
+  // mTLS 客户端私钥文件（PEM）
+  clientKeyPath?: string;
+}
+
+// Mock 上游故障注入规则
+export interface MockFault {
+  kind: "rateLimit" | "serverError" | "timeout" | "disconnect";
+  // 触发概率（0~1）
+  rate: number;
+}
+
+// 离线 Mock 上游配置（启用后代理返回录制/预置的响应，不请求真实上游）
+export interface MockUpstreamConfig {
+  enabled: boolean;
+  // 返回响应头前的延迟（毫秒）
+  latencyMs?: number;
+  // 流式响应相邻 SSE 事件之间的延迟（毫秒）
+  chunkDelayMs?: number;
+  // 故障注入规则（按顺序判定，命中第一条即生效）
+  faults?: MockFault[];
+}
+
+// 供应商计费币种配置
+export interface BillingCurrencyConfig {
+  // ISO 4217 币种代码（如 CNY）
+  currency: string;
+  // exchange: 按汇率表换算美元标价；fixed: 每 1 美元标价收取 fixedRate 单位
+  rateBasis?: "exchange" | "fixed";
+  // 固定比例（如“1 元 = 1 美元”填 "1"）
+  fixedRate?: string;
+}
+
+// 低余额告警配置
+export interface BalanceAlertConfig {
+  // 剩余额度低于该值时通知（单位与用量脚本返回的 unit 一致）
+  lowThreshold?: number;
+  // 余额耗尽时在故障转移队列中降级到末尾
+  demoteWhenExhausted?: boolean;
+}
+
 // 供应商元数据（字段名与后端一致，保持 snake_case）
 export interface ProviderMeta {
   // 自定义端点：以 URL 为键，值为端点信息
@@ -137,10 +189,20 @@ export interface ProviderMeta {
   testConfig?: ProviderTestConfig;
   // 供应商单独的代理配置
   proxyConfig?: ProviderProxyConfig;
+  // 供应商单独的上游 TLS 配置
+  tlsConfig?: UpstreamTlsConfig;
+  // 离线 Mock 上游配置（用于本地测试 CLI 集成与故障转移）
+  mockUpstream?: MockUpstreamConfig;
   // 供应商成本倍率
   costMultiplier?: string;
   // 供应商计费模式来源
   pricingModelSource?: string;
+  // 计费币种（未设置视为按美元计费）
+  billing?: BillingCurrencyConfig;
+  // 加权轮询权重（负载均衡策略为 weighted 时生效，默认 1，0 表示仅作为备选）
+  loadBalanceWeight?: number;
+  // 低余额告警（基于用量查询脚本返回的剩余额度）
+  balanceAlert?: BalanceAlertConfig;
   // Claude API 格式（仅 Claude 供应商使用）
   // - "anthropic": 原生 Anthropic Messages API 格式，直接透传
   // - "openai_chat": OpenAI Chat Completions 格式，需要格式转换
@@ -266,6 +328,10 @@ export interface Settings {
   // Maximum backup files to retain (default 10)
   backupRetainCount?: number;
 
+  // ===== 用量报告设置 =====
+  // 定期花费报告（周报/月报）
+  usageReport?: UsageReportSettings;
+
   // ===== 终端设置 =====
   // 首选终端应用（可选，默认使用系统默认终端）
   // macOS: "terminal" | "iterm2" | "warp" | "alacritty" | "kitty" | "ghostty"
@@ -284,6 +350,8 @@ export interface SessionMeta {
   lastActiveAt?: number;
   sourcePath?: string;
   resumeCommand?: string;
+  // 请求日志中该会话的累计成本与模型构成
+  usage?: SessionUsageStats;
 }
 
 export interface SessionMessage {
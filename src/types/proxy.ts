@@ -27,6 +27,9 @@ export interface ProxyStatus {
   last_error: string | null;
   failover_count: number;
   active_targets?: ActiveTarget[];
+  cache_hits?: number;
+  cache_misses?: number;
+  tls?: boolean;
 }
 
 export interface ActiveTarget {
@@ -120,6 +123,106 @@ export interface GlobalProxyConfig {
   enableLogging: boolean;
 }
 
+// 负载均衡策略（仅在故障转移开启时生效）
+export type LoadBalanceStrategy =
+  | "priority"
+  | "weighted"
+  | "least_latency"
+  | "least_cost";
+
+// 模型路由规则（按 sortIndex 顺序匹配，已设置的条件全部满足才命中）
+export interface RoutingRule {
+  id: string;
+  appType: string;
+  name: string;
+  enabled: boolean;
+  sortIndex: number;
+  modelPattern?: string;
+  thinking?: boolean;
+  minRequestBytes?: number;
+  maxRequestBytes?: number;
+  headerName?: string;
+  headerValue?: string;
+  providerId: string;
+  targetModel?: string;
+}
+
+// 代理钩子：定义 onRequest / onResponse / onSseEvent 的 JS 脚本
+export interface ProxyHook {
+  id: string;
+  appType: string;
+  name: string;
+  enabled: boolean;
+  sortIndex: number;
+  // 为空时对该 app 的全部供应商生效
+  providerId?: string;
+  script: string;
+  timeoutMs: number;
+}
+
+// Mock 上游 fixture：按顺序匹配，返回录制/预置的响应
+export interface MockFixture {
+  id: string;
+  appType: string;
+  name: string;
+  enabled: boolean;
+  sortIndex: number;
+  // 模型匹配（glob，为空时匹配所有模型）
+  modelPattern?: string;
+  // 请求体需包含的文本
+  promptContains?: string;
+  statusCode: number;
+  // 原生 JSON 响应、请求日志中记录的响应或纯文本
+  responseBody: string;
+  // 录制来源的请求 ID
+  sourceRequestId?: string;
+}
+
+export type ProxyTlsCertSource = "selfSigned" | "custom";
+
+// 代理监听 TLS 配置
+export interface ProxyTlsConfig {
+  enabled: boolean;
+  certSource: ProxyTlsCertSource;
+  certPath?: string;
+  keyPath?: string;
+  extraHosts: string[];
+}
+
+// 客户端认证配置（共享代理时使用）
+export interface ClientAuthConfig {
+  enabled: boolean;
+  allowLoopback: boolean;
+}
+
+export type ClientTokenQuotaPeriod = "daily" | "monthly";
+
+// 客户端令牌（不含明文）
+export interface ClientToken {
+  id: string;
+  name: string;
+  tokenPrefix: string;
+  requestLimit?: number;
+  costLimitUsd?: string;
+  quotaPeriod: ClientTokenQuotaPeriod;
+  createdAt: number;
+  lastUsedAt?: number;
+  revokedAt?: number;
+}
+
+// 新签发的令牌（明文仅返回一次）
+export interface IssuedClientToken {
+  token: ClientToken;
+  secret: string;
+}
+
+// 令牌在当前配额周期内的用量
+export interface ClientTokenUsage {
+  requests: number;
+  costUsd: string;
+  periodStart: number;
+}
+
 // 应用级代理配置（每个 app 独立）
 export interface AppProxyConfig {
   appType: string;
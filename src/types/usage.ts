@@ -32,9 +32,100 @@ export interface RequestLog {
   errorMessage?: string;
   requestBody?: string;
   responseBody?: string;
+  cacheHitRate?: number;
+  routingRule?: string;
+  fromCache: boolean;
+  clientTokenId?: string;
+  // 触发的整流事件：thinking_signature / thinking_budget / context_overflow
+  rectifier?: string;
+  // 供应商计费币种与以该币种计的总成本
+  billingCurrency?: string;
+  billingCost?: string;
+  source: UsageSource;
   createdAt: number;
 }
 
+export interface SessionCacheStats {
+  sessionId: string;
+  requestCount: number;
+  providerCount: number;
+  providerSwitches: number;
+  totalPromptTokens: number;
+  totalCacheReadTokens: number;
+  totalCacheCreationTokens: number;
+  cacheHitRate: number;
+  estimatedSavingsUsd: string;
+}
+
+export interface ModelUsageShare {
+  model: string;
+  requestCount: number;
+  totalTokens: number;
+  totalCost: string;
+}
+
+// 会话 ID 与会话管理器一致（代理记录的 codex_ 前缀已去除）
+export interface SessionUsageStats {
+  sessionId: string;
+  appType: string;
+  requestCount: number;
+  totalInputTokens: number;
+  totalOutputTokens: number;
+  totalCacheReadTokens: number;
+  totalCacheCreationTokens: number;
+  totalCost: string;
+  currency: string;
+  firstRequestAt: number;
+  lastRequestAt: number;
+  models: ModelUsageShare[];
+}
+
+export interface ProjectUsageStats {
+  // 找不到对应会话的用量归入 projectDir 为空的分组
+  projectDir?: string;
+  sessionCount: number;
+  requestCount: number;
+  totalTokens: number;
+  totalCost: string;
+  currency: string;
+  lastRequestAt: number;
+  models: ModelUsageShare[];
+}
+
+export type LatencyGroupBy = "provider" | "model" | "providerModel";
+
+export interface LatencyFilters {
+  appType?: string;
+  providerId?: string;
+  model?: string;
+  startDate?: number;
+  endDate?: number;
+}
+
+// 分位数只统计成功请求；首字延迟只统计流式请求，无样本时为空
+export interface LatencyMetrics {
+  requestCount: number;
+  errorCount: number;
+  errorRate: number;
+  latencyP50Ms?: number | null;
+  latencyP90Ms?: number | null;
+  latencyP99Ms?: number | null;
+  ttftP50Ms?: number | null;
+  ttftP90Ms?: number | null;
+  ttftP99Ms?: number | null;
+  outputTokensPerSec?: number | null;
+}
+
+export interface LatencyStats extends LatencyMetrics {
+  providerId?: string;
+  providerName?: string;
+  model?: string;
+}
+
+export interface LatencyTrendPoint extends LatencyMetrics {
+  date: string;
+}
+
 export interface PaginatedLogs {
   data: RequestLog[];
   total: number;
@@ -42,6 +133,15 @@ export interface PaginatedLogs {
   pageSize: number;
 }
 
+// 按输入规模分档的价格：prompt 总量超过阈值时整个请求按该档计价
+export interface PricingTier {
+  aboveInputTokens: number;
+  inputCostPerMillion: string;
+  outputCostPerMillion: string;
+  cacheReadCostPerMillion?: string;
+  cacheCreationCostPerMillion?: string;
+}
+
 export interface ModelPricing {
   modelId: string;
   displayName: string;
@@ -49,6 +149,38 @@ export interface ModelPricing {
   outputCostPerMillion: string;
   cacheReadCostPerMillion: string;
   cacheCreationCostPerMillion: string;
+  // 生效时间段 [effectiveFrom, effectiveUntil)，Unix 秒；0 表示不限起始时间
+  effectiveFrom: number;
+  effectiveUntil?: number;
+  tiers?: PricingTier[];
+}
+
+// 定价目录（LiteLLM / OpenRouter）中的一条模型定价
+export interface CatalogPrice {
+  modelId: string;
+  displayName: string;
+  inputCostPerMillion: string;
+  outputCostPerMillion: string;
+  cacheReadCostPerMillion: string;
+  cacheCreationCostPerMillion: string;
+  tiers?: PricingTier[];
+}
+
+export interface CatalogChange {
+  kind: "added" | "changed";
+  // 目录中的原始模型 ID
+  sourceId: string;
+  // 将写入的定价（modelId 为匹配到的现有 ID 或规范化后的新 ID）
+  incoming: CatalogPrice;
+  // 当前生效的定价（新增时为空）
+  current?: CatalogPrice;
+}
+
+export interface CatalogPreview {
+  format: "litellm" | "openrouter";
+  changes: CatalogChange[];
+  unchanged: number;
+  skipped: number;
 }
 
 export interface UsageSummary {
@@ -59,6 +191,7 @@ export interface UsageSummary {
   totalCacheCreationTokens: number;
   totalCacheReadTokens: number;
   successRate: number;
+  currency: string;
 }
 
 export interface DailyStats {
@@ -100,6 +233,18 @@ export interface LogFilters {
   statusFilter?: "success" | "error";
   startDate?: number;
   endDate?: number;
+  source?: UsageSource;
+}
+
+// 用量来源：proxy（代理请求）或 transcript（CLI 会话记录导入）
+export type UsageSource = "proxy" | "transcript";
+
+export interface TranscriptImportResult {
+  filesScanned: number;
+  filesSkipped: number;
+  recordsImported: number;
+  recordsDuplicate: number;
+  recordsProxied: number;
 }
 
 export interface ProviderLimitStatus {
@@ -110,6 +255,127 @@ export interface ProviderLimitStatus {
   monthlyUsage: string;
   monthlyLimit?: string;
   monthlyExceeded: boolean;
+  currency: string;
+}
+
+// 汇率：1 美元可兑换的该币种数量（USD 恒为 1，不入表）
+export interface ExchangeRate {
+  currency: string;
+  ratePerUsd: string;
+  source?: string;
+  updatedAt: number;
+}
+
+export type UsageExportFormat = "csv" | "json";
+
+export type UsageReportPeriod = "weekly" | "monthly";
+
+// 定期花费报告设置（设备级），未设置目录时输出到 ~/.cc-switch/reports
+export interface UsageReportSettings {
+  enabled: boolean;
+  periods: UsageReportPeriod[];
+  format: UsageExportFormat;
+  directory?: string;
+  currency?: string;
+}
+
+// 花费拆分：按供应商、模型或客户端令牌（未使用令牌时 key 为空）
+export interface SpendBreakdown {
+  key: string;
+  name: string;
+  appType?: string;
+  requestCount: number;
+  inputTokens: number;
+  outputTokens: number;
+  totalCost: string;
+}
+
+export type BudgetPeriod = "daily" | "monthly";
+
+// 花费预算：appType 为空表示全局预算，thresholds 为告警百分比
+export interface UsageBudget {
+  id: string;
+  name: string;
+  appType?: string;
+  period: BudgetPeriod;
+  limitAmount: string;
+  currency: string;
+  thresholds: number[];
+  enabled: boolean;
+  createdAt: number;
+  updatedAt: number;
+}
+
+// 预算当前周期执行情况，forecast 为月末花费预测（仅月度预算）
+export interface BudgetStatus extends UsageBudget {
+  periodStart: number;
+  periodEnd: number;
+  spent: string;
+  percent: number;
+  reachedThreshold?: number;
+  forecast?: string;
+  forecastPercent?: number;
+}
+
+// budget-threshold-crossed 事件负载
+export interface BudgetAlert {
+  budgetId: string;
+  name: string;
+  appType?: string;
+  period: BudgetPeriod;
+  threshold: number;
+  percent: number;
+  spent: string;
+  limitAmount: string;
+  currency: string;
+  forecast?: string;
+}
+
+// 用量脚本单个套餐的一次余额记录
+export interface BalanceSnapshot {
+  providerId: string;
+  appType: string;
+  planName: string;
+  remaining?: number;
+  used?: number;
+  total?: number;
+  unit?: string;
+  recordedAt: number;
+}
+
+// 余额耗尽预测：按近 7 天消耗速率估算
+export interface BalanceForecast {
+  providerId: string;
+  appType: string;
+  remaining: number;
+  unit?: string;
+  recordedAt: number;
+  burnRatePerDay?: number;
+  runsOutAt?: number;
+  runsOutInDays?: number;
+}
+
+// provider-balance-low 事件负载
+export interface BalanceAlert {
+  providerId: string;
+  providerName: string;
+  appType: string;
+  remaining: number;
+  threshold?: number;
+  unit?: string;
+  exhausted: boolean;
+  runsOutInDays?: number;
+}
+
+export interface UsageReport {
+  periodStart: number;
+  periodEnd: number;
+  generatedAt: number;
+  currency: string;
+  summary: UsageSummary;
+  byProvider: SpendBreakdown[];
+  byModel: SpendBreakdown[];
+  byClient: SpendBreakdown[];
 }
 
 export type TimeRange = "5m" | "10m" | "15m" | "30m" | "1h" | "5h" | "12h" | "1d" | "7d" | "30d" | "custom";